@@ -0,0 +1,12 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use kingdom_kards::server::request::Request;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(line) = std::str::from_utf8(data) {
+        let _ = Request::from_str(line);
+    }
+});
@@ -0,0 +1,213 @@
+//! This module contains `KingdomError`, the error type that is used throughout the crate. Every
+//! fallible public function returns `kingdom_kards::error::Result`, so that bad input from the
+//! network is reported as an error instead of panicking.
+
+use std::{fmt, io};
+
+use crate::server::{
+    request::{RequestParseError, RequestType},
+    response::{ActionParseError, ActionType, ResponseParseError, ResponseType},
+};
+
+/// Shorthand for `std::result::Result<T, KingdomError>`.
+pub type Result<T> = std::result::Result<T, KingdomError>;
+
+/// This is the type used for representing every error in the crate, both client-side and
+/// server-side.
+#[derive(Debug)]
+pub enum KingdomError {
+    /// Failed to connect to server at port.
+    FailedToConnect(String),
+    /// A `StreamHandler` was used before a connection was made.
+    NotConnected,
+    /// Expected a request of a different type.
+    ExpectedRequestType(RequestType),
+    /// Expected a response of a different type.
+    ExpectedResponseType(ResponseType),
+    /// Expected same request types, received different types.
+    MismatchedRequestTypes(RequestType, RequestType),
+    /// Expected same response types, received different types. The types are boxed, since a
+    /// `ResponseType` can hold a whole `Action`, to keep `KingdomError` small.
+    MismatchedResponseTypes(Box<ResponseType>, Box<ResponseType>),
+    /// Encounted a request parsing error.
+    RequestParse(RequestParseError),
+    /// Encountered a response parsing error.
+    ResponseParse(ResponseParseError),
+    /// Encountered an action parsing error.
+    ActionParse(ActionParseError),
+    /// Received an action that isn't valid at this point in the game.
+    InvalidAction(ActionType),
+    /// Received an action that hasn't been implemented yet.
+    UnsupportedAction(ActionType),
+    /// No player has this name.
+    PlayerNotFound(String),
+    /// A King was played against this teammate, but friendly fire is off.
+    FriendlyFire(String),
+    /// No player is seated at this index.
+    InvalidPlayerIndex(usize),
+    /// There is no card at this index of the player's hand.
+    InvalidHandIndex(usize),
+    /// A username was rejected, with the reason why.
+    InvalidName(String),
+    /// A rule could not be parsed from its `key=value` form.
+    InvalidRule(String),
+    /// A card code could not be parsed.
+    InvalidCard(String),
+    /// A card isn't in the player's hand.
+    CardNotInHand(String),
+    /// Cards were played in a way the rules don't allow, with the reason why.
+    InvalidPlay(String),
+    /// A typed command could not be parsed, with the reason why.
+    InvalidCommand(String),
+    /// A line of a snapshot could not be parsed, with the line number and reason why.
+    InvalidSnapshot(usize, String),
+    /// A line of a game log could not be parsed, with the line number and reason why.
+    InvalidGameLog(usize, String),
+    /// A line of a profiles file could not be parsed, with the line number and reason why.
+    InvalidProfiles(usize, String),
+    /// Replaying an action gave a different result than the game log recorded, with the
+    /// action's sequence number and what was different.
+    ReplayMismatch(u32, String),
+    /// A chat message was rejected by the host, with the reason why.
+    ChatRejected(String),
+    /// A change to the lobby (e.g. starting the game) was rejected, with the reason why.
+    LobbyRejected(String),
+    /// An admin command (e.g. pausing the game) was rejected, with the reason why.
+    AdminRejected(String),
+    /// The host kicked this player out of the lobby.
+    Kicked,
+    /// A room couldn't be created with this name.
+    InvalidRoomName(String),
+    /// The room with this name has no empty seats.
+    RoomFull(String),
+    /// The server already has as many rooms open as it can (see `MAX_ROOMS`).
+    TooManyRooms,
+    /// A client session was asked to do something it can't do yet, with what it is waiting for.
+    OutOfOrder(String),
+    /// A WebSocket connection failed, with the reason why.
+    WebSocket(String),
+    /// A tournament couldn't be set up, with the reason why.
+    InvalidTournament(String),
+    /// The host's deal didn't match what it committed to before dealing, with the reason why.
+    UnfairDeal(String),
+    /// The player backed out of an action before it was sent.
+    Cancelled,
+    /// Nothing was received before a clock ran out.
+    TimedOut,
+    /// Encountered a standard io::Error.
+    Io(io::Error),
+}
+
+impl fmt::Display for KingdomError {
+    /// When converted to strings, `KingdomError`s contain a descriptive message of what the
+    /// error was as well as any specific details about the error. It does not include where the
+    /// error occured.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KingdomError::FailedToConnect(msg) => write!(f, "Failed to connect to {msg}"),
+            KingdomError::NotConnected => write!(f, "Not connected to a server"),
+            KingdomError::ExpectedRequestType(request_type) => {
+                write!(f, "Expected request of type {request_type}")
+            }
+            KingdomError::ExpectedResponseType(response_type) => {
+                write!(f, "Expected response of type {response_type}")
+            }
+            KingdomError::MismatchedRequestTypes(one, two) => {
+                write!(f, "Expected same request types. Received {one} and {two}")
+            }
+            KingdomError::MismatchedResponseTypes(one, two) => {
+                write!(f, "Expected same response types. Received {one} and {two}")
+            }
+            KingdomError::RequestParse(err) => write!(f, "Request error: {err}"),
+            KingdomError::ResponseParse(err) => write!(f, "Response error: {err}"),
+            KingdomError::ActionParse(err) => write!(f, "Action error: {err}"),
+            KingdomError::InvalidAction(action_type) => {
+                write!(f, "Action '{action_type:?}' is not allowed right now")
+            }
+            KingdomError::UnsupportedAction(action_type) => {
+                write!(f, "Action '{action_type:?}' is not supported yet")
+            }
+            KingdomError::PlayerNotFound(name) => write!(f, "No player named '{name}'"),
+            KingdomError::FriendlyFire(name) => {
+                write!(f, "'{name}' is a teammate, and friendly fire is off")
+            }
+            KingdomError::InvalidPlayerIndex(index) => write!(f, "No player at index {index}"),
+            KingdomError::InvalidHandIndex(index) => write!(f, "No card at index {index} of hand"),
+            KingdomError::InvalidName(reason) => write!(f, "Invalid username: {reason}"),
+            KingdomError::InvalidRule(rule) => write!(f, "Invalid rule '{rule}'"),
+            KingdomError::InvalidCard(code) => write!(f, "Invalid card '{code}'"),
+            KingdomError::CardNotInHand(card) => write!(f, "'{card}' is not in your hand"),
+            KingdomError::InvalidPlay(reason) => write!(f, "Invalid play: {reason}"),
+            KingdomError::InvalidCommand(reason) => write!(f, "Invalid command: {reason}"),
+            KingdomError::UnfairDeal(reason) => {
+                write!(f, "The deal could not be verified: {reason}")
+            }
+            KingdomError::InvalidSnapshot(line, reason) => {
+                write!(f, "Invalid snapshot on line {line}: {reason}")
+            }
+            KingdomError::InvalidGameLog(line, reason) => {
+                write!(f, "Invalid game log on line {line}: {reason}")
+            }
+            KingdomError::InvalidProfiles(line, reason) => {
+                write!(f, "Invalid profiles on line {line}: {reason}")
+            }
+            KingdomError::ReplayMismatch(seq, reason) => {
+                write!(
+                    f,
+                    "Replay of action {seq} does not match game log: {reason}"
+                )
+            }
+            KingdomError::ChatRejected(reason) => write!(f, "Chat message rejected: {reason}"),
+            KingdomError::LobbyRejected(reason) => write!(f, "Lobby: {reason}"),
+            KingdomError::AdminRejected(reason) => write!(f, "Admin: {reason}"),
+            KingdomError::Kicked => write!(f, "Kicked from the lobby by the host"),
+            KingdomError::InvalidRoomName(name) => write!(f, "Invalid room name '{name}'"),
+            KingdomError::RoomFull(name) => write!(f, "Room '{name}' is full"),
+            KingdomError::TooManyRooms => write!(f, "No more rooms can be opened right now"),
+            KingdomError::OutOfOrder(waiting_for) => {
+                write!(f, "Not allowed right now, waiting for {waiting_for}")
+            }
+            KingdomError::WebSocket(reason) => write!(f, "WebSocket error: {reason}"),
+            KingdomError::InvalidTournament(reason) => write!(f, "Invalid tournament: {reason}"),
+            KingdomError::Cancelled => write!(f, "Cancelled"),
+            KingdomError::TimedOut => write!(f, "Timed out"),
+            KingdomError::Io(err) => write!(f, "IO Error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for KingdomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KingdomError::RequestParse(err) => Some(err),
+            KingdomError::ResponseParse(err) => Some(err),
+            KingdomError::ActionParse(err) => Some(err),
+            KingdomError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for KingdomError {
+    fn from(value: io::Error) -> Self {
+        KingdomError::Io(value)
+    }
+}
+
+impl From<RequestParseError> for KingdomError {
+    fn from(value: RequestParseError) -> Self {
+        KingdomError::RequestParse(value)
+    }
+}
+
+impl From<ResponseParseError> for KingdomError {
+    fn from(value: ResponseParseError) -> Self {
+        KingdomError::ResponseParse(value)
+    }
+}
+
+impl From<ActionParseError> for KingdomError {
+    fn from(value: ActionParseError) -> Self {
+        KingdomError::ActionParse(value)
+    }
+}
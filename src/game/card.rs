@@ -2,9 +2,11 @@
 //! playing card, as well as methods for creating and getting information from the
 //! playing cards.
 
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+use crate::error::KingdomError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Suit {
     Spades,
     Clubs,
@@ -12,19 +14,65 @@ pub enum Suit {
     Diamonds,
 }
 
-impl Display for Suit {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let suit = match self {
+impl Suit {
+    /// Every suit, in the order that a new deck is sorted in.
+    pub const ALL: [Suit; 4] = [Suit::Spades, Suit::Clubs, Suit::Hearts, Suit::Diamonds];
+
+    /// Converts from `Suit` to the letter used in card codes (e.g. `H` for Hearts).
+    pub fn to_symbol(&self) -> char {
+        match self {
+            Suit::Spades => 'S',
+            Suit::Clubs => 'C',
+            Suit::Hearts => 'H',
+            Suit::Diamonds => 'D',
+        }
+    }
+
+    /// Converts from the letter used in card codes back to `Suit`. Returns `None` if the symbol
+    /// is invalid.
+    pub fn from_symbol(symbol: char) -> Option<Suit> {
+        match symbol {
+            'S' => Some(Suit::Spades),
+            'C' => Some(Suit::Clubs),
+            'H' => Some(Suit::Hearts),
+            'D' => Some(Suit::Diamonds),
+            _ => None,
+        }
+    }
+
+    /// Returns the suit's full name (e.g. `Hearts`).
+    pub fn name(&self) -> &'static str {
+        match self {
             Suit::Spades => "Spades",
             Suit::Clubs => "Clubs",
             Suit::Hearts => "Hearts",
             Suit::Diamonds => "Diamonds",
-        };
-        write!(f, "{suit}")
+        }
+    }
+}
+
+impl Display for Suit {
+    /// Suits are written as their letter (e.g. `H` for Hearts).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_symbol())
+    }
+}
+
+impl FromStr for Suit {
+    type Err = KingdomError;
+
+    /// Converts from a suit's letter, in either case, to `Suit`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(symbol), None) => Suit::from_symbol(symbol.to_ascii_uppercase()),
+            _ => None,
+        }
+        .ok_or_else(|| KingdomError::InvalidCard(s.to_owned()))
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Value {
     Ace,
     Two,
@@ -42,6 +90,23 @@ pub enum Value {
 }
 
 impl Value {
+    /// Every value, in the order that a new deck is sorted in.
+    pub const ALL: [Value; 13] = [
+        Value::Ace,
+        Value::Two,
+        Value::Three,
+        Value::Four,
+        Value::Five,
+        Value::Six,
+        Value::Seven,
+        Value::Eight,
+        Value::Nine,
+        Value::Ten,
+        Value::Jack,
+        Value::Queen,
+        Value::King,
+    ];
+
     pub fn is_number(&self) -> bool {
         matches!(
             self,
@@ -57,25 +122,49 @@ impl Value {
         )
     }
 
-    pub fn to_number_value(&self) -> u16 {
+    /// Converts from `Value` to the symbol used in card codes (e.g. `K` for King, `10` for Ten).
+    pub fn to_symbol(&self) -> &str {
         match self {
-            Value::Two => 2,
-            Value::Three => 3,
-            Value::Four => 4,
-            Value::Five => 5,
-            Value::Six => 6,
-            Value::Seven => 7,
-            Value::Eight => 8,
-            Value::Nine => 9,
-            Value::Ten => 10,
-            _ => unreachable!(),
+            Value::Ace => "A",
+            Value::Two => "2",
+            Value::Three => "3",
+            Value::Four => "4",
+            Value::Five => "5",
+            Value::Six => "6",
+            Value::Seven => "7",
+            Value::Eight => "8",
+            Value::Nine => "9",
+            Value::Ten => "10",
+            Value::Jack => "J",
+            Value::Queen => "Q",
+            Value::King => "K",
         }
     }
-}
 
-impl Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let value = match self {
+    /// Converts from the symbol used in card codes back to `Value`. Returns `None` if the symbol
+    /// is invalid.
+    pub fn from_symbol(symbol: &str) -> Option<Value> {
+        match symbol {
+            "A" => Some(Value::Ace),
+            "2" => Some(Value::Two),
+            "3" => Some(Value::Three),
+            "4" => Some(Value::Four),
+            "5" => Some(Value::Five),
+            "6" => Some(Value::Six),
+            "7" => Some(Value::Seven),
+            "8" => Some(Value::Eight),
+            "9" => Some(Value::Nine),
+            "10" => Some(Value::Ten),
+            "J" => Some(Value::Jack),
+            "Q" => Some(Value::Queen),
+            "K" => Some(Value::King),
+            _ => None,
+        }
+    }
+
+    /// Returns the value's full name (e.g. `King`).
+    pub fn name(&self) -> &'static str {
+        match self {
             Value::Ace => "Ace",
             Value::Two => "Two",
             Value::Three => "Three",
@@ -89,8 +178,40 @@ impl Display for Value {
             Value::Jack => "Jack",
             Value::Queen => "Queen",
             Value::King => "King",
-        };
-        write!(f, "{value}")
+        }
+    }
+
+    /// Returns the numeric value of a Number card, or `None` if this isn't a Number card.
+    pub fn to_number_value(&self) -> Option<u16> {
+        match self {
+            Value::Two => Some(2),
+            Value::Three => Some(3),
+            Value::Four => Some(4),
+            Value::Five => Some(5),
+            Value::Six => Some(6),
+            Value::Seven => Some(7),
+            Value::Eight => Some(8),
+            Value::Nine => Some(9),
+            Value::Ten => Some(10),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    /// Values are written as their symbol (e.g. `K` for King, `10` for Ten).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_symbol())
+    }
+}
+
+impl FromStr for Value {
+    type Err = KingdomError;
+
+    /// Converts from a value's symbol, in either case, to `Value`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Value::from_symbol(&s.to_ascii_uppercase())
+            .ok_or_else(|| KingdomError::InvalidCard(s.to_owned()))
     }
 }
 
@@ -109,7 +230,7 @@ impl Display for Color {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Card {
     pub suit: Suit,
     pub value: Value,
@@ -128,8 +249,124 @@ fn color_to_ansi_code(color: &Color) -> &'static str {
 }
 
 impl Display for Card {
+    /// Cards are written as their value's symbol followed by their suit's letter (e.g. `KH` for
+    /// the King of Hearts, `10S` for the Ten of Spades).
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {} of {}", self.color(), self.value, self.suit)
+        write!(f, "{}{}", self.value, self.suit)
+    }
+}
+
+impl FromStr for Card {
+    type Err = KingdomError;
+
+    /// Converts from a card's code (e.g. `KH` or `10s`) to `Card`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || KingdomError::InvalidCard(s.to_owned());
+        let split = s
+            .len()
+            .checked_sub(1)
+            .filter(|split| s.is_char_boundary(*split));
+        let (value, suit) = s.split_at(split.ok_or_else(invalid)?);
+        match (Value::from_str(value), Suit::from_str(suit)) {
+            (Ok(value), Ok(suit)) => Ok(Card::new(suit, value)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The cards used for one play: the card itself, and the Number attached to it, if any. Plays are
+/// written as the card's code, followed by `+` and the attachment's code (e.g. `KH+5S`, `JC` or
+/// `AS`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Play {
+    pub card: Card,
+    pub attachment: Option<Card>,
+}
+
+impl Play {
+    pub fn new(card: Card, attachment: Option<Card>) -> Play {
+        Play { card, attachment }
+    }
+
+    /// Returns the value of the attached Number, or 0 if nothing is attached.
+    pub fn attachment_value(&self) -> u16 {
+        self.attachment
+            .and_then(|attachment| attachment.value().to_number_value())
+            .unwrap_or(0)
+    }
+}
+
+impl Display for Play {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.card)?;
+        if let Some(attachment) = self.attachment {
+            write!(f, "+{attachment}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Play {
+    type Err = KingdomError;
+
+    /// Converts from a play (e.g. `KH+5S`) to `Play`. Only Kings and Queens can have an
+    /// attachment, and only Numbers can be attached.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (card, attachment) = match s.split_once('+') {
+            Some((card, attachment)) => (card, Some(Card::from_str(attachment)?)),
+            None => (s, None),
+        };
+        let card = Card::from_str(card)?;
+        if let Some(attachment) = attachment {
+            let can_attach = matches!(card.value(), Value::King | Value::Queen);
+            if !can_attach || !attachment.value().is_number() {
+                return Err(KingdomError::InvalidCard(s.to_owned()));
+            }
+        }
+        Ok(Play::new(card, attachment))
+    }
+}
+
+/// A card as it is typed by a player, where the suit can be left out (e.g. `K` or `7`) to mean
+/// any card of that value. Written the same way as a card's code otherwise (e.g. `KH` or `7S`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CardToken {
+    pub value: Value,
+    pub suit: Option<Suit>,
+}
+
+impl CardToken {
+    /// Returns true if `card` is the card this token stands for.
+    pub fn matches(&self, card: &Card) -> bool {
+        *card.value() == self.value && self.suit.is_none_or(|suit| *card.suit() == suit)
+    }
+}
+
+impl Display for CardToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value)?;
+        if let Some(suit) = self.suit {
+            write!(f, "{suit}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CardToken {
+    type Err = KingdomError;
+
+    /// Converts from a card's code (e.g. `KH`) or value (e.g. `K`) to `CardToken`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(card) = Card::from_str(s) {
+            return Ok(CardToken {
+                value: *card.value(),
+                suit: Some(*card.suit()),
+            });
+        }
+        Ok(CardToken {
+            value: Value::from_str(s)?,
+            suit: None,
+        })
     }
 }
 
@@ -138,6 +375,14 @@ impl Card {
         Self { suit, value }
     }
 
+    /// Returns a new, unshuffled deck of all 52 cards.
+    pub fn full_deck() -> Vec<Card> {
+        Suit::ALL
+            .iter()
+            .flat_map(|suit| Value::ALL.iter().map(|value| Card::new(*suit, *value)))
+            .collect()
+    }
+
     pub fn suit(&self) -> &Suit {
         &self.suit
     }
@@ -153,10 +398,20 @@ impl Card {
         }
     }
 
+    /// Returns the card's full name (e.g. `Red King of Hearts`).
+    pub fn name(&self) -> String {
+        format!(
+            "{} {} of {}",
+            self.color(),
+            self.value.name(),
+            self.suit.name()
+        )
+    }
+
     pub fn to_colored_text(&self) -> String {
         let color = color_to_ansi_code(self.color());
-        let value = self.value;
-        let suit = self.suit;
+        let value = self.value.name();
+        let suit = self.suit.name();
         let color_reset = "\x1b[0m";
         format!("{color}{value} of {suit}{color_reset}")
     }
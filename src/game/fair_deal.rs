@@ -0,0 +1,281 @@
+//! This module contains the fair deal, which lets players check that the host didn't stack
+//! anyone's deck. Before dealing, the host shuffles every deck and commits to them by sending the
+//! hash of each deck along with a secret salt. Every player then sends some random entropy, which
+//! is combined and used to shuffle every deck again, so the host can't choose how the cards come
+//! out. Once the game is over, the host reveals the salt, the decks it committed to and everyone's
+//! entropy, and each player checks that the cards they were dealt match. Whenever a player's
+//! discard pile is shuffled back into their deck, it is shuffled with a random number generator
+//! seeded from everyone's entropy too, so each player also checks every card they drew.
+
+use std::{fmt::Display, str::FromStr};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{self, KingdomError},
+    server::response::ResponseParseError,
+};
+
+use super::{
+    card::Card,
+    player::{Player, PlayerCards},
+};
+
+/// Number of random bytes in a salt or a player's entropy.
+const RANDOM_BYTES: usize = 32;
+
+/// Everything the host reveals about the deal once the game is over.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reveal {
+    pub salt: String,
+    /// Every player's name, entropy and the deck the host committed to for them, in seat order.
+    pub seats: Vec<(String, String, Vec<Card>)>,
+}
+
+/// A message in the fair deal.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DealMessage {
+    /// The host's commitment to every player's deck, as each player's name and the hash of their
+    /// deck, in seat order. This is empty when a saved game is resumed, since its cards were
+    /// dealt before it was saved.
+    Commit(Vec<(String, String)>),
+    /// A player's entropy, in hex.
+    Entropy(String),
+    /// The decks the host committed to, revealed once the game is over.
+    Reveal(Reveal),
+}
+
+/// What a player keeps during the game to check the deal once it is over. Only the cards the
+/// player saw are kept: their hand as it was dealt, and every card they drew afterwards.
+#[derive(Debug, Clone)]
+pub struct Audit {
+    commitments: Vec<(String, String)>,
+    entropy: String,
+    hand: Vec<Card>,
+    /// Every draw, as the player's hand just before it and the cards drawn, in order.
+    draws: Vec<(Vec<Card>, Vec<Card>)>,
+}
+
+impl Audit {
+    /// Starts an audit of the deal, where `commitments` are what the host committed to, and
+    /// `entropy` is what this player sent.
+    pub fn new(commitments: Vec<(String, String)>, entropy: String) -> Audit {
+        Audit {
+            commitments,
+            entropy,
+            hand: Vec::new(),
+            draws: Vec::new(),
+        }
+    }
+
+    /// Records the hand that this player was dealt.
+    pub fn set_dealt(&mut self, hand: Vec<Card>) {
+        self.hand = hand;
+    }
+
+    /// Records that this player drew `drawn`, in order, when their hand was `hand`.
+    pub fn record_draw(&mut self, hand: Vec<Card>, drawn: Vec<Card>) {
+        self.draws.push((hand, drawn));
+    }
+
+    /// Checks `reveal` as the player named `name`: every revealed deck has to be a full deck
+    /// that matches the host's commitment, and this player's entropy has to have been used. The
+    /// hand they were dealt has to be what their deck gives once it is shuffled again, and every
+    /// card they drew has to be the next one in that deck, or in the deck made by shuffling their
+    /// discard pile with `reshuffle_rng` whenever it ran out. Returns an error with the first thing
+    /// that doesn't match.
+    pub fn check(&self, name: &str, reveal: &Reveal, hand_size: u8) -> error::Result<()> {
+        let unfair = |reason: String| Err(KingdomError::UnfairDeal(reason));
+        if reveal.seats.len() != self.commitments.len() {
+            return unfair("the host revealed a different number of decks".to_owned());
+        }
+        let mut full_deck = Card::full_deck();
+        full_deck.sort_by_key(Card::to_string);
+        for ((committed_name, hash), (player, _, deck)) in
+            self.commitments.iter().zip(&reveal.seats)
+        {
+            if committed_name != player {
+                return unfair(format!("no deck was revealed for '{committed_name}'"));
+            }
+            if commitment(deck, &reveal.salt) != *hash {
+                return unfair(format!("'{player}''s deck doesn't match the commitment"));
+            }
+            let mut sorted = deck.clone();
+            sorted.sort_by_key(Card::to_string);
+            if sorted != full_deck {
+                return unfair(format!("'{player}''s deck isn't a full deck"));
+            }
+        }
+        let entropy: Vec<(String, String)> = reveal
+            .seats
+            .iter()
+            .map(|(player, entropy, _)| (player.clone(), entropy.clone()))
+            .collect();
+        if !entropy.contains(&(name.to_owned(), self.entropy.clone())) {
+            return unfair("your entropy wasn't used".to_owned());
+        }
+        let Some((_, _, deck)) = reveal.seats.iter().find(|(player, ..)| player == name) else {
+            return unfair("your deck wasn't revealed".to_owned());
+        };
+        let mut deck = deck.clone();
+        mix(&mut deck, &entropy, name);
+        let dealt = deal(name, deck, hand_size);
+        if dealt.hand != self.hand {
+            return unfair("the cards you were dealt don't match your deck".to_owned());
+        }
+        let mut deck = dealt.deck;
+        let mut rng = reshuffle_rng(&entropy, name);
+        for (hand, drawn) in &self.draws {
+            /* Every card is in the deck, the hand or the discard pile, so the discard pile is
+             * every card that is in neither of the others. */
+            let discard_pile = full_deck
+                .iter()
+                .filter(|card| !hand.contains(card) && !deck.contains(card))
+                .copied()
+                .collect();
+            let cards = PlayerCards {
+                deck,
+                hand: hand.clone(),
+                discard_pile,
+            };
+            let mut player = Player::with_cards(name.to_owned(), cards);
+            if player.draw_with_reshuffle(drawn.len() as u8, &mut rng) != *drawn {
+                return unfair("the cards you drew don't match your deck".to_owned());
+            }
+            deck = player.cards().deck;
+        }
+        Ok(())
+    }
+}
+
+/// Returns `RANDOM_BYTES` random bytes from `rng`, written in hex.
+pub fn random_hex<R: Rng>(rng: &mut R) -> String {
+    let bytes: [u8; RANDOM_BYTES] = rng.gen();
+    hex(&bytes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Returns the commitment to `deck`, which is the SHA-256 hash of its card codes followed by
+/// `salt`, written in hex.
+pub fn commitment(deck: &[Card], salt: &str) -> String {
+    let codes: Vec<String> = deck.iter().map(Card::to_string).collect();
+    hex(&Sha256::digest(format!("{}|{salt}", codes.join(" "))))
+}
+
+/// Shuffles the deck of the player named `name` again with everyone's `entropy` (each player's
+/// name and entropy, in seat order). Every player's deck is shuffled differently.
+pub fn mix(deck: &mut [Card], entropy: &[(String, String)], name: &str) {
+    deck.shuffle(&mut seeded_rng(entropy, name));
+}
+
+/// Returns the random number generator that shuffles the discard pile of the player named `name`
+/// back into their deck, seeded from everyone's `entropy` (each player's name and entropy, in seat
+/// order). It is only used for that player's reshuffles, in order.
+pub fn reshuffle_rng(entropy: &[(String, String)], name: &str) -> ChaCha8Rng {
+    seeded_rng(entropy, &format!("{name}:reshuffle"))
+}
+
+fn seeded_rng(entropy: &[(String, String)], label: &str) -> ChaCha8Rng {
+    let mut hasher = Sha256::new();
+    for (player, entropy) in entropy {
+        hasher.update(format!("{player}:{entropy}\n"));
+    }
+    hasher.update(label);
+    ChaCha8Rng::from_seed(hasher.finalize().into())
+}
+
+/// Deals `deck` to the player named `name` the same way the host does, drawing their hand of
+/// `hand_size` cards from it.
+pub fn deal(name: &str, deck: Vec<Card>, hand_size: u8) -> PlayerCards {
+    let cards = PlayerCards {
+        deck,
+        ..Default::default()
+    };
+    let mut player = Player::with_cards(name.to_owned(), cards);
+    player.draw_n_times(hand_size);
+    player.cards()
+}
+
+/// Returns true if `entropy` is a player's entropy or a salt, written in hex.
+fn is_hex(entropy: &str) -> bool {
+    entropy.len() == RANDOM_BYTES * 2 && entropy.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl Display for DealMessage {
+    /// Format: `C,{P1_NAME}:{P1_HASH},...`, `E,{ENTROPY}` or
+    /// `R,{SALT},{P1_NAME}:{P1_ENTROPY}:{P1_DECK},...`, where each deck is a space-separated list
+    /// of card codes.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DealMessage::Commit(commitments) => {
+                write!(f, "C")?;
+                for (name, hash) in commitments {
+                    write!(f, ",{name}:{hash}")?;
+                }
+                Ok(())
+            }
+            DealMessage::Entropy(entropy) => write!(f, "E,{entropy}"),
+            DealMessage::Reveal(reveal) => {
+                write!(f, "R,{}", reveal.salt)?;
+                for (name, entropy, deck) in &reveal.seats {
+                    let codes: Vec<String> = deck.iter().map(Card::to_string).collect();
+                    write!(f, ",{name}:{entropy}:{}", codes.join(" "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for DealMessage {
+    type Err = ResponseParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        match parts.next() {
+            Some("C") => parts
+                .map(|part| match part.split_once(':') {
+                    Some((name, hash)) if is_hex(hash) => Ok((name.to_owned(), hash.to_owned())),
+                    _ => Err(ResponseParseError::InvalidDeal),
+                })
+                .collect::<Result<_, _>>()
+                .map(DealMessage::Commit),
+            Some("E") => match (parts.next(), parts.next()) {
+                (Some(entropy), None) if is_hex(entropy) => {
+                    Ok(DealMessage::Entropy(entropy.to_owned()))
+                }
+                _ => Err(ResponseParseError::InvalidDeal),
+            },
+            Some("R") => {
+                let salt = parts
+                    .next()
+                    .filter(|salt| is_hex(salt))
+                    .ok_or(ResponseParseError::InvalidDeal)?;
+                let seats = parts
+                    .map(|part| {
+                        let fields: Vec<&str> = part.split(':').collect();
+                        let [name, entropy, deck] = fields[..] else {
+                            return Err(ResponseParseError::InvalidDeal);
+                        };
+                        let deck = deck
+                            .split_whitespace()
+                            .map(Card::from_str)
+                            .collect::<Result<_, _>>()
+                            .map_err(|_| ResponseParseError::InvalidDeal)?;
+                        Ok((name.to_owned(), entropy.to_owned(), deck))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(DealMessage::Reveal(Reveal {
+                    salt: salt.to_owned(),
+                    seats,
+                }))
+            }
+            _ => Err(ResponseParseError::InvalidDeal),
+        }
+    }
+}
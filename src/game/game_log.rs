@@ -0,0 +1,411 @@
+//! This module contains the game log, which is an append-only record of everything that happens
+//! during a game. The host writes one game log per game, and game logs can be read back in order
+//! to replay a game. Every line of a game log is a single record, with its fields separated by
+//! `|`. Actions and game states use the same format as they do in responses.
+//!
+//! ```text
+//! KKLOG|1
+//! RULES|points=100,hand=5,king=10,queen=10,turn=120,reaction=20,teams=0,friendly_fire=0,fair_deal=0
+//! SEED|8214737394
+//! PLAYERS|2,ABC:100,DEF:100
+//! ACTION|1|S,0,ABC,|OK||0|2,ABC:100,DEF:100
+//! ACTION|2|K,5,ABC,DEF|OK|DEF:-15|0|2,ABC:100,DEF:85
+//! ACTION|3|E,0,ABC,|OK||1|2,ABC:100,DEF:85
+//! ```
+//!
+//! When a saved game is resumed, a `RESUME|{SEQ}` record is appended to its game log. Any
+//! actions from `SEQ` onwards were played after the game was saved, so they are dropped when the
+//! game log is read back.
+
+use std::{
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{
+    error::{self, KingdomError},
+    server::response::Action,
+};
+
+use super::{game_state::GameState, rules::Rules};
+
+/// Version of the game log format. This is written as the first record of every game log.
+pub const GAME_LOG_VERSION: u32 = 1;
+
+/// Whether or not the host accepted an action.
+#[derive(Debug, PartialEq)]
+pub enum ActionResult {
+    /// Format: `OK`.
+    Accepted,
+    /// Format: `ERR:{REASON}`.
+    Rejected(String),
+}
+
+impl ActionResult {
+    /// Converts from the result of handling an action to an `ActionResult`.
+    pub fn from_result(result: &error::Result<()>) -> ActionResult {
+        match result {
+            Ok(()) => ActionResult::Accepted,
+            /* Reasons must not contain the field separator or a newline. */
+            Err(err) => ActionResult::Rejected(err.to_string().replace(['|', '\n'], " ")),
+        }
+    }
+}
+
+impl Display for ActionResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionResult::Accepted => write!(f, "OK"),
+            ActionResult::Rejected(reason) => write!(f, "ERR:{reason}"),
+        }
+    }
+}
+
+impl FromStr for ActionResult {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "OK" {
+            Ok(ActionResult::Accepted)
+        } else if let Some(reason) = s.strip_prefix("ERR:") {
+            Ok(ActionResult::Rejected(reason.to_owned()))
+        } else {
+            Err(format!("invalid action result '{s}'"))
+        }
+    }
+}
+
+/// A single action that was played, along with what happened because of it.
+#[derive(Debug, PartialEq)]
+pub struct ActionRecord {
+    seq: u32,
+    action: Action,
+    result: ActionResult,
+    deltas: Vec<(String, i32)>,
+    game_state: GameState,
+}
+
+impl ActionRecord {
+    /// Returns the position of this action in the game log, starting from 1.
+    pub fn seq(&self) -> u32 {
+        self.seq
+    }
+
+    pub fn action(&self) -> &Action {
+        &self.action
+    }
+
+    pub fn result(&self) -> &ActionResult {
+        &self.result
+    }
+
+    /// Returns the change in points of every player affected by this action.
+    pub fn deltas(&self) -> &[(String, i32)] {
+        &self.deltas
+    }
+
+    /// Returns the game state after this action was handled.
+    pub fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+}
+
+/// A single line of a game log.
+#[derive(Debug, PartialEq)]
+pub enum LogRecord {
+    /// Format: `KKLOG|{VERSION}`.
+    Header(u32),
+    /// Format: `RULES|{RULES}`.
+    Rules(Rules),
+    /// Format: `SEED|{SEED}`.
+    Seed(u64),
+    /// Format: `PLAYERS|{GAME_STATE}`.
+    Players(GameState),
+    /// Format: `RESUME|{SEQ}`.
+    Resume(u32),
+    /// Format: `ACTION|{SEQ}|{ACTION}|{RESULT}|{DELTAS}|{TURN_INDEX}|{GAME_STATE}`, where
+    /// `DELTAS` is a comma-separated list of `{NAME}:{CHANGE_IN_POINTS}`.
+    Action(ActionRecord),
+}
+
+impl Display for LogRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogRecord::Header(version) => write!(f, "KKLOG|{version}"),
+            LogRecord::Rules(rules) => write!(f, "RULES|{rules}"),
+            LogRecord::Seed(seed) => write!(f, "SEED|{seed}"),
+            LogRecord::Players(game_state) => write!(f, "PLAYERS|{game_state}"),
+            LogRecord::Resume(seq) => write!(f, "RESUME|{seq}"),
+            LogRecord::Action(record) => {
+                let deltas: Vec<String> = record
+                    .deltas
+                    .iter()
+                    .map(|(name, delta)| format!("{name}:{delta:+}"))
+                    .collect();
+                write!(
+                    f,
+                    "ACTION|{}|{}|{}|{}|{}|{}",
+                    record.seq,
+                    record.action,
+                    record.result,
+                    deltas.join(","),
+                    record.game_state.current_player_index(),
+                    record.game_state
+                )
+            }
+        }
+    }
+}
+
+impl FromStr for LogRecord {
+    type Err = String;
+
+    /// Converts from a single line of a game log to a `LogRecord`. The error contains the
+    /// reason the line is invalid.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split('|');
+        let kind = fields.next().unwrap_or_default();
+        let fields: Vec<&str> = fields.collect();
+        let field = |index: usize| -> Result<&str, String> {
+            fields
+                .get(index)
+                .copied()
+                .ok_or_else(|| format!("missing field {} of {kind} record", index + 1))
+        };
+
+        match kind {
+            "KKLOG" => {
+                let version = field(0)?;
+                version
+                    .parse()
+                    .map(LogRecord::Header)
+                    .map_err(|_| format!("invalid version '{version}'"))
+            }
+            "RULES" => Rules::from_str(field(0)?)
+                .map(LogRecord::Rules)
+                .map_err(|err| err.to_string()),
+            "SEED" => {
+                let seed = field(0)?;
+                seed.parse()
+                    .map(LogRecord::Seed)
+                    .map_err(|_| format!("invalid seed '{seed}'"))
+            }
+            "PLAYERS" => GameState::from_str(field(0)?)
+                .map(LogRecord::Players)
+                .map_err(|err| err.to_string()),
+            "RESUME" => {
+                let seq = field(0)?;
+                seq.parse()
+                    .map(LogRecord::Resume)
+                    .map_err(|_| format!("invalid sequence number '{seq}'"))
+            }
+            "ACTION" => {
+                let seq = field(0)?;
+                let seq = seq
+                    .parse()
+                    .map_err(|_| format!("invalid sequence number '{seq}'"))?;
+                let action = Action::from_str(field(1)?).map_err(|err| err.to_string())?;
+                let result = ActionResult::from_str(field(2)?)?;
+                let deltas = parse_deltas(field(3)?)?;
+                let turn = field(4)?;
+                let turn = turn
+                    .parse()
+                    .map_err(|_| format!("invalid turn index '{turn}'"))?;
+                let mut game_state =
+                    GameState::from_str(field(5)?).map_err(|err| err.to_string())?;
+                game_state
+                    .set_current_player(turn)
+                    .map_err(|err| err.to_string())?;
+                Ok(LogRecord::Action(ActionRecord {
+                    seq,
+                    action,
+                    result,
+                    deltas,
+                    game_state,
+                }))
+            }
+            _ => Err(format!("unknown record type '{kind}'")),
+        }
+    }
+}
+
+fn parse_deltas(s: &str) -> Result<Vec<(String, i32)>, String> {
+    s.split(',')
+        .filter(|delta| !delta.is_empty())
+        .map(|delta| {
+            let (name, change) = delta
+                .split_once(':')
+                .ok_or_else(|| format!("invalid delta '{delta}'"))?;
+            let change = change
+                .trim_start_matches('+')
+                .parse()
+                .map_err(|_| format!("invalid delta '{delta}'"))?;
+            Ok((name.to_owned(), change))
+        })
+        .collect()
+}
+
+/// Writer for a game log. Every record is flushed as soon as it is written, so the game log is
+/// complete up to the last action even if the host is closed in the middle of a game.
+pub struct GameLog {
+    writer: Box<dyn Write + Send>,
+    next_seq: u32,
+}
+
+impl GameLog {
+    /// Creates the game log at `path` (appending if it already exists), then writes the header,
+    /// rules, seed and starting players.
+    pub fn create(
+        path: &Path,
+        rules: &Rules,
+        seed: u64,
+        game_state: &GameState,
+    ) -> error::Result<GameLog> {
+        let file: File = OpenOptions::new().create(true).append(true).open(path)?;
+        GameLog::from_writer(Box::new(file), rules, seed, game_state)
+    }
+
+    /// Same as `GameLog::create`, but writes to `writer` instead of a file.
+    pub fn from_writer(
+        writer: Box<dyn Write + Send>,
+        rules: &Rules,
+        seed: u64,
+        game_state: &GameState,
+    ) -> error::Result<GameLog> {
+        let mut game_log = GameLog {
+            writer,
+            next_seq: 1,
+        };
+        game_log.write_record(&LogRecord::Header(GAME_LOG_VERSION))?;
+        game_log.write_record(&LogRecord::Rules(rules.to_owned()))?;
+        game_log.write_record(&LogRecord::Seed(seed))?;
+        game_log.write_record(&LogRecord::Players(game_state.to_owned()))?;
+        Ok(game_log)
+    }
+
+    /// Reopens the game log at `path` to continue a saved game, where `next_seq` is the sequence
+    /// number of the first action played after the game was saved.
+    pub fn resume(path: &Path, next_seq: u32) -> error::Result<GameLog> {
+        let file: File = OpenOptions::new().append(true).open(path)?;
+        let mut game_log = GameLog {
+            writer: Box::new(file),
+            next_seq,
+        };
+        game_log.write_record(&LogRecord::Resume(next_seq))?;
+        Ok(game_log)
+    }
+
+    /// Returns the sequence number that the next action will be logged with.
+    pub fn next_seq(&self) -> u32 {
+        self.next_seq
+    }
+
+    /// Records `action`, whether or not it was accepted, and how it changed the game state from
+    /// `before` to `after`.
+    pub fn log_action(
+        &mut self,
+        action: &Action,
+        result: &error::Result<()>,
+        before: &GameState,
+        after: &GameState,
+    ) -> error::Result<()> {
+        let record = ActionRecord {
+            seq: self.next_seq,
+            action: action.to_owned(),
+            result: ActionResult::from_result(result),
+            deltas: GameState::points_deltas(before, after),
+            game_state: after.to_owned(),
+        };
+        self.next_seq += 1;
+        self.write_record(&LogRecord::Action(record))
+    }
+
+    fn write_record(&mut self, record: &LogRecord) -> error::Result<()> {
+        writeln!(self.writer, "{record}")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A complete game, read back from a game log.
+#[derive(Debug, PartialEq)]
+pub struct GameRecord {
+    rules: Rules,
+    seed: u64,
+    players: GameState,
+    actions: Vec<ActionRecord>,
+}
+
+impl GameRecord {
+    /// Reads and parses the game log at `path`.
+    pub fn read(path: &Path) -> error::Result<GameRecord> {
+        GameRecord::from_str(&fs::read_to_string(path)?)
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the game state at the start of the game.
+    pub fn players(&self) -> &GameState {
+        &self.players
+    }
+
+    pub fn actions(&self) -> &[ActionRecord] {
+        &self.actions
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = KingdomError;
+
+    /// Converts from the contents of a game log to a `GameRecord`. The header, rules, seed and
+    /// players must come before any actions.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut version = None;
+        let mut rules = None;
+        let mut seed = None;
+        let mut players = None;
+        let mut actions = Vec::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let line_num = index + 1;
+            let invalid = |reason: String| KingdomError::InvalidGameLog(line_num, reason);
+            if line.is_empty() {
+                continue;
+            }
+            match LogRecord::from_str(line).map_err(invalid)? {
+                LogRecord::Header(GAME_LOG_VERSION) => version = Some(GAME_LOG_VERSION),
+                LogRecord::Header(other) => {
+                    return Err(invalid(format!("unsupported version {other}")))
+                }
+                LogRecord::Rules(value) => rules = Some(value),
+                LogRecord::Seed(value) => seed = Some(value),
+                LogRecord::Players(value) => players = Some(value),
+                LogRecord::Resume(seq) => actions.retain(|record: &ActionRecord| record.seq < seq),
+                LogRecord::Action(record) => {
+                    if version.is_none() || rules.is_none() || seed.is_none() || players.is_none() {
+                        return Err(invalid(String::from("action before game setup")));
+                    }
+                    actions.push(record);
+                }
+            }
+        }
+
+        let missing = |record: &str| KingdomError::InvalidGameLog(0, format!("missing {record}"));
+        version.ok_or_else(|| missing("header"))?;
+        Ok(GameRecord {
+            rules: rules.ok_or_else(|| missing("rules"))?,
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            players: players.ok_or_else(|| missing("players"))?,
+            actions,
+        })
+    }
+}
@@ -2,11 +2,18 @@
 //! The GameState struct should only be used server side, and there should only
 //! be one instance of a GameState struct per server.
 
-use std::{cmp::max, fmt::Display};
+use std::{fmt::Display, str::FromStr};
 
-use crate::{server::constants::MAX_PLAYERS, ui::get_num_input};
+use crate::{
+    error::{self, KingdomError},
+    server::{
+        constants::MAX_PLAYERS,
+        response::{Action, ActionType, ResponseParseError},
+    },
+    ui::input::InputSource,
+};
 
-use super::player::Player;
+use super::{player::Player, rules::Rules};
 
 /// This is a struct for representing players server side, since it isn't necessary for the
 /// server to know which cards each player has, as long as everything is being validated server
@@ -15,11 +22,17 @@ use super::player::Player;
 pub struct PlayerDetails {
     name: String,
     points: u16,
+    /// Team the player is on, starting from 1, or `None` if there are no teams.
+    team: Option<u8>,
 }
 
 impl PlayerDetails {
     pub fn new(name: String, points: u16) -> PlayerDetails {
-        PlayerDetails { name, points }
+        PlayerDetails {
+            name,
+            points,
+            team: None,
+        }
     }
 
     pub fn name(&self) -> &str {
@@ -33,11 +46,32 @@ impl PlayerDetails {
     pub fn set_points(&mut self, points: u16) {
         self.points = points;
     }
+
+    pub fn team(&self) -> Option<u8> {
+        self.team
+    }
+
+    pub fn set_team(&mut self, team: Option<u8>) {
+        self.team = team;
+    }
+
+    /// Returns true if the player has no points left, and so is out of the game.
+    pub fn is_eliminated(&self) -> bool {
+        self.points == 0
+    }
 }
 
 impl Display for PlayerDetails {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} with {} points.", self.name(), self.points())
+        match self.team {
+            Some(team) => write!(
+                f,
+                "{} with {} points, on team {team}.",
+                self.name(),
+                self.points()
+            ),
+            None => write!(f, "{} with {} points.", self.name(), self.points()),
+        }
     }
 }
 
@@ -46,6 +80,7 @@ impl From<Player> for PlayerDetails {
         Self {
             name: String::from(value.name()),
             points: value.points(),
+            team: None,
         }
     }
 }
@@ -86,31 +121,84 @@ impl GameState {
         self.players.len()
     }
 
-    pub fn get_player(&self, pos: usize) -> &PlayerDetails {
-        let num_players = 0..self.players.len();
-        if !num_players.contains(&pos) {
-            panic!("Trying to access invalid player index");
+    /// Puts every player on one of `num_teams` teams, alternating in seat order. If `num_teams`
+    /// is 0, nobody is on a team.
+    pub fn assign_teams(&mut self, num_teams: u8) {
+        for (index, player) in self.players.iter_mut().enumerate() {
+            let team = (num_teams != 0).then(|| (index % num_teams as usize) as u8 + 1);
+            player.set_team(team);
         }
+    }
+
+    /// Returns true if the players named `a` and `b` are on the same team. Players are always
+    /// teammates of themselves, but only if there are teams.
+    pub fn are_teammates(&self, a: &str, b: &str) -> bool {
+        let team = |name| self.player_by_name(name).and_then(PlayerDetails::team);
+        team(a).is_some() && team(a) == team(b)
+    }
+
+    /// Groups `placements`, every player's name from first place to last, into tiers of players
+    /// who place together. Teammates place together, where the best placed of them placed.
+    /// Without teams, every player is in a tier of their own.
+    pub fn placement_tiers(&self, placements: &[String]) -> Vec<Vec<String>> {
+        let mut tiers: Vec<Vec<String>> = Vec::new();
+        for name in placements {
+            match tiers
+                .iter_mut()
+                .find(|tier| self.are_teammates(&tier[0], name))
+            {
+                Some(tier) => tier.push(name.clone()),
+                None => tiers.push(vec![name.clone()]),
+            }
+        }
+        tiers
+    }
 
-        self.players.get(pos).unwrap()
+    /// Returns every player on `team`, in seat order.
+    pub fn team_members(&self, team: u8) -> Vec<&PlayerDetails> {
+        self.players
+            .iter()
+            .filter(|player| player.team() == Some(team))
+            .collect()
     }
 
-    pub fn get_player_with_prompt(&self) -> &PlayerDetails {
+    /// Returns the player at index `pos`, or an error if there is no player at that index.
+    pub fn get_player(&self, pos: usize) -> error::Result<&PlayerDetails> {
+        self.players
+            .get(pos)
+            .ok_or(KingdomError::InvalidPlayerIndex(pos))
+    }
+
+    /// Prompts for a player, answered from `input`, and returns them.
+    pub fn get_player_with_prompt(
+        &self,
+        input: &mut dyn InputSource,
+    ) -> error::Result<&PlayerDetails> {
         self.print_all_players();
-        let player_pos = get_num_input("Choose a player: ", 1, self.num_players() as i32);
+        let player_pos = input.get_num_input("Choose a player: ", 1, self.num_players() as i32);
         self.get_player((player_pos - 1) as usize)
     }
 
-    pub fn subtract_points_from_player(&mut self, name: &str, num_points: u16) {
-        let player = self.player_by_name_mut(name).unwrap();
-        let points = max(0i16, player.points() as i16 - num_points as i16);
-        player.set_points(points as u16);
+    /// Subtracts `num_points` from the player named `name`, stopping at zero.
+    pub fn subtract_points_from_player(
+        &mut self,
+        name: &str,
+        num_points: u16,
+    ) -> error::Result<()> {
+        let player = self
+            .player_by_name_mut(name)
+            .ok_or_else(|| KingdomError::PlayerNotFound(name.to_owned()))?;
+        player.set_points(player.points().saturating_sub(num_points));
+        Ok(())
     }
 
-    pub fn add_points_to_player(&mut self, name: &str, num_points: u16) {
-        let player = self.player_by_name_mut(name).unwrap();
-        let points = player.points() + num_points;
-        player.set_points(points);
+    /// Adds `num_points` to the player named `name`.
+    pub fn add_points_to_player(&mut self, name: &str, num_points: u16) -> error::Result<()> {
+        let player = self
+            .player_by_name_mut(name)
+            .ok_or_else(|| KingdomError::PlayerNotFound(name.to_owned()))?;
+        player.set_points(player.points().saturating_add(num_points));
+        Ok(())
     }
 
     pub fn player_by_name(&self, name: &str) -> Option<&PlayerDetails> {
@@ -121,17 +209,48 @@ impl GameState {
         self.players.iter_mut().find(|player| player.name == name)
     }
 
-    pub fn current_player(&self) -> &PlayerDetails {
-        self.players.get(self.current_player).unwrap()
+    /// Returns the player whose turn it is, or `None` if there are no players.
+    pub fn current_player(&self) -> Option<&PlayerDetails> {
+        self.players.get(self.current_player)
     }
 
     pub fn current_player_index(&self) -> usize {
         self.current_player
     }
 
+    /// Sets whose turn it is, or returns an error if there is no player at `index`.
+    pub fn set_current_player(&mut self, index: usize) -> error::Result<()> {
+        if index >= self.players.len() {
+            return Err(KingdomError::InvalidPlayerIndex(index));
+        }
+        self.current_player = index;
+        Ok(())
+    }
+
+    /// Moves on to the next player who hasn't been eliminated. If every player has been
+    /// eliminated, this moves on to the next player.
     pub fn move_next_player(&mut self) {
         let num_players = self.players.len();
-        self.current_player = (self.current_player + 1) % (num_players);
+        for _ in 0..num_players {
+            self.current_player = (self.current_player + 1) % (num_players);
+            if !self.players[self.current_player].is_eliminated() {
+                break;
+            }
+        }
+    }
+
+    /// Returns the winner once every other player has been eliminated, or `None` if the game
+    /// isn't over yet. When there are teams, the game is over once every player who is left is
+    /// on the same team, and the winner is the first of them in seat order.
+    pub fn winner(&self) -> Option<&PlayerDetails> {
+        if self.players.len() < 2 {
+            return None;
+        }
+        let mut remaining = self.players.iter().filter(|player| !player.is_eliminated());
+        let winner = remaining.next()?;
+        remaining
+            .all(|player| player.team().is_some() && player.team() == winner.team())
+            .then_some(winner)
     }
 
     pub fn print_all_players(&self) {
@@ -156,4 +275,158 @@ impl GameState {
 
         true
     }
+
+    /// Returns the name of the player healed by the Queen `action`. Queens heal whoever played
+    /// them, or the teammate they were played on.
+    pub fn queen_target<'a>(&self, action: &'a Action) -> &'a str {
+        if self.are_teammates(action.from_player(), action.to_player()) {
+            action.to_player()
+        } else {
+            action.from_player()
+        }
+    }
+
+    /// Checks that `action` can be applied, following `rules`. Returns an error if a player in
+    /// the action doesn't exist, if the cards played couldn't have made the action, or if a King
+    /// is played against a teammate without friendly fire.
+    pub fn check_action(&self, action: &Action, rules: &Rules) -> error::Result<()> {
+        if self.player_by_name(action.from_player()).is_none() {
+            return Err(KingdomError::PlayerNotFound(
+                action.from_player().to_owned(),
+            ));
+        }
+        if let Some(play) = action.play().filter(|_| !action.matches_play()) {
+            return Err(KingdomError::InvalidCard(play.to_string()));
+        }
+        let is_king = matches!(action.action_type(), ActionType::PlayKing);
+        if is_king
+            && !rules.friendly_fire
+            && self.are_teammates(action.from_player(), action.to_player())
+        {
+            return Err(KingdomError::FriendlyFire(action.to_player().to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Applies the effects of `action` to the game state, following `rules`. This is used by
+    /// the host, clients and replays, so that every copy of the game state stays the same.
+    /// Returns an error if the action can't be applied (see `GameState::check_action`), or if
+    /// the action has no effect that can be applied.
+    pub fn apply_action(&mut self, action: &Action, rules: &Rules) -> error::Result<()> {
+        self.check_action(action, rules)?;
+
+        match action.action_type() {
+            ActionType::PlayKing => {
+                let damage = rules.king_damage + action.attachment();
+                self.subtract_points_from_player(action.to_player(), damage)
+            }
+            ActionType::PlayQueen => {
+                let healed_points = rules.queen_heal + action.attachment();
+                let target = self.queen_target(action);
+                self.add_points_to_player(target, healed_points)
+            }
+            ActionType::PlayJack => {
+                /* The host fills in how many points the negated card took away. */
+                self.add_points_to_player(action.from_player(), action.attachment())
+            }
+            ActionType::TurnStart => {
+                /* The host can pass over a player between turns (e.g. when it kicks them), so
+                 * every turn is whoever the host says it is. */
+                if let Some(index) = self
+                    .players
+                    .iter()
+                    .position(|player| player.name() == action.from_player())
+                {
+                    self.current_player = index;
+                }
+                Ok(())
+            }
+            ActionType::PlayNumber | ActionType::GameOver => Ok(()),
+            ActionType::TurnEnd => {
+                self.move_next_player();
+                Ok(())
+            }
+            ActionType::SetPoints => {
+                let player = self
+                    .player_by_name_mut(action.from_player())
+                    .ok_or_else(|| KingdomError::PlayerNotFound(action.from_player().to_owned()))?;
+                player.set_points(action.attachment());
+                Ok(())
+            }
+            other => Err(KingdomError::UnsupportedAction(other.to_owned())),
+        }
+    }
+
+    /// Returns the change in points of every player whose points are different in `after`,
+    /// in the order they are seated.
+    pub fn points_deltas(before: &GameState, after: &GameState) -> Vec<(String, i32)> {
+        after
+            .players
+            .iter()
+            .filter_map(|player| {
+                let old_points = before.player_by_name(player.name())?.points();
+                let delta = player.points() as i32 - old_points as i32;
+                (delta != 0).then(|| (player.name().to_owned(), delta))
+            })
+            .collect()
+    }
+}
+
+impl Display for GameState {
+    /// Format: `{NUM_PLAYERS},{P1_NAME}:{P1_POINTS},{P2_NAME}:{P2_POINTS}...`. This is the same
+    /// format used by `RES,GAME` responses. When there are teams, each player is followed by
+    /// their team (e.g. `{P1_NAME}:{P1_POINTS}:{P1_TEAM}`).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.num_players())?;
+        for player in self.players.iter() {
+            write!(f, ",{}:{}", player.name(), player.points())?;
+            if let Some(team) = player.team() {
+                write!(f, ":{team}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameState {
+    type Err = ResponseParseError;
+
+    /// Converts from `{NUM_PLAYERS},{P1_NAME}:{P1_POINTS}[:{P1_TEAM}]...` to a `GameState`. Any
+    /// values after the last player are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let num_players = match parts.next() {
+            Some(num_players) if !num_players.is_empty() => num_players,
+            _ => return Err(ResponseParseError::ExpectedNumPlayers),
+        };
+        let num_players = match num_players.parse::<u8>() {
+            Ok(n) => n,
+            Err(_) => return Err(ResponseParseError::ParseIntError),
+        };
+
+        let mut game_state = GameState::new();
+        for _ in 0..num_players {
+            let player = parts
+                .next()
+                .ok_or(ResponseParseError::InvalidNumArguments)?;
+            let mut fields = player.split(':');
+            let (Some(name), Some(points)) = (fields.next(), fields.next()) else {
+                return Err(ResponseParseError::InvalidNumArguments);
+            };
+            let points = match points.parse::<u16>() {
+                Ok(n) => n,
+                Err(_) => return Err(ResponseParseError::ParseIntError),
+            };
+            let team = match fields.next().map(str::parse::<u8>) {
+                Some(Ok(team)) => Some(team),
+                Some(Err(_)) => return Err(ResponseParseError::ParseIntError),
+                None => None,
+            };
+            let mut player = PlayerDetails::new(name.to_string(), points);
+            player.set_team(team);
+            game_state.add_player(player);
+        }
+
+        Ok(game_state)
+    }
 }
@@ -1,3 +1,12 @@
 pub mod card;
+pub mod fair_deal;
+pub mod game_log;
 pub mod game_state;
 pub mod player;
+pub mod profiles;
+pub mod referee;
+pub mod rules;
+#[cfg(test)]
+pub mod scenario;
+pub mod snapshot;
+pub mod staged;
@@ -1,23 +1,74 @@
 //! This module contains the code for handling player state client side.
 
-use std::panic;
+use std::fmt::Display;
+use std::str::FromStr;
 
-use crate::game::card::{Card, Suit, Value};
+use crate::error::{self, KingdomError};
+use crate::game::card::{Card, CardToken, Play, Value};
 use crate::server::constants::DECK_SIZE;
 use crate::server::response::{Action, ActionType};
-use crate::ui::{get_bool_input, get_num_input};
+use crate::ui::input::InputSource;
 
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, Rng};
 
 use super::game_state::GameState;
+use super::staged::StagedAction;
 
 pub struct Player {
     name: String,
     points: u16,
     hand: Vec<Card>,
     deck: Vec<Card>,
-    _discard_pile: Vec<Card>,
+    discard_pile: Vec<Card>,
+}
+
+/// Every card that a player owns, split into their deck, hand and discard pile. This is used for
+/// saving a player's cards and giving them back when a game is resumed.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PlayerCards {
+    pub deck: Vec<Card>,
+    pub hand: Vec<Card>,
+    pub discard_pile: Vec<Card>,
+}
+
+impl Display for PlayerCards {
+    /// Format: `{DECK},{HAND},{DISCARD_PILE}`, where each pile is a space-separated list of card
+    /// codes (e.g. `KH 10S`).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pile_to_string = |pile: &[Card]| {
+            pile.iter()
+                .map(Card::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        write!(
+            f,
+            "{},{},{}",
+            pile_to_string(&self.deck),
+            pile_to_string(&self.hand),
+            pile_to_string(&self.discard_pile)
+        )
+    }
+}
+
+impl FromStr for PlayerCards {
+    type Err = KingdomError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_pile = |pile: &str| -> error::Result<Vec<Card>> {
+            pile.split_whitespace().map(Card::from_str).collect()
+        };
+        let piles: Vec<&str> = s.split(',').collect();
+        let [deck, hand, discard_pile] = piles[..] else {
+            return Err(KingdomError::InvalidCard(s.to_owned()));
+        };
+        Ok(PlayerCards {
+            deck: parse_pile(deck)?,
+            hand: parse_pile(hand)?,
+            discard_pile: parse_pile(discard_pile)?,
+        })
+    }
 }
 
 impl Player {
@@ -26,7 +77,7 @@ impl Player {
             name: String::new(),
             hand: Vec::with_capacity(DECK_SIZE),
             deck: Vec::with_capacity(DECK_SIZE),
-            _discard_pile: Vec::with_capacity(DECK_SIZE),
+            discard_pile: Vec::with_capacity(DECK_SIZE),
             points: 100,
         };
 
@@ -42,7 +93,7 @@ impl Player {
             name,
             hand: Vec::with_capacity(DECK_SIZE),
             deck: Vec::with_capacity(DECK_SIZE),
-            _discard_pile: Vec::with_capacity(DECK_SIZE),
+            discard_pile: Vec::with_capacity(DECK_SIZE),
             points: 100,
         };
 
@@ -53,6 +104,20 @@ impl Player {
         player
     }
 
+    /// Returns a player named `name` who owns `cards`, without drawing any of them (e.g. when
+    /// a tournament table deals from its own shuffled deck).
+    pub fn with_cards(name: String, cards: PlayerCards) -> Player {
+        let mut player = Player {
+            name,
+            hand: Vec::with_capacity(DECK_SIZE),
+            deck: Vec::with_capacity(DECK_SIZE),
+            discard_pile: Vec::with_capacity(DECK_SIZE),
+            points: 100,
+        };
+        player.set_cards(cards);
+        player
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -65,6 +130,22 @@ impl Player {
         self.points
     }
 
+    /// Returns a copy of every card the player owns.
+    pub fn cards(&self) -> PlayerCards {
+        PlayerCards {
+            deck: self.deck.clone(),
+            hand: self.hand.clone(),
+            discard_pile: self.discard_pile.clone(),
+        }
+    }
+
+    /// Replaces every card the player owns with `cards` (e.g. when a game is resumed).
+    pub fn set_cards(&mut self, cards: PlayerCards) {
+        self.deck = cards.deck;
+        self.hand = cards.hand;
+        self.discard_pile = cards.discard_pile;
+    }
+
     pub fn hand(&self) -> &Vec<Card> {
         &self.hand
     }
@@ -73,11 +154,10 @@ impl Player {
         self.hand.len()
     }
 
-    pub fn get_card_in_hand(&self, n: usize) -> &Card {
-        if n >= self.hand_size() {
-            panic!("Invalid index of hand");
-        }
-        self.hand.get(n).unwrap()
+    /// Returns the card at index `n` of the player's hand, or an error if there is no card at
+    /// that index.
+    pub fn get_card_in_hand(&self, n: usize) -> error::Result<&Card> {
+        self.hand.get(n).ok_or(KingdomError::InvalidHandIndex(n))
     }
 
     pub fn get_card_with_prompt(&self) -> &Card {
@@ -88,9 +168,11 @@ impl Player {
     //     todo!()
     // }
 
+    /// Moves `card` from the player's hand to their discard pile. Returns `None` if the card
+    /// isn't in their hand.
     pub fn remove_card_from_hand(&mut self, card: &Card) -> Option<()> {
         if let Some(index) = self.hand.iter().position(|c| c == card) {
-            self.hand.remove(index);
+            self.discard_pile.push(self.hand.remove(index));
             Some(())
         } else {
             None
@@ -98,31 +180,7 @@ impl Player {
     }
 
     fn init_deck(&mut self) {
-        for suit in [Suit::Spades, Suit::Clubs, Suit::Hearts, Suit::Diamonds] {
-            for value in [
-                Value::Ace,
-                Value::Two,
-                Value::Three,
-                Value::Four,
-                Value::Five,
-                Value::Six,
-                Value::Seven,
-                Value::Eight,
-                Value::Nine,
-                Value::Ten,
-                Value::Jack,
-                Value::Queen,
-                Value::King,
-            ] {
-                self.deck.push(Card::new(suit, value));
-            }
-        }
-        self.deck.push(Card::new(Suit::Hearts, Value::Seven));
-        self.deck.push(Card::new(Suit::Spades, Value::Five));
-        self.deck.push(Card::new(Suit::Hearts, Value::Eight));
-        self.deck.push(Card::new(Suit::Spades, Value::Two));
-        self.deck.push(Card::new(Suit::Hearts, Value::Queen));
-        self.deck.push(Card::new(Suit::Spades, Value::King));
+        self.deck.extend(Card::full_deck());
     }
 
     fn shuffle_deck(&mut self) {
@@ -147,99 +205,309 @@ impl Player {
         }
     }
 
-    /// Prompts the player to play a card in their hand or end their turn. Returns None if no
-    /// playable card is selected.
-    pub fn get_action(&mut self, game_state: &GameState) -> Option<Action> {
+    /// Prompts the player to play a card in their hand or end their turn. Returns `Ok(None)` if
+    /// no playable card is selected. The action is only staged, so that the player can still
+    /// cancel it (see `StagedAction`). If the player cancels part way through (e.g. while choosing
+    /// the discards for a Number), or the input deadline passes, every card is put back and an
+    /// error is returned. Every prompt is answered from `input`.
+    pub fn get_action(
+        &mut self,
+        game_state: &GameState,
+        input: &mut dyn InputSource,
+    ) -> error::Result<Option<StagedAction>> {
+        let cards = self.cards();
         // Prompts the player to choose a card from their hand.
-        if let Some(action_card) = self.choose_card() {
-            // If the card is a number, the player discards the amount of that number then draws the
-            // same amount as that number.
-            if action_card.value().is_number() {
-                self.handle_number(&action_card)
-            }
-            // If the card is a King or Queen, then the player is prompted to add an attachment.
-            else if matches!(action_card.value(), Value::King | Value::Queen) {
-                Some(self.handle_king_queen(&action_card, game_state))
-            } else {
-                None
-            }
+        let Some(action_card) = self.choose_card(input) else {
+            return Ok(None);
+        };
+        // If the card is a number, the player discards the amount of that number. The host deals
+        // them the same amount once it accepts the action.
+        let action = if let Some(num_value) = action_card.value().to_number_value() {
+            self.handle_number(&action_card, num_value, input)
+        }
+        // If the card is a King or Queen, then the player is prompted to add an attachment.
+        else if matches!(action_card.value(), Value::King | Value::Queen) {
+            self.handle_king_queen(&action_card, game_state, input)
         } else {
-            None
+            return Ok(None);
+        };
+        match action {
+            Ok(action) => Ok(Some(StagedAction::new(action, cards))),
+            Err(err) => {
+                self.set_cards(cards);
+                Err(err)
+            }
         }
     }
 
-    fn handle_number(&mut self, action_card: &Card) -> Option<Action> {
-        if self.play_number(action_card).is_some() {
-            Some(Action::new(
-                ActionType::PlayNumber,
-                action_card.value().to_number_value(),
-                self.name.to_owned(),
-                String::new(),
-            ))
-        } else {
-            None
+    fn handle_number(
+        &mut self,
+        action_card: &Card,
+        num_value: u16,
+        input: &mut dyn InputSource,
+    ) -> error::Result<Action> {
+        let discards = self.play_number(action_card, num_value, input)?;
+        Ok(Action::from_play(
+            Play::new(*action_card, None),
+            self.name.to_owned(),
+            String::new(),
+        )
+        .with_discards(discards))
+    }
+
+    /// Returns true if the player has a Jack in their hand to react with.
+    pub fn has_jack(&self) -> bool {
+        self.hand
+            .iter()
+            .any(|card| matches!(card.value(), Value::Jack))
+    }
+
+    /// Returns the first Jack in the player's hand, if they have one.
+    pub fn jack(&self) -> Option<Card> {
+        self.hand
+            .iter()
+            .find(|card| matches!(card.value(), Value::Jack))
+            .copied()
+    }
+
+    /// Moves a Jack from the player's hand to their discard pile. Returns `None` if they don't
+    /// have one.
+    pub fn discard_jack(&mut self) -> Option<()> {
+        let jack = self.jack()?;
+        self.remove_card_from_hand(&jack)
+    }
+
+    /// Plays a King or Queen written as `play` (e.g. `KH+5S`) against `to_player`, moving its
+    /// cards from the player's hand to their discard pile. Returns an error, without moving any
+    /// cards, if the cards aren't in the player's hand or aren't a King or Queen.
+    pub fn play_cards(&mut self, play: &Play, to_player: &str) -> error::Result<Action> {
+        if !matches!(play.card.value(), Value::King | Value::Queen) {
+            return Err(KingdomError::InvalidPlay(format!(
+                "only Kings and Queens can be played against a player, not '{}'",
+                play.card
+            )));
+        }
+        if play
+            .attachment
+            .is_some_and(|attachment| !attachment.value().is_number())
+        {
+            return Err(KingdomError::InvalidPlay(
+                "only Numbers can be attached".to_owned(),
+            ));
+        }
+        let cards = [Some(play.card), play.attachment];
+        for card in cards.iter().flatten() {
+            if !self.hand.contains(card) {
+                return Err(KingdomError::CardNotInHand(card.to_string()));
+            }
+        }
+        for card in cards.iter().flatten() {
+            self.remove_card_from_hand(card);
         }
+        Ok(Action::from_play(
+            *play,
+            self.name.to_owned(),
+            to_player.to_owned(),
+        ))
     }
 
-    fn handle_king_queen(&mut self, action_card: &Card, game_state: &GameState) -> Action {
-        let attachment = self.play_king_queen();
-        let action_type = ActionType::from_card(action_card);
-        let to_player = game_state.get_player_with_prompt();
+    fn handle_king_queen(
+        &mut self,
+        action_card: &Card,
+        game_state: &GameState,
+        input: &mut dyn InputSource,
+    ) -> error::Result<Action> {
+        let attachment = self.play_king_queen(input)?;
+        let to_player = game_state.get_player_with_prompt(input)?;
         self.remove_card_from_hand(action_card);
-        Action::new(
-            action_type,
-            attachment,
+        Ok(Action::from_play(
+            Play::new(*action_card, attachment),
             self.name.to_owned(),
             to_player.name().to_owned(),
+        ))
+    }
+
+    /// Plays the Number `card`, discarding every card in `discards`. The same number of cards are
+    /// drawn once the host accepts the action (see `Player::apply_cards`). Returns an error, without
+    /// moving any cards, if `card` isn't a Number in the player's hand, or if `discards` aren't
+    /// exactly as many other cards in their hand as the Number is worth.
+    pub fn play_number_with_discards(
+        &mut self,
+        card: Card,
+        discards: &[Card],
+    ) -> error::Result<Action> {
+        let Some(num_value) = card.value().to_number_value() else {
+            return Err(KingdomError::InvalidPlay(format!(
+                "'{card}' is not a Number"
+            )));
+        };
+        if discards.len() != num_value as usize {
+            return Err(KingdomError::InvalidPlay(format!(
+                "'{card}' needs {num_value} cards discarded, not {}",
+                discards.len()
+            )));
+        }
+        for (i, discard) in discards.iter().enumerate() {
+            if *discard == card || discards[..i].contains(discard) {
+                return Err(KingdomError::InvalidPlay(format!(
+                    "'{discard}' can only be used once"
+                )));
+            }
+        }
+        for card in discards.iter().chain([&card]) {
+            if !self.hand.contains(card) {
+                return Err(KingdomError::CardNotInHand(card.to_string()));
+            }
+        }
+        for card in discards.iter().chain([&card]) {
+            self.remove_card_from_hand(card);
+        }
+        Ok(
+            Action::from_play(Play::new(card, None), self.name.to_owned(), String::new())
+                .with_discards(discards.to_vec()),
         )
     }
 
-    fn play_number(&mut self, card: &Card) -> Option<()> {
+    /// Draws `n` cards. Whenever the deck runs out, the discard pile is sorted, shuffled with `rng`
+    /// and becomes the deck, so the new deck only depends on which cards were discarded and on
+    /// `rng` (see `fair_deal::Audit`). Returns the cards that were drawn, which are fewer than `n`
+    /// only if the player has no cards left outside their hand.
+    pub fn draw_with_reshuffle<R: Rng>(&mut self, n: u8, rng: &mut R) -> Vec<Card> {
+        let mut drawn = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            if self.deck.is_empty() {
+                self.deck.append(&mut self.discard_pile);
+                self.deck.sort_by_key(Card::to_string);
+                self.deck.shuffle(rng);
+            }
+            let Some(card) = self.deck.pop() else {
+                break;
+            };
+            self.hand.push(card);
+            drawn.push(card);
+        }
+        drawn
+    }
+
+    /// Moves the cards that `action` plays out of the player's hand, as one transaction: either
+    /// every card moves, or an error is returned and none do. A Number's discards go to the
+    /// discard pile with it, then the same number of cards are drawn with `draw_with_reshuffle`.
+    /// Actions that aren't made by playing cards (e.g. a `TurnEnd`) move no cards, but every other
+    /// action has to say which cards were played.
+    pub fn apply_cards<R: Rng>(&mut self, action: &Action, rng: &mut R) -> error::Result<()> {
+        let Some(play) = action.play() else {
+            return match action.action_type() {
+                ActionType::TurnStart
+                | ActionType::TurnEnd
+                | ActionType::GameOver
+                | ActionType::SetPoints
+                | ActionType::Status
+                | ActionType::None => Ok(()),
+                _ => Err(KingdomError::InvalidPlay(
+                    "the cards that were played weren't given".to_owned(),
+                )),
+            };
+        };
+        if !action.matches_play() {
+            return Err(KingdomError::InvalidCard(play.to_string()));
+        }
+        let cards = self.cards();
+        let result = match action.action_type() {
+            ActionType::PlayKing | ActionType::PlayQueen => {
+                self.play_cards(play, action.to_player()).map(|_| ())
+            }
+            ActionType::PlayNumber => self
+                .play_number_with_discards(play.card, action.discards())
+                .map(|_| {
+                    self.draw_with_reshuffle(action.discards().len() as u8, rng);
+                }),
+            _ => self
+                .remove_card_from_hand(&play.card)
+                .ok_or_else(|| KingdomError::CardNotInHand(play.card.to_string())),
+        };
+        if result.is_err() {
+            self.set_cards(cards);
+        }
+        result
+    }
+
+    /// Returns the first card in the player's hand that `token` stands for, skipping any card in
+    /// `taken`.
+    pub fn find_card(&self, token: &CardToken, taken: &[Card]) -> Option<Card> {
+        self.hand
+            .iter()
+            .find(|card| token.matches(card) && !taken.contains(card))
+            .copied()
+    }
+
+    /// Moves `card` and the `num_value` cards that the player chooses to discard to their discard
+    /// pile, then returns the discards. The player can choose 0 to cancel, which returns
+    /// `KingdomError::Cancelled`.
+    fn play_number(
+        &mut self,
+        card: &Card,
+        num_value: u16,
+        input: &mut dyn InputSource,
+    ) -> error::Result<Vec<Card>> {
+        if num_value as usize >= self.hand_size() {
+            return Err(KingdomError::InvalidPlay(format!(
+                "'{card}' needs {num_value} other cards in your hand to discard"
+            )));
+        }
         self.remove_card_from_hand(card);
-        let num_value = card.value().to_number_value();
-        if (num_value as usize) > self.hand().len() {
-            println!("Invalid action! Number exceeds hand size.");
-            None
-        } else {
-            let mut num_discarded = 0;
-            while num_discarded < num_value {
-                if let Some(chosen) = self.choose_card() {
+        let mut discards = Vec::with_capacity(num_value as usize);
+        for num_discarded in 0..num_value {
+            let prompt = format!(
+                "Choose a card to discard ({} left): ",
+                num_value - num_discarded
+            );
+            match self.choose_card_with("Cancel", &prompt, input) {
+                Some(chosen) => {
                     self.remove_card_from_hand(&chosen);
-                    num_discarded += 1;
+                    discards.push(chosen);
                 }
+                /* The turn clock ran out part way through discarding. */
+                None if input.timed_out() => return Err(KingdomError::TimedOut),
+                None => return Err(KingdomError::Cancelled),
             }
-            self.draw_n_times(num_value as u8);
-            Some(())
         }
+        Ok(discards)
     }
 
-    fn play_king_queen(&mut self) -> u16 {
-        if let Some(attachment_card) = self.choose_attacment() {
-            let value = attachment_card.value().to_number_value();
-            self.remove_card_from_hand(&attachment_card);
-            value
-        }
-        // Player did not choose an attachment.
-        else {
-            0
-        }
+    fn play_king_queen(&mut self, input: &mut dyn InputSource) -> error::Result<Option<Card>> {
+        let Some(attachment_card) = self.choose_attacment(input)? else {
+            return Ok(None);
+        };
+        self.remove_card_from_hand(&attachment_card);
+        Ok(Some(attachment_card))
     }
 
-    fn print_options(&self) {
-        println!("0. End Turn");
+    /// Prints the player's hand, after `zero_option` as option 0.
+    fn print_options(&self, zero_option: &str) {
+        println!("0. {zero_option}");
         self.print_hand();
     }
 
-    fn choose_card(&self) -> Option<Card> {
-        self.print_options();
-        let choosen_action = get_num_input("Choose a card: ", 0, self.hand_size() as i32);
+    fn choose_card(&self, input: &mut dyn InputSource) -> Option<Card> {
+        self.choose_card_with("End Turn", "Choose a card: ", input)
+    }
+
+    /// Prompts the player with `prompt` to choose a card in their hand, or `zero_option`, for
+    /// which `None` is returned.
+    fn choose_card_with(
+        &self,
+        zero_option: &str,
+        prompt: &str,
+        input: &mut dyn InputSource,
+    ) -> Option<Card> {
+        self.print_options(zero_option);
+        let choosen_action = input.get_num_input(prompt, 0, self.hand_size() as i32);
         if choosen_action == 0 {
             None
         } else {
-            Some(
-                self.get_card_in_hand((choosen_action - 1) as usize)
-                    .to_owned(),
-            )
+            self.get_card_in_hand((choosen_action - 1) as usize)
+                .ok()
+                .copied()
         }
     }
 
@@ -263,20 +531,22 @@ impl Player {
     //     }
     // }
 
-    fn choose_attacment(&self) -> Option<Card> {
-        let use_attachment = get_bool_input("Attachment? ['yes' or 'no']: ", "yes", "no");
-        if use_attachment {
-            loop {
-                self.print_hand();
-                let choosen_card =
-                    get_num_input("Choose a number: ", 1, (self.hand_size() + 1) as i32) as usize;
-                let chosen_card = self.get_card_in_hand(choosen_card - 1);
-                if chosen_card.value().is_number() {
-                    return Some(chosen_card.to_owned());
+    /// Asks the player whether to attach a Number, and if so, which one. The player can choose 0
+    /// to cancel the whole action, which returns `KingdomError::Cancelled`.
+    fn choose_attacment(&self, input: &mut dyn InputSource) -> error::Result<Option<Card>> {
+        let use_attachment = input.get_bool_input("Attachment? ['yes' or 'no']: ", "yes", "no");
+        if !use_attachment {
+            return Ok(None);
+        }
+        loop {
+            match self.choose_card_with("Cancel", "Choose a number: ", input) {
+                Some(chosen_card) if chosen_card.value().is_number() => {
+                    return Ok(Some(chosen_card))
                 }
+                Some(_) => println!("Only Numbers can be attached."),
+                None if input.timed_out() => return Err(KingdomError::TimedOut),
+                None => return Err(KingdomError::Cancelled),
             }
-        } else {
-            None
         }
     }
 
@@ -292,7 +562,7 @@ impl Player {
 
     pub fn _print_deck(&self) {
         for card in &self.deck {
-            println!("{card}")
+            println!("{}", card.name())
         }
     }
 
@@ -302,6 +572,13 @@ impl Player {
         }
     }
 
+    /// Prints every card in the player's hand next to its code (e.g. `KH`), for typing commands.
+    pub fn print_hand_codes(&self) {
+        for card in &self.hand {
+            println!("{:>3}  {}", card.to_string(), card.to_colored_text());
+        }
+    }
+
     pub fn _print_hand_unicode(&self) {
         for card in &self.hand {
             println!("{}", card.to_unicode());
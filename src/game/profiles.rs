@@ -0,0 +1,325 @@
+//! This module contains `Profiles`, the host's record of every player who has finished a game,
+//! and `ProfileStore`, which keeps it on disk. Profiles are keyed by player name, and are written
+//! one record per line in the same style as snapshots. Names are written with `%`, `|` and line
+//! breaks percent-encoded (e.g. `A|B` is written as `A%7CB`), so that any name can be read back.
+//!
+//! ```text
+//! KKPROFILES|1
+//! PLAYER|ABC|{GAMES_PLAYED}|{WINS}|{ELIMINATIONS}|{RATING}
+//! PLAYER|DEF|{GAMES_PLAYED}|{WINS}|{ELIMINATIONS}|{RATING}
+//! ```
+
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use crate::{
+    error::{self, KingdomError},
+    logging,
+    utils::perror_in_fn,
+};
+
+/// Version of the profiles format. This is written as the first record of every profiles file.
+pub const PROFILES_VERSION: u32 = 1;
+
+/// Elo rating that every player starts with.
+pub const INITIAL_RATING: f64 = 1200.0;
+
+/// Most that a player's rating can change by in one game.
+const K_FACTOR: f64 = 32.0;
+
+/// Everything the host knows about one player.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub games_played: u32,
+    pub wins: u32,
+    /// How many other players this player has eliminated.
+    pub eliminations: u32,
+    /// Elo rating, which goes up when the player places above players rated higher than them.
+    pub rating: f64,
+}
+
+impl Profile {
+    /// Returns a new profile for the player named `name`, who hasn't played any games.
+    pub fn new(name: &str) -> Profile {
+        Profile {
+            name: name.to_owned(),
+            games_played: 0,
+            wins: 0,
+            eliminations: 0,
+            rating: INITIAL_RATING,
+        }
+    }
+}
+
+/// The profile of every player who has finished a game.
+#[derive(Debug, PartialEq, Default)]
+pub struct Profiles {
+    profiles: Vec<Profile>,
+}
+
+impl Profiles {
+    /// Reads and parses the profiles at `path`. If there is no file at `path`, there are no
+    /// profiles yet.
+    pub fn load(path: &Path) -> error::Result<Profiles> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Profiles::from_str(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Profiles::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the profiles to `path`. Like snapshots, the profiles are written to a temporary
+    /// file first.
+    pub fn save(&self, path: &Path) -> error::Result<()> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, self.to_string())?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Returns the profile of the player named `name`.
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Records a finished game. `placements` are the names of every player in the game in tiers
+    /// that placed together (e.g. a team, see `GameState::placement_tiers`), with the winners
+    /// first and whoever was eliminated first last, and `eliminations` are the name of each
+    /// player who eliminated someone, once for every player they eliminated. Everyone in the
+    /// first tier is credited with a win.
+    ///
+    /// Ratings are updated as if every player had played everyone in the other tiers, winning
+    /// against everyone they placed above. Players in the same tier don't change each other's
+    /// ratings.
+    pub fn record_game(&mut self, placements: &[Vec<String>], eliminations: &[String]) {
+        let players: Vec<(usize, &String, f64)> = placements
+            .iter()
+            .enumerate()
+            .flat_map(|(tier, names)| names.iter().map(move |name| (tier, name)))
+            .map(|(tier, name)| {
+                let rating = self
+                    .get(name)
+                    .map_or(INITIAL_RATING, |profile| profile.rating);
+                (tier, name, rating)
+            })
+            .collect();
+        for &(tier, name, rating) in &players {
+            let opponents: Vec<(usize, f64)> = players
+                .iter()
+                .filter(|(other, ..)| *other != tier)
+                .map(|&(other, _, rating)| (other, rating))
+                .collect();
+            let num_opponents = opponents.len().max(1) as f64;
+            let change: f64 = opponents
+                .iter()
+                .map(|(other, other_rating)| {
+                    let score = if tier < *other { 1.0 } else { 0.0 };
+                    let expected = 1.0 / (1.0 + 10f64.powf((other_rating - rating) / 400.0));
+                    K_FACTOR / num_opponents * (score - expected)
+                })
+                .sum();
+            let profile = self.get_or_insert(name);
+            profile.games_played += 1;
+            profile.wins += u32::from(tier == 0);
+            profile.rating += change;
+        }
+        for name in eliminations {
+            self.get_or_insert(name).eliminations += 1;
+        }
+    }
+
+    /// Returns every profile from the highest rating to the lowest.
+    pub fn standings(&self) -> Vec<&Profile> {
+        let mut standings: Vec<&Profile> = self.profiles.iter().collect();
+        standings.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+        standings
+    }
+
+    /// Prints the standings, with every player's stats.
+    pub fn print_leaderboard(&self) {
+        if self.profiles.is_empty() {
+            println!("No games have been finished yet.");
+            return;
+        }
+        println!(
+            "{:<4} {:<25} {:>6} {:>5} {:>4} {:>5} {:>5}",
+            "Rank", "Player", "Rating", "Games", "Wins", "Win %", "Elims"
+        );
+        for (index, profile) in self.standings().iter().enumerate() {
+            let win_rate = 100.0 * profile.wins as f64 / profile.games_played.max(1) as f64;
+            println!(
+                "{:<4} {:<25} {:>6.0} {:>5} {:>4} {:>4.0}% {:>5}",
+                index + 1,
+                profile.name,
+                profile.rating,
+                profile.games_played,
+                profile.wins,
+                win_rate,
+                profile.eliminations,
+            );
+        }
+    }
+
+    fn get_or_insert(&mut self, name: &str) -> &mut Profile {
+        let index = match self
+            .profiles
+            .iter()
+            .position(|profile| profile.name == name)
+        {
+            Some(index) => index,
+            None => {
+                self.profiles.push(Profile::new(name));
+                self.profiles.len() - 1
+            }
+        };
+        &mut self.profiles[index]
+    }
+}
+
+impl Display for Profiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "KKPROFILES|{PROFILES_VERSION}")?;
+        for profile in self.profiles.iter() {
+            writeln!(
+                f,
+                "PLAYER|{}|{}|{}|{}|{:.2}",
+                escape_name(&profile.name),
+                profile.games_played,
+                profile.wins,
+                profile.eliminations,
+                profile.rating
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Profiles {
+    type Err = KingdomError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut version = None;
+        let mut profiles = Vec::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let invalid = |reason: String| KingdomError::InvalidProfiles(index + 1, reason);
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('|').collect();
+            match fields[..] {
+                ["KKPROFILES", value] => {
+                    let value: u32 = value
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid version '{value}'")))?;
+                    if value != PROFILES_VERSION {
+                        return Err(invalid(format!("unsupported version {value}")));
+                    }
+                    version = Some(value);
+                }
+                ["PLAYER", name, games_played, wins, eliminations, rating] => {
+                    let count = |value: &str| {
+                        value
+                            .parse()
+                            .map_err(|_| invalid(format!("invalid count '{value}'")))
+                    };
+                    profiles.push(Profile {
+                        name: unescape_name(name),
+                        games_played: count(games_played)?,
+                        wins: count(wins)?,
+                        eliminations: count(eliminations)?,
+                        rating: rating
+                            .parse()
+                            .map_err(|_| invalid(format!("invalid rating '{rating}'")))?,
+                    });
+                }
+                _ => return Err(invalid(format!("invalid record '{line}'"))),
+            }
+        }
+
+        if version.is_none() {
+            return Err(KingdomError::InvalidProfiles(
+                0,
+                String::from("missing header"),
+            ));
+        }
+        Ok(Profiles { profiles })
+    }
+}
+
+/// Characters that can't be written in a name field as they are, and what they are written as.
+const ESCAPES: [(char, &str); 4] = [('%', "%25"), ('|', "%7C"), ('\n', "%0A"), ('\r', "%0D")];
+
+/// Returns `name` as it is written in a profiles file (see `ESCAPES`).
+fn escape_name(name: &str) -> String {
+    name.chars()
+        .map(
+            |c| match ESCAPES.iter().find(|(escaped, _)| *escaped == c) {
+                Some((_, code)) => (*code).to_owned(),
+                None => c.to_string(),
+            },
+        )
+        .collect()
+}
+
+/// Returns the name that `field` was written from by `escape_name`. Anything that isn't one of
+/// the `ESCAPES` is kept as it is.
+fn unescape_name(field: &str) -> String {
+    let mut name = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(c) = rest.chars().next() {
+        match ESCAPES.iter().find(|(_, code)| rest.starts_with(code)) {
+            Some((escaped, code)) => {
+                name.push(*escaped);
+                rest = &rest[code.len()..];
+            }
+            None => {
+                name.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    name
+}
+
+/// Profiles that are shared by every room on the server, and saved every time a game is
+/// finished.
+pub struct ProfileStore {
+    path: PathBuf,
+    profiles: Mutex<Profiles>,
+}
+
+impl ProfileStore {
+    /// Loads the profiles at `path`, which are saved back to `path` whenever they change.
+    pub fn open(path: &Path) -> error::Result<ProfileStore> {
+        Ok(ProfileStore {
+            path: path.to_owned(),
+            profiles: Mutex::new(Profiles::load(path)?),
+        })
+    }
+
+    /// Records a finished game (see `Profiles::record_game`), then saves the profiles.
+    pub fn record_game(&self, placements: &[Vec<String>], eliminations: &[String]) {
+        let mut profiles = self.profiles.lock().unwrap();
+        profiles.record_game(placements, eliminations);
+        match profiles.save(&self.path) {
+            Ok(()) => logging::info(
+                "profiles",
+                &format!("Saved profiles to {}", self.path.display()),
+            ),
+            Err(err) => perror_in_fn("ProfileStore::record_game", "host", err),
+        }
+    }
+
+    /// Prints the standings, with every player's stats.
+    pub fn print_leaderboard(&self) {
+        self.profiles.lock().unwrap().print_leaderboard();
+    }
+}
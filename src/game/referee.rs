@@ -0,0 +1,112 @@
+//! This module contains the referee, which applies the rules to every card that is played: which
+//! actions the turn player can play, how the cards move, and how a Jack blocks a King. The host,
+//! tournament tables and `Scenario` all play through it, so they can't disagree about the rules.
+
+use rand::Rng;
+
+use crate::{
+    error::{self, KingdomError},
+    server::response::{Action, ActionType},
+    utils::variant_eq,
+};
+
+use super::{
+    card::Play,
+    game_state::{GameState, PlayerDetails},
+    player::Player,
+    rules::Rules,
+};
+
+/// Plays `action` as the turn player's next action, where `player` holds the turn player's
+/// cards. Jacks are only played as reactions (see `block`), Aces aren't supported yet, and every
+/// other card is played with `apply_with_cards`.
+pub fn play<R: Rng>(
+    player: &mut Player,
+    action: &Action,
+    game_state: &mut GameState,
+    rules: &Rules,
+    rng: &mut R,
+) -> error::Result<()> {
+    match action.action_type() {
+        ActionType::PlayKing | ActionType::PlayQueen | ActionType::PlayNumber => {
+            let turn_player = game_state.current_player().map(PlayerDetails::name);
+            if turn_player != Some(action.from_player()) {
+                return Err(KingdomError::InvalidPlay(format!(
+                    "it isn't '{}''s turn",
+                    action.from_player()
+                )));
+            }
+            apply_with_cards(player, action, game_state, rules, rng)
+        }
+        ActionType::PlayJack => Err(KingdomError::InvalidAction(ActionType::PlayJack)),
+        ActionType::PlayBlackAce | ActionType::PlayRedAce => Err(KingdomError::UnsupportedAction(
+            action.action_type().to_owned(),
+        )),
+        ActionType::TurnEnd => game_state.apply_action(action, rules),
+        other => Err(KingdomError::InvalidAction(other.to_owned())),
+    }
+}
+
+/// Moves the cards that `action` plays out of `player`'s hand, dealing any cards they draw with
+/// `rng`, then applies it to `game_state`. If either breaks the rules, neither happens, so the
+/// player's cards always match the game state.
+pub fn apply_with_cards<R: Rng>(
+    player: &mut Player,
+    action: &Action,
+    game_state: &mut GameState,
+    rules: &Rules,
+    rng: &mut R,
+) -> error::Result<()> {
+    if player.name() != action.from_player() {
+        return Err(KingdomError::InvalidPlay(format!(
+            "'{}' can't play cards for '{}'",
+            player.name(),
+            action.from_player()
+        )));
+    }
+    let cards = player.cards();
+    let result = player
+        .apply_cards(action, rng)
+        .and_then(|()| game_state.apply_action(action, rules));
+    if result.is_err() {
+        player.set_cards(cards);
+    }
+    result
+}
+
+/// Returns how many points the target of `action` has, if the target can react to it. Only
+/// Kings played against another player can be reacted to.
+pub fn reaction_target_points(action: &Action, game_state: &GameState) -> Option<u16> {
+    let is_king = variant_eq(action.action_type(), &ActionType::PlayKing);
+    if !is_king || action.to_player() == action.from_player() {
+        return None;
+    }
+    game_state
+        .player_by_name(action.to_player())
+        .map(PlayerDetails::points)
+}
+
+/// Returns the Jack that the target of `king` plays to block it, where `points_before` is how
+/// many points they had before the King, and `play` is the Jack from their hand, if it is known.
+/// The Jack gives back whatever the King took away. It still has to be applied with
+/// `apply_with_cards`.
+pub fn block(
+    king: &Action,
+    points_before: u16,
+    play: Option<Play>,
+    game_state: &GameState,
+) -> Action {
+    let points_after = game_state
+        .player_by_name(king.to_player())
+        .map_or(points_before, PlayerDetails::points);
+    let jack = Action::new(
+        ActionType::PlayJack,
+        points_before.saturating_sub(points_after),
+        king.to_player().to_owned(),
+        king.from_player().to_owned(),
+    );
+    match play {
+        Some(play) => jack.with_play(play),
+        None => jack,
+    }
+}
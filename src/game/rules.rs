@@ -0,0 +1,137 @@
+//! This module contains the `Rules` struct, which holds every value that can be changed between
+//! games (e.g. how many points each player starts with).
+
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use crate::{error::KingdomError, server::constants::MAX_PLAYERS};
+
+/// Most teams a game can have, so that every team can have at least two players.
+pub const MAX_TEAMS: u8 = (MAX_PLAYERS / 2) as u8;
+
+/// The set of rules that a game is played with. The default rules are the ones described in
+/// Rules.md.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rules {
+    /// Number of points each player starts with.
+    pub starting_points: u16,
+    /// Number of cards each player draws at the start of the game.
+    pub hand_size: u8,
+    /// Damage dealt by a King, before its attachment is added.
+    pub king_damage: u16,
+    /// Points healed by a Queen, before its attachment is added.
+    pub queen_heal: u16,
+    /// Seconds each player has to play their turn, or 0 for no limit.
+    pub turn_secs: u16,
+    /// Seconds a player has to react to a card played against them, or 0 for no limit.
+    pub reaction_secs: u16,
+    /// Number of teams, which players are seated on in turn (e.g. with 2 teams, the first and
+    /// third seats are on team 1). If this is 0, every player plays for themselves.
+    pub teams: u8,
+    /// Whether Kings can be played against teammates.
+    pub friendly_fire: bool,
+    /// Whether the host commits to every deck before dealing, so that players can check the deal
+    /// once the game is over (see `fair_deal`).
+    pub fair_deal: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            starting_points: 100,
+            hand_size: 5,
+            king_damage: 10,
+            queen_heal: 10,
+            turn_secs: 120,
+            reaction_secs: 20,
+            teams: 0,
+            friendly_fire: false,
+            fair_deal: false,
+        }
+    }
+}
+
+impl Display for Rules {
+    /// Format: `points={STARTING_POINTS},hand={HAND_SIZE},king={KING_DAMAGE},queen={QUEEN_HEAL},
+    /// turn={TURN_SECS},reaction={REACTION_SECS},teams={TEAMS},friendly_fire={0 or 1},
+    /// fair_deal={0 or 1}`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "points={},hand={},king={},queen={},turn={},reaction={},teams={},friendly_fire={},\
+             fair_deal={}",
+            self.starting_points,
+            self.hand_size,
+            self.king_damage,
+            self.queen_heal,
+            self.turn_secs,
+            self.reaction_secs,
+            self.teams,
+            u8::from(self.friendly_fire),
+            u8::from(self.fair_deal)
+        )
+    }
+}
+
+impl Rules {
+    /// Returns how long each turn lasts, or `None` if turns have no time limit.
+    pub fn turn_duration(&self) -> Option<Duration> {
+        (self.turn_secs != 0).then(|| Duration::from_secs(self.turn_secs.into()))
+    }
+
+    /// Returns how long players have to react, or `None` if reactions have no time limit.
+    pub fn reaction_duration(&self) -> Option<Duration> {
+        (self.reaction_secs != 0).then(|| Duration::from_secs(self.reaction_secs.into()))
+    }
+
+    /// Returns true if players are split into teams.
+    pub fn has_teams(&self) -> bool {
+        self.teams != 0
+    }
+
+    /// Returns a copy of these rules with `changes` made to them, where `changes` is a
+    /// comma-separated list of `key=value` pairs. Any rule that isn't listed is left as it is.
+    /// There can be no teams, or from 2 to `MAX_TEAMS` teams.
+    pub fn with_changes(&self, changes: &str) -> Result<Rules, KingdomError> {
+        let mut rules = self.clone();
+        for pair in changes.split(',').filter(|pair| !pair.is_empty()) {
+            let invalid = || KingdomError::InvalidRule(pair.to_owned());
+            let (key, value) = pair.split_once('=').ok_or_else(invalid)?;
+            match key {
+                "points" => rules.starting_points = value.parse().map_err(|_| invalid())?,
+                "hand" => rules.hand_size = value.parse().map_err(|_| invalid())?,
+                "king" => rules.king_damage = value.parse().map_err(|_| invalid())?,
+                "queen" => rules.queen_heal = value.parse().map_err(|_| invalid())?,
+                "turn" => rules.turn_secs = value.parse().map_err(|_| invalid())?,
+                "reaction" => rules.reaction_secs = value.parse().map_err(|_| invalid())?,
+                "teams" => match value.parse() {
+                    Ok(teams) if teams == 0 || (2..=MAX_TEAMS).contains(&teams) => {
+                        rules.teams = teams
+                    }
+                    _ => return Err(invalid()),
+                },
+                "friendly_fire" => match value {
+                    "0" => rules.friendly_fire = false,
+                    "1" => rules.friendly_fire = true,
+                    _ => return Err(invalid()),
+                },
+                "fair_deal" => match value {
+                    "0" => rules.fair_deal = false,
+                    "1" => rules.fair_deal = true,
+                    _ => return Err(invalid()),
+                },
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+impl FromStr for Rules {
+    type Err = KingdomError;
+
+    /// Converts from a comma-separated list of `key=value` pairs to `Rules`. Any rule that
+    /// isn't listed keeps its default value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rules::default().with_changes(s)
+    }
+}
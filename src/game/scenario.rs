@@ -0,0 +1,285 @@
+//! This module contains `Scenario`, which is used by tests to check the rules the way the host
+//! applies them, without starting a server. Players are declared with exact cards and points,
+//! then plays and reactions are fed in one at a time, and the resulting points, piles and events
+//! are checked along the way. Every method panics with a description of what went wrong, so
+//! scenarios read as a single chain.
+//!
+//! ```ignore
+//! Scenario::new()
+//!     .player("Alice", 100)
+//!     .hand("KH 5S")
+//!     .player("Bob", 100)
+//!     .hand("JC")
+//!     .play("K,5,Alice,Bob,KH+5S")
+//!     .assert_points("Bob", 85)
+//!     .block("JC")
+//!     .assert_points("Bob", 100);
+//! ```
+
+use std::str::FromStr;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    error::{self, KingdomError},
+    server::response::Action,
+};
+
+use super::{
+    card::{Card, Play},
+    game_state::{GameState, PlayerDetails},
+    player::{Player, PlayerCards},
+    referee,
+    rules::Rules,
+};
+
+/// Seed used for every reshuffle, so that scenarios always play out the same way.
+const SEED: u64 = 0;
+
+/// Something that was fed into a scenario, and whether the host would have accepted it.
+#[derive(Debug, PartialEq)]
+pub struct Event {
+    /// The action as it is written in the game log (e.g. `K,5,Alice,Bob,KH+5S`).
+    pub action: String,
+    /// The reason the action was rejected, if it was.
+    pub result: Result<(), String>,
+}
+
+/// A game that is played one action at a time against the host-side rules (see the module
+/// documentation).
+pub struct Scenario {
+    rules: Rules,
+    game_state: GameState,
+    players: Vec<Player>,
+    rng: ChaCha8Rng,
+    events: Vec<Event>,
+    /// The last King that was accepted, and how many points its target had before it, while
+    /// they can still block it.
+    pending_king: Option<(Action, u16)>,
+}
+
+/// Parses `cards`, a space-separated list of card codes.
+fn parse_cards(cards: &str) -> Vec<Card> {
+    cards
+        .split_whitespace()
+        .map(|code| Card::from_str(code).unwrap_or_else(|err| panic!("{err}")))
+        .collect()
+}
+
+impl Scenario {
+    /// Starts a scenario with the default rules.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Scenario {
+        Scenario::with_rules(Rules::default())
+    }
+
+    /// Starts a scenario with `rules`.
+    pub fn with_rules(rules: Rules) -> Scenario {
+        Scenario {
+            rules,
+            game_state: GameState::new(),
+            players: Vec::new(),
+            rng: ChaCha8Rng::seed_from_u64(SEED),
+            events: Vec::new(),
+            pending_king: None,
+        }
+    }
+
+    /// Seats a player named `name` with `points` and no cards. The first player seated takes
+    /// the first turn. `hand`, `deck` and `discard_pile` give this player their cards.
+    pub fn player(mut self, name: &str, points: u16) -> Scenario {
+        self.game_state
+            .add_player(PlayerDetails::new(name.to_owned(), points));
+        self.players
+            .push(Player::with_cards(name.to_owned(), PlayerCards::default()));
+        self
+    }
+
+    /// Sets the hand of the player seated last.
+    pub fn hand(self, cards: &str) -> Scenario {
+        self.with_last_cards(|last| last.hand = parse_cards(cards))
+    }
+
+    /// Sets the deck of the player seated last. Cards are drawn from the end of the deck.
+    pub fn deck(self, cards: &str) -> Scenario {
+        self.with_last_cards(|last| last.deck = parse_cards(cards))
+    }
+
+    /// Sets the discard pile of the player seated last.
+    pub fn discard_pile(self, cards: &str) -> Scenario {
+        self.with_last_cards(|last| last.discard_pile = parse_cards(cards))
+    }
+
+    fn with_last_cards(mut self, change: impl FnOnce(&mut PlayerCards)) -> Scenario {
+        let last = self
+            .players
+            .last_mut()
+            .expect("a player has to be seated before they are given cards");
+        let mut cards = last.cards();
+        change(&mut cards);
+        last.set_cards(cards);
+        self
+    }
+
+    /// Plays `action`, written as it is in the game log, as the turn player, with
+    /// `referee::play`, the same as the host.
+    pub fn play(mut self, action: &str) -> Scenario {
+        let action = Action::from_str(action).unwrap_or_else(|err| panic!("{err:?}"));
+        let before = referee::reaction_target_points(&action, &self.game_state);
+        let index = self.game_state.current_player_index();
+        let result = match self.players.get_mut(index) {
+            Some(player) => referee::play(
+                player,
+                &action,
+                &mut self.game_state,
+                &self.rules,
+                &mut self.rng,
+            ),
+            None => Err(KingdomError::PlayerNotFound(format!("seat {index}"))),
+        };
+        self.pending_king = before
+            .filter(|_| result.is_ok())
+            .map(|before| (action.to_owned(), before));
+        self.record(&action, result)
+    }
+
+    /// Blocks the last King with `jack`, a Jack in its target's hand, the way the host does when
+    /// the target reacts (see `referee::block`).
+    pub fn block(mut self, jack: &str) -> Scenario {
+        let Some((king, before)) = self.pending_king.take() else {
+            panic!("there is no King to block");
+        };
+        let play = Play::from_str(jack).unwrap_or_else(|err| panic!("{err}"));
+        let jack = referee::block(&king, before, Some(play), &self.game_state);
+        let result = self.index_of(jack.from_player()).and_then(|index| {
+            referee::apply_with_cards(
+                &mut self.players[index],
+                &jack,
+                &mut self.game_state,
+                &self.rules,
+                &mut self.rng,
+            )
+        });
+        self.record(&jack, result)
+    }
+
+    /// Ends the turn player's turn.
+    pub fn end_turn(mut self) -> Scenario {
+        self.pending_king = None;
+        let Some(turn_player) = self.game_state.current_player() else {
+            panic!("nobody is seated");
+        };
+        let action = Action::new_turn_end(turn_player.name());
+        if let Err(err) = self.game_state.apply_action(&action, &self.rules) {
+            panic!("{err}");
+        }
+        self.record(&action, Ok(()))
+    }
+
+    fn record(mut self, action: &Action, result: error::Result<()>) -> Scenario {
+        self.events.push(Event {
+            action: action.to_string(),
+            result: result.map_err(|err| err.to_string()),
+        });
+        self
+    }
+
+    fn index_of(&self, name: &str) -> error::Result<usize> {
+        self.players
+            .iter()
+            .position(|player| player.name() == name)
+            .ok_or_else(|| KingdomError::PlayerNotFound(name.to_owned()))
+    }
+
+    fn points(&self, name: &str) -> u16 {
+        self.game_state
+            .player_by_name(name)
+            .map_or(0, PlayerDetails::points)
+    }
+
+    /// Returns the cards of the player named `name`.
+    pub fn cards(&self, name: &str) -> PlayerCards {
+        let index = self.index_of(name).unwrap_or_else(|err| panic!("{err}"));
+        self.players[index].cards()
+    }
+
+    /// Returns everything that was fed into the scenario, oldest first.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Checks that the last action was accepted.
+    #[track_caller]
+    pub fn assert_accepted(self) -> Scenario {
+        let event = self.events.last().expect("nothing has been played");
+        assert_eq!(event.result, Ok(()), "'{}' was rejected", event.action);
+        self
+    }
+
+    /// Checks that the last action was rejected, for a reason that contains `reason`.
+    #[track_caller]
+    pub fn assert_rejected(self, reason: &str) -> Scenario {
+        let event = self.events.last().expect("nothing has been played");
+        match &event.result {
+            Ok(()) => panic!("'{}' was accepted", event.action),
+            Err(err) => assert!(
+                err.contains(reason),
+                "'{}' was rejected because '{err}', not '{reason}'",
+                event.action
+            ),
+        }
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_points(self, name: &str, points: u16) -> Scenario {
+        assert_eq!(self.points(name), points, "{name}'s points");
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_hand(self, name: &str, cards: &str) -> Scenario {
+        assert_eq!(self.cards(name).hand, parse_cards(cards), "{name}'s hand");
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_deck(self, name: &str, cards: &str) -> Scenario {
+        assert_eq!(self.cards(name).deck, parse_cards(cards), "{name}'s deck");
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_discard_pile(self, name: &str, cards: &str) -> Scenario {
+        let discard_pile = self.cards(name).discard_pile;
+        assert_eq!(discard_pile, parse_cards(cards), "{name}'s discard pile");
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_eliminated(self, name: &str) -> Scenario {
+        let player = self.game_state.player_by_name(name);
+        assert!(
+            player.is_some_and(PlayerDetails::is_eliminated),
+            "{name} wasn't eliminated"
+        );
+        self
+    }
+
+    /// Checks that whoever's turn it is is the player named `name`.
+    #[track_caller]
+    pub fn assert_turn(self, name: &str) -> Scenario {
+        let turn_player = self.game_state.current_player().map(PlayerDetails::name);
+        assert_eq!(turn_player, Some(name), "turn player");
+        self
+    }
+
+    /// Checks that the player named `name` is the only one left.
+    #[track_caller]
+    pub fn assert_winner(self, name: &str) -> Scenario {
+        let winner = self.game_state.winner().map(PlayerDetails::name);
+        assert_eq!(winner, Some(name), "winner");
+        self
+    }
+}
@@ -0,0 +1,200 @@
+//! This module contains `Snapshot`, which holds everything needed to resume a game after the host
+//! is restarted. Snapshots are written as one record per line, with fields separated by `|`,
+//! in the same style as game logs.
+//!
+//! ```text
+//! KKSAVE|1
+//! RULES|points=100,hand=5,king=10,queen=10,turn=120,reaction=20,teams=0,friendly_fire=0,fair_deal=0
+//! RNG|8214737394|0
+//! LOG|12|game-8214737394.kklog
+//! GAME|1|2,ABC:100,DEF:85
+//! TIMER|120
+//! CARDS|ABC|{DECK},{HAND},{DISCARD_PILE}
+//! CARDS|DEF|{DECK},{HAND},{DISCARD_PILE}
+//! ```
+
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::error::{self, KingdomError};
+
+use super::{game_state::GameState, player::PlayerCards, rules::Rules};
+
+/// Version of the snapshot format. This is written as the first record of every snapshot.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// A saved game, taken at the start of a turn.
+#[derive(Debug, PartialEq)]
+pub struct Snapshot {
+    pub rules: Rules,
+    /// Seed of the host's random number generator.
+    pub seed: u64,
+    /// How far the host's random number generator has advanced since it was seeded.
+    pub rng_word_pos: u128,
+    /// Path of the game log, and the sequence number of the next action that will be logged.
+    pub game_log: Option<(PathBuf, u32)>,
+    /// Points of every player in seat order, and whose turn it is.
+    pub game_state: GameState,
+    /// Seconds left on the turn clock of the current turn. If this is `None`, the turn gets the
+    /// full turn clock when the game is resumed.
+    pub turn_secs_left: Option<u16>,
+    /// Deck, hand and discard pile of every player, by name.
+    pub cards: Vec<(String, PlayerCards)>,
+}
+
+impl Snapshot {
+    /// Reads and parses the snapshot at `path`.
+    pub fn load(path: &Path) -> error::Result<Snapshot> {
+        Snapshot::from_str(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the snapshot to `path`. The snapshot is written to a temporary file first, so an
+    /// interrupted save never leaves a half-written snapshot behind.
+    pub fn save(&self, path: &Path) -> error::Result<()> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, self.to_string())?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Returns the name of every player in seat order, who are the only players who can rejoin.
+    pub fn names(&self) -> Vec<String> {
+        self.game_state
+            .all_players()
+            .iter()
+            .map(|player| player.name().to_owned())
+            .collect()
+    }
+
+    /// Returns the saved cards of the player named `name`.
+    pub fn cards_of(&self, name: &str) -> Option<&PlayerCards> {
+        self.cards
+            .iter()
+            .find(|(player, _)| player == name)
+            .map(|(_, cards)| cards)
+    }
+}
+
+impl Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "KKSAVE|{SNAPSHOT_VERSION}")?;
+        writeln!(f, "RULES|{}", self.rules)?;
+        writeln!(f, "RNG|{}|{}", self.seed, self.rng_word_pos)?;
+        if let Some((path, next_seq)) = &self.game_log {
+            writeln!(f, "LOG|{next_seq}|{}", path.display())?;
+        }
+        writeln!(
+            f,
+            "GAME|{}|{}",
+            self.game_state.current_player_index(),
+            self.game_state
+        )?;
+        if let Some(turn_secs_left) = self.turn_secs_left {
+            writeln!(f, "TIMER|{turn_secs_left}")?;
+        }
+        for (name, cards) in self.cards.iter() {
+            writeln!(f, "CARDS|{name}|{cards}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = KingdomError;
+
+    /// Converts from the contents of a snapshot to a `Snapshot`. Every player in the game state
+    /// must have saved cards.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut version = None;
+        let mut rules = None;
+        let mut rng = None;
+        let mut game_log = None;
+        let mut game_state = None;
+        let mut turn_secs_left = None;
+        let mut cards = Vec::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let invalid = |reason: String| KingdomError::InvalidSnapshot(index + 1, reason);
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(3, '|').collect();
+            match fields[..] {
+                ["KKSAVE", value] => {
+                    let value: u32 = value
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid version '{value}'")))?;
+                    if value != SNAPSHOT_VERSION {
+                        return Err(invalid(format!("unsupported version {value}")));
+                    }
+                    version = Some(value);
+                }
+                ["RULES", value] => {
+                    rules = Some(Rules::from_str(value).map_err(|err| invalid(err.to_string()))?)
+                }
+                ["RNG", seed, word_pos] => {
+                    let seed = seed
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid seed '{seed}'")))?;
+                    let word_pos = word_pos
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid word position '{word_pos}'")))?;
+                    rng = Some((seed, word_pos));
+                }
+                ["LOG", next_seq, path] => {
+                    let next_seq = next_seq
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid sequence number '{next_seq}'")))?;
+                    game_log = Some((PathBuf::from(path), next_seq));
+                }
+                ["GAME", turn, value] => {
+                    let turn = turn
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid turn index '{turn}'")))?;
+                    let mut value =
+                        GameState::from_str(value).map_err(|err| invalid(err.to_string()))?;
+                    value
+                        .set_current_player(turn)
+                        .map_err(|err| invalid(err.to_string()))?;
+                    game_state = Some(value);
+                }
+                ["TIMER", value] => {
+                    let value = value
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid seconds left '{value}'")))?;
+                    turn_secs_left = Some(value);
+                }
+                ["CARDS", name, value] => {
+                    let value =
+                        PlayerCards::from_str(value).map_err(|err| invalid(err.to_string()))?;
+                    cards.push((name.to_owned(), value));
+                }
+                _ => return Err(invalid(format!("invalid record '{line}'"))),
+            }
+        }
+
+        let missing = |record: &str| KingdomError::InvalidSnapshot(0, format!("missing {record}"));
+        version.ok_or_else(|| missing("header"))?;
+        let (seed, rng_word_pos) = rng.ok_or_else(|| missing("random number generator"))?;
+        let snapshot = Snapshot {
+            rules: rules.ok_or_else(|| missing("rules"))?,
+            seed,
+            rng_word_pos,
+            game_log,
+            game_state: game_state.ok_or_else(|| missing("game state"))?,
+            turn_secs_left,
+            cards,
+        };
+        for player in snapshot.game_state.all_players() {
+            if snapshot.cards_of(player.name()).is_none() {
+                return Err(missing(&format!("cards of {}", player.name())));
+            }
+        }
+        Ok(snapshot)
+    }
+}
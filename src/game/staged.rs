@@ -0,0 +1,103 @@
+//! This module contains `StagedAction`, an action that a player has built on their turn but not
+//! yet sent to the server. The cards it uses have already left the player's hand, so it can be
+//! previewed, then either cancelled, which puts every card back, or confirmed.
+
+use crate::{
+    error,
+    game::{
+        card::Card,
+        game_state::GameState,
+        player::{Player, PlayerCards},
+        rules::Rules,
+    },
+    server::response::Action,
+    ui::input::InputSource,
+};
+
+/// An action that hasn't been sent yet, along with the player's cards from before they built it.
+#[derive(Debug)]
+pub struct StagedAction {
+    action: Action,
+    cards: PlayerCards,
+}
+
+impl StagedAction {
+    /// Stages `action`, where `cards` are the player's cards from before they built it.
+    pub fn new(action: Action, cards: PlayerCards) -> StagedAction {
+        StagedAction { action, cards }
+    }
+
+    pub fn action(&self) -> &Action {
+        &self.action
+    }
+
+    /// Returns a description of what the action would do to `game_state` if it was confirmed,
+    /// over as many lines as there are changes (e.g. `ABC played KH+5S against DEF` then
+    /// `  DEF: 100 -> 85 points`). Returns an error if the action would break the rules.
+    pub fn preview(&self, game_state: &GameState, rules: &Rules) -> error::Result<String> {
+        let mut after = game_state.clone();
+        after.apply_action(&self.action, rules)?;
+        let mut preview = self.action.summary();
+        for (name, delta) in GameState::points_deltas(game_state, &after) {
+            let Some(details) = after.player_by_name(&name) else {
+                continue;
+            };
+            let points = details.points();
+            let old_points = points as i32 - delta;
+            preview += &format!("\n  {name}: {old_points} -> {points} points");
+            if details.is_eliminated() {
+                preview += ", eliminated";
+            }
+        }
+        let discards = self.action.discards();
+        if !discards.is_empty() {
+            let codes: Vec<String> = discards.iter().map(Card::to_string).collect();
+            preview += &format!(
+                "\n  Discarding {}, then drawing {} cards",
+                codes.join(" "),
+                discards.len()
+            );
+        }
+        Ok(preview)
+    }
+
+    /// Prints the preview of the action, then asks the player to confirm it. Returns the action if
+    /// they confirm it. Otherwise, or if it would break the rules or the input deadline passes,
+    /// `player`'s cards are put back and `None` is returned. The prompt is answered from `input`.
+    pub fn confirm_with_prompt(
+        self,
+        player: &mut Player,
+        game_state: &GameState,
+        rules: &Rules,
+        input: &mut dyn InputSource,
+    ) -> Option<Action> {
+        match self.preview(game_state, rules) {
+            Ok(preview) => println!("{preview}"),
+            Err(err) => {
+                println!("Error! {err}");
+                self.cancel(player);
+                return None;
+            }
+        }
+        if input.get_bool_input("Confirm? [y/n]: ", "y", "n") && !input.timed_out() {
+            Some(self.confirm())
+        } else {
+            if !input.timed_out() {
+                println!("Cancelled. Your cards are back in your hand.");
+            }
+            self.cancel(player);
+            None
+        }
+    }
+
+    /// Puts every card the action used back where it was in `player`'s cards.
+    pub fn cancel(self, player: &mut Player) {
+        player.set_cards(self.cards);
+    }
+
+    /// Returns the action so it can be sent. A Number's replacements are dealt by whoever checks
+    /// the action (the host, or a tournament table), so cancelling never shows any new cards.
+    pub fn confirm(self) -> Action {
+        self.action
+    }
+}
@@ -1,5 +1,9 @@
+pub mod error;
 pub mod game;
+pub mod logging;
+pub mod replay;
 pub mod server;
+pub mod tournament;
 pub mod ui;
 pub mod utils;
 
@@ -7,13 +11,31 @@ pub mod utils;
 mod tests {
 
     use core::panic;
+    use std::io::Write;
     use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
 
+    use crate::game::card::*;
+    use crate::game::fair_deal::*;
+    use crate::game::game_log::*;
     use crate::game::game_state::GameState;
     use crate::game::game_state::PlayerDetails;
     use crate::game::player::*;
+    use crate::game::profiles::*;
+    use crate::game::rules::{Rules, MAX_TEAMS};
+    use crate::game::scenario::Scenario;
+    use crate::game::snapshot::Snapshot;
+    use crate::replay::Replay;
+    use crate::server::chat::ChatMessage;
+    use crate::server::lobby::*;
     use crate::server::request::*;
     use crate::server::response::*;
+    use crate::server::rooms::*;
+    use crate::server::view::*;
+    use crate::tournament::*;
+    use crate::ui::commands::*;
+    use crate::ui::input::*;
+    use proptest::prelude::*;
 
     #[test]
     fn player_initialization() {
@@ -28,6 +50,22 @@ mod tests {
         assert_eq!(player.hand_size(), 6);
     }
 
+    #[test]
+    fn players_own_a_standard_deck() {
+        for player in [Player::new(), Player::with_name(String::from("John Smith"))] {
+            let cards = player.cards();
+            let mut owned: Vec<String> = [cards.deck, cards.hand, cards.discard_pile]
+                .concat()
+                .iter()
+                .map(Card::to_string)
+                .collect();
+            owned.sort();
+            let mut standard: Vec<String> = Card::full_deck().iter().map(Card::to_string).collect();
+            standard.sort();
+            assert_eq!(owned, standard);
+        }
+    }
+
     #[test]
     fn str_to_request() {
         let test_one = Request::from_str("REQ,NAME");
@@ -137,4 +175,2075 @@ mod tests {
         let test_four = Response::new(ResponseType::GameState(Some(game_state))).to_string();
         assert_eq!(test_four, "RES,GAME,2,ABC:90,DEF:20");
     }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut player = Player::with_name(String::from("ABC"));
+        player.draw_card();
+        let cards = player.cards();
+        assert_eq!(PlayerCards::from_str(&cards.to_string()).unwrap(), cards);
+
+        let own = OwnCards::of(&cards);
+        let response = Response::from_cards(Some(own.clone())).to_string();
+        assert!(response.starts_with(&format!("RES,CARDS,{},", cards.deck.len())));
+        assert_eq!(
+            Response::from_str(&response).unwrap().into_cards().unwrap(),
+            Some(own)
+        );
+        assert_eq!(
+            Response::from_str("RES,CARDS")
+                .unwrap()
+                .into_cards()
+                .unwrap(),
+            None
+        );
+
+        let mut game_state = GameState::new();
+        game_state.add_player(PlayerDetails::new("ABC".to_string(), 90));
+        game_state.add_player(PlayerDetails::new("DEF".to_string(), 20));
+        game_state.set_current_player(1).unwrap();
+        let snapshot = Snapshot {
+            rules: Rules::default(),
+            seed: 7,
+            rng_word_pos: 128,
+            game_log: Some((std::path::PathBuf::from("game-7.kklog"), 12)),
+            game_state,
+            turn_secs_left: Some(45),
+            cards: vec![
+                (String::from("ABC"), cards),
+                (String::from("DEF"), PlayerCards::default()),
+            ],
+        };
+        let loaded = Snapshot::from_str(&snapshot.to_string()).unwrap();
+        assert_eq!(loaded, snapshot);
+        assert_eq!(loaded.game_state.current_player_index(), 1);
+
+        let missing_cards = snapshot.to_string().replace("CARDS|DEF|,,\n", "");
+        assert!(Snapshot::from_str(&missing_cards).is_err());
+    }
+
+    #[test]
+    fn resume_reseats_players() {
+        use crate::server::host::ServerInstance;
+        use crate::server::session::*;
+
+        /* Bob's turn was interrupted, after Alice had discarded two cards. */
+        let mut game_state = GameState::from_str("2,Alice:70,Bob:40").unwrap();
+        game_state.set_current_player(1).unwrap();
+        let saved_cards = |hand: std::ops::Range<usize>, discarded: usize| {
+            let mut deck = Card::full_deck();
+            let hand: Vec<Card> = deck.drain(hand).collect();
+            let discard_pile = deck.split_off(deck.len() - discarded);
+            PlayerCards {
+                deck,
+                hand,
+                discard_pile,
+            }
+        };
+        let cards = vec![
+            (String::from("Alice"), saved_cards(0..5, 2)),
+            (String::from("Bob"), saved_cards(10..15, 0)),
+        ];
+        let snapshot = Snapshot {
+            rules: Rules::from_str("turn=0,reaction=0").unwrap(),
+            seed: 7,
+            rng_word_pos: 0,
+            game_log: None,
+            game_state,
+            turn_secs_left: None,
+            cards: cards.clone(),
+        };
+
+        let rooms = RoomManager::bind("127.0.0.1:0", None, None).unwrap();
+        let address = rooms.accept().unwrap().to_string();
+        let room = ServerInstance::open_resumed_room(&rooms, "Resumed", snapshot).unwrap();
+        let code = room.code().to_owned();
+        /* The players rejoin in the opposite order to their seats, and only with saved names. */
+        let rejoin = |names: &'static [&'static str]| {
+            let mut session = ClientSession::connect(&address).unwrap();
+            session.choose_room(RoomChoice::Join(code.clone())).unwrap();
+            let (name, rejected) = names.split_last().unwrap();
+            for rejected in rejected {
+                assert!(!session.choose_name(rejected).unwrap(), "{rejected}");
+            }
+            assert!(session.choose_name(name).unwrap());
+            session.handle().set_ready(true).unwrap();
+            std::thread::spawn(move || loop {
+                if let SessionEvent::TurnStarted { player, .. } = session.next_event().unwrap() {
+                    break (session, player);
+                }
+            })
+        };
+        let bob = rejoin(&["Carol", "Bob"]);
+        let alice = rejoin(&["Alice"]);
+
+        for ((name, saved), seat) in cards.iter().zip([alice, bob]) {
+            let (session, turn_player) = seat.join().unwrap();
+            let seats: Vec<(&str, u16)> = session
+                .game_state()
+                .all_players()
+                .iter()
+                .map(|player| (player.name(), player.points()))
+                .collect();
+            assert_eq!(seats, [("Alice", 70), ("Bob", 40)], "{name}");
+            assert_eq!(turn_player, "Bob", "{name}");
+            let own = session.player().cards();
+            assert_eq!(own.hand, saved.hand, "{name}");
+            assert_eq!(own.discard_pile, saved.discard_pile, "{name}");
+            assert_eq!(session.deck_size(), saved.deck.len(), "{name}");
+        }
+    }
+
+    #[test]
+    fn expired_clocks_ignore_late_replies() {
+        use crate::server::constants::{ACTION_REQUEST, ACTION_RESPONSE};
+        use crate::server::host::ServerInstance;
+        use crate::server::session::*;
+        use crate::server::StreamHandler;
+        use std::thread::{sleep, spawn};
+        use std::time::Duration;
+
+        /* Once a request has run out of time, its late answer is dropped, but only until the
+        peer sends a request of their own. */
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut peer = StreamHandler::new(
+            std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+        );
+        let mut host = StreamHandler::new(listener.accept().unwrap().0);
+        let answer = |action: &str| Response::from_action(Action::from_str(action).unwrap());
+        host.ignore_late_response();
+        peer.send_response(&answer("E,0,Alice,")).unwrap();
+        peer.send_response(&answer("X,0,Alice,")).unwrap();
+        let received = host.await_response(ACTION_RESPONSE).unwrap();
+        assert_eq!(received.to_string(), answer("X,0,Alice,").to_string());
+        host.ignore_late_response();
+        peer.send_request(ACTION_REQUEST).unwrap();
+        peer.send_response(&answer("E,0,Alice,")).unwrap();
+        host.await_request(ACTION_REQUEST).unwrap();
+        let received = host.await_response(ACTION_RESPONSE).unwrap();
+        assert_eq!(received.to_string(), answer("E,0,Alice,").to_string());
+
+        /* Longer than a clock of one second, with the host's grace period. */
+        const TOO_LATE: Duration = Duration::from_millis(3500);
+        let card = |code: &str| Card::from_str(code).unwrap();
+        /* Starts a game between Alice and Bob from a save, where it's Alice's turn. */
+        let start = |rules: &str| {
+            let hands = [
+                ["KH", "5S", "2C", "3C", "4C"].map(card),
+                ["JH", "6C", "7C", "8C", "9C"].map(card),
+            ];
+            let mut deck = Card::full_deck();
+            deck.retain(|card| !hands.concat().contains(card));
+            let bob_deck = deck.split_off(deck.len() / 2);
+            let cards = vec![
+                (
+                    String::from("Alice"),
+                    PlayerCards {
+                        deck,
+                        hand: hands[0].to_vec(),
+                        discard_pile: Vec::new(),
+                    },
+                ),
+                (
+                    String::from("Bob"),
+                    PlayerCards {
+                        deck: bob_deck,
+                        hand: hands[1].to_vec(),
+                        discard_pile: Vec::new(),
+                    },
+                ),
+            ];
+            let snapshot = Snapshot {
+                rules: Rules::from_str(rules).unwrap(),
+                seed: 7,
+                rng_word_pos: 0,
+                game_log: None,
+                game_state: GameState::from_str("2,Alice:70,Bob:40").unwrap(),
+                turn_secs_left: None,
+                cards,
+            };
+            let rooms = RoomManager::bind("127.0.0.1:0", None, None).unwrap();
+            let address = rooms.accept().unwrap().to_string();
+            let room = ServerInstance::open_resumed_room(&rooms, "Clocks", snapshot).unwrap();
+            let rejoin = |name: &'static str| {
+                let mut session = ClientSession::connect(&address).unwrap();
+                session
+                    .choose_room(RoomChoice::Join(room.code().to_owned()))
+                    .unwrap();
+                assert!(session.choose_name(name).unwrap());
+                session.handle().set_ready(true).unwrap();
+                spawn(move || {
+                    await_turn(&mut session, "Alice");
+                    session
+                })
+            };
+            let alice = rejoin("Alice");
+            let bob = rejoin("Bob");
+            (alice.join().unwrap(), bob.join().unwrap())
+        };
+        fn await_turn(session: &mut ClientSession, turn_player: &str) {
+            loop {
+                if let SessionEvent::TurnStarted { player, .. } = session.next_event().unwrap() {
+                    assert_eq!(player, turn_player);
+                    break;
+                }
+            }
+        }
+        let points = |session: &ClientSession, name: &str| {
+            session.game_state().player_by_name(name).unwrap().points()
+        };
+
+        /* Alice answers after her turn clock ran out, so her turn ends without the Number. */
+        let (mut alice, mut bob) = start("turn=1,reaction=0");
+        let alice = spawn(move || {
+            assert!(matches!(
+                alice.next_event().unwrap(),
+                SessionEvent::ActionRequested
+            ));
+            sleep(TOO_LATE);
+            let number = Action::from_play(
+                Play::new(card("5S"), None),
+                String::from("Alice"),
+                String::from("Alice"),
+            );
+            assert!(!alice.submit_action(number).unwrap().accepted);
+            await_turn(&mut alice, "Bob");
+            alice
+        });
+        await_turn(&mut bob, "Bob");
+        let alice = alice.join().unwrap();
+        assert_eq!(points(&alice, "Alice"), 70);
+        assert_eq!(points(&bob, "Alice"), 70);
+        assert!(alice.player().cards().hand.contains(&card("5S")));
+
+        /* Bob tries to block the King after his reaction clock ran out, so the King stands. */
+        let (mut alice, mut bob) = start("turn=0,reaction=1");
+        let bob = spawn(move || {
+            loop {
+                if let SessionEvent::ReactionRequested(_) = bob.next_event().unwrap() {
+                    break;
+                }
+            }
+            sleep(TOO_LATE);
+            bob.respond_to_reaction(true).unwrap();
+            await_turn(&mut bob, "Bob");
+            bob
+        });
+        assert!(matches!(
+            alice.next_event().unwrap(),
+            SessionEvent::ActionRequested
+        ));
+        let king = Action::from_play(
+            Play::new(card("KH"), Some(card("5S"))),
+            String::from("Alice"),
+            String::from("Bob"),
+        );
+        assert!(alice.submit_action(king).unwrap().accepted);
+        assert!(matches!(
+            alice.next_event().unwrap(),
+            SessionEvent::ActionRequested
+        ));
+        let turn_end = Action::new_turn_end("Alice");
+        assert!(alice.submit_action(turn_end).unwrap().accepted);
+        await_turn(&mut alice, "Bob");
+        let bob = bob.join().unwrap();
+        assert!(points(&alice, "Bob") < 40);
+        assert_eq!(points(&bob, "Bob"), points(&alice, "Bob"));
+        assert!(bob.player().cards().hand.contains(&card("JH")));
+    }
+
+    /// `Vec<u8>` that can be read after being moved into a `GameLog`.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn rules_and_reactions() {
+        let rules = Rules::from_str("turn=30,reaction=0").unwrap();
+        assert_eq!(
+            rules.turn_duration(),
+            Some(std::time::Duration::from_secs(30))
+        );
+        assert_eq!(rules.reaction_duration(), None);
+
+        let response = Response::from_rules(rules.clone());
+        let parsed = Response::from_str(&response.to_string()).unwrap();
+        assert_eq!(parsed.into_rules().unwrap(), rules);
+
+        let mut game_state = GameState::new();
+        game_state.add_player(PlayerDetails::new("ABC".to_string(), 100));
+        game_state.add_player(PlayerDetails::new("DEF".to_string(), 100));
+        let king = Action::new(
+            ActionType::PlayKing,
+            5,
+            "ABC".to_string(),
+            "DEF".to_string(),
+        );
+        let jack = Action::new(
+            ActionType::PlayJack,
+            15,
+            "DEF".to_string(),
+            "ABC".to_string(),
+        );
+        game_state.apply_action(&king, &rules).unwrap();
+        assert_eq!(game_state.player_by_name("DEF").unwrap().points(), 85);
+        game_state.apply_action(&jack, &rules).unwrap();
+        assert_eq!(game_state.player_by_name("DEF").unwrap().points(), 100);
+    }
+
+    #[test]
+    fn chat_messages() {
+        let whisper = ChatMessage::from_command("ABC", "/w DEF hi, how are you?").unwrap();
+        assert_eq!(whisper.to(), Some("DEF"));
+        assert_eq!(whisper.text(), "hi, how are you?");
+        let response = Response::from_chat(whisper.clone());
+        assert_eq!(response.to_string(), "RES,CHAT,ABC,DEF,hi, how are you?");
+        let parsed = Response::from_str(&response.to_string()).unwrap();
+        assert_eq!(parsed.into_chat().unwrap(), whisper);
+
+        let emote = ChatMessage::from_command("ABC", "/me waves").unwrap();
+        assert_eq!(emote.to_string(), "ABC,,/me waves");
+        assert_eq!(emote.emote(), Some("waves"));
+        assert!(ChatMessage::from_command("ABC", "/w DEF").is_none());
+        assert!(ChatMessage::from_command("ABC", "/dance").is_none());
+    }
+
+    #[test]
+    fn chat_relay_rules() {
+        use crate::server::chat::ChatRelay;
+        use crate::server::constants::{CHAT_RATE_LIMIT, MAX_CHAT_LEN};
+        use crate::server::StreamWriter;
+        use std::sync::mpsc;
+
+        let relay = ChatRelay::default();
+        let mut inboxes = Vec::new();
+        for (name, can_send) in [("ABC", true), ("DEF", true), ("GHI", false)] {
+            let (sender, receiver) = mpsc::channel();
+            relay.join(name, StreamWriter::channel(sender), can_send);
+            inboxes.push(receiver);
+        }
+        let received = |inbox: &mpsc::Receiver<Option<String>>| -> Vec<String> {
+            inbox.try_iter().flatten().collect()
+        };
+        let send = |from: &str, text: &str| {
+            relay.relay(
+                from,
+                ChatMessage::new(from.to_owned(), None, text.to_owned()),
+            );
+        };
+
+        /* Spectators can read chat, but what they send is only answered with why it was
+        rejected. */
+        send("GHI", "hello");
+        assert!(received(&inboxes[0]).is_empty());
+        assert_eq!(
+            received(&inboxes[2]),
+            ["RES,CHAT,*,GHI,Chat message rejected: spectators can only read chat"]
+        );
+
+        /* Messages can be up to `MAX_CHAT_LEN` characters long. */
+        let longest = "é".repeat(MAX_CHAT_LEN);
+        send("ABC", &longest);
+        let line = format!("RES,CHAT,ABC,,{longest}");
+        assert_eq!(received(&inboxes[1]), [line.as_str()]);
+        assert_eq!(received(&inboxes[2]), [line.as_str()]);
+        send("ABC", &format!("{longest}!"));
+        assert!(received(&inboxes[1]).is_empty());
+        assert_eq!(
+            received(&inboxes[0]),
+            [
+                format!("RES,CHAT,ABC,,{longest}"),
+                format!(
+                    "RES,CHAT,*,ABC,Chat message rejected: message is longer than {MAX_CHAT_LEN} characters"
+                ),
+            ]
+        );
+
+        /* Only `CHAT_RATE_LIMIT` messages can be sent at once, and a rejected message doesn't
+        count towards the limit. */
+        for n in 1..CHAT_RATE_LIMIT {
+            send("ABC", &n.to_string());
+        }
+        send("ABC", "one too many");
+        assert_eq!(received(&inboxes[1]).len(), CHAT_RATE_LIMIT - 1);
+        let rejected = received(&inboxes[0]);
+        assert_eq!(rejected.len(), CHAT_RATE_LIMIT);
+        assert!(
+            rejected[CHAT_RATE_LIMIT - 1]
+                .starts_with("RES,CHAT,*,ABC,Chat message rejected: too many"),
+            "{rejected:?}"
+        );
+        send("DEF", "still here");
+        assert_eq!(received(&inboxes[0]), ["RES,CHAT,DEF,,still here"]);
+    }
+
+    #[test]
+    fn lobby_rejects_invalid_names() {
+        use crate::error::KingdomError;
+
+        let lobby = Lobby::new(Rules::default(), None);
+        let too_long = "A".repeat(25);
+        for name in ["A:B", "*", "", "A,B", "A|B", "Abc1", "João", &too_long] {
+            assert!(
+                matches!(lobby.join(name), Err(KingdomError::InvalidName(_))),
+                "{name}"
+            );
+        }
+        assert!(lobby.players().is_empty());
+        assert!(lobby.join(&too_long[1..]).is_ok());
+    }
+
+    #[test]
+    fn lobby_ready_up() {
+        let lobby = Lobby::new(Rules::default(), None);
+        assert!(lobby.join("ABC").is_ok());
+        assert!(lobby.join("ABC").is_err());
+        assert!(lobby.start().is_err());
+        assert!(lobby.join("DEF").is_ok());
+        lobby.set_ready("ABC", true);
+        assert!(lobby.start().is_err());
+        lobby.set_ready("DEF", true);
+        assert_eq!(lobby.change_rules("turn=30").unwrap().turn_secs, 30);
+        assert!(lobby.start().is_err());
+
+        lobby.set_ready("ABC", true);
+        lobby.set_ready("DEF", true);
+        lobby.kick("DEF").unwrap();
+        assert!(lobby.join("DEF").is_err());
+        assert!(lobby.join("GHI").is_ok());
+        lobby.set_ready("GHI", true);
+        assert_eq!(lobby.start().unwrap(), vec!["ABC", "GHI"]);
+        assert_eq!(lobby.status_of("ABC"), LobbyStatus::Starting);
+        assert_eq!(lobby.status_of("DEF"), LobbyStatus::Kicked);
+
+        let response = Response::from_lobby(lobby.state_for("ABC"));
+        assert_eq!(response.to_string(), "RES,LOBBY,S,6,ABC:Y,GHI:Y");
+        let parsed = Response::from_str(&response.to_string()).unwrap();
+        assert_eq!(parsed.into_lobby().unwrap(), lobby.state_for("ABC"));
+    }
+
+    #[test]
+    fn room_choices() {
+        let create = RoomChoice::Create {
+            name: String::from("Fun Table"),
+            rules: String::from("turn=60,hand=4"),
+        };
+        let response = Response::from_room(create.clone());
+        assert_eq!(response.to_string(), "RES,ROOM,C,Fun Table,turn=60,hand=4");
+        let parsed = Response::from_str(&response.to_string()).unwrap();
+        assert_eq!(parsed.into_room().unwrap(), create);
+        let parsed = Response::from_str("RES,ROOM,J,1234").unwrap();
+        assert_eq!(
+            parsed.into_room().unwrap(),
+            RoomChoice::Join(String::from("1234"))
+        );
+
+        let rooms = vec![
+            RoomInfo::new(String::from("1234"), String::from("Host"), 2, 6),
+            RoomInfo::new(String::from("0042"), String::from("Fun Table"), 1, 4),
+        ];
+        let response = Response::from_rooms(rooms.clone());
+        assert_eq!(
+            response.to_string(),
+            "RES,ROOMS,1234:Host:2:6,0042:Fun Table:1:4"
+        );
+        let parsed = Response::from_str(&response.to_string()).unwrap();
+        assert_eq!(parsed.into_rooms().unwrap(), rooms);
+        let empty = Response::from_str("RES,ROOMS").unwrap();
+        assert!(empty.into_rooms().unwrap().is_empty());
+    }
+
+    #[test]
+    fn room_limit_and_cleanup() {
+        use crate::error::KingdomError;
+        use crate::server::constants::MAX_ROOMS;
+        use crate::server::session::ClientSession;
+        use std::time::{Duration, Instant};
+
+        /* Only `MAX_ROOMS` rooms can be open at once, each with its own join code. */
+        let rooms = RoomManager::bind("127.0.0.1:0", None, None).unwrap();
+        let mut handlers = Vec::new();
+        for _ in 0..MAX_ROOMS {
+            let lobby = Lobby::new(Rules::default(), None);
+            handlers.push(rooms.create_room("Test", lobby).unwrap());
+        }
+        let mut codes: Vec<&str> = handlers.iter().map(|(room, _)| room.code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), MAX_ROOMS);
+        let lobby = Lobby::new(Rules::default(), None);
+        assert!(matches!(
+            rooms.create_room("Test", lobby),
+            Err(KingdomError::TooManyRooms)
+        ));
+        rooms.close(handlers[0].0.code());
+        let lobby = Lobby::new(Rules::default(), None);
+        assert!(rooms.create_room("Test", lobby).is_ok());
+
+        /* A room that a player created is closed once the last player in it leaves. */
+        let rooms = RoomManager::bind("127.0.0.1:0", None, None).unwrap();
+        let address = rooms.accept().unwrap().to_string();
+        let mut session = ClientSession::connect(&address).unwrap();
+        let create = RoomChoice::Create {
+            name: String::from("Fun Table"),
+            rules: String::new(),
+        };
+        let created = session.choose_room(create).unwrap();
+        assert_eq!(created.len(), 1);
+        assert!(session.choose_name("ABC").unwrap());
+        assert_eq!(rooms.rooms().len(), 1);
+        drop(session);
+        let left = Instant::now();
+        while !rooms.rooms().is_empty() {
+            assert!(
+                left.elapsed() < Duration::from_secs(5),
+                "room wasn't closed"
+            );
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert!(rooms.room_by_code(created[0].code()).is_none());
+    }
+
+    #[test]
+    fn elimination_and_profiles() {
+        let mut game_state = GameState::from_str("3,ABC:10,DEF:10,GHI:10").unwrap();
+        let rules = Rules::default();
+        let king = Action::new(
+            ActionType::PlayKing,
+            0,
+            String::from("ABC"),
+            String::from("DEF"),
+        );
+        game_state.apply_action(&king, &rules).unwrap();
+        assert!(game_state.winner().is_none());
+        game_state
+            .apply_action(&Action::new_turn_end("ABC"), &rules)
+            .unwrap();
+        /* DEF has been eliminated, so their turn is skipped. */
+        assert_eq!(game_state.current_player().unwrap().name(), "GHI");
+        let king = Action::new(
+            ActionType::PlayKing,
+            5,
+            String::from("GHI"),
+            String::from("ABC"),
+        );
+        game_state.apply_action(&king, &rules).unwrap();
+        assert_eq!(game_state.winner().unwrap().name(), "GHI");
+
+        let mut profiles = Profiles::default();
+        let placements = game_state.placement_tiers(&["GHI", "ABC", "DEF"].map(String::from));
+        assert_eq!(placements.len(), 3);
+        profiles.record_game(&placements, &["ABC", "GHI"].map(String::from));
+        let winner = profiles.get("GHI").unwrap();
+        assert_eq!(
+            (winner.games_played, winner.wins, winner.eliminations),
+            (1, 1, 1)
+        );
+        assert_eq!(winner.rating, INITIAL_RATING + 16.0);
+        assert_eq!(profiles.get("ABC").unwrap().rating, INITIAL_RATING);
+        assert_eq!(profiles.standings()[2].name, "DEF");
+        let parsed = Profiles::from_str(&profiles.to_string()).unwrap();
+        assert_eq!(parsed, profiles);
+
+        /* Teammates place together, so the whole winning team wins, and teammates don't change
+         * each other's ratings. */
+        let mut game_state = GameState::from_str("4,ABC:100,DEF:0,GHI:0,JKL:0").unwrap();
+        game_state.assign_teams(2);
+        let placements =
+            game_state.placement_tiers(&["ABC", "DEF", "JKL", "GHI"].map(String::from));
+        assert_eq!(
+            placements,
+            [
+                ["ABC", "GHI"].map(String::from),
+                ["DEF", "JKL"].map(String::from)
+            ]
+        );
+        let mut profiles = Profiles::default();
+        profiles.record_game(&placements, &[]);
+        for (name, wins, rating) in [
+            ("ABC", 1, INITIAL_RATING + 16.0),
+            ("GHI", 1, INITIAL_RATING + 16.0),
+            ("DEF", 0, INITIAL_RATING - 16.0),
+            ("JKL", 0, INITIAL_RATING - 16.0),
+        ] {
+            let profile = profiles.get(name).unwrap();
+            assert_eq!((profile.wins, profile.rating), (wins, rating), "{name}");
+        }
+
+        /* Names are escaped, so a name that looks like a record can't stop the profiles from
+         * being opened again. */
+        let path = std::env::temp_dir().join("kingdom-kards-test-profiles.kkprofiles");
+        let _ = std::fs::remove_file(&path);
+        let store = ProfileStore::open(&path).unwrap();
+        let names = ["A|B", "C\nPLAYER|D|1|1|0|1200.00", "%7C", "ABC"].map(String::from);
+        store.record_game(&[names[..2].to_vec(), names[2..].to_vec()], &[]);
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("PLAYER|A%7CB|1|1|0|"), "{saved}");
+        assert!(ProfileStore::open(&path).is_ok());
+        let loaded = Profiles::load(&path).unwrap();
+        assert_eq!(loaded.standings().len(), names.len());
+        for name in &names {
+            assert_eq!(loaded.get(name).unwrap().games_played, 1, "{name}");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn game_log_replay() {
+        let rules = Rules::default();
+        let mut game_state = GameState::new();
+        game_state.add_player(PlayerDetails::new("ABC".to_string(), 100));
+        game_state.add_player(PlayerDetails::new("DEF".to_string(), 100));
+
+        let buffer = SharedBuffer::default();
+        let mut game_log =
+            GameLog::from_writer(Box::new(buffer.clone()), &rules, 42, &game_state).unwrap();
+        let actions = [
+            Action::new_turn_start("ABC"),
+            Action::new(
+                ActionType::PlayKing,
+                5,
+                "ABC".to_string(),
+                "DEF".to_string(),
+            ),
+            Action::new(
+                ActionType::PlayBlackAce,
+                0,
+                "ABC".to_string(),
+                "DEF".to_string(),
+            ),
+            Action::new_turn_end("ABC"),
+            Action::new(ActionType::PlayQueen, 2, "DEF".to_string(), String::new()),
+        ];
+        for action in actions.iter() {
+            let before = game_state.clone();
+            let result = game_state.apply_action(action, &rules);
+            game_log
+                .log_action(action, &result, &before, &game_state)
+                .unwrap();
+        }
+
+        let contents = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[3], "PLAYERS|2,ABC:100,DEF:100");
+        assert_eq!(
+            lines[5],
+            "ACTION|2|K,5,ABC,DEF|OK|DEF:-15|0|2,ABC:100,DEF:85"
+        );
+
+        let record = GameRecord::from_str(&contents).unwrap();
+        assert_eq!(record.seed(), 42);
+        assert_eq!(record.actions().len(), actions.len());
+        assert!(matches!(
+            record.actions()[2].result(),
+            ActionResult::Rejected(_)
+        ));
+
+        let replay = Replay::new(record);
+        assert!(replay.verify().is_ok());
+        let last = replay.state_after(actions.len()).unwrap();
+        assert_eq!(last.to_string(), "2,ABC:100,DEF:97");
+        assert_eq!(last.current_player_index(), 1);
+
+        /* Actions after a resume point are dropped. */
+        let resumed = format!("{contents}RESUME|4\n");
+        let record = GameRecord::from_str(&resumed).unwrap();
+        assert_eq!(record.actions().len(), 3);
+
+        /* Tampering with a recorded game state is caught. */
+        let tampered = contents.replace("DEF:85", "DEF:80");
+        let replay = Replay::new(GameRecord::from_str(&tampered).unwrap());
+        assert!(replay.verify().is_err());
+    }
+
+    #[test]
+    fn round_robin_tournament() {
+        let entrants: Vec<Entrant> = "ABC:aggressive,DEF:cautious,GHI:random"
+            .split(',')
+            .map(|entrant| Entrant::from_str(entrant).unwrap())
+            .collect();
+        assert_eq!(entrants[2].to_string(), "GHI:random");
+        assert!(Entrant::from_str("ABC:unknown").is_err());
+        assert!(Entrant::from_str("A1:random").is_err());
+
+        let log_dir = std::env::temp_dir().join("kingdom-kards-test-tournament");
+        let config = TournamentConfig {
+            rules: Rules::from_str("points=30").unwrap(),
+            seed: Some(7),
+            log_dir: Some(log_dir.clone()),
+            ..Default::default()
+        };
+        let standings = Tournament::new(entrants.clone(), config.clone())
+            .unwrap()
+            .run()
+            .unwrap();
+        /* Everyone plays both others, once from each seat. */
+        assert!(standings.iter().all(|standing| standing.games_played == 4));
+        assert_eq!(
+            standings.iter().map(|standing| standing.score).sum::<u32>(),
+            6
+        );
+
+        /* The same seed plays the same tournament, and every game can be replayed. */
+        let rerun = Tournament::new(entrants, config).unwrap().run().unwrap();
+        assert_eq!(rerun, standings);
+        let record = GameRecord::read(&log_dir.join("round-1-table-1-game-2.kklog")).unwrap();
+        assert!(Replay::new(record).verify().is_ok());
+    }
+
+    #[test]
+    fn team_mode() {
+        let mut rules = Rules::from_str("points=20,teams=2").unwrap();
+        assert!(Rules::from_str("teams=1").is_err());
+        assert!(Rules::from_str("friendly_fire=2").is_err());
+
+        let mut game_state = GameState::from_str("4,ABC:20,DEF:20,GHI:20,JKL:20").unwrap();
+        game_state.assign_teams(rules.teams);
+        assert_eq!(
+            game_state.to_string(),
+            "4,ABC:20:1,DEF:20:2,GHI:20:1,JKL:20:2"
+        );
+        assert_eq!(
+            GameState::from_str(&game_state.to_string()).unwrap(),
+            game_state
+        );
+        assert!(game_state.are_teammates("ABC", "GHI"));
+        assert!(!game_state.are_teammates("ABC", "DEF"));
+
+        let action = |action_type, from: &str, to: &str| {
+            Action::new(action_type, 0, from.to_string(), to.to_string())
+        };
+        /* Kings can't hit teammates unless friendly fire is on. */
+        let friendly_king = action(ActionType::PlayKing, "ABC", "GHI");
+        assert!(game_state.apply_action(&friendly_king, &rules).is_err());
+        rules.friendly_fire = true;
+        game_state.apply_action(&friendly_king, &rules).unwrap();
+        assert_eq!(game_state.player_by_name("GHI").unwrap().points(), 10);
+
+        /* Queens heal teammates, but not opponents. */
+        let queen = action(ActionType::PlayQueen, "ABC", "GHI");
+        game_state.apply_action(&queen, &rules).unwrap();
+        assert_eq!(game_state.player_by_name("GHI").unwrap().points(), 20);
+        let queen = action(ActionType::PlayQueen, "ABC", "DEF");
+        game_state.apply_action(&queen, &rules).unwrap();
+        assert_eq!(game_state.player_by_name("ABC").unwrap().points(), 30);
+
+        /* The team wins once every opponent is eliminated. */
+        game_state.subtract_points_from_player("DEF", 20).unwrap();
+        assert!(game_state.winner().is_none());
+        game_state.subtract_points_from_player("JKL", 20).unwrap();
+        assert_eq!(game_state.winner().unwrap().team(), Some(1));
+    }
+
+    #[test]
+    fn card_notation() {
+        let king = Card::from_str("KH").unwrap();
+        assert_eq!(king.to_string(), "KH");
+        assert_eq!(king.name(), "Red King of Hearts");
+        assert_eq!(Card::from_str("10s").unwrap().to_string(), "10S");
+        assert!(Card::from_str("1X").is_err());
+
+        let play = Play::from_str("KH+5S").unwrap();
+        assert_eq!(play, Play::new(king, Some(Card::from_str("5S").unwrap())));
+        assert_eq!(play.to_string(), "KH+5S");
+        /* Only Kings and Queens take attachments, and only Numbers can be attached. */
+        assert!(Play::from_str("JC+5S").is_err());
+        assert!(Play::from_str("KH+QS").is_err());
+
+        let action = Action::from_play(play, "ABC".to_string(), "DEF".to_string());
+        assert_eq!(action.to_string(), "K,5,ABC,DEF,KH+5S");
+        assert_eq!(Action::from_str("K,5,ABC,DEF,KH+5S").unwrap(), action);
+        assert_eq!(Action::from_str("K,5,ABC,DEF").unwrap().play(), None);
+
+        /* The cards have to match the action they were played for. */
+        let rules = Rules::default();
+        let mut game_state = GameState::from_str("2,ABC:20,DEF:20").unwrap();
+        let wrong = Action::from_str("K,7,ABC,DEF,KH+5S").unwrap();
+        assert!(game_state.apply_action(&wrong, &rules).is_err());
+        game_state.apply_action(&action, &rules).unwrap();
+        assert_eq!(game_state.player_by_name("DEF").unwrap().points(), 5);
+    }
+
+    #[test]
+    fn text_commands() {
+        let rules = Rules::default();
+        let game_state = GameState::from_str("2,ABC:100,DEF:100").unwrap();
+        let cards = PlayerCards::from_str("AS 4S,KH 7C 2D 5S 9H QS,").unwrap();
+        let mut player = Player::with_cards("ABC".to_string(), cards);
+
+        assert_eq!(Command::from_str("HAND").unwrap(), Command::Hand);
+        assert!(Command::from_str("end now").is_err());
+        assert!(Command::from_str("number 3 keep 2").is_err());
+
+        /* Commands that break the rules are rejected without moving any cards. */
+        let rejected = [
+            "play K",
+            "play 7 def",
+            "play K+7 ghi",
+            "play KS def",
+            "number 3 discard 2",
+            "number 2 discard 7 7",
+        ];
+        for command in rejected {
+            let command = Command::from_str(command).unwrap();
+            assert!(command.stage(&mut player, &game_state, &rules).is_err());
+        }
+        assert_eq!(player.hand_size(), 6);
+
+        let play = Command::from_str("play K+7 @def").unwrap();
+        let staged = play.stage(&mut player, &game_state, &rules).unwrap();
+        let action = staged.confirm();
+        assert_eq!(action.to_string(), "K,7,ABC,DEF,KH+7C");
+        assert_eq!(action.summary(), "ABC played KH+7C against DEF");
+
+        let number = Command::from_str("number 2 discard 5 qs").unwrap();
+        let staged = number.stage(&mut player, &game_state, &rules).unwrap();
+        let action = staged.confirm();
+        assert_eq!(action.to_string(), "N,2,ABC,,2D,5S QS");
+        /* The 2 and both discards leave, and nothing is drawn until the host deals. */
+        assert_eq!(player.cards().to_string(), "AS 4S,9H,KH 7C 5S QS 2D");
+    }
+
+    #[test]
+    fn staged_actions() {
+        let rules = Rules::default();
+        let game_state = GameState::from_str("2,ABC:100,DEF:15").unwrap();
+        let cards = PlayerCards::from_str("AS 4S,KH 7C 2D 5S 9H QS,").unwrap();
+        let mut player = Player::with_cards("ABC".to_string(), cards.clone());
+
+        /* Nothing is drawn while a Number is staged, and cancelling puts every card back. */
+        let number = Command::from_str("number 2 discard 5S QS").unwrap();
+        let staged = number.stage(&mut player, &game_state, &rules).unwrap();
+        assert_eq!(player.deck_size(), 2);
+        assert_eq!(
+            staged.preview(&game_state, &rules).unwrap(),
+            "ABC played 2D\n  Discarding 5S QS, then drawing 2 cards"
+        );
+        staged.cancel(&mut player);
+        assert_eq!(player.cards(), cards);
+
+        let king = Command::from_str("play KH+7C def").unwrap();
+        let staged = king.stage(&mut player, &game_state, &rules).unwrap();
+        assert_eq!(
+            staged.preview(&game_state, &rules).unwrap(),
+            "ABC played KH+7C against DEF\n  DEF: 15 -> 0 points, eliminated"
+        );
+        staged.cancel(&mut player);
+        assert_eq!(player.cards(), cards);
+    }
+
+    #[test]
+    fn number_transaction() {
+        use rand::SeedableRng;
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let cards = PlayerCards::from_str("AS,KH 7C 2D 5S 9H QS,3C").unwrap();
+        let mut player = Player::with_cards("ABC".to_string(), cards.clone());
+
+        /* Every card moves, or none do. */
+        let rejected = [
+            "N,2,ABC,,2D,5S",
+            "N,2,ABC,,2D,5S 8H",
+            "N,2,ABC,,2D,5S 5S",
+            "N,2,ABC,,2D,2D 5S",
+            "N,3,ABC,,2D,5S QS",
+            "N,2,ABC,",
+        ];
+        for action in rejected {
+            let action = Action::from_str(action).unwrap();
+            assert!(player.apply_cards(&action, &mut rng).is_err(), "{action}");
+            assert_eq!(player.cards(), cards);
+        }
+
+        let action = Action::from_str("N,2,ABC,,2D,5S QS").unwrap();
+        assert_eq!(
+            action.discards(),
+            [Card::from_str("5S").unwrap(), Card::from_str("QS").unwrap()]
+        );
+        player.apply_cards(&action, &mut rng).unwrap();
+        /* The last card in the deck is drawn, then the discard pile is shuffled into the deck. */
+        let after = player.cards();
+        assert_eq!(player.hand_size(), 5);
+        assert!(player.hand().contains(&Card::from_str("AS").unwrap()));
+        assert!(after.discard_pile.is_empty());
+        assert_eq!(after.deck.len(), 3);
+        let mut all: Vec<Card> = [after.deck, after.hand, after.discard_pile].concat();
+        let mut before: Vec<Card> = [cards.deck, cards.hand, cards.discard_pile].concat();
+        all.sort_by_key(Card::to_string);
+        before.sort_by_key(Card::to_string);
+        assert_eq!(all, before);
+    }
+
+    #[test]
+    fn scripted_input() {
+        let rules = Rules::default();
+        let game_state = GameState::from_str("2,ABC:100,DEF:15").unwrap();
+        let cards = PlayerCards::from_str("AS 4S,KH 7C 2D 5S 9H QS,").unwrap();
+
+        /* Answers that aren't valid are asked for again, the same as at the terminal. */
+        let mut input = ScriptedInput::new(["x", "1", "maybe", "yes", "2", "9", "2", "y"]);
+        let mut player = Player::with_cards("ABC".to_string(), cards.clone());
+        let staged = player.get_action(&game_state, &mut input).unwrap().unwrap();
+        let action = staged.confirm_with_prompt(&mut player, &game_state, &rules, &mut input);
+        assert_eq!(action.unwrap().to_string(), "K,7,ABC,DEF,KH+7C");
+        assert_eq!(input.remaining(), 0);
+
+        /* A script that runs out gets no input, which ends the turn. */
+        assert!(player
+            .get_action(&game_state, &mut input)
+            .unwrap()
+            .is_none());
+
+        /* A bot's session replays to the same actions once recorded. */
+        let path = std::env::temp_dir().join("kingdom-kards-test-input.txt");
+        let play = |input: &mut dyn InputSource| {
+            let mut player = Player::with_cards("ABC".to_string(), cards.clone());
+            (0..5)
+                .map(|_| match player.get_action(&game_state, input) {
+                    Ok(Some(staged)) => Ok(staged.action().to_string()),
+                    Ok(None) => Ok(String::new()),
+                    Err(err) => Err(err.to_string()),
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut recording = RecordingInput::create(&path, Box::new(BotInput::new(7))).unwrap();
+        let recorded = play(&mut recording);
+        drop(recording);
+        let mut replay = ScriptedInput::load(&path).unwrap();
+        assert_eq!(play(&mut replay), recorded);
+        assert_eq!(replay.remaining(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn client_session() {
+        use crate::server::constants::*;
+        use crate::server::session::*;
+        use crate::server::StreamHandler;
+        use crate::{error::KingdomError, utils::variant_eq};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let cards = PlayerCards::from_str("AS 4S,KH 7C 2D 5S 9H QS,").unwrap();
+        let dealt = OwnCards::of(&cards);
+        /* A host that plays one turn each for Alice and Bob, exactly as the real host would. */
+        let host = std::thread::spawn(move || {
+            let mut host = StreamHandler::new(listener.accept().unwrap().0);
+            let action = |action: &str| Response::from_action(Action::from_str(action).unwrap());
+            let choice = host
+                .send_request_await_response(ROOM_REQUEST, ROOM_RESPONSE)
+                .and_then(Response::into_room)
+                .unwrap();
+            assert_eq!(choice, RoomChoice::Join("1234".to_string()));
+            let rooms = vec![RoomInfo::new("1234".to_string(), "Test".to_string(), 1, 2)];
+            host.await_request_send_response(ROOMS_REQUEST, &Response::from_rooms(rooms))
+                .unwrap();
+            host.await_request_send_response(STATUS_REQUEST, STATUS_RESPONSE_YES)
+                .unwrap();
+            let name = host
+                .send_request_await_response(NAME_REQUEST, NAME_RESPONSE)
+                .and_then(Response::into_name)
+                .unwrap();
+            assert_eq!(name, "Alice");
+            host.await_request_send_response(STATUS_REQUEST, STATUS_RESPONSE_YES)
+                .unwrap();
+            let players = vec![("Alice".to_string(), true), ("Bob".to_string(), true)];
+            let lobby = LobbyState::new(LobbyStatus::Starting, 2, players);
+            host.send_response(&Response::from_lobby(lobby)).unwrap();
+            let game_state = GameState::from_str("2,Alice:100,Bob:100").unwrap();
+            let exchanges = [
+                (GAME_STATE_REQUEST, Response::from_game_state(game_state)),
+                (RULES_REQUEST, Response::from_rules(Rules::default())),
+                (CARDS_REQUEST, Response::from_cards(Some(dealt))),
+                (ACTION_REQUEST, action("S,120,Alice,")),
+                (VIEW_REQUEST, Response::from_view(TableView::default())),
+            ];
+            for (request, response) in &exchanges {
+                host.await_request_send_response(request, response).unwrap();
+            }
+            let mut played = Vec::new();
+            loop {
+                let action = host
+                    .send_request_await_response(ACTION_REQUEST, ACTION_RESPONSE)
+                    .and_then(Response::into_action)
+                    .unwrap();
+                if variant_eq(action.action_type(), &ActionType::TurnEnd) {
+                    break;
+                }
+                let cards = PlayerCards::from_str("AS 4S,2D 5S 9H QS,KH 7C").unwrap();
+                host.send_response(STATUS_RESPONSE_YES).unwrap();
+                host.send_response(&Response::from_cards(Some(OwnCards::of(&cards))))
+                    .unwrap();
+                played.push(action.to_string());
+            }
+            let exchanges = [
+                (ACTION_REQUEST, action("S,0,Bob,")),
+                (VIEW_REQUEST, Response::from_view(TableView::default())),
+                (ACTION_REQUEST, action("K,0,Bob,Alice,KS")),
+            ];
+            for (request, response) in &exchanges {
+                host.await_request_send_response(request, response).unwrap();
+            }
+            let reaction = host
+                .send_request_await_response(ACTION_REQUEST, ACTION_RESPONSE)
+                .and_then(Response::into_action)
+                .unwrap();
+            played.push(reaction.to_string());
+            for response in [action("E,0,Bob,"), action("O,0,Alice,")] {
+                host.await_request_send_response(ACTION_REQUEST, &response)
+                    .unwrap();
+            }
+            played
+        });
+
+        let mut session = ClientSession::connect(&address).unwrap();
+        /* Nothing is sent out of order. */
+        assert!(matches!(
+            session.choose_name("Alice"),
+            Err(KingdomError::OutOfOrder(_))
+        ));
+        let rooms = session.choose_room(RoomChoice::Join("1234".to_string()));
+        assert_eq!(rooms.unwrap()[0].name(), "Test");
+        assert!(session.choose_name("Alice").unwrap());
+        match session.next_event().unwrap() {
+            SessionEvent::Lobby(lobby) => assert_eq!(lobby.status(), LobbyStatus::Starting),
+            event => panic!("{event:?}"),
+        }
+        assert!(matches!(
+            session.next_event().unwrap(),
+            SessionEvent::GameStarted
+        ));
+        /* Only the number of cards in the deck is sent, not the cards themselves. */
+        assert_eq!(
+            session.player().cards(),
+            OwnCards::of(&cards).into_player_cards()
+        );
+        assert_eq!(session.deck_size(), 2);
+        match session.next_event().unwrap() {
+            SessionEvent::TurnStarted { player, seconds } => {
+                assert_eq!((&*player, seconds), ("Alice", 120))
+            }
+            event => panic!("{event:?}"),
+        }
+        assert!(matches!(
+            session.next_event().unwrap(),
+            SessionEvent::ActionRequested
+        ));
+        let (game_state, rules) = (session.game_state().clone(), session.rules().clone());
+        let command = Command::from_str("play K+7 bob").unwrap();
+        let staged = command
+            .stage(session.player_mut(), &game_state, &rules)
+            .unwrap();
+        let result = session.submit_action(staged.confirm()).unwrap();
+        assert_eq!(
+            result,
+            ActionResult {
+                accepted: true,
+                drawn: Vec::new()
+            }
+        );
+        assert_eq!(
+            session.game_state().player_by_name("Bob").unwrap().points(),
+            83
+        );
+        assert!(matches!(
+            session.next_event().unwrap(),
+            SessionEvent::ActionRequested
+        ));
+        session.end_turn().unwrap();
+
+        assert!(matches!(
+            session.next_event().unwrap(),
+            SessionEvent::TurnStarted { .. }
+        ));
+        match session.next_event().unwrap() {
+            SessionEvent::ReactionRequested(king) => assert_eq!(king.from_player(), "Bob"),
+            event => panic!("{event:?}"),
+        }
+        assert_eq!(
+            session
+                .game_state()
+                .player_by_name("Alice")
+                .unwrap()
+                .points(),
+            90
+        );
+        /* Without a Jack, blocking is declined. */
+        session.respond_to_reaction(true).unwrap();
+        assert!(matches!(
+            session.next_event().unwrap(),
+            SessionEvent::Action(_)
+        ));
+        match session.next_event().unwrap() {
+            SessionEvent::GameOver { winner, fair_deal } => {
+                assert_eq!(winner, "Alice");
+                assert!(fair_deal.is_none());
+            }
+            event => panic!("{event:?}"),
+        }
+        assert!(session.is_over());
+        assert_eq!(host.join().unwrap(), ["K,7,Alice,Bob,KH+7C", "X,0,Alice,"]);
+    }
+
+    #[test]
+    fn websocket_players() {
+        use crate::server::session::*;
+        use crate::server::websocket;
+        use tungstenite::Message;
+
+        let rooms = RoomManager::bind("127.0.0.1:0", None, None).unwrap();
+        let address = rooms.accept_websockets("127.0.0.1:0").unwrap().to_string();
+        let (room, _handlers) = rooms
+            .create_room("Test", Lobby::new(Rules::default(), None))
+            .unwrap();
+
+        /* Every line of the protocol is one text frame. */
+        let stream = std::net::TcpStream::connect(&address).unwrap();
+        let (mut socket, _) = tungstenite::client(format!("ws://{address}/"), stream).unwrap();
+        assert_eq!(socket.read().unwrap(), Message::text("REQ,ROOM"));
+        let list = Response::from_room(RoomChoice::List).to_string();
+        socket.send(Message::text(list)).unwrap();
+        socket.send(Message::text("REQ,ROOMS")).unwrap();
+        let rooms_response = Response::from_rooms(vec![room.info()]).to_string();
+        assert_eq!(socket.read().unwrap(), Message::text(rooms_response));
+        socket.close(None).unwrap();
+
+        /* A player on a WebSocket joins the same way as a player on TCP. */
+        let mut session = ClientSession::new(websocket::connect(&address).unwrap());
+        let rooms = session.choose_room(RoomChoice::Join(room.code().to_string()));
+        assert_eq!(rooms.unwrap(), [room.info()]);
+        assert!(session.choose_name("Alice").unwrap());
+        session.handle().set_ready(true).unwrap();
+        let mut ready = Vec::new();
+        while ready.len() < 2 {
+            match session.next_event().unwrap() {
+                SessionEvent::Lobby(lobby) => ready.push(lobby.players().to_vec()),
+                SessionEvent::Rules(rules) => assert_eq!(rules, Rules::default()),
+                event => panic!("{event:?}"),
+            }
+        }
+        assert_eq!(
+            ready,
+            [
+                vec![("Alice".to_string(), false)],
+                vec![("Alice".to_string(), true)]
+            ]
+        );
+    }
+
+    #[test]
+    fn web_table() {
+        use crate::server::web;
+        use std::io::Read;
+
+        let address = web::serve("127.0.0.1:0", 5465).unwrap();
+        let get = |path: &str| {
+            let mut stream = std::net::TcpStream::connect(address).unwrap();
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: {address}\r\n").unwrap();
+            write!(stream, "Connection: close\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        /* The page is told which port to play over WebSocket on. */
+        let page = get("/");
+        assert!(page.starts_with("HTTP/1.1 200"), "{page}");
+        assert!(page.contains(r#"<meta name="websocket-port" content="5465">"#));
+        assert!(get("/table.js").contains("class Connection"));
+        assert!(get("/table.css").starts_with("HTTP/1.1 200"));
+        assert!(get("/game.kksave").starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn http_api() {
+        use crate::server::admin::*;
+        use crate::server::api;
+        use std::io::Read;
+
+        let rooms = RoomManager::bind("127.0.0.1:0", None, None).unwrap();
+        let (room, _handlers) = rooms
+            .create_room("Test", Lobby::new(Rules::default(), None))
+            .unwrap();
+        let address = api::serve("127.0.0.1:0", Arc::clone(&rooms), room.code(), "secret").unwrap();
+        let call = |method: &str, path: &str, token: Option<&str>| {
+            let mut stream = std::net::TcpStream::connect(address).unwrap();
+            write!(stream, "{method} {path} HTTP/1.1\r\nHost: {address}\r\n").unwrap();
+            if let Some(token) = token {
+                write!(stream, "Authorization: Bearer {token}\r\n").unwrap();
+            }
+            write!(stream, "Content-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            let (head, body) = response.split_once("\r\n\r\n").unwrap();
+            (head[9..12].parse::<u16>().unwrap(), body.to_string())
+        };
+
+        let code = room.code();
+        assert_eq!(
+            call("GET", "/rooms", None),
+            (
+                200,
+                format!(
+                    r#"[{{"code":"{code}","name":"Test","players":0,"seats":6,"status":"waiting"}}]"#
+                )
+            )
+        );
+        assert_eq!(
+            call("GET", "/state", None),
+            (
+                200,
+                format!(
+                    r#"{{"room":"{code}","name":"Test","status":"waiting","turn":null,"players":[]}}"#
+                )
+            )
+        );
+        /* Admin endpoints need the token. */
+        assert_eq!(call("POST", "/pause", None).0, 401);
+        assert_eq!(call("POST", "/pause", Some("guess")).0, 401);
+        assert_eq!(call("POST", "/pause", Some("secreT")).0, 401);
+        assert_eq!(call("POST", "/pause", Some("secrets")).0, 401);
+        assert_eq!(call("GET", "/pause", Some("secret")).0, 405);
+        assert_eq!(call("GET", "/game.kksave", None).0, 404);
+        assert_eq!(
+            call("POST", "/pause", Some("secret")),
+            (
+                400,
+                String::from(r#"{"error":"Admin: the game hasn't started"}"#)
+            )
+        );
+
+        /* Once the game starts, every action is published to the room's monitor. */
+        let mut game_state = GameState::from_str("2,Alice:100,Bob:100").unwrap();
+        room.monitor().start(&game_state);
+        let king = Action::from_str("K,5,Alice,Bob,KH+5S").unwrap();
+        game_state.apply_action(&king, &Rules::default()).unwrap();
+        room.monitor().record(&king, true, &game_state);
+        let (status, state) = call("GET", "/state", None);
+        assert_eq!(status, 200);
+        assert!(
+            state.contains(r#""status":"playing","turn":"Alice""#),
+            "{state}"
+        );
+        assert!(state.contains(r#"{"name":"Bob","points":85,"team":null,"eliminated":false}"#));
+        let (_, log) = call("GET", "/log?since=0", None);
+        assert_eq!(
+            log,
+            r#"{"last":1,"events":[{"seq":1,"action":"K,5,Alice,Bob,KH+5S","summary":"Alice played KH+5S against Bob","accepted":true}]}"#
+        );
+        assert_eq!(
+            call("GET", "/log?since=1", None).1,
+            r#"{"last":1,"events":[]}"#
+        );
+
+        /* A rejected play stays in the player's hand, so none of its cards are published. */
+        let rejected = Action::from_str("N,2,Bob,,2D,5S QS").unwrap();
+        room.monitor().record(&rejected, false, &game_state);
+        let (_, log) = call("GET", "/log?since=1", None);
+        assert_eq!(
+            log,
+            r#"{"last":2,"events":[{"seq":2,"action":"N,0,Bob,","summary":"Bob played a number card","accepted":false}]}"#
+        );
+        for card in ["2D", "5S", "QS"] {
+            assert!(!log.contains(card), "{log}");
+        }
+
+        /* Commands are queued for the game, which takes the ones that can't wait first. */
+        assert_eq!(call("POST", "/kick?player=Carol", Some("secret")).0, 404);
+        assert_eq!(call("POST", "/kick?player=Bob", Some("secret")).0, 200);
+        /* Query keys and values are percent-decoded before they are matched. */
+        let kick = format!("/kick?r%6Fom={code}&player=B%6F%62");
+        assert_eq!(call("POST", &kick, Some("secret")).0, 200);
+        assert_eq!(call("POST", "/end", Some("secret")).0, 200);
+        assert_eq!(call("POST", "/pause", Some("secret")).0, 200);
+        assert_eq!(room.monitor().status(), GameStatus::Paused);
+        assert_eq!(
+            room.monitor().next_interrupt("Alice"),
+            Some(AdminCommand::End)
+        );
+        assert_eq!(
+            room.monitor().next_command(),
+            Some(AdminCommand::Kick(String::from("Bob")))
+        );
+        assert_eq!(
+            room.monitor().next_command(),
+            Some(AdminCommand::Kick(String::from("Bob")))
+        );
+        assert_eq!(call("POST", "/resume", Some("secret")).0, 200);
+        room.monitor().finish();
+        assert!(call("GET", "/rooms", None).1.contains(r#""status":"over""#));
+    }
+
+    #[test]
+    fn set_points() {
+        let mut game_state = GameState::from_str("3,Alice:100,Bob:100,Carol:100").unwrap();
+        let kick = Action::from_str("P,0,Bob,").unwrap();
+        assert_eq!(kick.summary(), "the host set Bob's points to 0");
+        game_state.apply_action(&kick, &Rules::default()).unwrap();
+        assert!(game_state.player_by_name("Bob").unwrap().is_eliminated());
+        /* Every turn is whoever the host starts it for, so a kicked player can be passed over. */
+        let start = Action::from_str("S,0,Carol,").unwrap();
+        game_state.apply_action(&start, &Rules::default()).unwrap();
+        assert_eq!(game_state.current_player().unwrap().name(), "Carol");
+    }
+
+    #[test]
+    fn admin_console() {
+        use crate::{error::KingdomError, server::admin::*};
+
+        let monitor = Arc::new(GameMonitor::default());
+        let mut game_state = GameState::from_str("3,Alice:100,Bob:100,Carol:0").unwrap();
+        monitor.start(&game_state);
+        assert!(matches!(
+            monitor.send(AdminCommand::SetPoints(String::from("Dave"), 5)),
+            Err(KingdomError::PlayerNotFound(_))
+        ));
+        assert!(monitor
+            .send(AdminCommand::SetPoints(String::from("Carol"), 5))
+            .is_err());
+
+        /* Only commands that end the turn player's turn can't wait for their next action. */
+        for command in [
+            AdminCommand::SetPoints(String::from("Bob"), 0),
+            AdminCommand::SetPoints(String::from("Alice"), 50),
+            AdminCommand::Save(None),
+            AdminCommand::Skip,
+        ] {
+            monitor.send(command).unwrap();
+        }
+        assert_eq!(monitor.next_interrupt("Alice"), Some(AdminCommand::Skip));
+        assert_eq!(
+            monitor.next_interrupt("Bob"),
+            Some(AdminCommand::SetPoints(String::from("Bob"), 0))
+        );
+        assert_eq!(monitor.next_interrupt("Alice"), None);
+        assert!(AdminCommand::Shutdown.interrupts("Alice"));
+
+        /* Passing over a player between turns is published without an action. */
+        game_state.move_next_player();
+        monitor.update(&game_state);
+        let published = monitor.game_state().unwrap();
+        assert_eq!(published.current_player().unwrap().name(), "Bob");
+
+        let waiter = {
+            let monitor = Arc::clone(&monitor);
+            std::thread::spawn(move || monitor.wait_until_over())
+        };
+        monitor.finish();
+        waiter.join().unwrap();
+        assert_eq!(monitor.next_command(), None);
+    }
+
+    #[test]
+    fn hidden_information() {
+        use rand::SeedableRng;
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let mut alice = Player::with_cards(
+            "Alice".to_string(),
+            PlayerCards::from_str("2C 3C,KH 7C 2D 5S 9H,6D").unwrap(),
+        );
+        let bob = PlayerCards::from_str("4H 5H,QS 8S,").unwrap();
+
+        /* A Number's discards are public, but the cards drawn for it are not. */
+        let number = Action::from_str("N,2,Alice,,2D,5S 9H").unwrap();
+        let hand = alice.hand().clone();
+        alice.apply_cards(&number, &mut rng).unwrap();
+        let broadcast = Response::from_action(public_action(&number)).to_string();
+        assert_eq!(broadcast, "RES,ACT,N,2,Alice,,2D,5S 9H");
+        for drawn in alice.hand().iter().filter(|card| !hand.contains(card)) {
+            assert!(!broadcast.contains(&drawn.to_string()));
+        }
+
+        let cards = vec![
+            ("Alice".to_string(), alice.cards()),
+            ("Bob".to_string(), bob),
+        ];
+        let viewers = [
+            Viewer::Player("Alice".to_string()),
+            Viewer::Player("Bob".to_string()),
+            Viewer::Spectator,
+        ];
+        for viewer in viewers {
+            let sent = Response::from_view(TableView::build(&cards, &viewer)).to_string();
+            let view = Response::from_str(&sent).unwrap().into_view().unwrap();
+            for ((name, owned), seat) in cards.iter().zip(view.seats()) {
+                let visible = viewer.can_see_hand(name);
+                assert_eq!(seat.hand.as_ref(), visible.then_some(&owned.hand));
+                assert_eq!(seat.hand_size, owned.hand.len());
+                assert_eq!(seat.deck_size, owned.deck.len());
+                assert_eq!(seat.discard_pile, owned.discard_pile);
+                /* Nobody is sent a card from any deck, or from a hand that isn't theirs. */
+                let seat_sent = sent
+                    .split(',')
+                    .find(|part| part.starts_with(&format!("{name}:")))
+                    .unwrap();
+                let private = owned.hand.iter().filter(|_| !visible);
+                for card in owned.deck.iter().chain(private) {
+                    let card = card.to_string();
+                    assert!(
+                        !seat_sent.split([':', ' ']).any(|code| code == card),
+                        "{card} was sent to {viewer:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hidden_information_in_a_game() {
+        use crate::server::admin::AdminCommand;
+        use crate::server::session::*;
+        use crate::server::StreamHandler;
+        use crate::tournament::bot::{Bot, Seat, Strategy};
+        use crate::utils::variant_eq;
+        use std::sync::mpsc;
+
+        /* The game log and saves go in a directory of their own, which is removed at the end. */
+        let data_dir = std::env::temp_dir().join("kingdom-kards-test-hidden-information");
+        let _ = std::fs::remove_dir_all(&data_dir);
+        std::fs::create_dir_all(&data_dir).unwrap();
+        let rooms = RoomManager::bind("127.0.0.1:0", None, Some(data_dir.clone())).unwrap();
+        let address = rooms.accept().unwrap().to_string();
+        /* Two bots play a whole game, while every line the host sends each of them is kept. */
+        let play = move |name: &str, choice: RoomChoice, code: mpsc::Sender<String>| {
+            let handler = StreamHandler::new(std::net::TcpStream::connect(&address).unwrap());
+            let sent = Arc::new(Mutex::new(Vec::new()));
+            let received = Arc::clone(&sent);
+            handler.on_line(move |line| received.lock().unwrap().push(line.to_owned()));
+            let mut session = ClientSession::new(handler);
+            let room = session.choose_room(choice).unwrap();
+            let _ = code.send(room[0].code().to_owned());
+            assert!(session.choose_name(name).unwrap());
+            session.handle().set_ready(true).unwrap();
+            let mut bot = Bot::new(Strategy::Random, 0);
+            let mut rejected = false;
+            let mut turns = 0;
+            let fair_deal = loop {
+                match session.next_event().unwrap() {
+                    SessionEvent::TurnStarted { .. } => {
+                        turns += 1;
+                        /* Hands can run out of playable cards, so the host ends the game. */
+                        if turns == 40 && name == "Alice" {
+                            let room = rooms.room_by_code(room[0].code()).unwrap();
+                            room.monitor().send(AdminCommand::End).unwrap();
+                        }
+                    }
+                    SessionEvent::ActionRequested => {
+                        let game_state = session.game_state().clone();
+                        let rules = session.rules().clone();
+                        let action = bot.next_action(session.player_mut(), &game_state, &rules);
+                        match action.filter(|_| !rejected) {
+                            Some(action) => {
+                                rejected = !session.submit_action(action).unwrap().accepted;
+                            }
+                            None => {
+                                session.end_turn().unwrap();
+                                rejected = false;
+                            }
+                        }
+                    }
+                    SessionEvent::ReactionRequested(_) => {
+                        let block = session.player().has_jack();
+                        session.respond_to_reaction(block).unwrap();
+                    }
+                    SessionEvent::GameOver { fair_deal, .. } => break fair_deal,
+                    _ => {}
+                }
+            };
+            let sent = sent.lock().unwrap().clone();
+            (fair_deal, sent)
+        };
+
+        let rules = "points=30,turn=0,reaction=0,fair_deal=1".to_owned();
+        let (code, created) = mpsc::channel();
+        let alice = {
+            let play = play.clone();
+            let choice = RoomChoice::Create {
+                name: "Leaks".to_owned(),
+                rules,
+            };
+            std::thread::spawn(move || play("Alice", choice, code))
+        };
+        let choice = RoomChoice::Join(created.recv().unwrap());
+        let (code, _) = mpsc::channel();
+        let bob = std::thread::spawn(move || play("Bob", choice, code));
+
+        for (name, seat) in [("Alice", alice), ("Bob", bob)] {
+            let (fair_deal, sent) = seat.join().unwrap();
+            assert!(matches!(fair_deal, Some(Ok(()))), "{fair_deal:?}");
+            let mut is_over = false;
+            for line in sent.iter().filter(|line| line.starts_with("RES,")) {
+                let response = Response::from_str(line).unwrap();
+                /* Every line is understood in full, so nothing else can be hidden in it. */
+                assert_eq!(response.to_string(), *line);
+                match response.response_type() {
+                    /* A player's own cards only say how many are in their deck. */
+                    ResponseType::Cards(Some(cards)) => assert_eq!(
+                        cards.deck_size + cards.hand.len() + cards.discard_pile.len(),
+                        52,
+                        "{line}"
+                    ),
+                    ResponseType::View(Some(view)) => {
+                        for seat in view.seats() {
+                            assert_eq!(seat.hand.is_some(), seat.name == name, "{line}");
+                        }
+                    }
+                    ResponseType::PlayerAction(Some(action)) => {
+                        assert_eq!(*action, public_action(action));
+                        is_over |= variant_eq(action.action_type(), &ActionType::GameOver);
+                    }
+                    /* The decks are only revealed for the fair deal once the game is over. */
+                    ResponseType::Deal(Some(message)) => {
+                        assert!(is_over || !matches!(**message, DealMessage::Reveal(_)))
+                    }
+                    _ => {}
+                }
+            }
+            assert!(is_over);
+        }
+        let written: Vec<String> = std::fs::read_dir(&data_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert!(
+            written.iter().any(|file| file.ends_with(".kklog")),
+            "{written:?}"
+        );
+        assert!(
+            written.iter().any(|file| file.ends_with(".kksave")),
+            "{written:?}"
+        );
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn fair_deal() {
+        use rand::SeedableRng;
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let salt = random_hex(&mut rng);
+        let names = ["Alice", "Bob"];
+        let mut seats = Vec::new();
+        for name in names {
+            let mut deck = Card::full_deck();
+            rand::seq::SliceRandom::shuffle(&mut deck[..], &mut rng);
+            seats.push((name.to_string(), random_hex(&mut rng), deck));
+        }
+        let commitments: Vec<(String, String)> = seats
+            .iter()
+            .map(|(name, _, deck)| (name.clone(), commitment(deck, &salt)))
+            .collect();
+        let entropy: Vec<(String, String)> = seats
+            .iter()
+            .map(|(name, entropy, _)| (name.clone(), entropy.clone()))
+            .collect();
+        let reveal = Reveal { salt, seats };
+
+        /* Every message survives being sent. */
+        let messages = [
+            DealMessage::Commit(commitments.clone()),
+            DealMessage::Entropy(entropy[0].1.clone()),
+            DealMessage::Reveal(reveal.clone()),
+        ];
+        for message in messages {
+            let sent = Response::from_deal(message.clone()).to_string();
+            assert_eq!(
+                Response::from_str(&sent).unwrap().into_deal().unwrap(),
+                message
+            );
+        }
+        assert!(DealMessage::from_str("E,not-hex").is_err());
+
+        let mut audit = Audit::new(commitments, entropy[1].1.clone());
+        let mut deck = reveal.seats[1].2.clone();
+        mix(&mut deck, &entropy, "Bob");
+        audit.set_dealt(deal("Bob", deck.clone(), 5).hand);
+        assert!(audit.check("Bob", &reveal, 5).is_ok());
+        /* Alice's deck was mixed differently, so it doesn't match what Bob was dealt. */
+        assert!(audit.check("Alice", &reveal, 5).is_err());
+
+        let mut tampered = reveal.clone();
+        tampered.seats[0].2.swap(0, 1);
+        assert!(audit.check("Bob", &tampered, 5).is_err());
+        let mut ignored = reveal.clone();
+        ignored.seats[1].1 = random_hex(&mut rng);
+        assert!(audit.check("Bob", &ignored, 5).is_err());
+        let mut stacked = audit.clone();
+        stacked.set_dealt(deal("Bob", Card::full_deck(), 5).hand);
+        assert!(stacked.check("Bob", &reveal, 5).is_err());
+
+        /* Bob plays through his whole deck, so his discard pile is shuffled back into it. Every
+         * card he draws is checked, including the ones after the reshuffle, which has to use the
+         * rng that everyone's entropy chose. */
+        let play_through = |mut rng: rand_chacha::ChaCha8Rng| {
+            let mut audit = audit.clone();
+            let mut bob = Player::with_cards("Bob".to_owned(), deal("Bob", deck.clone(), 5));
+            for _ in 0..60 {
+                let card = bob.hand()[0];
+                bob.remove_card_from_hand(&card);
+                let hand = bob.hand().clone();
+                let drawn = bob.draw_with_reshuffle(1, &mut rng);
+                audit.record_draw(hand, drawn);
+            }
+            audit
+        };
+        let fair = play_through(reshuffle_rng(&entropy, "Bob"));
+        assert!(fair.check("Bob", &reveal, 5).is_ok());
+        let unfair = play_through(rand_chacha::ChaCha8Rng::seed_from_u64(0));
+        assert!(unfair.check("Bob", &reveal, 5).is_err());
+    }
+
+    /* Scenarios for every paragraph of Rules.md, played against the host-side rules. */
+
+    #[test]
+    fn rules_starting_hand_and_elimination() {
+        let rules = Rules::default();
+        assert_eq!(rules.starting_points, 100);
+        let dealt = deal("Alice", Card::full_deck(), rules.hand_size);
+        assert_eq!((dealt.hand.len(), dealt.deck.len()), (5, 47));
+
+        Scenario::new()
+            .player("Alice", 100)
+            .hand("KH 5S")
+            .player("Bob", 15)
+            .play("K,5,Alice,Bob,KH+5S")
+            .assert_accepted()
+            .assert_points("Bob", 0)
+            .assert_eliminated("Bob")
+            .assert_winner("Alice");
+    }
+
+    #[test]
+    fn rules_played_cards_go_to_discard_pile() {
+        Scenario::new()
+            .player("Alice", 100)
+            .hand("KH 5S QD 3C")
+            .player("Bob", 100)
+            .hand("KS")
+            .play("K,0,Bob,Alice,KS")
+            .assert_rejected("isn't 'Bob''s turn")
+            .play("K,5,Alice,Bob,KH+5S")
+            .assert_accepted()
+            .play("Q,3,Alice,,QD+3C")
+            .assert_accepted()
+            .assert_hand("Alice", "")
+            .assert_discard_pile("Alice", "KH 5S QD 3C")
+            .play("K,0,Alice,Bob,KH")
+            .assert_rejected("'KH' is not in your hand")
+            .end_turn()
+            .assert_turn("Bob")
+            .play("K,0,Bob,Alice,KS")
+            .assert_accepted()
+            .assert_points("Alice", 103);
+    }
+
+    #[test]
+    fn rules_reshuffle_discard_pile_into_deck() {
+        let scenario = Scenario::new()
+            .player("Alice", 100)
+            .hand("2C 5S 3C")
+            .deck("8D")
+            .discard_pile("9H 10H")
+            .player("Bob", 100)
+            .play("N,2,Alice,,2C,5S 3C")
+            .assert_accepted()
+            .assert_discard_pile("Alice", "");
+        /* The one card left in the deck is drawn first, then the rest come from the discard pile
+         * once it has been shuffled into the deck. */
+        let cards = scenario.cards("Alice");
+        assert_eq!(cards.hand.len(), 2);
+        assert_eq!(cards.hand[0], Card::from_str("8D").unwrap());
+        assert_eq!(cards.deck.len(), 4);
+        let mut all: Vec<String> = cards
+            .hand
+            .iter()
+            .chain(&cards.deck)
+            .map(Card::to_string)
+            .collect();
+        all.sort();
+        assert_eq!(all, ["10H", "2C", "3C", "5S", "8D", "9H"]);
+    }
+
+    #[test]
+    fn rules_king() {
+        Scenario::new()
+            .player("Alice", 100)
+            .hand("KH KD 5S")
+            .player("Bob", 100)
+            .play("K,0,Alice,Bob,KH")
+            .assert_points("Bob", 90)
+            .play("K,5,Alice,Bob,KD+5S")
+            .assert_points("Bob", 75)
+            .assert_points("Alice", 100);
+    }
+
+    #[test]
+    fn rules_queen() {
+        Scenario::new()
+            .player("Alice", 50)
+            .hand("QH QD 6S")
+            .player("Bob", 100)
+            .play("Q,0,Alice,,QH")
+            .assert_points("Alice", 60)
+            .play("Q,6,Alice,,QD+6S")
+            .assert_points("Alice", 76)
+            .assert_points("Bob", 100);
+    }
+
+    /* Jacks played on Queens and Red Aces are in `rules_jack_on_queen` and
+     * `rules_jack_on_red_ace`, which are ignored until the host supports them. */
+    #[test]
+    fn rules_jack() {
+        Scenario::new()
+            .player("Alice", 100)
+            .hand("KH 8S KD JD")
+            .player("Bob", 100)
+            .hand("JC")
+            .play("J,0,Alice,Bob,JD")
+            .assert_rejected("not allowed")
+            .play("K,8,Alice,Bob,KH+8S")
+            .assert_points("Bob", 82)
+            .block("JC")
+            .assert_accepted()
+            .assert_points("Bob", 100)
+            .assert_discard_pile("Bob", "JC")
+            .play("K,0,Alice,Bob,KD")
+            .block("JC")
+            .assert_rejected("'JC' is not in your hand")
+            .assert_points("Bob", 90);
+    }
+
+    #[test]
+    fn rules_number() {
+        Scenario::new()
+            .player("Alice", 100)
+            .hand("3S 4C 5D 6H 7C")
+            .deck("2H 9H 10H")
+            .player("Bob", 100)
+            .play("N,3,Alice,,3S,4C")
+            .assert_rejected("needs 3 cards discarded")
+            .assert_hand("Alice", "3S 4C 5D 6H 7C")
+            .play("N,3,Alice,,3S,4C 5D 6H")
+            .assert_accepted()
+            .assert_hand("Alice", "7C 10H 9H 2H")
+            .assert_deck("Alice", "")
+            .assert_discard_pile("Alice", "4C 5D 6H 3S");
+    }
+
+    #[test]
+    #[ignore = "Rules.md §Card Effects (Black Ace) not implemented"]
+    fn rules_black_ace() {
+        Scenario::new()
+            .player("Alice", 100)
+            .hand("AS")
+            .discard_pile("KH")
+            .player("Bob", 100)
+            .play("B,0,Alice,,AS")
+            .assert_accepted()
+            .assert_hand("Alice", "KH")
+            .assert_discard_pile("Alice", "AS");
+    }
+
+    #[test]
+    #[ignore = "Rules.md §Card Effects (Red Ace) not implemented"]
+    fn rules_red_ace() {
+        Scenario::new()
+            .player("Alice", 100)
+            .hand("KH 4S")
+            .player("Bob", 100)
+            .hand("AH")
+            .player("Carol", 100)
+            .play("K,4,Alice,Bob,KH+4S")
+            .play("R,14,Bob,Carol,AH")
+            .assert_accepted()
+            .assert_points("Bob", 100)
+            .assert_points("Carol", 86)
+            .assert_discard_pile("Bob", "AH");
+    }
+
+    #[test]
+    #[ignore = "Rules.md §Card Effects (Jack on a Queen) not implemented"]
+    fn rules_jack_on_queen() {
+        Scenario::new()
+            .player("Alice", 50)
+            .hand("QH")
+            .player("Bob", 100)
+            .hand("JC")
+            .play("Q,0,Alice,,QH")
+            .assert_points("Alice", 60)
+            .block("JC")
+            .assert_accepted()
+            .assert_points("Alice", 50);
+    }
+
+    #[test]
+    #[ignore = "Rules.md §Card Effects (Jack on a Red Ace) not implemented"]
+    fn rules_jack_on_red_ace() {
+        Scenario::new()
+            .player("Alice", 100)
+            .hand("KH JD")
+            .player("Bob", 100)
+            .hand("AH")
+            .play("K,0,Alice,Bob,KH")
+            .play("R,10,Bob,Alice,AH")
+            .assert_points("Alice", 90)
+            .block("JD")
+            .assert_accepted()
+            .assert_points("Alice", 100)
+            .assert_points("Bob", 90);
+    }
+
+    /* Until Aces are supported, they are rejected without moving any cards. */
+    #[test]
+    fn unsupported_aces_move_no_cards() {
+        Scenario::new()
+            .player("Alice", 100)
+            .hand("KH AS")
+            .discard_pile("QH")
+            .player("Bob", 100)
+            .hand("AH")
+            .play("B,0,Alice,,AS")
+            .assert_rejected("not supported yet")
+            .assert_hand("Alice", "KH AS")
+            .assert_discard_pile("Alice", "QH")
+            .play("K,0,Alice,Bob,KH")
+            .play("R,10,Bob,Alice,AH")
+            .assert_rejected("not supported yet")
+            .assert_hand("Bob", "AH")
+            .assert_points("Bob", 90)
+            .assert_points("Alice", 100);
+    }
+
+    /* Generators for property tests. Names never contain the separators used by the protocol,
+     * since players can't choose names with them. */
+    fn name_strategy() -> impl Strategy<Value = String> {
+        "[A-Za-z][A-Za-z0-9_ ]{0,11}"
+    }
+
+    fn card_strategy() -> impl Strategy<Value = Card> {
+        prop::sample::select(Card::full_deck())
+    }
+
+    fn cards_strategy() -> impl Strategy<Value = Vec<Card>> {
+        prop::collection::vec(card_strategy(), 0..8)
+    }
+
+    fn play_strategy() -> impl Strategy<Value = Play> {
+        let numbers: Vec<Card> = Card::full_deck()
+            .into_iter()
+            .filter(|card| card.value().is_number())
+            .collect();
+        (
+            card_strategy(),
+            prop::option::of(prop::sample::select(numbers)),
+        )
+            .prop_map(|(card, attachment)| {
+                let can_attach = matches!(card.value(), Value::King | Value::Queen);
+                Play::new(card, attachment.filter(|_| can_attach))
+            })
+    }
+
+    fn action_type_strategy() -> impl Strategy<Value = ActionType> {
+        (0..12).prop_map(|index| match index {
+            0 => ActionType::PlayKing,
+            1 => ActionType::PlayQueen,
+            2 => ActionType::PlayJack,
+            3 => ActionType::PlayNumber,
+            4 => ActionType::PlayBlackAce,
+            5 => ActionType::PlayRedAce,
+            6 => ActionType::TurnStart,
+            7 => ActionType::TurnEnd,
+            8 => ActionType::GameOver,
+            9 => ActionType::Status,
+            10 => ActionType::SetPoints,
+            _ => ActionType::None,
+        })
+    }
+
+    fn action_strategy() -> impl Strategy<Value = Action> {
+        (
+            action_type_strategy(),
+            any::<u16>(),
+            name_strategy(),
+            prop::option::of(name_strategy()),
+            prop::option::of((play_strategy(), cards_strategy())),
+        )
+            .prop_map(|(action_type, attachment, from, to, play)| {
+                let action = Action::new(action_type, attachment, from, to.unwrap_or_default());
+                match play {
+                    Some((play, discards)) => action.with_play(play).with_discards(discards),
+                    None => action,
+                }
+            })
+    }
+
+    fn game_state_strategy() -> impl Strategy<Value = GameState> {
+        let player = (name_strategy(), any::<u16>(), prop::option::of(1..=4u8));
+        prop::collection::vec(player, 0..6).prop_map(|players| {
+            let mut game_state = GameState::new();
+            for (name, points, team) in players {
+                let mut player = PlayerDetails::new(name, points);
+                player.set_team(team);
+                game_state.add_player(player);
+            }
+            game_state
+        })
+    }
+
+    fn player_cards_strategy() -> impl Strategy<Value = PlayerCards> {
+        (cards_strategy(), cards_strategy(), cards_strategy()).prop_map(
+            |(deck, hand, discard_pile)| PlayerCards {
+                deck,
+                hand,
+                discard_pile,
+            },
+        )
+    }
+
+    fn rules_strategy() -> impl Strategy<Value = Rules> {
+        (
+            (any::<u16>(), any::<u8>(), any::<u16>(), any::<u16>()),
+            (
+                any::<u16>(),
+                any::<u16>(),
+                prop_oneof![Just(0), 2..=MAX_TEAMS],
+            ),
+            (any::<bool>(), any::<bool>()),
+        )
+            .prop_map(|(points, clocks, flags)| Rules {
+                starting_points: points.0,
+                hand_size: points.1,
+                king_damage: points.2,
+                queen_heal: points.3,
+                turn_secs: clocks.0,
+                reaction_secs: clocks.1,
+                teams: clocks.2,
+                friendly_fire: flags.0,
+                fair_deal: flags.1,
+            })
+    }
+
+    fn hex_strategy() -> impl Strategy<Value = String> {
+        "[0-9a-f]{64}"
+    }
+
+    fn deal_strategy() -> impl Strategy<Value = DealMessage> {
+        let commitments = prop::collection::vec((name_strategy(), hex_strategy()), 0..4);
+        let seats =
+            prop::collection::vec((name_strategy(), hex_strategy(), cards_strategy()), 0..4);
+        prop_oneof![
+            commitments.prop_map(DealMessage::Commit),
+            hex_strategy().prop_map(DealMessage::Entropy),
+            (hex_strategy(), seats)
+                .prop_map(|(salt, seats)| DealMessage::Reveal(Reveal { salt, seats })),
+        ]
+    }
+
+    fn request_strategy() -> impl Strategy<Value = Request> {
+        (0..12).prop_map(|index| {
+            Request::new(match index {
+                0 => RequestType::Name,
+                1 => RequestType::Status,
+                2 => RequestType::PlayerAction,
+                3 => RequestType::GameState,
+                4 => RequestType::Cards,
+                5 => RequestType::Rules,
+                6 => RequestType::Chat,
+                7 => RequestType::Lobby,
+                8 => RequestType::Room,
+                9 => RequestType::Rooms,
+                10 => RequestType::View,
+                _ => RequestType::Deal,
+            })
+        })
+    }
+
+    /// Every response that carries a value, since a response without one is only ever sent to
+    /// ask for it.
+    fn response_strategy() -> impl Strategy<Value = Response> {
+        let seat_cards = prop::collection::vec((name_strategy(), player_cards_strategy()), 0..4);
+        let room = prop_oneof![
+            Just(RoomChoice::List),
+            (name_strategy(), "[a-z_=0-9,]{0,20}")
+                .prop_map(|(name, rules)| RoomChoice::Create { name, rules }),
+            "[A-Z0-9]{4}".prop_map(RoomChoice::Join),
+        ];
+        let room_info = ("[A-Z0-9]{4}", name_strategy(), any::<u8>(), any::<u8>())
+            .prop_map(|(code, name, players, seats)| RoomInfo::new(code, name, players, seats));
+        prop_oneof![
+            name_strategy().prop_map(Response::from_name),
+            action_strategy().prop_map(Response::from_action),
+            any::<bool>().prop_map(|yes| Response::new(ResponseType::Status(Some(if yes {
+                StatusType::Yes
+            } else {
+                StatusType::No
+            })))),
+            game_state_strategy().prop_map(Response::from_game_state),
+            prop::option::of(player_cards_strategy())
+                .prop_map(|cards| Response::from_cards(cards.as_ref().map(OwnCards::of))),
+            rules_strategy().prop_map(Response::from_rules),
+            (
+                name_strategy(),
+                prop::option::of(name_strategy()),
+                "[ -~]{0,40}"
+            )
+                .prop_map(|(from, to, text)| Response::from_chat(ChatMessage::new(from, to, text))),
+            (
+                any::<u8>(),
+                prop::collection::vec((name_strategy(), any::<bool>()), 0..6)
+            )
+                .prop_map(|(seats, players)| Response::from_lobby(LobbyState::new(
+                    LobbyStatus::Waiting,
+                    seats,
+                    players
+                ))),
+            room.prop_map(Response::from_room),
+            prop::collection::vec(room_info, 0..4).prop_map(Response::from_rooms),
+            (seat_cards, prop::option::of(name_strategy())).prop_map(|(cards, viewer)| {
+                let viewer = viewer.map_or(Viewer::Spectator, Viewer::Player);
+                Response::from_view(TableView::build(&cards, &viewer))
+            }),
+            deal_strategy().prop_map(Response::from_deal),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn request_round_trip(request in request_strategy()) {
+            prop_assert_eq!(Request::from_str(&request.to_string()).unwrap(), request);
+        }
+
+        #[test]
+        fn response_round_trip(response in response_strategy()) {
+            prop_assert_eq!(Response::from_str(&response.to_string()).unwrap(), response);
+        }
+
+        #[test]
+        fn action_round_trip(action in action_strategy()) {
+            prop_assert_eq!(Action::from_str(&action.to_string()).unwrap(), action);
+        }
+
+        #[test]
+        fn game_state_round_trip(game_state in game_state_strategy()) {
+            prop_assert_eq!(GameState::from_str(&game_state.to_string()).unwrap(), game_state);
+        }
+
+        /* The same inputs as the fuzz targets, so that the parsers are checked on every run. */
+        #[test]
+        fn parsers_never_panic(
+            line in "(REQ|RES)?(,[A-Z]{0,5})?(,[ -~]{0,12}){0,7}|\\PC{0,40}"
+        ) {
+            let _ = Request::from_str(&line);
+            let _ = Response::from_str(&line);
+            let _ = Action::from_str(&line);
+            let _ = GameState::from_str(&line);
+        }
+    }
 }
@@ -0,0 +1,151 @@
+//! This module contains the logging subsystem used for debugging output. Log messages are
+//! filtered by `Level`, and are written to `stderr` unless a log file has been set. By default,
+//! only errors are logged, so protocol traces are never shown to players unless they are enabled
+//! with `--log-level` (or the `KINGDOM_KARDS_LOG` environment variable).
+
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Mutex,
+    },
+};
+
+/// Environment variable used for setting the log level (e.g. `KINGDOM_KARDS_LOG=trace`).
+pub const LOG_LEVEL_VAR: &str = "KINGDOM_KARDS_LOG";
+/// Environment variable used for setting the path of the log file.
+pub const LOG_FILE_VAR: &str = "KINGDOM_KARDS_LOG_FILE";
+
+/// The severity of a log message. Levels are ordered from least verbose (`Error`) to most
+/// verbose (`Trace`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Something went wrong, such as a failed read from a `TcpStream`.
+    Error = 1,
+    /// Something unexpected happened, but it could be recovered from.
+    Warn,
+    /// General information about what the program is doing.
+    Info,
+    /// Information that is only useful for debugging.
+    Debug,
+    /// Every request and response sent or received.
+    Trace,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        };
+        write!(f, "{level}")
+    }
+}
+
+impl FromStr for Level {
+    type Err = ();
+
+    /// Converts from a level name (case-insensitive) to a `Level`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Maximum level that will be logged, stored as the discriminant of `Level`.
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Error as u8);
+/// File that log messages are written to. If `None`, messages are written to `stderr`.
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Sets the maximum level that will be logged.
+pub fn set_level(level: Level) {
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Appends all future log messages to the file at `path` instead of `stderr`.
+pub fn set_log_file(path: &Path) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    *LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+    Ok(())
+}
+
+/// Configures logging from the `KINGDOM_KARDS_LOG` and `KINGDOM_KARDS_LOG_FILE` environment
+/// variables. Invalid values are ignored.
+pub fn init_from_env() {
+    if let Ok(level) = std::env::var(LOG_LEVEL_VAR) {
+        if let Ok(level) = Level::from_str(&level) {
+            set_level(level);
+        }
+    }
+    if let Ok(path) = std::env::var(LOG_FILE_VAR) {
+        if let Err(err) = set_log_file(Path::new(&path)) {
+            eprintln!("Unable to open log file '{path}': {err}");
+        }
+    }
+}
+
+/// Returns true if messages of `level` will be logged.
+pub fn enabled(level: Level) -> bool {
+    (level as u8) <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Logs `msg` at `level`. `target` is the name of whatever produced the message (e.g. the
+/// function name or the peer of a stream).
+pub fn log(level: Level, target: &str, msg: &str) {
+    if !enabled(level) {
+        return;
+    }
+
+    let mut log_file = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    match log_file.as_mut() {
+        Some(file) => {
+            /* A failed write to the log file shouldn't take down the game. */
+            let _ = writeln!(file, "[{level}] {target}: {msg}");
+        }
+        None => eprintln!(
+            "{}[{level}] {target}: {msg}\x1b[0m",
+            level_to_ansi_code(level)
+        ),
+    }
+}
+
+pub fn error(target: &str, msg: &str) {
+    log(Level::Error, target, msg);
+}
+
+pub fn warn(target: &str, msg: &str) {
+    log(Level::Warn, target, msg);
+}
+
+pub fn info(target: &str, msg: &str) {
+    log(Level::Info, target, msg);
+}
+
+pub fn debug(target: &str, msg: &str) {
+    log(Level::Debug, target, msg);
+}
+
+pub fn trace(target: &str, msg: &str) {
+    log(Level::Trace, target, msg);
+}
+
+fn level_to_ansi_code(level: Level) -> &'static str {
+    match level {
+        Level::Error | Level::Warn => "\x1b[33m",
+        Level::Info => "\x1b[0m",
+        Level::Debug | Level::Trace => "\x1b[34m",
+    }
+}
@@ -1,29 +1,212 @@
-use kingdom_kards::server::client::ClientInstance;
-use kingdom_kards::server::host::ServerInstance;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use kingdom_kards::error;
+use kingdom_kards::game::game_log::GameRecord;
+use kingdom_kards::game::profiles::Profiles;
+use kingdom_kards::game::rules::Rules;
+use kingdom_kards::logging::{self, Level};
+use kingdom_kards::replay::Replay;
+use kingdom_kards::server::client::{ClientConfig, ClientInstance};
+use kingdom_kards::server::constants::{API_ADDRESS, WEB_ADDRESS};
+use kingdom_kards::server::host::{HostConfig, ServerInstance};
 use kingdom_kards::server::utils::{choose_mode, Mode};
+use kingdom_kards::tournament::{Entrant, Tournament, TournamentConfig};
+use kingdom_kards::ui::input::{InputSource, RecordingInput, ScriptedInput, Terminal};
+use kingdom_kards::ui::{enable_line_editing, get_input};
 use kingdom_kards::utils::clear_screen;
 
+const USAGE: &str = "Usage: kingdom-kards [host | replay <game log> | leaderboard <profiles> | \
+tournament <name:strategy,...>] [--log-level <error|warn|info|debug|trace>] [--log-file <path>] \
+[--seed <number>] [--rules <key=value,...>] [--game-log <path>] [--save <path>] \
+[--data-dir <path>] [--resume <path>] [--profiles <path>] [--format <round-robin|swiss>] [--rounds <number>] \
+[--table-size <number>] [--log-dir <path>] [--commands] [--record-input <path>] \
+[--replay-input <path>] [--websocket <address>] [--web] [--api] [--admin-token <token>]";
+
+/// What the application was started to do.
+enum Command {
+    /// Show the menu for hosting or joining a game.
+    Menu,
+    /// Host a game without showing the menu.
+    Host,
+    /// Step through the game log at the path.
+    Replay(PathBuf),
+    /// Print the standings from the profiles at the path.
+    Leaderboard(PathBuf),
+    /// Run a tournament between the comma-separated list of entrants.
+    Tournament(String),
+}
+
 fn main() {
+    logging::init_from_env();
+    let Some((command, config, tournament_config, client_config)) = parse_args() else {
+        eprintln!("{USAGE}");
+        return;
+    };
+
+    match command {
+        Command::Replay(path) => return replay(&path),
+        Command::Leaderboard(path) => return leaderboard(&path),
+        Command::Tournament(entrants) => {
+            let tournament_config = TournamentConfig {
+                rules: config.rules,
+                seed: config.seed,
+                ..tournament_config
+            };
+            return tournament(&entrants, tournament_config);
+        }
+        Command::Menu | Command::Host => {}
+    }
+    if client_config.command_mode {
+        enable_line_editing();
+    }
+
     clear_screen();
     println!("Starting Kingdom Kards...\n");
 
-    let mode = choose_mode();
+    let mut input = match input_source(&client_config) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("Unable to set up input: {err}");
+            return;
+        }
+    };
+    let mode = match command {
+        Command::Host => Mode::HostGame,
+        _ => choose_mode(input.as_mut()),
+    };
 
     match mode {
         Mode::HostGame => {
-            let mut server = ServerInstance::create();
-            server.start();
+            let mut server = match ServerInstance::with_config(config) {
+                Ok(server) => server,
+                Err(err) => {
+                    eprintln!("Unable to start server: {err}");
+                    return;
+                }
+            };
+            if let Err(err) = server.start() {
+                eprintln!("Server stopped: {err}");
+                return;
+            }
             server._wait();
         }
         Mode::ConnectGame => {
-            let mut client = ClientInstance::new();
+            let mut client = ClientInstance::with_input(client_config, input);
 
             if client.connect_to_server("127.0.0.1:5464").is_none() {
                 return; /* close application */
             }
 
-            client.start();
-            client._wait();
+            if let Err(err) = client.start() {
+                eprintln!("Disconnected from server: {err}");
+            }
+        }
+    }
+}
+
+/// Returns where the player's answers come from: the terminal, after any answers being replayed,
+/// with every answer recorded if asked to.
+fn input_source(config: &ClientConfig) -> error::Result<Box<dyn InputSource>> {
+    let mut input: Box<dyn InputSource> = match &config.replay_input {
+        Some(path) => Box::new(ScriptedInput::load(path)?.then(Box::new(Terminal))),
+        None => Box::new(Terminal),
+    };
+    if let Some(path) = &config.record_input {
+        input = Box::new(RecordingInput::create(path, input)?);
+    }
+    Ok(input)
+}
+
+/// Reads the game log at `path`, checks that it replays to the same game states that were
+/// recorded, then starts the replay viewer.
+fn replay(path: &Path) {
+    let record = match GameRecord::read(path) {
+        Ok(record) => record,
+        Err(err) => {
+            eprintln!("Unable to read game log '{}': {err}", path.display());
+            return;
+        }
+    };
+    let replay = Replay::new(record);
+    match replay.verify() {
+        Ok(()) => println!(
+            "Verified all {} actions against the game log.",
+            replay.num_actions()
+        ),
+        Err(err) => println!("Warning! {err}"),
+    }
+    get_input("Press enter to start the replay...", 5);
+    replay.start();
+}
+
+/// Prints the standings and every player's stats from the profiles at `path`.
+fn leaderboard(path: &Path) {
+    match Profiles::load(path) {
+        Ok(profiles) => profiles.print_leaderboard(),
+        Err(err) => eprintln!("Unable to read profiles '{}': {err}", path.display()),
+    }
+}
+
+/// Parses the entrants in `entrants`, then runs a tournament between them and prints the
+/// standings.
+fn tournament(entrants: &str, config: TournamentConfig) {
+    let entrants: Result<Vec<Entrant>, _> = entrants.split(',').map(Entrant::from_str).collect();
+    let result = entrants
+        .and_then(|entrants| Tournament::new(entrants, config))
+        .and_then(|mut tournament| tournament.run());
+    if let Err(err) = result {
+        eprintln!("Unable to run tournament: {err}");
+    }
+}
+
+/// Parses the command and command line flags, which take priority over environment variables.
+/// Returns `None` if the command or any flag is invalid.
+fn parse_args() -> Option<(Command, HostConfig, TournamentConfig, ClientConfig)> {
+    let mut command = Command::Menu;
+    let mut config = HostConfig::default();
+    let mut tournament_config = TournamentConfig::default();
+    let mut client_config = ClientConfig::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "host" => command = Command::Host,
+            "replay" => command = Command::Replay(PathBuf::from(args.next()?)),
+            "leaderboard" => command = Command::Leaderboard(PathBuf::from(args.next()?)),
+            "tournament" => command = Command::Tournament(args.next()?),
+            "--log-level" => {
+                let level = Level::from_str(&args.next()?).ok()?;
+                logging::set_level(level);
+            }
+            "--log-file" => {
+                let path = args.next()?;
+                if let Err(err) = logging::set_log_file(Path::new(&path)) {
+                    eprintln!("Unable to open log file '{path}': {err}");
+                    return None;
+                }
+            }
+            "--seed" => config.seed = Some(args.next()?.parse().ok()?),
+            "--rules" => config.rules = Rules::from_str(&args.next()?).ok()?,
+            "--game-log" => config.game_log = Some(PathBuf::from(args.next()?)),
+            "--save" => config.save = Some(PathBuf::from(args.next()?)),
+            "--data-dir" => config.data_dir = Some(PathBuf::from(args.next()?)),
+            "--resume" => config.resume = Some(PathBuf::from(args.next()?)),
+            "--profiles" => config.profiles = Some(PathBuf::from(args.next()?)),
+            "--websocket" => config.websocket = Some(args.next()?),
+            "--web" => config.web = Some(WEB_ADDRESS.to_owned()),
+            "--api" => config.api = Some(API_ADDRESS.to_owned()),
+            "--admin-token" => config.admin_token = Some(args.next()?),
+            "--format" => tournament_config.format = args.next()?.parse().ok()?,
+            "--rounds" => tournament_config.rounds = Some(args.next()?.parse().ok()?),
+            "--table-size" => tournament_config.table_size = args.next()?.parse().ok()?,
+            "--log-dir" => tournament_config.log_dir = Some(PathBuf::from(args.next()?)),
+            "--commands" => client_config.command_mode = true,
+            "--record-input" => client_config.record_input = Some(PathBuf::from(args.next()?)),
+            "--replay-input" => client_config.replay_input = Some(PathBuf::from(args.next()?)),
+            _ => return None,
         }
     }
+    /* Games hosted from the command line are recorded in the current directory by default. */
+    config.data_dir.get_or_insert_with(|| PathBuf::from("."));
+    Some((command, config, tournament_config, client_config))
 }
@@ -0,0 +1,139 @@
+//! This module contains the replay viewer, which steps forwards and backwards through a game log
+//! and checks that replaying every action gives the same game state that the host recorded.
+
+use crate::{
+    error::{self, KingdomError},
+    game::{
+        game_log::{ActionRecord, ActionResult, GameRecord},
+        game_state::GameState,
+        rules::Rules,
+    },
+    server::commentator::Commentator,
+    ui::get_input,
+    utils::clear_screen,
+};
+
+/// A game that has been read from a game log, along with the game state after every action.
+pub struct Replay {
+    record: GameRecord,
+    /// `states[0]` is the game state at the start of the game, and `states[n]` is the game state
+    /// after the `n`th action.
+    states: Vec<GameState>,
+}
+
+impl Replay {
+    /// Replays every action in `record`, starting from the recorded players.
+    pub fn new(record: GameRecord) -> Replay {
+        let mut game_state = record.players().clone();
+        let mut states = Vec::with_capacity(record.actions().len() + 1);
+        states.push(game_state.clone());
+        for action_record in record.actions() {
+            /* Errors are reported by `verify`, the replay just keeps going. */
+            let _ = Replay::replay_action(&mut game_state, action_record, record.rules());
+            states.push(game_state.clone());
+        }
+        Replay { record, states }
+    }
+
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    /// Returns the number of actions in the replay.
+    pub fn num_actions(&self) -> usize {
+        self.record.actions().len()
+    }
+
+    /// Returns the game state after `num_actions` actions have been played, or `None` if there
+    /// aren't that many actions.
+    pub fn state_after(&self, num_actions: usize) -> Option<&GameState> {
+        self.states.get(num_actions)
+    }
+
+    /// Checks that replaying every action gives the same game state and points deltas that were
+    /// recorded in the game log. Returns an error for the first action that doesn't match.
+    pub fn verify(&self) -> error::Result<()> {
+        let rules = self.record.rules();
+        let mut game_state = self.record.players().clone();
+        for action_record in self.record.actions() {
+            let seq = action_record.seq();
+            let before = game_state.clone();
+            Replay::replay_action(&mut game_state, action_record, rules)
+                .map_err(|err| KingdomError::ReplayMismatch(seq, err.to_string()))?;
+            if game_state != *action_record.game_state() {
+                return Err(KingdomError::ReplayMismatch(
+                    seq,
+                    format!(
+                        "expected game state '{}', got '{game_state}'",
+                        action_record.game_state()
+                    ),
+                ));
+            }
+            if GameState::points_deltas(&before, &game_state) != action_record.deltas() {
+                return Err(KingdomError::ReplayMismatch(
+                    seq,
+                    String::from("points deltas are different"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a single recorded action to `game_state`. Actions that the host rejected have no
+    /// effect.
+    fn replay_action(
+        game_state: &mut GameState,
+        action_record: &ActionRecord,
+        rules: &Rules,
+    ) -> error::Result<()> {
+        match action_record.result() {
+            ActionResult::Accepted => game_state.apply_action(action_record.action(), rules),
+            ActionResult::Rejected(_) => Ok(()),
+        }
+    }
+
+    /// Starts the interactive replay viewer, which re-renders the table after every step until
+    /// the user quits.
+    pub fn start(&self) {
+        let mut position = 0;
+        loop {
+            self.render(position);
+            let input = get_input("[n]ext, [p]revious, [q]uit: ", 5).to_lowercase();
+            match input.as_str() {
+                "" | "n" | "next" => position = (position + 1).min(self.num_actions()),
+                "p" | "prev" => position = position.saturating_sub(1),
+                "q" | "quit" => break,
+                _ => {}
+            }
+        }
+    }
+
+    /// Prints the action at `position` (if any) and the table after it.
+    fn render(&self, position: usize) {
+        clear_screen();
+        println!(
+            "Replay (seed {}, rules {}): action {position} of {}\n",
+            self.record.seed(),
+            self.record.rules(),
+            self.num_actions()
+        );
+        let game_state = &self.states[position];
+        match position
+            .checked_sub(1)
+            .map(|index| &self.record.actions()[index])
+        {
+            None => println!("The game is starting."),
+            Some(action_record) => match action_record.result() {
+                ActionResult::Accepted => Commentator::action(action_record.action(), game_state),
+                ActionResult::Rejected(reason) => println!(
+                    "{} tried to play {}, but it was rejected: {reason}",
+                    action_record.action().from_player(),
+                    action_record.action()
+                ),
+            },
+        }
+        println!();
+        Commentator::table(game_state);
+        println!();
+    }
+}
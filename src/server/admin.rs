@@ -0,0 +1,292 @@
+//! This module contains `GameMonitor`, which lets the host watch and step into the game in a room
+//! from outside the game loop (e.g. from the HTTP API in `api`, or the host's admin console). The game publishes what every
+//! player may see of it to the monitor, and takes the host's commands from it between actions.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    path::PathBuf,
+    sync::{Condvar, Mutex},
+};
+
+use crate::{
+    error::{self, KingdomError},
+    game::game_state::GameState,
+};
+
+use super::{
+    response::Action,
+    view::{public_action, rejected_action},
+};
+
+/// Something the host can tell a game to do while it is being played.
+#[derive(Debug, PartialEq)]
+pub enum AdminCommand {
+    /// Takes the named player out of the game by setting their points to 0, then disconnects
+    /// them. If it is their turn, their turn ends.
+    Kick(String),
+    /// Sets the named player's points (e.g. to settle a dispute). Setting them to 0 takes the
+    /// player out of the game, but leaves them connected.
+    SetPoints(String, u16),
+    /// Ends the turn player's turn. Between turns, the next player's turn is passed over instead.
+    Skip,
+    /// Saves the game to the path, or to where the game is saved at the start of every turn if
+    /// there is none.
+    Save(Option<PathBuf>),
+    /// Ends the game at once. Whoever has the most points wins, and ties go to whoever is seated
+    /// first.
+    End,
+    /// Saves the game once the turn player's turn has ended, then disconnects every player
+    /// without a winner, so that the game can be resumed later.
+    Shutdown,
+}
+
+impl AdminCommand {
+    /// Returns true if the command can't wait for `turn_player` to make their next action (e.g.
+    /// because it kicks them). Every other command is carried out once they have made it.
+    pub fn interrupts(&self, turn_player: &str) -> bool {
+        match self {
+            AdminCommand::Kick(name) | AdminCommand::SetPoints(name, 0) => name == turn_player,
+            AdminCommand::SetPoints(..) | AdminCommand::Save(_) => false,
+            AdminCommand::Skip | AdminCommand::End | AdminCommand::Shutdown => true,
+        }
+    }
+}
+
+/// How far along the game in a room is.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GameStatus {
+    /// Players are still joining the lobby.
+    Waiting,
+    Playing,
+    /// The host has paused the game, so no turn starts until it is resumed.
+    Paused,
+    Over,
+}
+
+impl Display for GameStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            GameStatus::Waiting => "waiting",
+            GameStatus::Playing => "playing",
+            GameStatus::Paused => "paused",
+            GameStatus::Over => "over",
+        };
+        write!(f, "{status}")
+    }
+}
+
+/// An action that the game handled, as every player saw it.
+#[derive(Debug, PartialEq)]
+pub struct GameEvent {
+    /// Position of the action in the game, starting from 1.
+    pub seq: u32,
+    pub action: Action,
+    /// False if the host rejected the action, in which case it had no effect.
+    pub accepted: bool,
+}
+
+impl Clone for GameEvent {
+    fn clone(&self) -> Self {
+        GameEvent {
+            seq: self.seq,
+            action: self.action.to_owned(),
+            accepted: self.accepted,
+        }
+    }
+}
+
+#[derive(Default)]
+struct MonitorState {
+    /// The game state as every player sees it, once the game has started.
+    game_state: Option<GameState>,
+    events: Vec<GameEvent>,
+    commands: VecDeque<AdminCommand>,
+    paused: bool,
+    over: bool,
+}
+
+/// What the host can see of the game in one room, and the commands it has sent to it. It is
+/// shared between the thread playing the game and whatever the host is controlling it from.
+#[derive(Default)]
+pub struct GameMonitor {
+    state: Mutex<MonitorState>,
+    /// Notified whenever the game is paused or resumed, a command is sent, or the game is over.
+    changed: Condvar,
+}
+
+impl GameMonitor {
+    /// Returns how far along the game is.
+    pub fn status(&self) -> GameStatus {
+        let state = self.state.lock().unwrap();
+        match (&state.game_state, state.over, state.paused) {
+            (_, true, _) => GameStatus::Over,
+            (None, _, _) => GameStatus::Waiting,
+            (Some(_), _, true) => GameStatus::Paused,
+            (Some(_), _, false) => GameStatus::Playing,
+        }
+    }
+
+    /// Returns the game state, or `None` if the game hasn't started.
+    pub fn game_state(&self) -> Option<GameState> {
+        self.state.lock().unwrap().game_state.clone()
+    }
+
+    /// Returns every action after the one numbered `seq`, oldest first. Every action is returned
+    /// if `seq` is 0.
+    pub fn events_since(&self, seq: u32) -> Vec<GameEvent> {
+        let state = self.state.lock().unwrap();
+        state.events.iter().skip(seq as usize).cloned().collect()
+    }
+
+    /// Returns the number of the latest action, or 0 if there hasn't been one.
+    pub fn last_seq(&self) -> u32 {
+        self.state.lock().unwrap().events.len() as u32
+    }
+
+    /// Pauses the game. The turn being played carries on, but the next one doesn't start until
+    /// the game is resumed.
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if the game isn't being played, or is already paused.
+    pub fn pause(&self) -> error::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        GameMonitor::check_playing(&state)?;
+        if state.paused {
+            return Err(rejected("the game is already paused"));
+        }
+        state.paused = true;
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    /// Resumes the game after it was paused.
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if the game isn't paused.
+    pub fn resume(&self) -> error::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.paused || state.over {
+            return Err(rejected("the game isn't paused"));
+        }
+        state.paused = false;
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    /// Sends `command` to the game, which carries it out between actions (see
+    /// `AdminCommand::interrupts`).
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if the game isn't being played, or if a player being
+    /// kicked, or whose points are being set, isn't in the game.
+    pub fn send(&self, command: AdminCommand) -> error::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        GameMonitor::check_playing(&state)?;
+        let name = match &command {
+            AdminCommand::Kick(name) | AdminCommand::SetPoints(name, _) => Some(name),
+            _ => None,
+        };
+        if let (Some(name), Some(game_state)) = (name, &state.game_state) {
+            let player = game_state
+                .player_by_name(name)
+                .ok_or_else(|| KingdomError::PlayerNotFound(name.to_owned()))?;
+            if player.is_eliminated() {
+                return Err(rejected(&format!("'{name}' is already out of the game")));
+            }
+        }
+        state.commands.push_back(command);
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    /// Returns an error unless the game has started and isn't over.
+    fn check_playing(state: &MonitorState) -> error::Result<()> {
+        match (&state.game_state, state.over) {
+            (None, _) => Err(rejected("the game hasn't started")),
+            (_, true) => Err(rejected("the game is over")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Publishes `game_state` as the game starts.
+    pub fn start(&self, game_state: &GameState) {
+        self.update(game_state);
+    }
+
+    /// Publishes `game_state` after it changed without an action (e.g. when the host passed over
+    /// whoever's turn was next).
+    pub fn update(&self, game_state: &GameState) {
+        self.state.lock().unwrap().game_state = Some(game_state.clone());
+    }
+
+    /// Records that the game handled `action`, leaving it in `game_state`. Only the parts of the
+    /// action that every player may see are kept (see `view::public_action`), and none of the
+    /// cards of a rejected action are (see `view::rejected_action`).
+    pub fn record(&self, action: &Action, accepted: bool, game_state: &GameState) {
+        let mut state = self.state.lock().unwrap();
+        let seq = state.events.len() as u32 + 1;
+        state.events.push(GameEvent {
+            seq,
+            action: if accepted {
+                public_action(action)
+            } else {
+                rejected_action(action)
+            },
+            accepted,
+        });
+        state.game_state = Some(game_state.clone());
+    }
+
+    /// Records that the game is over. Commands that haven't been carried out are dropped.
+    pub fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.over = true;
+        state.paused = false;
+        state.commands.clear();
+        self.changed.notify_all();
+    }
+
+    /// Returns true if the game is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    /// Takes the oldest command that hasn't been carried out, if there is one.
+    pub fn next_command(&self) -> Option<AdminCommand> {
+        self.state.lock().unwrap().commands.pop_front()
+    }
+
+    /// Takes the oldest command that can't wait for `turn_player` to make their next action, if
+    /// there is one.
+    pub fn next_interrupt(&self, turn_player: &str) -> Option<AdminCommand> {
+        let mut state = self.state.lock().unwrap();
+        let index = state
+            .commands
+            .iter()
+            .position(|command| command.interrupts(turn_player))?;
+        state.commands.remove(index)
+    }
+
+    /// Blocks until the game is over.
+    pub fn wait_until_over(&self) {
+        let state = self.state.lock().unwrap();
+        let _state = self.changed.wait_while(state, |state| !state.over).unwrap();
+    }
+
+    /// Blocks until the game is resumed or a command is sent, if the game is paused.
+    pub fn wait_while_paused(&self) {
+        let state = self.state.lock().unwrap();
+        let _state = self
+            .changed
+            .wait_while(state, |state| state.paused && state.commands.is_empty())
+            .unwrap();
+    }
+}
+
+fn rejected(reason: &str) -> KingdomError {
+    KingdomError::AdminRejected(reason.to_owned())
+}
@@ -0,0 +1,312 @@
+//! This module contains the host's HTTP API, which lets scripts (or `curl`) watch the games on the
+//! server and step into them. Every answer is JSON. Reading is open to anyone who can reach the
+//! address, but the admin endpoints need the host's admin token as a bearer token:
+//!
+//! - `GET /state`: the game state of a room, with every player's points and whose turn it is.
+//! - `GET /log?since={SEQ}`: every action of a room's game after the one numbered `SEQ`.
+//! - `GET /rooms`: every room on the server.
+//! - `POST /kick?player={NAME}`: kicks a player, from the lobby or from the game.
+//! - `POST /pause`, `POST /resume`: pauses the game before the next turn, or resumes it.
+//! - `POST /end`: ends the game. Whoever has the most points wins.
+//!
+//! Every endpoint except `/rooms` is for the host's own room, unless `room={CODE}` is given.
+
+use std::{fmt::Write, net::SocketAddr, sync::Arc, thread};
+
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    error::{self, KingdomError},
+    game::game_state::GameState,
+    utils::perror_in_fn,
+};
+
+use super::{
+    admin::{AdminCommand, GameStatus},
+    rooms::{Room, RoomManager},
+};
+
+/// Binds to `address`, then serves the HTTP API for every room in `rooms` on a new thread.
+/// Requests that don't name a room are for the room with the join code `default_room`, and the
+/// admin endpoints are called with `token`. Returns the address that was bound (e.g. to find the
+/// port when binding port 0).
+///
+/// ## Returns
+///
+/// This function will return an error if it is unable to bind to `address`.
+pub fn serve(
+    address: &str,
+    rooms: Arc<RoomManager>,
+    default_room: &str,
+    token: &str,
+) -> error::Result<SocketAddr> {
+    let server =
+        Server::http(address).map_err(|_| KingdomError::FailedToConnect(address.to_owned()))?;
+    let local_addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| KingdomError::FailedToConnect(address.to_owned()))?;
+    println!("Serving the HTTP API at http://{local_addr}/");
+    let api = Api {
+        rooms,
+        default_room: default_room.to_owned(),
+        token: token.to_owned(),
+    };
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            api.respond(request);
+        }
+    });
+    Ok(local_addr)
+}
+
+/// An answer to a request, as its status code and JSON body.
+type Reply = (u16, String);
+
+struct Api {
+    rooms: Arc<RoomManager>,
+    default_room: String,
+    token: String,
+}
+
+impl Api {
+    /// Answers `request`, logging any error that stops the answer from being sent.
+    fn respond(&self, request: Request) {
+        let (status, body) = self.route(&request);
+        let mut response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(
+                Header::from_bytes("Content-Type", "application/json").expect("header is valid"),
+            );
+        if status == 401 {
+            response.add_header(
+                Header::from_bytes("WWW-Authenticate", "Bearer").expect("header is valid"),
+            );
+        }
+        if let Err(err) = request.respond(response) {
+            perror_in_fn("api::respond", "api", err);
+        }
+    }
+
+    /// Works out the answer to `request` from its method, path and query.
+    fn route(&self, request: &Request) -> Reply {
+        let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+        let param = |key: &str| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(name, _)| percent_decode(name) == key)
+                .map(|(_, value)| percent_decode(value))
+        };
+        let is_admin = matches!(path, "/kick" | "/pause" | "/resume" | "/end");
+        if !is_admin && !matches!(path, "/state" | "/log" | "/rooms") {
+            return error_reply(404, "not found");
+        }
+        let method = if is_admin { Method::Post } else { Method::Get };
+        if *request.method() != method {
+            return error_reply(405, &format!("{path} only answers {method}"));
+        }
+        if is_admin && !self.is_authorized(request) {
+            return error_reply(401, "a valid admin token is needed");
+        }
+        if path == "/rooms" {
+            return (200, rooms_json(&self.rooms.rooms()));
+        }
+        let code = param("room").unwrap_or_else(|| self.default_room.clone());
+        let Some(room) = self.rooms.room_by_code(&code) else {
+            return error_reply(404, &format!("no room with join code '{code}'"));
+        };
+        let result = match path {
+            "/state" => return (200, state_json(&room)),
+            "/log" => match param("since").as_deref().unwrap_or("0").parse() {
+                Ok(since) => return (200, log_json(&room, since)),
+                Err(_) => return error_reply(400, "'since' must be a sequence number"),
+            },
+            "/kick" => match param("player") {
+                Some(name) if room.monitor().status() == GameStatus::Waiting => {
+                    room.lobby().kick(&name)
+                }
+                Some(name) => room.monitor().send(AdminCommand::Kick(name)),
+                None => return error_reply(400, "'player' is needed"),
+            },
+            "/pause" => room.monitor().pause(),
+            "/resume" => room.monitor().resume(),
+            _ => room.monitor().send(AdminCommand::End),
+        };
+        match result {
+            Ok(()) => (200, String::from(r#"{"ok":true}"#)),
+            Err(err @ KingdomError::PlayerNotFound(_)) => error_reply(404, &err.to_string()),
+            Err(err) => error_reply(400, &err.to_string()),
+        }
+    }
+
+    /// Returns true if `request` has the header `Authorization: Bearer {TOKEN}`. The token is
+    /// compared in constant time, so how long the check takes doesn't give away how much of a
+    /// guessed token was right.
+    fn is_authorized(&self, request: &Request) -> bool {
+        request.headers().iter().any(|header| {
+            header.field.equiv("Authorization")
+                && header
+                    .value
+                    .as_str()
+                    .strip_prefix("Bearer ")
+                    .is_some_and(|token| constant_time_eq(token, &self.token))
+        })
+    }
+}
+
+/// Returns true if `a` and `b` are equal, looking at every byte whatever the bytes are. Only
+/// the length can be told from how long this takes.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Decodes a query key or value, where `+` is a space and `%XX` is the byte with hex code `XX`
+/// (e.g. `Jo%C3%A3o+Silva` is `João Silva`). Anything that isn't a valid escape is kept as it is.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit));
+        match (bytes[i], escape) {
+            (_, Some(hex)) => {
+                let hex = std::str::from_utf8(hex).expect("hex digits are ASCII");
+                decoded.push(u8::from_str_radix(hex, 16).expect("hex digits are valid"));
+                i += 3;
+            }
+            (b'+', None) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, None) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn error_reply(status: u16, reason: &str) -> Reply {
+    (status, format!(r#"{{"error":{}}}"#, json_string(reason)))
+}
+
+/// Format: `[{"code":"1234","name":"Host","players":2,"seats":8,"status":"waiting"},...]`
+fn rooms_json(rooms: &[Arc<Room>]) -> String {
+    let rooms: Vec<String> = rooms
+        .iter()
+        .map(|room| {
+            let info = room.info();
+            format!(
+                r#"{{"code":{},"name":{},"players":{},"seats":{},"status":"{}"}}"#,
+                json_string(info.code()),
+                json_string(info.name()),
+                info.players(),
+                info.seats(),
+                room.monitor().status()
+            )
+        })
+        .collect();
+    format!("[{}]", rooms.join(","))
+}
+
+/// Format: `{"room":"1234","name":"Host","status":"playing","turn":"Alice","players":[...]}`,
+/// where every player is `{"name":"Alice","points":100,"team":null,"eliminated":false}`. Until
+/// the game starts, `turn` is null and every player is `{"name":"Alice","ready":true}` instead.
+fn state_json(room: &Room) -> String {
+    let game_state = room.monitor().game_state();
+    let (turn, players) = match &game_state {
+        Some(game_state) => (
+            game_state
+                .current_player()
+                .map_or(String::from("null"), |player| json_string(player.name())),
+            players_json(game_state),
+        ),
+        None => {
+            let players: Vec<String> = room
+                .lobby()
+                .players()
+                .iter()
+                .map(|(name, ready)| format!(r#"{{"name":{},"ready":{ready}}}"#, json_string(name)))
+                .collect();
+            (String::from("null"), players)
+        }
+    };
+    format!(
+        r#"{{"room":{},"name":{},"status":"{}","turn":{turn},"players":[{}]}}"#,
+        json_string(room.code()),
+        json_string(room.name()),
+        room.monitor().status(),
+        players.join(",")
+    )
+}
+
+fn players_json(game_state: &GameState) -> Vec<String> {
+    game_state
+        .all_players()
+        .iter()
+        .map(|player| {
+            let team = player
+                .team()
+                .map_or(String::from("null"), |team| team.to_string());
+            format!(
+                r#"{{"name":{},"points":{},"team":{team},"eliminated":{}}}"#,
+                json_string(player.name()),
+                player.points(),
+                player.is_eliminated()
+            )
+        })
+        .collect()
+}
+
+/// Format: `{"last":2,"events":[{"seq":2,"action":"E,0,Alice,","summary":"...","accepted":true}]}`,
+/// where `last` is the number of the latest action, to be passed as `since` next time.
+fn log_json(room: &Room, since: u32) -> String {
+    let monitor = room.monitor();
+    let events: Vec<String> = monitor
+        .events_since(since)
+        .iter()
+        .map(|event| {
+            format!(
+                r#"{{"seq":{},"action":{},"summary":{},"accepted":{}}}"#,
+                event.seq,
+                json_string(&event.action.to_string()),
+                json_string(&event.action.summary()),
+                event.accepted
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"last":{},"events":[{}]}}"#,
+        monitor.last_seq(),
+        events.join(",")
+    )
+}
+
+/// Returns `s` as a JSON string, in quotes and with every character escaped that has to be.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
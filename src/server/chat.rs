@@ -0,0 +1,239 @@
+//! This module contains `ChatMessage`, and `ChatRelay`, which the host uses to pass chat messages
+//! on to every player. Chat is sent without being requested, so it can be sent at any point in the
+//! game, including during other players' turns.
+
+use std::{collections::VecDeque, fmt::Display, str::FromStr, sync::Mutex, time::Instant};
+
+use crate::{
+    error::{self, KingdomError},
+    logging,
+};
+
+use super::{
+    constants::{CHAT_RATE_LIMIT, CHAT_RATE_WINDOW, HOST_CHAT_NAME, MAX_CHAT_LEN},
+    response::ResponseParseError,
+    StreamWriter,
+};
+
+/// Start of the text of an emote (e.g. `/me waves`).
+const EMOTE_PREFIX: &str = "/me ";
+
+/// A chat message, which is either sent to everyone or whispered to one player.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ChatMessage {
+    from: String,
+    to: Option<String>,
+    text: String,
+}
+
+impl ChatMessage {
+    /// Returns a new instance of `ChatMessage`. If `to` is `None`, the message is sent to
+    /// everyone.
+    pub fn new(from: String, to: Option<String>, text: String) -> ChatMessage {
+        ChatMessage { from, to, text }
+    }
+
+    /// Parses a chat command typed by the player named `from`. `/say {TEXT}` sends `TEXT` to
+    /// everyone, `/w {NAME} {TEXT}` whispers it to the player named `NAME`, and `/me {TEXT}` is an
+    /// emote. Returns `None` if `line` isn't a chat command.
+    pub fn from_command(from: &str, line: &str) -> Option<ChatMessage> {
+        let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let rest = rest.trim();
+        match command {
+            "/say" => Some(ChatMessage::new(from.to_owned(), None, rest.to_owned())),
+            "/me" => Some(ChatMessage::new(
+                from.to_owned(),
+                None,
+                format!("{EMOTE_PREFIX}{rest}"),
+            )),
+            "/w" => {
+                let (to, text) = rest.split_once(' ')?;
+                Some(ChatMessage::new(
+                    from.to_owned(),
+                    Some(to.to_owned()),
+                    text.trim().to_owned(),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to `self.from`.
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    /// Returns the name of the player the message is whispered to, if it is a whisper.
+    pub fn to(&self) -> Option<&str> {
+        self.to.as_deref()
+    }
+
+    /// Returns a reference to `self.text`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns what the sender did if the message is an emote (e.g. "waves" for `/me waves`).
+    pub fn emote(&self) -> Option<&str> {
+        self.text.strip_prefix(EMOTE_PREFIX)
+    }
+}
+
+impl Display for ChatMessage {
+    /// Format: `{FROM_PLAYER},{TO_PLAYER},{TEXT}`, where `TO_PLAYER` is empty unless the message
+    /// is a whisper.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let to = self.to.as_deref().unwrap_or_default();
+        write!(f, "{},{to},{}", self.from, self.text)
+    }
+}
+
+impl FromStr for ChatMessage {
+    type Err = ResponseParseError;
+
+    /// Converts from a string to a `ChatMessage`. The text may contain commas.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ',');
+        let (Some(from), Some(to), Some(text)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(ResponseParseError::InvalidNumArguments);
+        };
+        if from.is_empty() {
+            return Err(ResponseParseError::ExpectedName);
+        }
+        let to = (!to.is_empty()).then(|| to.to_owned());
+        Ok(ChatMessage::new(from.to_owned(), to, text.to_owned()))
+    }
+}
+
+/// Someone who can read chat, and who can send it unless they are a spectator.
+struct ChatMember {
+    name: String,
+    writer: StreamWriter,
+    can_send: bool,
+    /// When each of the member's recent messages were sent, oldest first.
+    sent: VecDeque<Instant>,
+}
+
+/// Passes chat messages on to every member of a game, or to the one member they are whispered
+/// to. Every message is checked first, so that no member can send messages that are too long or
+/// send too many messages at once.
+#[derive(Default)]
+pub struct ChatRelay {
+    members: Mutex<Vec<ChatMember>>,
+}
+
+impl ChatRelay {
+    /// Adds the member named `name` to the chat, who is sent chat with `writer`. Spectators join
+    /// with `can_send` set to false, so that they can read chat but not send it.
+    pub fn join(&self, name: &str, writer: StreamWriter, can_send: bool) {
+        self.members.lock().unwrap().push(ChatMember {
+            name: name.to_owned(),
+            writer,
+            can_send,
+            sent: VecDeque::new(),
+        });
+    }
+
+    /// Removes the member named `name` from the chat (e.g. when they are kicked).
+    pub fn leave(&self, name: &str) {
+        self.members
+            .lock()
+            .unwrap()
+            .retain(|member| member.name != name);
+    }
+
+    /// Checks `message` from the member named `from`, then passes it on. The message is always
+    /// sent back to `from` too, so they can see that it was sent. If the message is rejected,
+    /// `from` is sent the reason why instead.
+    pub fn relay(&self, from: &str, message: ChatMessage) {
+        let mut members = self.members.lock().unwrap();
+        /* Who the message is from is decided by which stream it came in on, not what it says. */
+        let message = ChatMessage::new(from.to_owned(), message.to, message.text);
+        if let Err(err) = ChatRelay::check(&mut members, &message) {
+            logging::info("chat", &format!("Rejected message from {from}: {err}"));
+            let notice = ChatMessage::new(
+                HOST_CHAT_NAME.to_owned(),
+                Some(from.to_owned()),
+                err.to_string(),
+            );
+            ChatRelay::send(&members, from, notice);
+            return;
+        }
+
+        match message.to() {
+            Some(to) => {
+                let to = to.to_owned();
+                ChatRelay::send(&members, &to, message.clone());
+                if to != from {
+                    ChatRelay::send(&members, from, message);
+                }
+            }
+            None => {
+                for member in members.iter() {
+                    ChatRelay::send(&members, &member.name, message.clone());
+                }
+            }
+        }
+    }
+
+    /// Sends `text` to every member from the host (e.g. when the host pauses the game).
+    pub fn announce(&self, text: &str) {
+        let members = self.members.lock().unwrap();
+        for member in members.iter() {
+            let message = ChatMessage::new(HOST_CHAT_NAME.to_owned(), None, text.to_owned());
+            ChatRelay::send(&members, &member.name, message);
+        }
+    }
+
+    /// Returns an error if `message` can't be sent, either because it is empty or too long, the
+    /// sender can't send chat or has sent too many messages, or it is whispered to someone who
+    /// isn't in the chat.
+    fn check(members: &mut [ChatMember], message: &ChatMessage) -> error::Result<()> {
+        let rejected = |reason: &str| Err(KingdomError::ChatRejected(reason.to_owned()));
+        if let Some(to) = message.to() {
+            if !members.iter().any(|member| member.name == to) {
+                return Err(KingdomError::PlayerNotFound(to.to_owned()));
+            }
+        }
+        let sender = members
+            .iter_mut()
+            .find(|member| member.name == message.from())
+            .ok_or_else(|| KingdomError::PlayerNotFound(message.from().to_owned()))?;
+        if !sender.can_send {
+            return rejected("spectators can only read chat");
+        }
+        if message.text().trim().is_empty() {
+            return rejected("message is empty");
+        }
+        if message.text().chars().count() > MAX_CHAT_LEN {
+            return rejected(&format!("message is longer than {MAX_CHAT_LEN} characters"));
+        }
+
+        let now = Instant::now();
+        while sender
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= CHAT_RATE_WINDOW)
+        {
+            sender.sent.pop_front();
+        }
+        if sender.sent.len() >= CHAT_RATE_LIMIT {
+            return rejected(&format!(
+                "too many messages, wait {} seconds",
+                CHAT_RATE_WINDOW.as_secs()
+            ));
+        }
+        sender.sent.push_back(now);
+        Ok(())
+    }
+
+    /// Sends `message` to the member named `name`, if they are in the chat.
+    fn send(members: &[ChatMember], name: &str, message: ChatMessage) {
+        let Some(member) = members.iter().find(|member| member.name == name) else {
+            return;
+        };
+        if let Err(err) = member.writer.send_chat(message) {
+            logging::warn("chat", &format!("Unable to send chat to {name}: {err}"));
+        }
+    }
+}
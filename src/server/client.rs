@@ -1,58 +1,94 @@
 //! This module contains a set of functions for client side commmunication
-//! with the server.
+//! with the server. The protocol itself is played by `ClientSession`, and `ClientInstance` is the
+//! terminal frontend for it, which prompts the player and prints what happens.
 
 use std::io::{self, Write};
-use std::net::TcpStream;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::game::game_state::{self, GameState};
-use crate::game::player::Player;
+use crate::error::{self, KingdomError};
+use crate::game::card::Card;
+use crate::game::rules::Rules;
+use crate::game::staged::StagedAction;
 use crate::server::commentator::Commentator;
-use crate::server::response::{Response, ResponseType, StatusType};
-use crate::ui::get_input;
+use crate::ui::commands::{self, Command, HELP};
+use crate::ui::input::{InputSource, Terminal};
+use crate::ui::{interrupt_input, set_command_hook, set_completions, set_input_deadline};
 use crate::utils::{perror_in_fn, variant_eq};
 
-use super::constants::{
-    ACTION_REQUEST, ACTION_RESPONSE, GAME_STATE_REQUEST, GAME_STATE_RESPONSE, MAX_USERNAME_LEN,
-    NAME_REQUEST, STATUS_REQUEST, STATUS_RESPONSE,
-};
+use super::constants::{JOIN_CODE_LEN, MAX_ROOM_NAME_LEN, MAX_USERNAME_LEN};
+use super::lobby::LobbyStatus;
 use super::response::{Action, ActionType};
-use super::StreamHandler;
+use super::rooms::RoomChoice;
+use super::session::{ClientSession, SessionEvent};
 
-/// This is the type used for representing client-side errors.
-pub enum ClientError {
-    InvalidCharacterFound,
-    CommaFound,
+/// Most actions kept for the `log` command.
+const LOG_LENGTH: usize = 10;
+
+/// Longest command that can be typed in command mode.
+const MAX_COMMAND_LEN: usize = 100;
+
+/// Options for how the client is played.
+#[derive(Debug, Default, Clone)]
+pub struct ClientConfig {
+    /// Whether the player types commands (e.g. `play K+7 bob`) on their turn, instead of choosing
+    /// cards by number. Commands can only be tab-completed if `ui::enable_line_editing` is called
+    /// before any input is read.
+    pub command_mode: bool,
+    /// File that every answer the player gives is recorded to, so the session can be replayed.
+    pub record_input: Option<PathBuf>,
+    /// File of answers, one per line, that are given before the player takes over at the
+    /// terminal (e.g. a session recorded with `record_input`).
+    pub replay_input: Option<PathBuf>,
 }
 
 /// This is the type used for representing a single client instance.
 /// There should only be one `ClientInstance` per running process of
 /// `kingdom-kards`.
 pub struct ClientInstance {
-    /// `StreamHandler` for `TcpStream` connect to server.
-    handler: Option<StreamHandler>,
-    /// Name of player.
-    player: Player,
-    /// Name of turn player.
-    turn_player: String,
+    /// The connection to the server, once there is one.
+    session: Option<ClientSession>,
+    /// True while the player is waiting in the lobby, when they can say whether they are ready.
+    in_lobby: Arc<AtomicBool>,
+    config: ClientConfig,
+    /// The last few actions applied to the game state, oldest first, for the `log` command.
+    recent_actions: Vec<Action>,
+    /// Where the answers to every prompt come from.
+    input: Box<dyn InputSource>,
 }
 
 impl ClientInstance {
     /// Creates a new client instance with parameters uninitialized.
     #[allow(clippy::new_without_default)]
     pub fn new() -> ClientInstance {
+        ClientInstance::with_config(ClientConfig::default())
+    }
+
+    /// Creates a new client instance that is played using `config`, at the terminal.
+    pub fn with_config(config: ClientConfig) -> ClientInstance {
+        ClientInstance::with_input(config, Box::new(Terminal))
+    }
+
+    /// Creates a new client instance that is played using `config`, with every prompt answered
+    /// from `input` (e.g. a script or a bot).
+    pub fn with_input(config: ClientConfig, input: Box<dyn InputSource>) -> ClientInstance {
         ClientInstance {
-            handler: None,
-            player: Player::new(),
-            turn_player: String::new(),
+            session: None,
+            in_lobby: Arc::default(),
+            config,
+            recent_actions: Vec::new(),
+            input,
         }
     }
 
-    /// This function connects client instance to server at `port`. If the client is unable to
-    /// connect to the server initially, then the program will prompt the user to try again. If
-    /// they answer no, then the program will exit. If they answer yes, then the client will
-    /// attempt to connect to the server again.
+    /// This function connects client instance to server at `port`, and lets the player choose a
+    /// room to join. If the client is unable to connect to the server initially, then the program
+    /// will prompt the user to try again. If they answer no, then the program will exit. If they
+    /// answer yes, then the client will attempt to connect to the server again.
     ///
     /// ## Returns
     ///
@@ -60,17 +96,14 @@ impl ClientInstance {
     /// `Some(())`
     pub fn connect_to_server(&mut self, port: &str) -> Option<()> {
         loop {
-            if let Ok(stream) = TcpStream::connect(port) {
-                let mut handler = StreamHandler::new(stream);
-                if ClientInstance::is_room_open(&mut handler) {
-                    self.handler = Some(handler);
-                    println!("Joined Room.");
-                    break Some(());
-                } else {
-                    println!("Room is full.");
+            if let Ok(mut session) = ClientSession::connect(port) {
+                if !ClientInstance::choose_room(&mut session, self.input.as_mut()) {
                     break None;
                 }
-            } else if !ClientInstance::try_connect_again() {
+                self.session = Some(session);
+                println!("Joined Room.");
+                break Some(());
+            } else if !ClientInstance::try_connect_again(self.input.as_mut()) {
                 return None;
             } else {
                 thread::sleep(Duration::from_millis(500));
@@ -78,33 +111,83 @@ impl ClientInstance {
         }
     }
 
-    fn handler_mut(&mut self) -> &mut StreamHandler {
-        self.handler.as_mut().unwrap()
+    fn session(&self) -> error::Result<&ClientSession> {
+        self.session.as_ref().ok_or(KingdomError::NotConnected)
+    }
+
+    fn session_mut(&mut self) -> error::Result<&mut ClientSession> {
+        self.session.as_mut().ok_or(KingdomError::NotConnected)
+    }
+
+    /// Lets the player list the open rooms, create a room, or join a room with its join code,
+    /// until they have chosen a room. Returns false if the room is full, or the connection to the
+    /// server is lost.
+    fn choose_room(session: &mut ClientSession, input: &mut dyn InputSource) -> bool {
+        loop {
+            println!("1. List open rooms");
+            println!("2. Create a room");
+            println!("3. Join a room with a join code\n");
+            let choice = match input.get_num_input("Choose an option [1, 2 or 3]: ", 1, 3) {
+                1 => RoomChoice::List,
+                2 => RoomChoice::Create {
+                    name: input.get_input("Room name: ", MAX_ROOM_NAME_LEN + 1),
+                    rules: ClientInstance::get_rules_input(input),
+                },
+                _ => RoomChoice::Join(input.get_input("Join code: ", JOIN_CODE_LEN + 1)),
+            };
+            let is_list = matches!(choice, RoomChoice::List);
+            let rooms = match session.choose_room(choice) {
+                Ok(rooms) => rooms,
+                Err(KingdomError::RoomFull(_)) => {
+                    println!("Room is full.");
+                    return false;
+                }
+                Err(err) => {
+                    perror_in_fn("choose_room", "server", err);
+                    return false;
+                }
+            };
+            match rooms.first() {
+                _ if is_list => Commentator::rooms(&rooms),
+                Some(room) => {
+                    println!(
+                        "Entering room '{}' with join code {}.",
+                        room.name(),
+                        room.code()
+                    );
+                    return true;
+                }
+                None => println!("Unable to enter that room."),
+            }
+        }
     }
 
-    fn is_room_open(handler: &mut StreamHandler) -> bool {
-        let status = handler.send_request_await_response(STATUS_REQUEST, STATUS_RESPONSE);
-        match status {
-            Ok(response) => match response.response_type() {
-                ResponseType::Status(Some(StatusType::Yes)) => true,
-                ResponseType::Status(Some(StatusType::No)) => false,
-                _ => unreachable!(),
-            },
-            Err(err) => {
-                perror_in_fn("is_room_open", err);
-                false
+    /// Prompts the user for changes to the default rules of a new room, until they enter valid
+    /// changes or nothing.
+    fn get_rules_input(input: &mut dyn InputSource) -> String {
+        const MAX_RULES_LEN: usize = 100;
+        loop {
+            let rules = input.get_input(
+                "Rules (e.g. turn=60,hand=4, or nothing for the default rules): ",
+                MAX_RULES_LEN,
+            );
+            match Rules::default().with_changes(&rules) {
+                Ok(_) => break rules,
+                Err(err) => println!("Error! {err}"),
             }
         }
     }
 
-    fn try_connect_again() -> bool {
+    fn try_connect_again(input: &mut dyn InputSource) -> bool {
         println!("Failed to connect to server.");
         io::stdout().flush().expect("Unable to flush stdout");
 
         const MAX_INPUT_LEN: usize = 5;
-        let input = get_input("Try again [y/n]: ", MAX_INPUT_LEN).to_lowercase();
+        let answer = input
+            .get_input("Try again [y/n]: ", MAX_INPUT_LEN)
+            .to_lowercase();
 
-        if input == "n" || input == "no" {
+        if answer == "n" || answer == "no" {
             println!("Okay. Closing application");
             false
         } else {
@@ -113,241 +196,309 @@ impl ClientInstance {
         }
     }
 
-    /// Starts the the gameplay loop client side. First, client must choose a username,
-    /// then, they will start the actual game. `connect_to_server()` must be called before
+    /// Starts the the gameplay loop client side. First, client must choose a username and wait
+    /// in the lobby until the host starts the game, then, they will start the actual game. `connect_to_server()` must be called before
     /// this function is called.
     ///
-    /// # Panics
+    /// ## Returns
     ///
-    /// This function will panic if there is no connection to the server
-    /// (i.e. `connect_to_server()`) was not called or it failed.
-    pub fn start(&mut self) {
-        self.choose_player_name();
-        let mut game_state = self.get_game_state_from_server();
-        game_state.print_all_players();
-        self.start_game_loop(&mut game_state);
+    /// This function will return an error if there is no connection to the server
+    /// (i.e. `connect_to_server()`) was not called or it failed, or if the connection is lost.
+    pub fn start(&mut self) -> error::Result<()> {
+        self.choose_player_name()?;
+        self.wait_in_lobby()?;
+        self.start_game_loop()
     }
 
-    // fn get_handler(client: &mut ClientInstance) -> &mut StreamHandler {
-    //     client.handler.as_mut().unwrap()
-    // }
-
     /// This function is used to initiate communcation with the server so the
     /// player can choose a unique username.
     ///
-    /// # Panics
+    /// ## Returns
     ///
-    /// This function will panic if an invalid type is received from the server,
-    /// but that should be impossible because `await_request()` and `await_response()`
-    /// both check what type is received.
-    pub fn choose_player_name(&mut self) {
-        let mut is_accepted = false;
-        let handler = self.handler.as_mut().unwrap();
-        let mut name = String::new();
-
-        while !is_accepted {
-            name = ClientInstance::send_name_to_server(handler);
-            is_accepted = ClientInstance::get_name_status_from_server(handler);
+    /// This function will return an error if there is no connection to the server.
+    pub fn choose_player_name(&mut self) -> error::Result<()> {
+        let session = self.session.as_mut().ok_or(KingdomError::NotConnected)?;
+        loop {
+            let name = ClientInstance::get_name_input(self.input.as_mut());
+            if session.choose_name(&name)? {
+                println!("Name was accepted by server.");
+                break;
+            }
+            println!("Name was rejected by server.");
         }
-        self.player.set_name(name);
-        println!("Joined room as {}", self.player.name());
+        println!("Joined room as {}", session.player().name());
+        self.start_commands()?;
+        Ok(())
     }
 
-    /// Sends name request an awaits response, printing any errors that may
-    /// occur.
-    fn send_name_to_server(handler: &mut StreamHandler) -> String {
-        let name = ClientInstance::get_name_input();
-        let name_response = Response::from_name(name.clone());
-        if let Err(err) = handler.await_request_send_response(NAME_REQUEST, &name_response) {
-            perror_in_fn("choose_player_name", err);
-        }
-        name
+    /// Prints every chat message from the server, stops any prompt as soon as the host ends the
+    /// player's turn for them, and lets the player type commands at any time. `/say {TEXT}`, `/w {NAME} {TEXT}` and `/me {TEXT}` send chat, and `/ready` and
+    /// `/unready` say whether the player is ready while they are in the lobby.
+    fn start_commands(&mut self) -> error::Result<()> {
+        let in_lobby = Arc::clone(&self.in_lobby);
+        let session = self.session()?;
+        session.on_chat(|message| Commentator::chat(&message));
+        session.on_forced_turn_end(interrupt_input);
+        let handle = session.handle();
+        set_command_hook(Some(Box::new(move |line| {
+            let result = match line {
+                "/ready" | "/unready" if !in_lobby.load(Ordering::SeqCst) => {
+                    println!("The game has already started.");
+                    return;
+                }
+                "/ready" => handle.set_ready(true),
+                "/unready" => handle.set_ready(false),
+                _ => match handle.send_chat_command(line) {
+                    Err(KingdomError::InvalidCommand(_)) => {
+                        println!("Commands: /say {{TEXT}}, /w {{NAME}} {{TEXT}}, /me {{TEXT}}, /ready, /unready");
+                        return;
+                    }
+                    result => result,
+                },
+            };
+            if let Err(err) = result {
+                perror_in_fn("start_commands", "server", err);
+            }
+        })));
+        println!("Type /say {{TEXT}} to chat, or /w {{NAME}} {{TEXT}} to whisper.");
+        Ok(())
     }
 
-    /// Sends status requests to the server to check if the name entered is valid.
-    /// If the name is invalid, then the function will return false. Otherwise, it
-    /// will return true.
-    ///
-    /// # Panics
-    ///
-    /// This function panics if it receives any invalid types, which should
-    /// be impossible when using `send` and `await` functions.
-    fn get_name_status_from_server(handler: &mut StreamHandler) -> bool {
-        if let Err(err) = handler.send_request(STATUS_REQUEST) {
-            perror_in_fn("get_name_status", err);
-        }
+    /// Waits in the lobby until the host starts the game, printing the lobby and the rules every
+    /// time they change. Returns an error if the host kicks this player.
+    fn wait_in_lobby(&mut self) -> error::Result<()> {
+        println!("Type /ready when you are ready to start, or /unready if you are not.");
+        self.in_lobby.store(true, Ordering::SeqCst);
+        let result = self.await_lobby_start();
+        self.in_lobby.store(false, Ordering::SeqCst);
+        result
+    }
 
-        let status_response = handler.await_response(STATUS_RESPONSE);
-        match status_response {
-            Ok(response) => match response.response_type() {
-                ResponseType::Status(Some(StatusType::Yes)) => {
-                    println!("Name was accepted by server.");
-                    true
-                }
-                ResponseType::Status(Some(StatusType::No)) => {
-                    println!("Name was rejected by server.");
-                    false
+    fn await_lobby_start(&mut self) -> error::Result<()> {
+        loop {
+            match self.session_mut()?.next_event()? {
+                SessionEvent::Rules(rules) => println!("Rules: {rules}"),
+                SessionEvent::Lobby(lobby) => {
+                    Commentator::lobby(&lobby);
+                    match lobby.status() {
+                        LobbyStatus::Waiting => {}
+                        LobbyStatus::Starting => break Ok(()),
+                        LobbyStatus::Kicked => break Err(KingdomError::Kicked),
+                    }
                 }
-                _ => unreachable!(),
-            },
-            Err(err) => {
-                perror_in_fn("get_name_status", err);
-                false
+                _ => {}
             }
         }
     }
 
     /// Prompts the user to enter a username until they enter a valid username.
-    fn get_name_input() -> String {
+    fn get_name_input(input: &mut dyn InputSource) -> String {
         loop {
-            let name = get_input("Enter a username: ", MAX_USERNAME_LEN);
-            if ClientInstance::validate_name_input(&name) {
-                break name;
+            let name = input.get_input("Enter a username: ", MAX_USERNAME_LEN);
+            match ClientSession::validate_name(&name) {
+                Ok(()) => break name,
+                Err(err) => println!("Error! {err}"),
             }
         }
     }
 
-    /// Checks to see if name is only alphabetical ASCII characters
-    fn validate_name_input(name: &str) -> bool {
-        if !name.is_ascii() || !name.chars().all(char::is_alphabetic) {
-            println!(
-                "Error! Username cannot contain special characters or numbers (e.g. 1, $, ç, ♥︎)."
-            );
-            false
-        } else {
-            true
+    /// Starts core gameplay loop, which prints every event from the session and prompts the
+    /// player whenever the host is waiting for them, until the game is over.
+    fn start_game_loop(&mut self) -> error::Result<()> {
+        loop {
+            match self.session_mut()?.next_event()? {
+                SessionEvent::GameStarted => {
+                    let session = self.session()?;
+                    session.game_state().print_all_players();
+                    if session.rules().fair_deal && !session.audits_deal() {
+                        println!(
+                            "This game was resumed from a save, so its deal can't be checked."
+                        );
+                    }
+                }
+                SessionEvent::TurnStarted { player, seconds } => {
+                    Commentator::turn_start(&player, seconds);
+                    if player == self.session()?.player().name() {
+                        let deadline = (seconds != 0)
+                            .then(|| Instant::now() + Duration::from_secs(seconds.into()));
+                        set_input_deadline(deadline);
+                    }
+                }
+                SessionEvent::ActionRequested => self.play_action()?,
+                SessionEvent::Action(action) => {
+                    if variant_eq(action.action_type(), &ActionType::TurnEnd) {
+                        set_input_deadline(None);
+                    }
+                    Commentator::action(&action, self.session()?.game_state());
+                    self.record_action(action);
+                }
+                SessionEvent::ReactionRequested(king) => {
+                    Commentator::action(&king, self.session()?.game_state());
+                    self.record_action(king.to_owned());
+                    self.react_to_king(&king)?;
+                }
+                SessionEvent::GameOver { winner, fair_deal } => {
+                    Commentator::game_over(&winner, self.session()?.game_state());
+                    if let Some(result) = fair_deal {
+                        Commentator::fair_deal(&result);
+                    }
+                    break Ok(());
+                }
+                SessionEvent::Lobby(_) | SessionEvent::Rules(_) => {}
+            }
         }
     }
 
-    /// Requests current game state from server.
-    fn get_game_state_from_server(&mut self) -> GameState {
-        let handler = self.handler.as_mut().unwrap();
-        let status = handler.send_request_await_response(GAME_STATE_REQUEST, GAME_STATE_RESPONSE);
-        match status {
-            Ok(response) => {
-                if let ResponseType::GameState(game_state) = response.response_type() {
-                    game_state.as_ref().unwrap().to_owned()
-                } else {
-                    unreachable!()
-                }
-            }
-            Err(err) => {
-                perror_in_fn("get_game_state", err);
-                GameState::new()
-            }
+    /// Chooses this player's next action and submits it, then says whether the host accepted it
+    /// and which cards were drawn.
+    fn play_action(&mut self) -> error::Result<()> {
+        let action = self.choose_action()?;
+        let is_turn_end = variant_eq(action.action_type(), &ActionType::TurnEnd);
+        let result = self.session_mut()?.submit_action(action.to_owned())?;
+        if is_turn_end {
+            set_input_deadline(None);
+        }
+        if !result.accepted {
+            println!("The host rejected that action, so your cards were given back.");
+            return Ok(());
         }
+        if !result.drawn.is_empty() {
+            let drawn: Vec<String> = result.drawn.iter().map(Card::to_string).collect();
+            println!("You drew {}.", drawn.join(" "));
+        }
+        self.record_action(action);
+        Ok(())
     }
 
-    /// Starts core gameplay loop.
-    fn start_game_loop(&mut self, game_state: &mut GameState) {
+    /// Prompts the player for their next action, ending their turn if they don't have one. Each
+    /// action is staged first, so the player sees what it would do and can cancel it, which puts
+    /// their cards back, before anything is sent. If the turn clock runs out while they are
+    /// choosing, any cards they used are given back and their turn ends instead. Actions that the
+    /// host would reject (e.g. a King against a teammate) are given back too, and the player
+    /// chooses again.
+    fn choose_action(&mut self) -> error::Result<Action> {
+        let session = self.session.as_mut().ok_or(KingdomError::NotConnected)?;
+        let (game_state, rules) = (session.game_state().clone(), session.rules().clone());
         loop {
-            let turn_player = self.get_turn_player_from_server();
-            println!("Staring {turn_player}'s Turn");
-            if turn_player == self.player.name() {
-                self.start_client_player_turn(game_state);
+            let staged = if self.config.command_mode {
+                self.stage_command()?
             } else {
-                self.start_other_player_turn(game_state);
+                self.stage_card()?
+            };
+            let session = self.session.as_mut().ok_or(KingdomError::NotConnected)?;
+            // If there is no action, end turn.
+            let Some(staged) = staged else {
+                break Ok(Action::new_turn_end(session.player().name()));
+            };
+            let player = session.player_mut();
+            if let Some(action) =
+                staged.confirm_with_prompt(player, &game_state, &rules, self.input.as_mut())
+            {
+                break Ok(action);
             }
         }
     }
 
-    /// Gets action from server signifying the start of a player's turn.
-    /// If the turn player is this current player, this function returns true,
-    /// else, it returns false.
-    fn get_turn_player_from_server(&mut self) -> String {
-        println!("GETTING TURN PLAYER FROM SERVER.");
-        let handler = self.handler.as_mut().unwrap();
-        let status = handler.send_request_await_response(ACTION_REQUEST, ACTION_RESPONSE);
-        match status {
-            Ok(response) => {
-                if let ResponseType::PlayerAction(Some(action)) = response.response_type() {
-                    if variant_eq(action.action_type(), &ActionType::TurnStart) {
-                        /* Turn player is stored as "from_player" in Action. */
-                        action.from_player().to_owned()
-                    } else {
-                        unreachable!()
-                    }
-                } else {
-                    unreachable!()
+    /// Prompts the player to choose a card by number, and stages the action it makes. Returns
+    /// `None` if they end their turn, or the turn clock runs out or the host ends it.
+    fn stage_card(&mut self) -> error::Result<Option<StagedAction>> {
+        let session = self.session.as_mut().ok_or(KingdomError::NotConnected)?;
+        let game_state = session.game_state().clone();
+        let player = session.player_mut();
+        loop {
+            let status = player.get_action(&game_state, self.input.as_mut());
+            if self.input.timed_out() {
+                if let Ok(Some(staged)) = status {
+                    staged.cancel(player);
                 }
+                println!("Your turn is over.");
+                return Ok(None);
             }
-            Err(err) => {
-                perror_in_fn("get_turn_start", err);
-                "".to_string()
+            match status {
+                Ok(staged) => return Ok(staged),
+                Err(KingdomError::Cancelled) => {
+                    println!("Cancelled. Your cards are back in your hand.")
+                }
+                Err(err) => println!("Error! {err}"),
             }
         }
     }
 
-    fn start_client_player_turn(&mut self, game_state: &mut GameState) {
+    /// Reads commands until the player plays a card or ends their turn, and stages the action it
+    /// makes. Commands that would break the rules are rejected with the reason why, and the
+    /// player types another one. Returns `None` if they end their turn, or the turn clock runs
+    /// out or the host ends it.
+    fn stage_command(&mut self) -> error::Result<Option<StagedAction>> {
+        let session = self.session.as_mut().ok_or(KingdomError::NotConnected)?;
+        let (game_state, rules) = (session.game_state().clone(), session.rules().clone());
+        set_completions(commands::completions(session.player(), &game_state));
         loop {
-            if let Some(action) = self.player.get_action(game_state) {
-                self.send_action_to_server(&action);
-            }
-            // If there is no action, end turn.
-            else {
-                self.send_action_to_server(&Action::new_turn_end(self.player.name()));
-                break;
+            let input = self
+                .input
+                .get_input("Command (type 'help' for a list): ", MAX_COMMAND_LEN);
+            if self.input.timed_out() {
+                println!("Your turn is over.");
+                return Ok(None);
             }
-        }
-    }
-
-    fn start_other_player_turn(&mut self, game_state: &mut GameState) {
-        let handler = self.handler_mut();
-        loop {
-            let status = handler.send_request_await_response(ACTION_REQUEST, ACTION_RESPONSE);
-            match status {
-                Ok(response) => {
-                    if let ResponseType::PlayerAction(Some(action)) = response.response_type() {
-                        if matches!(action.action_type(), ActionType::TurnEnd) {
-                            break;
-                        } else {
-                            ClientInstance::handle_other_player_action(action, handler, game_state);
-                        }
-                    } else {
-                        unreachable!()
+            let command = match Command::from_str(&input) {
+                Ok(command) => command,
+                Err(err) => {
+                    println!("Error! {err}");
+                    continue;
+                }
+            };
+            match command {
+                Command::Hand => session.player().print_hand_codes(),
+                Command::State => {
+                    Commentator::table(&game_state);
+                    Commentator::cards(session.view());
+                }
+                Command::Log if self.recent_actions.is_empty() => {
+                    println!("Nothing has been played yet.")
+                }
+                Command::Log => {
+                    for action in &self.recent_actions {
+                        println!("{}", action.summary());
                     }
                 }
-                Err(err) => perror_in_fn("start_other_player_turn", err),
+                Command::Help => println!("{HELP}"),
+                Command::End => return Ok(None),
+                command => match command.stage(session.player_mut(), &game_state, &rules) {
+                    Ok(staged) => return Ok(Some(staged)),
+                    Err(err) => println!("Error! {err}"),
+                },
             }
         }
     }
 
-    fn handle_other_player_action(
-        action: &Action,
-        handler: &mut StreamHandler,
-        game_state: &mut GameState,
-    ) {
-        match action.action_type() {
-            ActionType::PlayKing => {
-                let to_player = action.to_player();
-                let num_points = 10 + action.attachment();
-                game_state.subtract_points_from_player(to_player, num_points);
-                Commentator::play_king(action, game_state)
-            }
-            ActionType::PlayQueen => {
-                let to_player = action.to_player();
-                let num_points = 10 + action.attachment();
-                game_state.add_points_to_player(to_player, num_points);
-                Commentator::play_king(action, game_state)
-            }
-            ActionType::PlayJack => todo!(),
-            ActionType::PlayNumber => Commentator::play_number(action),
-            ActionType::PlayBlackAce => todo!(),
-            ActionType::PlayRedAce => todo!(),
-            ActionType::TurnStart => Commentator::turn_start(action.from_player()),
-            ActionType::TurnEnd => Commentator::turn_start(action.from_player()),
-            ActionType::Status => todo!(),
-            ActionType::None => todo!(),
-        }
+    /// Answers the server's request for a reaction to `king`, which was played against this
+    /// player. If they have a Jack, they have the reaction clock to choose whether to block the
+    /// King with it. Otherwise, or if the reaction clock runs out, the reaction is declined.
+    fn react_to_king(&mut self, king: &Action) -> error::Result<()> {
+        let session = self.session.as_mut().ok_or(KingdomError::NotConnected)?;
+        let block = session.player().has_jack() && {
+            let deadline = session
+                .rules()
+                .reaction_duration()
+                .map(|duration| Instant::now() + duration);
+            set_input_deadline(deadline);
+            let prompt = format!("Block {}'s King with a Jack? [y/n]: ", king.from_player());
+            let block = self.input.get_bool_input(&prompt, "y", "n");
+            set_input_deadline(None);
+            block
+        };
+        session.respond_to_reaction(block)
     }
 
-    /// Sends action to server, printing any errors that may occur.
-    fn send_action_to_server(&mut self, action: &Action) {
-        let handler = self.handler_mut();
-        if let Err(err) = handler
-            .await_request_send_response(ACTION_REQUEST, &Response::from_action(action.to_owned()))
-        {
-            perror_in_fn("send_action_to_server", err);
+    /// Keeps `action` for the `log` command, forgetting the oldest action once there are more
+    /// than `LOG_LENGTH`.
+    fn record_action(&mut self, action: Action) {
+        if variant_eq(action.action_type(), &ActionType::None) {
+            return;
+        }
+        self.recent_actions.push(action);
+        if self.recent_actions.len() > LOG_LENGTH {
+            self.recent_actions.remove(0);
         }
     }
 
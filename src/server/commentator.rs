@@ -1,19 +1,183 @@
-use crate::{game::game_state::GameState, server::response::Action};
+use crate::{
+    error,
+    game::game_state::{GameState, PlayerDetails},
+    server::{
+        chat::ChatMessage,
+        lobby::{LobbyState, LobbyStatus},
+        response::{Action, ActionType},
+        rooms::RoomInfo,
+        view::TableView,
+    },
+};
 
 pub struct Commentator;
 
 impl Commentator {
-    pub fn turn_start(name: &str) {
-        println!("{name} is starting their turn.");
+    /// Prints commentary for any action. `game_state` should be the game state after the action
+    /// was applied.
+    pub fn action(action: &Action, game_state: &GameState) {
+        match action.action_type() {
+            ActionType::PlayKing => Commentator::play_king(action, game_state),
+            ActionType::PlayQueen => Commentator::play_queen(action, game_state),
+            ActionType::PlayJack => Commentator::play_jack(action, game_state),
+            ActionType::PlayNumber => Commentator::play_number(action),
+            ActionType::TurnStart => {
+                Commentator::turn_start(action.from_player(), action.attachment())
+            }
+            ActionType::TurnEnd => Commentator::turn_end(action.from_player()),
+            ActionType::GameOver => Commentator::game_over(action.from_player(), game_state),
+            ActionType::SetPoints => {
+                Commentator::set_points(action.from_player(), action.attachment())
+            }
+            other => println!("{} played {other:?}.", action.from_player()),
+        }
+    }
+
+    /// Prints a chat message, set apart from the rest of the commentary.
+    pub fn chat(message: &ChatMessage) {
+        match (message.emote(), message.to()) {
+            (Some(emote), _) => println!("[chat] * {} {emote}", message.from()),
+            (None, Some(to)) => {
+                println!("[whisper] {} -> {to}: {}", message.from(), message.text())
+            }
+            (None, None) => println!("[chat] {}: {}", message.from(), message.text()),
+        }
+    }
+
+    /// Prints who is in the lobby and who is ready.
+    pub fn lobby(lobby: &LobbyState) {
+        match lobby.status() {
+            LobbyStatus::Starting => println!("The game is starting!"),
+            LobbyStatus::Kicked => println!("You were kicked from the lobby."),
+            LobbyStatus::Waiting => {
+                println!(
+                    "Lobby ({}/{} seats taken):",
+                    lobby.players().len(),
+                    lobby.seats()
+                );
+                for (name, ready) in lobby.players() {
+                    let ready = if *ready { "ready" } else { "not ready" };
+                    println!("  {name:<16} {ready}");
+                }
+            }
+        }
+    }
+
+    /// Prints the rooms that can be joined, with their join codes.
+    pub fn rooms(rooms: &[RoomInfo]) {
+        if rooms.is_empty() {
+            println!("There are no open rooms. Create one to start playing!");
+            return;
+        }
+        println!("Open rooms:");
+        for room in rooms {
+            println!(
+                "  {}  {:<25} {}/{} seats taken",
+                room.code(),
+                room.name(),
+                room.players(),
+                room.seats()
+            );
+        }
+    }
+
+    /// Prints every player's points, marking whose turn it is.
+    pub fn table(game_state: &GameState) {
+        for (index, player) in game_state.all_players().iter().enumerate() {
+            let marker = if index == game_state.current_player_index() {
+                '>'
+            } else {
+                ' '
+            };
+            match player.team() {
+                Some(team) => println!(
+                    "{marker} {:<16} {:>4} points  team {team}",
+                    player.name(),
+                    player.points()
+                ),
+                None => println!(
+                    "{marker} {:<16} {:>4} points",
+                    player.name(),
+                    player.points()
+                ),
+            }
+        }
+    }
+
+    /// Prints how many cards every player has and what is on top of their discard pile, as of
+    /// the start of the turn.
+    pub fn cards(view: &TableView) {
+        for seat in view.seats() {
+            let top = match seat.discard_pile.last() {
+                Some(card) => format!(", {card} on top"),
+                None => String::new(),
+            };
+            println!(
+                "  {:<16} {:>2} in hand  {:>2} in deck  {:>2} discarded{top}",
+                seat.name,
+                seat.hand_size,
+                seat.deck_size,
+                seat.discard_pile.len()
+            );
+        }
+    }
+
+    /// `seconds` is how long the player has to take their turn, or 0 if there is no turn clock.
+    pub fn turn_start(name: &str, seconds: u16) {
+        if seconds != 0 {
+            println!("{name} is starting their turn, and has {seconds} seconds.");
+        } else {
+            println!("{name} is starting their turn.");
+        }
     }
 
     pub fn turn_end(name: &str) {
         println!("{name} has ended their turn.")
     }
 
+    /// Prints that the host set a player's points, which eliminates them if it is 0 (e.g. when
+    /// they are kicked).
+    pub fn set_points(name: &str, points: u16) {
+        if points == 0 {
+            println!("The host removed {name} from the game.")
+        } else {
+            println!("The host set {name}'s points to {points}.")
+        }
+    }
+
+    /// Prints who won the game. When there are teams, everyone on the winner's team wins.
+    pub fn game_over(winner: &str, game_state: &GameState) {
+        let team = game_state
+            .player_by_name(winner)
+            .and_then(PlayerDetails::team);
+        match team {
+            Some(team) => {
+                let members: Vec<&str> = game_state
+                    .team_members(team)
+                    .iter()
+                    .map(|player| player.name())
+                    .collect();
+                println!(
+                    "Game over! Team {team} ({}) is the last team standing, and wins the game.",
+                    members.join(", ")
+                )
+            }
+            None => println!("Game over! {winner} is the last player standing, and wins the game."),
+        }
+    }
+
+    /// Prints whether the cards this player was dealt matched the decks the host committed to.
+    pub fn fair_deal(result: &error::Result<()>) {
+        match result {
+            Ok(()) => println!("The deal was checked, and it was fair."),
+            Err(err) => println!("WARNING! {err}"),
+        }
+    }
+
     pub fn play_king(action: &Action, game_state: &GameState) {
-        let to_player = game_state.player_by_name(action.to_player());
-        let to_player = to_player.unwrap();
+        let Some(to_player) = game_state.player_by_name(action.to_player()) else {
+            return;
+        };
         if action.attachment() != 0 {
             println!(
                 "{} played a King with {} against {}.",
@@ -36,27 +200,39 @@ impl Commentator {
     }
 
     pub fn play_queen(action: &Action, game_state: &GameState) {
-        let to_player = game_state.player_by_name(action.to_player());
-        let to_player = to_player.unwrap();
+        /* Queens heal the player who played them, or a teammate. */
+        let Some(player) = game_state.player_by_name(game_state.queen_target(action)) else {
+            return;
+        };
+        let on_teammate = if player.name() != action.from_player() {
+            format!(" on {}", player.name())
+        } else {
+            String::new()
+        };
         if action.attachment() != 0 {
             println!(
-                "{} played a Queen with {} against {}.",
+                "{} played a Queen with {}{on_teammate}.",
                 action.from_player(),
                 action.attachment(),
-                action.to_player()
             );
         } else {
-            println!(
-                "{} played a Queen against {}.",
-                action.from_player(),
-                action.to_player(),
-            );
+            println!("{} played a Queen{on_teammate}.", action.from_player());
         }
+        println!("{} now has {} points.", player.name(), player.points());
+    }
+
+    pub fn play_jack(action: &Action, game_state: &GameState) {
+        /* Jacks are played by the target of a King, against the player who played it. */
+        let Some(player) = game_state.player_by_name(action.from_player()) else {
+            return;
+        };
         println!(
-            "{} now has {} points.",
-            to_player.name(),
-            to_player.points()
+            "{} blocked {}'s King with a Jack, and got back {} points.",
+            action.from_player(),
+            action.to_player(),
+            action.attachment(),
         );
+        println!("{} now has {} points.", player.name(), player.points());
     }
 
     pub fn play_number(action: &Action) {
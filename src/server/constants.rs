@@ -1,5 +1,7 @@
 //! This module contains constants for anything server related.
 
+use std::time::Duration;
+
 use super::{
     request::{Request, RequestType},
     response::{Response, ResponseType, StatusType},
@@ -10,11 +12,42 @@ pub const MAX_PLAYERS: usize = 6;
 pub const DECK_SIZE: usize = 52;
 pub const MAX_USERNAME_LEN: usize = 25;
 
+// Room Constants
+pub const MAX_ROOM_NAME_LEN: usize = 25;
+pub const JOIN_CODE_LEN: usize = 4;
+/// Most rooms that can be open on a server at once, so that players can't use up every join code.
+pub const MAX_ROOMS: usize = 100;
+
+// Web Constants
+/// Address that players can connect to over WebSocket when the web table is served and no other
+/// WebSocket address was chosen.
+pub const WEBSOCKET_ADDRESS: &str = "127.0.0.1:5465";
+/// Address that the web table is served on over HTTP (see `web`).
+pub const WEB_ADDRESS: &str = "127.0.0.1:5466";
+/// Address that the HTTP API is served on (see `api`).
+pub const API_ADDRESS: &str = "127.0.0.1:5467";
+
+// Chat Constants
+pub const MAX_CHAT_LEN: usize = 200;
+/// Most chat messages a client can send within `CHAT_RATE_WINDOW`.
+pub const CHAT_RATE_LIMIT: usize = 5;
+pub const CHAT_RATE_WINDOW: Duration = Duration::from_secs(10);
+/// Name that the host's own chat messages (e.g. rejected messages) are sent from. It can't be
+/// used as a username, since the lobby only lets players join with names made of letters (see
+/// `Lobby::validate_name`).
+pub const HOST_CHAT_NAME: &str = "*";
+
 // Request Constants
 pub const NAME_REQUEST: &Request = &Request::new(RequestType::Name);
 pub const ACTION_REQUEST: &Request = &Request::new(RequestType::PlayerAction);
 pub const STATUS_REQUEST: &Request = &Request::new(RequestType::Status);
 pub const GAME_STATE_REQUEST: &Request = &Request::new(RequestType::GameState);
+pub const CARDS_REQUEST: &Request = &Request::new(RequestType::Cards);
+pub const RULES_REQUEST: &Request = &Request::new(RequestType::Rules);
+pub const ROOM_REQUEST: &Request = &Request::new(RequestType::Room);
+pub const ROOMS_REQUEST: &Request = &Request::new(RequestType::Rooms);
+pub const VIEW_REQUEST: &Request = &Request::new(RequestType::View);
+pub const DEAL_REQUEST: &Request = &Request::new(RequestType::Deal);
 
 // Response Constants
 pub const NAME_RESPONSE: &Response = &Response::new(ResponseType::Name(None));
@@ -25,3 +58,9 @@ pub const STATUS_RESPONSE_YES: &Response =
 pub const STATUS_RESPONSE_NO: &Response =
     &Response::new(ResponseType::Status(Some(StatusType::No)));
 pub const GAME_STATE_RESPONSE: &Response = &Response::new(ResponseType::GameState(None));
+pub const CARDS_RESPONSE: &Response = &Response::new(ResponseType::Cards(None));
+pub const RULES_RESPONSE: &Response = &Response::new(ResponseType::Rules(None));
+pub const ROOM_RESPONSE: &Response = &Response::new(ResponseType::Room(None));
+pub const ROOMS_RESPONSE: &Response = &Response::new(ResponseType::Rooms(None));
+pub const VIEW_RESPONSE: &Response = &Response::new(ResponseType::View(None));
+pub const DEAL_RESPONSE: &Response = &Response::new(ResponseType::Deal(None));
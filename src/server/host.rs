@@ -1,37 +1,83 @@
 //! This module contains a set of functions for creating a server and
 //! handling clients.
 
-use std::{net::TcpListener, thread, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    sync::{mpsc::Receiver, Arc},
+    thread,
+    time::{Duration, Instant},
+};
 
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 use crate::{
-    game::game_state::{GameState, PlayerDetails},
-    server::{
-        constants::{STATUS_REQUEST, STATUS_RESPONSE_YES},
-        print_internal_info,
+    error::{self, KingdomError},
+    game::{
+        card::Card,
+        fair_deal::{self, DealMessage, Reveal},
+        game_log::GameLog,
+        game_state::{GameState, PlayerDetails},
+        player::{Player, PlayerCards},
+        profiles::ProfileStore,
+        referee,
+        rules::Rules,
+        snapshot::Snapshot,
     },
+    logging,
+    ui::get_input,
     utils::{perror_in_fn, variant_eq},
 };
 
 use super::{
+    admin::{AdminCommand, GameStatus},
+    api,
     constants::{
-        ACTION_REQUEST, ACTION_RESPONSE, GAME_STATE_REQUEST, MAX_PLAYERS, NAME_REQUEST,
-        NAME_RESPONSE, STATUS_RESPONSE_NO,
+        ACTION_REQUEST, ACTION_RESPONSE, CARDS_REQUEST, DEAL_REQUEST, DEAL_RESPONSE,
+        GAME_STATE_REQUEST, MAX_PLAYERS, RULES_REQUEST, STATUS_RESPONSE_NO, STATUS_RESPONSE_YES,
+        VIEW_REQUEST, WEBSOCKET_ADDRESS,
     },
+    lobby::{Lobby, MIN_PLAYERS},
     response::{Action, ActionType, Response, ResponseType},
-    StreamHandler,
+    rooms::{Room, RoomManager},
+    view::{public_action, OwnCards, TableView, Viewer},
+    web, StreamHandler,
 };
 
+/// How long the host waits past the turn and reaction clocks before giving up on a client, so
+/// that clients whose own clocks run out have time to answer.
+const CLOCK_GRACE: Duration = Duration::from_secs(2);
+
+/// Name of the room that the host plays in, which is created when the server starts.
+const HOST_ROOM_NAME: &str = "Host";
+
+/// Longest command that can be typed into the lobby or admin console.
+const MAX_CONSOLE_COMMAND_LEN: usize = 100;
+
+/// How often the host checks for admin commands that can't wait while it waits for the turn
+/// player's next action (see `AdminCommand::interrupts`).
+const ADMIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Type used for representing clients (`StreamHandler` and `PlayerDetails`) server-side.
 struct Client {
     handler: StreamHandler,
-    player: Option<PlayerDetails>,
+    player: PlayerDetails,
+    /// The player's cards. The host deals them and keeps this copy, so that every card the
+    /// player says they played can be checked.
+    cards: Player,
+    /// Shuffles the player's discard pile back into their deck when the deal is fair (see
+    /// `fair_deal::reshuffle_rng`). Otherwise, the host's random number generator is used.
+    reshuffle_rng: Option<ChaCha8Rng>,
 }
 
 impl Client {
-    fn new(handler: StreamHandler, player: Option<PlayerDetails>) -> Client {
-        Client { handler, player }
+    fn new(handler: StreamHandler, player: PlayerDetails) -> Client {
+        Client {
+            handler,
+            player,
+            cards: Player::with_cards(String::new(), PlayerCards::default()),
+            reshuffle_rng: None,
+        }
     }
 
     fn handler_mut(&mut self) -> &mut StreamHandler {
@@ -39,36 +85,237 @@ impl Client {
     }
 
     fn set_player(&mut self, player: PlayerDetails) {
-        self.player = Some(player);
+        self.player = player;
     }
 
     fn player(&self) -> &PlayerDetails {
-        self.player.as_ref().unwrap()
+        &self.player
     }
 }
 
+/// Settings that a server is started with.
+#[derive(Debug, Default)]
+pub struct HostConfig {
+    /// Rules the game is played with.
+    pub rules: Rules,
+    /// Seed for the server's random number generator. If this is `None`, a random seed is used.
+    pub seed: Option<u64>,
+    /// Where the game log is written. If this is `None`, the game log is written to
+    /// `game-{SEED}.kklog` in `data_dir`.
+    pub game_log: Option<PathBuf>,
+    /// Where the game is saved at the start of every turn. If this is `None`, the game is saved
+    /// to `game-{SEED}.kksave` in `data_dir`.
+    pub save: Option<PathBuf>,
+    /// Directory that game logs and saves are written to, for every room on the server, when
+    /// no other path is given for them. If this is `None`, they are only written to the paths in
+    /// `game_log` and `save`, so nothing is written by default.
+    pub data_dir: Option<PathBuf>,
+    /// Snapshot to resume the game from. When resuming, the rules and seed come from the
+    /// snapshot instead of this config.
+    pub resume: Option<PathBuf>,
+    /// Where player profiles are kept. If this is `None`, no profiles are kept.
+    pub profiles: Option<PathBuf>,
+    /// Address that players can also connect to over WebSocket (e.g. `127.0.0.1:5465`). If this
+    /// is `None`, players can only connect over TCP, unless the web table is served.
+    pub websocket: Option<String>,
+    /// Address that the web table is served on over HTTP (see `web`). The web table plays over
+    /// WebSocket, so players can then also connect at `WEBSOCKET_ADDRESS` if `websocket` is
+    /// `None`. If this is `None`, the web table isn't served.
+    pub web: Option<String>,
+    /// Address that the HTTP API is served on (see `api`). If this is `None`, it isn't served.
+    pub api: Option<String>,
+    /// Token that the HTTP API's admin endpoints are called with. If this is `None`, a random
+    /// token is printed when the server starts.
+    pub admin_token: Option<String>,
+}
+
+/// The game in one room of the server. The host's own room is controlled from the console, and
+/// every room that a player creates is played on a thread of its own.
 pub struct ServerInstance {
     game_state: GameState,
-    listener: TcpListener,
+    /// Every room on the server, including this one.
+    rooms: Arc<RoomManager>,
+    room: Arc<Room>,
+    /// Where the stream of every player in the room is received once the game starts.
+    handlers: Receiver<(String, StreamHandler)>,
     clients: Vec<Client>,
     current_client: usize,
-    join_code: String,
+    rules: Rules,
+    seed: u64,
+    rng: ChaCha8Rng,
+    /// Where the game log is written, if it is written at all.
+    game_log_path: Option<PathBuf>,
+    game_log: Option<GameLog>,
+    /// Where the game is saved at the start of every turn, if it is saved at all.
+    save_path: Option<PathBuf>,
+    /// Snapshot that the game is being resumed from, if any.
+    resume: Option<Snapshot>,
+    /// When the turn player runs out of time, if there is a turn clock.
+    turn_deadline: Option<Instant>,
+    /// Who eliminated whom, as `(ELIMINATOR, ELIMINATED)`, in the order they were eliminated.
+    eliminations: Vec<(String, String)>,
+    /// What the host reveals about the deal once the game is over, if the deal was fair.
+    fair_deal: Option<Reveal>,
+    /// Address that players can also connect to over WebSocket, if any.
+    websocket: Option<String>,
+    /// Address that the web table is served on, if any.
+    web: Option<String>,
+    /// Address that the HTTP API is served on, and the token for its admin endpoints, if any.
+    api: Option<(String, Option<String>)>,
+    /// True once the host has ended the game, which happens before the next turn starts.
+    end_requested: bool,
+    /// True once the host has shut down the server, which happens before the next turn starts.
+    shutdown_requested: bool,
 }
 
 impl ServerInstance {
-    /// Starts instance of Kingdom Kards server.
+    /// Starts instance of Kingdom Kards server, with a room for the host.
     /// Hosted locally on port 5464 because 'king' - phone keypad -> '5464'
-    pub fn create() -> ServerInstance {
-        let port = "127.0.0.1:5464".to_string();
-        let listener = TcpListener::bind(port).expect("Failed to bind to port 127.0.0.1:5464");
-        // The maximum amount of players allowed to join a game.
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if it is unable to bind to the port.
+    pub fn create() -> error::Result<ServerInstance> {
+        ServerInstance::with_config(HostConfig::default())
+    }
+
+    /// Same as `ServerInstance::create`, but with the rules, seed and file paths set by
+    /// `config`.
+    ///
+    /// ## Returns
+    ///
+    /// This function will also return an error if the snapshot being resumed or the profiles
+    /// can't be read.
+    pub fn with_config(config: HostConfig) -> error::Result<ServerInstance> {
+        let resume = match &config.resume {
+            Some(path) => Some(Snapshot::load(path)?),
+            None => None,
+        };
+        let profiles = match &config.profiles {
+            Some(path) => Some(ProfileStore::open(path)?),
+            None => None,
+        };
+        let rooms = RoomManager::bind("127.0.0.1:5464", profiles, config.data_dir.clone())?;
+        let lobby = match &resume {
+            Some(snapshot) => Lobby::new(snapshot.rules.clone(), Some(snapshot.names())),
+            None => Lobby::new(config.rules.clone(), None),
+        };
+        let (room, handlers) = rooms.create_room(HOST_ROOM_NAME, lobby)?;
+        Ok(ServerInstance::in_room(
+            rooms, room, handlers, config, resume,
+        ))
+    }
+
+    /// Creates a room named `name` for a player, with `rules`, and plays its game on a new
+    /// thread. The game starts once everyone in the room is ready, and the room is closed when
+    /// the game is over or if it is abandoned first. Returns the room, or an error if it can't be
+    /// created (see `RoomManager::create_room`).
+    pub(super) fn open_player_room(
+        rooms: &Arc<RoomManager>,
+        name: &str,
+        rules: Rules,
+    ) -> error::Result<Arc<Room>> {
+        let lobby = Lobby::auto_starting(rules, None);
+        ServerInstance::open_room(rooms, name, lobby, None)
+    }
+
+    /// Creates a room named `name` that resumes `snapshot`, and plays its game on a new thread.
+    /// Only the players in the snapshot can join, and the game starts once all of them have
+    /// rejoined and are ready. Returns the room, or an error if it can't be created.
+    pub fn open_resumed_room(
+        rooms: &Arc<RoomManager>,
+        name: &str,
+        snapshot: Snapshot,
+    ) -> error::Result<Arc<Room>> {
+        let lobby = Lobby::auto_starting(snapshot.rules.clone(), Some(snapshot.names()));
+        ServerInstance::open_room(rooms, name, lobby, Some(snapshot))
+    }
+
+    /// Creates a room named `name` with `lobby`, which has to start on its own, then plays its
+    /// game on a new thread, resuming `resume` if it is given. The room is closed when the game is
+    /// over or if it is abandoned first.
+    fn open_room(
+        rooms: &Arc<RoomManager>,
+        name: &str,
+        lobby: Lobby,
+        resume: Option<Snapshot>,
+    ) -> error::Result<Arc<Room>> {
+        let (room, handlers) = rooms.create_room(name, lobby)?;
+        let config = HostConfig {
+            data_dir: rooms.data_dir().map(Path::to_owned),
+            ..HostConfig::default()
+        };
+        let mut server = ServerInstance::in_room(
+            Arc::clone(rooms),
+            Arc::clone(&room),
+            handlers,
+            config,
+            resume,
+        );
+        thread::spawn(move || {
+            if let Some(names) = server.room.await_start() {
+                server.play(&names);
+            }
+            server.rooms.close(server.room.code());
+        });
+        Ok(room)
+    }
+
+    /// Creates the game for `room`, with the rules from its lobby. The seed and file paths are
+    /// set by `config`, unless the game is being resumed from `resume`.
+    fn in_room(
+        rooms: Arc<RoomManager>,
+        room: Arc<Room>,
+        handlers: Receiver<(String, StreamHandler)>,
+        config: HostConfig,
+        resume: Option<Snapshot>,
+    ) -> ServerInstance {
+        let (seed, rng) = match &resume {
+            Some(snapshot) => {
+                let mut rng = ChaCha8Rng::seed_from_u64(snapshot.seed);
+                rng.set_word_pos(snapshot.rng_word_pos);
+                (snapshot.seed, rng)
+            }
+            None => {
+                let seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+                (seed, ChaCha8Rng::seed_from_u64(seed))
+            }
+        };
+        let saved_game_log = resume
+            .as_ref()
+            .and_then(|snapshot| snapshot.game_log.as_ref())
+            .map(|(path, _)| path.clone());
+        let in_data_dir = |file: String| config.data_dir.as_ref().map(|dir| dir.join(file));
+        let game_log_path = config
+            .game_log
+            .or(saved_game_log)
+            .or_else(|| in_data_dir(format!("game-{seed}.kklog")));
+        let save_path = config
+            .save
+            .or_else(|| in_data_dir(format!("game-{seed}.kksave")));
 
         ServerInstance {
             game_state: GameState::new(),
-            listener,
-            join_code: "1234".to_string(),
+            rules: room.lobby().rules(),
+            rooms,
+            room,
+            handlers,
             clients: Vec::with_capacity(MAX_PLAYERS),
             current_client: 0,
+            seed,
+            rng,
+            game_log_path,
+            game_log: None,
+            save_path,
+            resume,
+            turn_deadline: None,
+            eliminations: Vec::new(),
+            fair_deal: None,
+            websocket: config.websocket,
+            web: config.web,
+            api: config.api.map(|address| (address, config.admin_token)),
+            end_requested: false,
+            shutdown_requested: false,
         }
     }
 
@@ -77,131 +324,240 @@ impl ServerInstance {
         &self.game_state
     }
 
-    fn turn_client_mut(&mut self) -> Option<&mut Client> {
-        let turn_player = self.game_state.current_player();
-        self.clients
-            .iter_mut()
-            .find(|c| c.player().name() == turn_player.name())
-    }
-
-    fn turn_player_name(&self) -> &str {
-        let turn_player = self.game_state.current_player();
-        turn_player.name()
+    /// Starts up server operations. First, players choose a room and join its lobby. Other
+    /// players' rooms start on their own, while the host chooses the rules and number of seats
+    /// for the host's room from the console. Once every player is ready and the host starts the
+    /// game, the core gameplay loop starts, and the console takes admin commands while it runs.
+    /// When resuming a saved game, only the players in the snapshot can join the host's room, and
+    /// they are put back in their seats.
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if the listener can't be shared with the thread that
+    /// accepts players, or if the WebSocket, web table or HTTP API address can't be bound.
+    pub fn start(&mut self) -> error::Result<()> {
+        self.rooms.accept()?;
+        let websocket = match (&self.websocket, &self.web) {
+            (Some(address), _) => Some(address.as_str()),
+            (None, Some(_)) => Some(WEBSOCKET_ADDRESS),
+            (None, None) => None,
+        };
+        if let Some(address) = websocket {
+            let websocket_addr = self.rooms.accept_websockets(address)?;
+            if let Some(web) = &self.web {
+                web::serve(web, websocket_addr.port())?;
+            }
+        }
+        if let Some((address, token)) = &self.api {
+            let token = token
+                .clone()
+                .unwrap_or_else(|| fair_deal::random_hex(&mut rand::thread_rng()));
+            api::serve(address, Arc::clone(&self.rooms), self.room.code(), &token)?;
+            println!("Admin token for the HTTP API: {token}");
+        }
+        println!("Starting lobby with join code: {}", self.room.code());
+        let names = ServerInstance::run_lobby_console(&self.rooms, self.room.lobby());
+        let room = Arc::clone(&self.room);
+        thread::spawn(move || ServerInstance::run_admin_console(&room));
+        self.play(&names);
+        self.rooms.close(self.room.code());
+        Ok(())
     }
 
-    /// Starts up server operations. First, the server accepts the number
-    /// of players (between 2 and 6). Next, players will enter their usernames
-    /// and the server will validate that the names are unique. After that, the
-    /// core gameplay loop starts.
-    pub fn start(&mut self) {
-        // let num_players = get_num_input("Enter number of players (min. 2, max. 6): ", 2, 6);
-        // TODO: change back to `num_players` for full application
-        self.accept_players(2);
-        self.reject_extra_players();
-        println!("Starting server with join code: {}", self.join_code);
-        self.name_players();
+    /// Seats the players in `names`, then plays the game until too few of them are left.
+    fn play(&mut self, names: &[String]) {
+        self.rules = self.room.lobby().rules();
+        self.seat_players(names);
+        if self.resume.is_some() {
+            self.restore_seats();
+        }
         self.game_state.print_all_players();
-        // self.randomize_players();
+        self.create_game_log();
         self.send_game_state();
+        self.send_rules();
+        self.deal_cards();
+        self.room.monitor().start(&self.game_state);
         self.start_game_loop();
+        self.room.monitor().finish();
     }
 
-    /// Allows `num_players` clients to join, exits after all players have joined.
-    fn accept_players(&mut self, num_players: u8) {
-        let mut num_connections = 0;
-        println!("Accepting players...");
-        while num_connections < num_players {
-            match self.listener.accept() {
-                Ok((stream, _)) => {
-                    let mut handler = StreamHandler::new(stream);
-                    if let Err(err) = handler.await_request(STATUS_REQUEST) {
-                        perror_in_fn("accept_players", err);
-                    }
-                    /* Accept player. */
-                    if let Err(err) = handler.send_response(STATUS_RESPONSE_YES) {
-                        perror_in_fn("accept_players", err);
-                    }
-                    self.clients.push(Client::new(handler, None));
-                    num_connections += 1;
+    /// Reads lobby commands from the console until the host starts the game. Returns the names
+    /// of the players in seat order.
+    fn run_lobby_console(rooms: &RoomManager, lobby: &Lobby) -> Vec<String> {
+        ServerInstance::print_lobby_help();
+        loop {
+            let input = get_input("lobby> ", MAX_CONSOLE_COMMAND_LEN);
+            let (command, argument) = input.split_once(' ').unwrap_or((&input, ""));
+            let result = match command {
+                "start" => match lobby.start() {
+                    Ok(names) => return names,
+                    Err(err) => Err(err),
+                },
+                "kick" => lobby.kick(argument),
+                "seats" => argument
+                    .parse()
+                    .map_err(|_| KingdomError::LobbyRejected(format!("invalid seats '{argument}'")))
+                    .and_then(|seats| lobby.set_seats(seats)),
+                "rules" => lobby
+                    .change_rules(argument)
+                    .map(|rules| println!("Rules: {rules}")),
+                "list" => {
+                    lobby.print();
+                    Ok(())
                 }
-                Err(err) => {
-                    perror_in_fn("accept_players", err);
+                "rooms" => {
+                    rooms.print();
+                    Ok(())
+                }
+                "leaderboard" => match rooms.profiles() {
+                    Some(profiles) => {
+                        profiles.print_leaderboard();
+                        Ok(())
+                    }
+                    None => Err(KingdomError::LobbyRejected(String::from(
+                        "no profiles are being kept (see --profiles)",
+                    ))),
+                },
+                "" => Ok(()),
+                _ => {
+                    ServerInstance::print_lobby_help();
+                    Ok(())
                 }
+            };
+            if let Err(err) = result {
+                println!("Error! {err}");
             }
         }
     }
 
-    /// Starts a new thread that rejects all players that join
-    /// after the room is full.
-    fn reject_extra_players(&mut self) {
-        let listener = self.listener.try_clone().unwrap();
-        thread::spawn(move || {
-            for connection in listener.incoming() {
-                let handler = &mut StreamHandler::new(connection.unwrap());
-                if let Err(err) =
-                    /* Client asking if room is full. */
-                    handler.await_request_send_response(STATUS_REQUEST, STATUS_RESPONSE_NO)
-                {
-                    perror_in_fn("reject_extra_players", err);
-                }
-            }
-        });
+    fn print_lobby_help() {
+        println!(
+            "Lobby commands: start, kick {{NAME}}, seats {{{MIN_PLAYERS}-{MAX_PLAYERS}}}, \
+            rules {{KEY=VALUE,...}}, list, rooms, leaderboard"
+        );
     }
 
-    /// Prompts every user to enter a username and verifies that each username is unique.
-    fn name_players(&mut self) {
-        for client in self.clients.iter_mut() {
-            let handler = client.handler_mut();
-            let mut is_accepted = false;
-            let mut name = String::new();
-            while !is_accepted {
-                name = ServerInstance::get_client_name(handler);
-                is_accepted =
-                    ServerInstance::send_name_status(handler, &mut self.game_state, name.as_str());
+    /// Reads admin commands from the console while the game in `room` is played, and sends them
+    /// to the game through the room's monitor. Shutting down exits once the game has been saved.
+    fn run_admin_console(room: &Room) {
+        ServerInstance::print_admin_help();
+        let monitor = room.monitor();
+        loop {
+            let input = get_input("admin> ", MAX_CONSOLE_COMMAND_LEN);
+            let (command, argument) = input.split_once(' ').unwrap_or((&input, ""));
+            let result = match command {
+                "players" => ServerInstance::print_players(room),
+                "state" => {
+                    ServerInstance::print_state(room);
+                    Ok(())
+                }
+                "kick" => monitor.send(AdminCommand::Kick(argument.to_owned())),
+                "pause" => monitor.pause(),
+                "resume" => monitor.resume(),
+                "skip" => monitor.send(AdminCommand::Skip),
+                "set-points" => argument
+                    .rsplit_once(' ')
+                    .and_then(|(name, points)| Some((name, points.parse().ok()?)))
+                    .ok_or_else(|| {
+                        KingdomError::AdminRejected(String::from(
+                            "usage: set-points {NAME} {POINTS}",
+                        ))
+                    })
+                    .and_then(|(name, points)| {
+                        monitor.send(AdminCommand::SetPoints(name.to_owned(), points))
+                    }),
+                "save" => {
+                    let path = (!argument.is_empty()).then(|| PathBuf::from(argument));
+                    monitor.send(AdminCommand::Save(path))
+                }
+                "shutdown" => {
+                    if monitor.send(AdminCommand::Shutdown).is_ok() {
+                        monitor.wait_until_over();
+                    }
+                    println!("Shutting down the server.");
+                    std::process::exit(0);
+                }
+                "" => Ok(()),
+                _ => {
+                    ServerInstance::print_admin_help();
+                    Ok(())
+                }
+            };
+            if let Err(err) = result {
+                println!("Error! {err}");
             }
-            client.set_player(PlayerDetails::new(name, 100));
         }
     }
 
-    fn get_client_name(handler: &mut StreamHandler) -> String {
-        let status = handler.send_request_await_response(NAME_REQUEST, NAME_RESPONSE);
-        match status {
-            Ok(response) => {
-                if let ResponseType::Name(name) = response.response_type() {
-                    name.as_ref().unwrap().to_owned()
-                } else {
-                    unreachable!("Received response of incorrect type");
-                }
-            }
-            Err(err) => {
-                perror_in_fn("get_client_name", err);
-                "".to_string()
-            }
-        }
+    fn print_admin_help() {
+        println!(
+            "Admin commands: players, state, kick {{NAME}}, pause, resume, skip, \
+            set-points {{NAME}} {{POINTS}}, save [PATH], shutdown"
+        );
     }
 
-    fn send_name_status(
-        handler: &mut StreamHandler,
-        game_state: &mut GameState,
-        name: &str,
-    ) -> bool {
-        if let Err(err) = handler.await_request(STATUS_REQUEST) {
-            perror_in_fn("send_status", err);
+    /// Prints every player in the game in `room` in seat order, with whose turn it is and who is
+    /// out of the game.
+    fn print_players(room: &Room) -> error::Result<()> {
+        let game_state = room
+            .monitor()
+            .game_state()
+            .ok_or_else(|| KingdomError::AdminRejected(String::from("the game hasn't started")))?;
+        println!("Players in room '{}':", room.name());
+        for (index, player) in game_state.all_players().iter().enumerate() {
+            let note = if player.is_eliminated() {
+                " (out)"
+            } else if index == game_state.current_player_index() {
+                " (turn)"
+            } else {
+                ""
+            };
+            println!("\t{}. {player}{note}", index + 1);
         }
-        if game_state.is_unique_name(name) {
-            /* Add a new player with 100 points. */
-            let new_player = PlayerDetails::new(name.to_owned(), 100);
-            game_state.add_player(new_player);
-            if let Err(err) = handler.send_response(STATUS_RESPONSE_YES) {
-                perror_in_fn("ServerInstance::send_status", err);
-            }
+        Ok(())
+    }
 
-            return true;
-        } else if let Err(err) = handler.send_response(STATUS_RESPONSE_NO) {
-            perror_in_fn("ServerInstance::send_status", err);
+    /// Prints how far along the game in `room` is, whose turn it is, and the latest action.
+    fn print_state(room: &Room) {
+        let monitor = room.monitor();
+        let status = monitor.status();
+        let turn = monitor
+            .game_state()
+            .and_then(|game_state| {
+                game_state
+                    .current_player()
+                    .map(|player| player.name().to_owned())
+            })
+            .filter(|_| matches!(status, GameStatus::Playing | GameStatus::Paused));
+        match turn {
+            Some(name) => println!(
+                "The game in room '{}' is {status}, and it is {name}'s turn.",
+                room.name()
+            ),
+            None => println!("The game in room '{}' is {status}.", room.name()),
+        }
+        let last_seq = monitor.last_seq();
+        match monitor.events_since(last_seq.saturating_sub(1)).first() {
+            Some(event) => println!(
+                "{last_seq} actions so far. The latest: {}.",
+                event.action.summary()
+            ),
+            None => println!("No actions so far."),
         }
+    }
 
-        false
+    /// Waits for the stream of every player in `names` to be handed over from the lobby, then
+    /// seats them in the same order. If there are teams, players are put on them in turn.
+    fn seat_players(&mut self, names: &[String]) {
+        let mut seated: Vec<(String, StreamHandler)> =
+            self.handlers.iter().take(names.len()).collect();
+        seated.sort_by_key(|(name, _)| names.iter().position(|seat| seat == name));
+        for (name, handler) in seated {
+            let player = PlayerDetails::new(name, self.rules.starting_points);
+            self.game_state.add_player(player.clone());
+            self.clients.push(Client::new(handler, player));
+        }
+        self.game_state.assign_teams(self.rules.teams);
     }
 
     fn send_game_state(&mut self) {
@@ -210,214 +566,929 @@ impl ServerInstance {
             if let Err(err) =
                 handler.await_request_send_response(GAME_STATE_REQUEST, &game_state_response)
             {
-                perror_in_fn("send_game_state", err);
+                perror_in_fn("send_game_state", handler.peer(), err);
+            }
+        }
+    }
+
+    /// Sends every client the rules, so that their copies of the game state are kept the same
+    /// way as the host's.
+    fn send_rules(&mut self) {
+        for Client { handler, .. } in self.clients.iter_mut() {
+            let rules_response = Response::from_rules(self.rules.clone());
+            if let Err(err) = handler.await_request_send_response(RULES_REQUEST, &rules_response) {
+                perror_in_fn("send_rules", handler.peer(), err);
             }
         }
     }
 
-    fn randomize_players(&mut self) {
-        self.clients.shuffle(&mut thread_rng());
+    /// Puts every client back in their saved seat, and restores the saved points and turn.
+    fn restore_seats(&mut self) {
+        let Some(snapshot) = &self.resume else {
+            return;
+        };
+        let seats = &snapshot.game_state;
+        self.clients.sort_by_key(|client| {
+            seats
+                .all_players()
+                .iter()
+                .position(|player| player.name() == client.player().name())
+        });
+        for (client, player) in self.clients.iter_mut().zip(seats.all_players()) {
+            client.set_player(player.clone());
+        }
+        self.game_state = seats.clone();
+        self.current_client = seats.current_player_index();
+        println!("Resumed saved game.");
     }
 
-    fn client_by_name(&self, name: &str) -> &Client {
-        for client in self.clients.iter() {
-            if client.player().name() == name {
-                return client;
+    /// Deals every client a deck shuffled with the host's random number generator and their
+    /// starting hand, or gives them back their saved cards when resuming, then sends every client
+    /// their cards.
+    fn deal_cards(&mut self) {
+        let mut fair_decks = if self.rules.fair_deal {
+            self.fair_decks()
+        } else {
+            None
+        };
+        for index in 0..self.clients.len() {
+            let name = self.clients[index].player().name().to_owned();
+            let saved = self
+                .resume
+                .as_ref()
+                .and_then(|snapshot| snapshot.cards_of(&name))
+                .cloned();
+            let cards = saved.unwrap_or_else(|| {
+                let deck = match &mut fair_decks {
+                    Some(decks) => std::mem::take(&mut decks[index]),
+                    None => {
+                        let mut deck = Card::full_deck();
+                        deck.shuffle(&mut self.rng);
+                        deck
+                    }
+                };
+                fair_deal::deal(&name, deck, self.rules.hand_size)
+            });
+            let reshuffle_rng = match (&fair_decks, &self.fair_deal) {
+                (Some(_), Some(reveal)) => {
+                    let entropy: Vec<(String, String)> = reveal
+                        .seats
+                        .iter()
+                        .map(|(name, entropy, _)| (name.clone(), entropy.clone()))
+                        .collect();
+                    Some(fair_deal::reshuffle_rng(&entropy, &name))
+                }
+                _ => None,
+            };
+            let client = &mut self.clients[index];
+            client.reshuffle_rng = reshuffle_rng;
+            client.cards = Player::with_cards(name, cards.clone());
+            let handler = client.handler_mut();
+            let response = Response::from_cards(Some(OwnCards::of(&cards)));
+            if let Err(err) = handler.await_request_send_response(CARDS_REQUEST, &response) {
+                perror_in_fn("deal_cards", handler.peer(), err);
             }
         }
-        unreachable!()
     }
 
-    fn client_by_name_mut(&mut self, name: &str) -> &mut Client {
+    /// Starts the fair deal (see `fair_deal`): shuffles a deck for every player, sends everyone
+    /// the commitment to them, and shuffles them again with the entropy that everyone sends back.
+    /// Returns the decks to deal from in seat order, or `None` when resuming a saved game, since
+    /// its cards were dealt before it was saved.
+    fn fair_decks(&mut self) -> Option<Vec<Vec<Card>>> {
+        if self.resume.is_some() {
+            self.send_deal("fair_decks", &DealMessage::Commit(Vec::new()));
+            return None;
+        }
+        let salt = fair_deal::random_hex(&mut rand::thread_rng());
+        let mut seats = Vec::new();
+        for client in &self.clients {
+            let mut deck = Card::full_deck();
+            deck.shuffle(&mut self.rng);
+            seats.push((client.player().name().to_owned(), String::new(), deck));
+        }
+        let commitments = seats
+            .iter()
+            .map(|(name, _, deck)| (name.clone(), fair_deal::commitment(deck, &salt)))
+            .collect();
+        self.send_deal("fair_decks", &DealMessage::Commit(commitments));
+        for (client, (_, entropy, _)) in self.clients.iter_mut().zip(seats.iter_mut()) {
+            let handler = client.handler_mut();
+            let result = handler
+                .send_request_await_response(DEAL_REQUEST, DEAL_RESPONSE)
+                .and_then(Response::into_deal);
+            match result {
+                Ok(DealMessage::Entropy(sent)) => *entropy = sent,
+                Ok(_) => perror_in_fn(
+                    "fair_decks",
+                    handler.peer(),
+                    KingdomError::ExpectedResponseType(ResponseType::Deal(None)),
+                ),
+                Err(err) => perror_in_fn("fair_decks", handler.peer(), err),
+            }
+        }
+        let entropy: Vec<(String, String)> = seats
+            .iter()
+            .map(|(name, entropy, _)| (name.clone(), entropy.clone()))
+            .collect();
+        let decks = seats
+            .iter()
+            .map(|(name, _, deck)| {
+                let mut deck = deck.clone();
+                fair_deal::mix(&mut deck, &entropy, name);
+                deck
+            })
+            .collect();
+        self.fair_deal = Some(Reveal { salt, seats });
+        Some(decks)
+    }
+
+    /// Sends `message` from the fair deal to every client.
+    fn send_deal(&mut self, fn_name: &str, message: &DealMessage) {
+        let response = Response::from_deal(message.clone());
         for client in self.clients.iter_mut() {
-            if client.player().name() == name {
-                return client;
+            let handler = client.handler_mut();
+            if let Err(err) = handler.await_request_send_response(DEAL_REQUEST, &response) {
+                perror_in_fn(fn_name, handler.peer(), err);
+            }
+        }
+    }
+
+    /// Returns every player's name and cards as the host keeps them, in seat order.
+    fn seat_cards(&self) -> Vec<(String, PlayerCards)> {
+        self.clients
+            .iter()
+            .map(|client| (client.player().name().to_owned(), client.cards.cards()))
+            .collect()
+    }
+
+    /// Sends every client their own view of the table, which shows their hand, and only how many
+    /// cards everyone else has and what they have discarded.
+    fn send_views(&mut self) {
+        let cards = self.seat_cards();
+        for client in self.clients.iter_mut() {
+            if !client.handler.is_connected() {
+                continue;
+            }
+            let viewer = Viewer::Player(client.player().name().to_owned());
+            let response = Response::from_view(TableView::build(&cards, &viewer));
+            let handler = client.handler_mut();
+            if let Err(err) = handler.await_request_send_response(VIEW_REQUEST, &response) {
+                perror_in_fn("send_views", handler.peer(), err);
+            }
+        }
+    }
+
+    /// Saves the game with every player's cards as the host keeps them, where `next_seq` is the
+    /// sequence number of the first action of the current turn and `turn_secs` is how long the
+    /// turn player has.
+    fn save_game(&mut self, next_seq: Option<u32>, turn_secs: u16) {
+        let Some(path) = &self.save_path else {
+            return;
+        };
+        match self.snapshot(next_seq, turn_secs).save(path) {
+            Ok(()) => logging::info("host", &format!("Saved game to {}", path.display())),
+            Err(err) => perror_in_fn("save_game", "host", err),
+        }
+    }
+
+    /// Saves the game to `path`, or to the save path if it is `None`, when the host asks for it.
+    /// During a turn, what is left on the turn clock is saved, and the turn starts over from the
+    /// current game state when it is resumed. Returns where the game was saved, if it was.
+    fn save_game_to(&mut self, path: Option<PathBuf>, during_turn: bool) -> Option<PathBuf> {
+        let Some(path) = path.or_else(|| self.save_path.clone()) else {
+            println!(
+                "The game in room '{}' has no save path, so it wasn't saved (see --data-dir).",
+                self.room.name()
+            );
+            return None;
+        };
+        let next_seq = self.game_log.as_ref().map(GameLog::next_seq);
+        let turn_secs = match self.turn_deadline {
+            Some(deadline) if during_turn => {
+                let left = deadline
+                    .saturating_duration_since(Instant::now())
+                    .saturating_sub(CLOCK_GRACE);
+                /* A turn clock of 0 seconds would mean there is no turn clock. */
+                u16::try_from(left.as_secs()).unwrap_or(u16::MAX).max(1)
+            }
+            _ => self.rules.turn_secs,
+        };
+        match self.snapshot(next_seq, turn_secs).save(&path) {
+            Ok(()) => {
+                println!(
+                    "Saved the game in room '{}' to {}",
+                    self.room.name(),
+                    path.display()
+                );
+                Some(path)
+            }
+            Err(err) => {
+                perror_in_fn("save_game_to", "host", err);
+                None
             }
         }
-        unreachable!()
     }
 
-    /// Starts core gameplay loop.
+    /// Returns a snapshot of the game as it is now, with every player's cards as the host keeps
+    /// them. See `save_game` for `next_seq` and `turn_secs`.
+    fn snapshot(&self, next_seq: Option<u32>, turn_secs: u16) -> Snapshot {
+        Snapshot {
+            rules: self.rules.clone(),
+            seed: self.seed,
+            rng_word_pos: self.rng.get_word_pos(),
+            game_log: self.game_log_path.clone().zip(next_seq),
+            game_state: self.game_state.clone(),
+            turn_secs_left: Some(turn_secs),
+            cards: self.seat_cards(),
+        }
+    }
+
+    /// Creates the game log and writes the rules, seed and players to it. When resuming, the
+    /// saved game log is continued instead. If there is no game log path, or the game log can't
+    /// be created, the game continues without one.
+    fn create_game_log(&mut self) {
+        let Some(path) = self.game_log_path.as_deref() else {
+            return;
+        };
+        let saved_seq = self
+            .resume
+            .as_ref()
+            .and_then(|snapshot| snapshot.game_log.as_ref())
+            .filter(|(saved_path, _)| saved_path == path)
+            .map(|(_, next_seq)| *next_seq);
+        let game_log = match saved_seq {
+            Some(next_seq) => GameLog::resume(path, next_seq),
+            None => GameLog::create(path, &self.rules, self.seed, &self.game_state),
+        };
+        match game_log {
+            Ok(game_log) => {
+                println!("Writing game log to {}", path.display());
+                self.game_log = Some(game_log);
+            }
+            Err(err) => perror_in_fn("create_game_log", "host", err),
+        }
+    }
+
+    /// Records `action` in the game log and the room's monitor, where `before` is the game state
+    /// before the action was handled.
+    fn log_action(&mut self, action: &Action, result: &error::Result<()>, before: &GameState) {
+        self.room
+            .monitor()
+            .record(action, result.is_ok(), &self.game_state);
+        let Some(game_log) = self.game_log.as_mut() else {
+            return;
+        };
+        if let Err(err) = game_log.log_action(action, result, before, &self.game_state) {
+            perror_in_fn("log_action", "host", err);
+        }
+    }
+
+    fn client_by_name_mut(&mut self, name: &str) -> error::Result<&mut Client> {
+        self.clients
+            .iter_mut()
+            .find(|client| client.player().name() == name)
+            .ok_or_else(|| KingdomError::PlayerNotFound(name.to_owned()))
+    }
+
+    /// Starts core gameplay loop, which runs until only one player is left, the host ends the
+    /// game, or too few players are still connected to keep playing.
     fn start_game_loop(&mut self) {
         loop {
+            if self.run_admin_between_turns() {
+                break;
+            }
             self.start_current_turn();
             self.start_action_loop();
             self.move_next_player();
+            if let Some(winner) = self.game_state.winner() {
+                let winner = winner.name().to_owned();
+                self.end_game(&winner);
+                break;
+            }
+            let connected = self
+                .clients
+                .iter()
+                .filter(|client| client.handler.is_connected())
+                .count();
+            if connected < MIN_PLAYERS {
+                println!(
+                    "Too few players are left in room '{}', so the game is over.",
+                    self.room.name()
+                );
+                break;
+            }
+        }
+    }
+
+    /// Carries out the host's admin commands between turns, and waits while the game is paused.
+    /// A player who was kicked before their turn started is passed over. Returns true if the
+    /// game is over (e.g. because the host ended it).
+    fn run_admin_between_turns(&mut self) -> bool {
+        let room = Arc::clone(&self.room);
+        let mut was_paused = false;
+        loop {
+            while let Some(command) = room.monitor().next_command() {
+                self.run_admin_command(command, false);
+            }
+            if self.end_requested {
+                let winner = self.points_leader();
+                self.end_game(&winner);
+                return true;
+            }
+            if self.shutdown_requested {
+                self.shut_down();
+                return true;
+            }
+            if let Some(winner) = self.game_state.winner() {
+                let winner = winner.name().to_owned();
+                self.end_game(&winner);
+                return true;
+            }
+            if !room.monitor().is_paused() {
+                break;
+            }
+            if !was_paused {
+                println!("Paused the game in room '{}'.", room.name());
+                room.announce("The host paused the game.");
+                was_paused = true;
+            }
+            room.monitor().wait_while_paused();
+        }
+        if was_paused {
+            println!("Resumed the game in room '{}'.", room.name());
+            room.announce("The host resumed the game.");
+        }
+        self.pass_over_eliminated();
+        false
+    }
+
+    /// Passes over whoever's turn is next if they have been eliminated (e.g. by being kicked
+    /// between turns), so that the next player who hasn't been eliminated goes instead.
+    fn pass_over_eliminated(&mut self) {
+        if self
+            .game_state
+            .current_player()
+            .is_some_and(PlayerDetails::is_eliminated)
+        {
+            self.pass_over_current_player();
+        }
+    }
+
+    /// Passes over whoever's turn is next, so that the next player after them who hasn't been
+    /// eliminated goes instead.
+    fn pass_over_current_player(&mut self) {
+        self.game_state.move_next_player();
+        self.current_client = self.game_state.current_player_index();
+        self.room.monitor().update(&self.game_state);
+    }
+
+    /// Carries out `command` from the host, where `during_turn` is true if it is carried out
+    /// during a turn instead of between turns. Returns true if the turn player's turn has to
+    /// end because of it.
+    fn run_admin_command(&mut self, command: AdminCommand, during_turn: bool) -> bool {
+        match command {
+            AdminCommand::Kick(name) => self.kick(&name, during_turn),
+            AdminCommand::SetPoints(name, points) => self.set_points(&name, points, during_turn),
+            AdminCommand::Skip => {
+                if !during_turn {
+                    self.pass_over_eliminated();
+                }
+                let name = self.current_client().player().name().to_owned();
+                println!("Skipped {name}'s turn in room '{}'.", self.room.name());
+                self.room
+                    .announce(&format!("The host skipped {name}'s turn."));
+                if !during_turn {
+                    self.pass_over_current_player();
+                }
+                during_turn
+            }
+            AdminCommand::Save(path) => {
+                self.save_game_to(path, during_turn);
+                false
+            }
+            AdminCommand::End => {
+                println!("The host ended the game in room '{}'.", self.room.name());
+                self.end_requested = true;
+                during_turn
+            }
+            AdminCommand::Shutdown => {
+                self.shutdown_requested = true;
+                during_turn
+            }
+        }
+    }
+
+    /// Applies and logs an action from the host that sets `name`'s points to `points`. Returns
+    /// the action, or `None` if it couldn't be applied (e.g. there is no such player).
+    fn apply_set_points(&mut self, name: &str, points: u16) -> Option<Action> {
+        let action = Action::new(
+            ActionType::SetPoints,
+            points,
+            name.to_owned(),
+            String::new(),
+        );
+        let before = self.game_state.clone();
+        let result = self.game_state.apply_action(&action, &self.rules);
+        self.log_action(&action, &result, &before);
+        match result {
+            Ok(()) => Some(action),
+            Err(err) => {
+                perror_in_fn("apply_set_points", "host", err);
+                None
+            }
+        }
+    }
+
+    /// Sets the points of the player named `name` to `points`, and tells every player. Returns
+    /// true if it is their turn and they have been eliminated, in which case their turn has to end.
+    fn set_points(&mut self, name: &str, points: u16, during_turn: bool) -> bool {
+        let Some(action) = self.apply_set_points(name, points) else {
+            return false;
+        };
+        println!(
+            "Set {name}'s points to {points} in room '{}'.",
+            self.room.name()
+        );
+        self.room
+            .announce(&format!("The host set {name}'s points to {points}."));
+        self.send_to_all(&action, during_turn);
+        during_turn && points == 0 && self.current_client().player().name() == name
+    }
+
+    /// Takes the player named `name` out of the game by setting their points to 0, then
+    /// disconnects them. Returns true if it is their turn, which then has to end.
+    fn kick(&mut self, name: &str, during_turn: bool) -> bool {
+        let Some(action) = self.apply_set_points(name, 0) else {
+            return false;
+        };
+        println!("Kicked '{name}' from room '{}'.", self.room.name());
+        self.room
+            .announce(&format!("The host kicked {name} from the game."));
+        self.room.leave_chat(name);
+        if let Ok(client) = self.client_by_name_mut(name) {
+            client.handler_mut().close();
+        }
+        self.send_to_all(&action, during_turn);
+        during_turn && self.current_client().player().name() == name
+    }
+
+    /// Returns the name of whoever has the most points. Ties go to whoever is seated first.
+    fn points_leader(&self) -> String {
+        self.game_state
+            .all_players()
+            .iter()
+            .rev()
+            .max_by_key(|player| player.points())
+            .map(|player| player.name().to_owned())
+            .unwrap_or_default()
+    }
+
+    /// Saves the game so that it can be resumed with `--resume`, then disconnects every player
+    /// without a winner.
+    fn shut_down(&mut self) {
+        self.pass_over_eliminated();
+        match self.save_game_to(None, false) {
+            Some(path) => println!(
+                "Shut down the game in room '{}'. Resume it with --resume {}",
+                self.room.name(),
+                path.display()
+            ),
+            None => println!("Shut down the game in room '{}'.", self.room.name()),
+        }
+        self.room
+            .announce("The host shut down the server. The game can be resumed later.");
+        for client in self.clients.iter_mut() {
+            client.handler_mut().close();
+        }
+    }
+
+    /// Tells every player that `winner` has won, and records it in the game log. If profiles are
+    /// being kept, every player's profile is updated.
+    fn end_game(&mut self, winner: &str) {
+        let team = self
+            .game_state
+            .player_by_name(winner)
+            .and_then(PlayerDetails::team);
+        match team {
+            Some(team) => println!("Team {team} won the game in room '{}'!", self.room.name()),
+            None => println!("'{winner}' won the game in room '{}'!", self.room.name()),
+        }
+        let action = Action::new(ActionType::GameOver, 0, winner.to_owned(), String::new());
+        let before = self.game_state.clone();
+        let result = self.game_state.apply_action(&action, &self.rules);
+        self.log_action(&action, &result, &before);
+        self.room.monitor().finish();
+        let response = Response::from_action(public_action(&action));
+        for client in self.clients.iter_mut() {
+            let handler = client.handler_mut();
+            if !handler.is_connected() {
+                continue;
+            }
+            if let Err(err) = handler.await_request_send_response(ACTION_REQUEST, &response) {
+                perror_in_fn("end_game", handler.peer(), err);
+            }
+        }
+        if let Some(reveal) = self.fair_deal.take() {
+            self.send_deal("end_game", &DealMessage::Reveal(reveal));
+        }
+
+        let Some(profiles) = self.rooms.profiles() else {
+            return;
+        };
+        /* Players who are left come next, then players place in the reverse of the order they
+         * were eliminated in. Teammates place together, so the winner's whole team wins. */
+        let mut placements = vec![winner.to_owned()];
+        let remaining = self
+            .game_state
+            .all_players()
+            .iter()
+            .filter(|player| !player.is_eliminated())
+            .map(|player| player.name());
+        let eliminated = self.eliminations.iter().rev().map(|(_, name)| name);
+        let players = self
+            .game_state
+            .all_players()
+            .iter()
+            .map(|player| player.name());
+        for name in remaining
+            .chain(eliminated.map(String::as_str))
+            .chain(players)
+        {
+            if !placements.iter().any(|placed| placed == name) {
+                placements.push(name.to_owned());
+            }
         }
+        let eliminators: Vec<String> = self
+            .eliminations
+            .iter()
+            .map(|(eliminator, _)| eliminator.clone())
+            .collect();
+        let tiers = self.game_state.placement_tiers(&placements);
+        profiles.record_game(&tiers, &eliminators);
     }
 
-    fn send_to_all_except_turn_player(&mut self, res: &Response) {
+    /// Credits `eliminator` with every player who has been eliminated since `before`.
+    fn record_eliminations(&mut self, before: &GameState, eliminator: &str) {
+        for player in self.game_state.all_players() {
+            let was_eliminated = before
+                .player_by_name(player.name())
+                .is_none_or(PlayerDetails::is_eliminated);
+            if player.is_eliminated() && !was_eliminated {
+                println!("'{eliminator}' eliminated '{}'.", player.name());
+                self.eliminations
+                    .push((eliminator.to_owned(), player.name().to_owned()));
+            }
+        }
+    }
+
+    /// Sends `action` to every player except the turn player, skipping anyone who has
+    /// disconnected. Only the parts of it that every player may see are sent (see
+    /// `view::public_action`).
+    fn send_to_all_except_turn_player(&mut self, action: &Action) {
+        let res = &Response::from_action(public_action(action));
         for (index, client) in self.clients.iter_mut().enumerate() {
-            if index != self.current_client {
-                let client_name = client.player().name();
-                print_internal_info(&format!("SENDING RESPONSE TO {}", client_name));
-                if let Err(err) = client
-                    .handler
-                    .await_request_send_response(ACTION_REQUEST, res)
-                {
-                    perror_in_fn("send_to_all_except_turn_player", err);
+            if index != self.current_client && client.handler.is_connected() {
+                logging::debug(
+                    "send_to_all_except_turn_player",
+                    &format!("Sending response to {}", client.player().name()),
+                );
+                let handler = client.handler_mut();
+                if let Err(err) = handler.await_request_send_response(ACTION_REQUEST, res) {
+                    perror_in_fn("send_to_all_except_turn_player", handler.peer(), err);
                 }
             }
         }
-        // let name = self.game_state.current_player().name();
-        // for client in self.clients.iter_mut() {
-        //     if client.player().name() != name {
-        //         let handler = client.handler_mut();
-        //         println!("SENDING RESPONSE TO {name}");
-        //         if let Err(err) = handler.await_request_send_response(ACTION_REQUEST, res) {
-        //             perror_in_fn("send_action_to_clients_except", err);
-        //         }
-        //     }
-        // }
     }
 
+    /// Sends `action` to every player who is still connected. During a turn, the turn player is
+    /// sent it without asking for it (as with a Jack), but between turns every player asks.
+    fn send_to_all(&mut self, action: &Action, during_turn: bool) {
+        self.send_to_all_except_turn_player(action);
+        let response = Response::from_action(public_action(action));
+        let handler = self.current_client_mut().handler_mut();
+        if !handler.is_connected() {
+            return;
+        }
+        let result = if during_turn {
+            handler.send_response(&response)
+        } else {
+            handler.await_request_send_response(ACTION_REQUEST, &response)
+        };
+        if let Err(err) = result {
+            perror_in_fn("send_to_all", handler.peer(), err);
+        }
+    }
+
+    /// Starts the current player's turn, and starts the turn clock. When resuming, the first turn
+    /// gets whatever was left on the turn clock when the game was saved.
     fn start_current_turn(&mut self) {
-        let client = self.current_client_mut();
-        let client_name = client.player().name();
-        let response = Response::new_turn_start(client_name.to_owned());
+        let next_seq = self.game_log.as_ref().map(GameLog::next_seq);
+        let client_name = self.current_client().player().name().to_owned();
+        let turn_secs = self
+            .resume
+            .as_mut()
+            .and_then(|snapshot| snapshot.turn_secs_left.take())
+            .unwrap_or(self.rules.turn_secs);
+        self.turn_deadline = (turn_secs != 0)
+            .then(|| Instant::now() + Duration::from_secs(turn_secs.into()) + CLOCK_GRACE);
+        /* The turn player's seconds are sent as the attachment, so clients can count down. */
+        let action = Action::new(
+            ActionType::TurnStart,
+            turn_secs,
+            client_name.clone(),
+            String::new(),
+        );
+        let before = self.game_state.clone();
+        let result = self.game_state.apply_action(&action, &self.rules);
+        self.log_action(&action, &result, &before);
+        let response = Response::from_action(action.to_owned());
         println!("Starting {}'s turn", client_name);
-        if let Err(err) = client
-            .handler
-            .await_request_send_response(ACTION_REQUEST, &response)
-        {
-            perror_in_fn("start_current_turn", err);
+        let handler = self.current_client_mut().handler_mut();
+        if let Err(err) = handler.await_request_send_response(ACTION_REQUEST, &response) {
+            perror_in_fn("start_current_turn", handler.peer(), err);
         }
-        self.send_to_all_except_turn_player(&response);
+        self.send_to_all_except_turn_player(&action);
+        self.send_views();
+        self.save_game(next_seq, turn_secs);
     }
 
+    /// Waits for the turn player's next action. If the turn clock runs out first, or the host
+    /// sends an admin command that can't wait (e.g. kicking the turn player), the turn player is
+    /// sent a forced `TurnEnd`, which is then handled as their action. Every other admin command
+    /// sent since their last action is carried out before the next one is asked for.
     fn await_player_action(&mut self) -> Action {
-        let client = self.current_client_mut();
-        let status = client
-            .handler
-            .send_request_await_response(ACTION_REQUEST, ACTION_RESPONSE);
-        let action = match status {
-            Ok(response) => match response.response_type() {
-                ResponseType::PlayerAction(action) => action.as_ref().unwrap().to_owned(),
-                _ => unreachable!(),
-            },
-            Err(err) => {
-                perror_in_fn("host.rs::await_player_action", err);
-                Action::default()
+        let room = Arc::clone(&self.room);
+        let name = self.current_client().player().name().to_owned();
+        while let Some(command) = room.monitor().next_command() {
+            if self.run_admin_command(command, true) {
+                return self.force_turn_end(&name);
             }
-        };
+        }
+        let handler = self.current_client_mut().handler_mut();
+        if let Err(err) = handler.send_request(ACTION_REQUEST) {
+            perror_in_fn("host.rs::await_player_action", handler.peer(), err);
+            return Action::default();
+        }
+        let deadline = self.turn_deadline;
+        loop {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let timeout = remaining.map_or(ADMIN_POLL_INTERVAL, |remaining| {
+                remaining.min(ADMIN_POLL_INTERVAL)
+            });
+            let handler = self.current_client_mut().handler_mut();
+            let status = handler
+                .await_response_timeout(ACTION_RESPONSE, Some(timeout))
+                .and_then(Response::into_action);
+            /* An answer that arrives after the deadline is ignored, like a late one. */
+            let expired = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            match status {
+                Ok(_) | Err(KingdomError::TimedOut) if expired => {
+                    println!("'{name}' ran out of time. Ending their turn.");
+                    break;
+                }
+                Ok(action) => return action,
+                Err(KingdomError::TimedOut) => {
+                    let Some(command) = room.monitor().next_interrupt(&name) else {
+                        continue;
+                    };
+                    if self.run_admin_command(command, true) {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    perror_in_fn("host.rs::await_player_action", handler.peer(), err);
+                    return Action::default();
+                }
+            }
+        }
+        /* The turn player may still answer the request, after their turn has ended. */
+        self.current_client_mut()
+            .handler_mut()
+            .ignore_late_response();
+        self.force_turn_end(&name)
+    }
+
+    /// Ends the turn of the turn player named `name` without waiting for them, sending them a
+    /// forced `TurnEnd` if they are still connected. Returns the `TurnEnd`.
+    fn force_turn_end(&mut self, name: &str) -> Action {
+        let action = Action::new_turn_end(name);
+        let response = Response::from_action(action.to_owned());
+        let handler = self.current_client_mut().handler_mut();
+        if handler.is_connected() {
+            if let Err(err) = handler.send_response(&response) {
+                perror_in_fn("host.rs::await_player_action", handler.peer(), err);
+            }
+        }
         action
     }
 
     fn start_action_loop(&mut self) {
         loop {
-            let action = self.await_player_action();
-            if variant_eq(action.action_type(), &ActionType::TurnEnd) {
-                let response = Response::from_action(action);
-                self.send_to_all_except_turn_player(&response);
-                break;
+            let before = self.game_state.clone();
+            let mut action = self.await_player_action();
+            let is_turn_end = variant_eq(action.action_type(), &ActionType::TurnEnd);
+            if is_turn_end {
+                /* The turn always ends with the turn player, whoever the client says it is. */
+                action = Action::new_turn_end(self.current_client().player().name());
+            }
+            let target_points = referee::reaction_target_points(&action, &self.game_state);
+            let result = self.handle_action(&action);
+            if !is_turn_end {
+                self.send_action_result(result.is_ok());
+            }
+            if let Err(err) = result {
+                /* Invalid actions are not passed on to the other players. */
+                let peer = self.current_client().handler.peer().to_owned();
+                perror_in_fn("start_action_loop", &peer, err);
             } else {
-                self.handle_action(&action);
-                let response = Response::from_action(action);
-                self.send_to_all_except_turn_player(&response);
+                self.send_to_all_except_turn_player(&action);
+                if let Some(points_before) = target_points {
+                    self.await_reaction(&action, points_before);
+                }
+                self.record_eliminations(&before, action.from_player());
+            }
+            if is_turn_end {
+                break;
             }
         }
     }
 
-    fn handle_action(&mut self, action: &Action) {
-        match action.action_type() {
-            ActionType::PlayKing => self.handle_king(action),
-            ActionType::PlayQueen => self.handle_queen(action),
-            ActionType::PlayJack => self.handle_jack(),
-            ActionType::PlayNumber => self.handle_number(action),
-            ActionType::PlayBlackAce => self.handle_black_ace(),
-            ActionType::PlayRedAce => self.handle_red_ace(),
-            _ => unreachable!(),
-        }
-    }
-
-    fn handle_king(&mut self, action: &Action) {
-        let to_player_name = action.to_player();
-        // self.check_for_player_response(action.from_player(), to_player_name);
-        let damage = 10 + action.attachment();
-        let to_player = self.game_state.player_by_name_mut(to_player_name).unwrap();
-        to_player.set_points(to_player.points() - damage);
-        println!(
-            "ACTION: '{}' played King with {} against {}. '{}' now has {} points.",
-            action.from_player(),
-            action.attachment(),
-            action.to_player(),
-            action.to_player(),
-            to_player.points(),
-        );
+    /// Tells the turn player whether their action was accepted, then sends them their cards, so
+    /// that their hand matches the host's copy either way (e.g. with the cards drawn for a
+    /// Number, or with every card put back if the action was rejected).
+    fn send_action_result(&mut self, accepted: bool) {
+        let client = self.current_client_mut();
+        let status = if accepted {
+            STATUS_RESPONSE_YES
+        } else {
+            STATUS_RESPONSE_NO
+        };
+        let cards = Response::from_cards(Some(OwnCards::of(&client.cards.cards())));
+        let handler = client.handler_mut();
+        for response in [status, &cards] {
+            if let Err(err) = handler.send_response(response) {
+                perror_in_fn("send_action_result", handler.peer(), err);
+                return;
+            }
+        }
     }
 
-    fn check_for_player_response(&mut self, from_player: &str, to_player: &str) -> Option<Action> {
-        let client = self.client_by_name_mut(to_player);
-        let handler = client.handler_mut();
-        let status = handler.send_request_await_response(ACTION_REQUEST, ACTION_RESPONSE);
-        match status {
-            Ok(response) => {
-                if let ResponseType::PlayerAction(Some(action)) = response.response_type() {
-                    match action.action_type() {
-                        ActionType::PlayJack => {
-                            return Some(Action::new(
-                                ActionType::PlayJack,
-                                0,
-                                to_player.to_owned(),
-                                from_player.to_owned(),
-                            ));
-                        }
-                        ActionType::PlayRedAce => todo!(),
-                        ActionType::None => return None,
-                        _ => unreachable!(),
-                    }
+    /// Gives the target of `king` the reaction clock to block it with a Jack, where
+    /// `points_before` is how many points the target had before the King. If the reaction clock
+    /// runs out, the reaction is treated as declined. A Jack is sent to every player, including
+    /// the turn player, who gets it without asking for it.
+    fn await_reaction(&mut self, king: &Action, points_before: u16) {
+        let target = king.to_player().to_owned();
+        let timeout = self
+            .rules
+            .reaction_duration()
+            .map(|duration| duration + CLOCK_GRACE);
+        let status = self.client_by_name_mut(&target).and_then(|client| {
+            client
+                .handler_mut()
+                .send_request_await_response_timeout(ACTION_REQUEST, ACTION_RESPONSE, timeout)
+                .and_then(Response::into_action)
+        });
+        let play = match status {
+            Ok(reaction) if variant_eq(reaction.action_type(), &ActionType::PlayJack) => {
+                reaction.play().copied()
+            }
+            Ok(_) => {
+                println!("'{target}' did not react to the King.");
+                return;
+            }
+            Err(KingdomError::TimedOut) => {
+                println!("'{target}' ran out of time to react to the King.");
+                if let Ok(client) = self.client_by_name_mut(&target) {
+                    client.handler_mut().ignore_late_response();
                 }
+                return;
             }
-            Err(err) => perror_in_fn("check_for_player_response", err),
+            Err(err) => {
+                perror_in_fn("await_reaction", &target, err);
+                return;
+            }
+        };
+
+        let jack = referee::block(king, points_before, play, &self.game_state);
+        if let Err(err) = self.handle_jack(&jack) {
+            perror_in_fn("await_reaction", jack.from_player(), err);
+            return;
+        }
+        self.send_to_all_except_turn_player(&jack);
+        let response = Response::from_action(public_action(&jack));
+        let handler = self.current_client_mut().handler_mut();
+        if let Err(err) = handler.send_response(&response) {
+            perror_in_fn("await_reaction", handler.peer(), err);
         }
-        None
     }
 
-    fn handle_queen(&mut self, action: &Action) {
-        let player = self
-            .game_state
-            .player_by_name_mut(action.from_player())
-            .unwrap();
-        let healed_points = 10 + action.attachment();
-        player.set_points(player.points() + healed_points);
-        println!(
-            "ACTION: '{}' played a Queen with {}. '{}' now has {}",
-            player.name(),
-            action.attachment(),
-            player.name(),
-            player.points(),
+    /// Handles `action` with `referee::play`, then records it in the game log whether or not it
+    /// was valid.
+    fn handle_action(&mut self, action: &Action) -> error::Result<()> {
+        let before = self.game_state.clone();
+        let client = &mut self.clients[self.current_client];
+        let rng = client.reshuffle_rng.as_mut().unwrap_or(&mut self.rng);
+        let result = referee::play(
+            &mut client.cards,
+            action,
+            &mut self.game_state,
+            &self.rules,
+            rng,
         );
+        if result.is_ok() {
+            self.print_action(action);
+        }
+        self.log_action(action, &result, &before);
+        result
     }
 
-    fn handle_jack(&mut self) {
-        todo!()
-    }
-
-    fn handle_number(&mut self, action: &Action) {
-        let player = self
-            .game_state
-            .player_by_name_mut(action.from_player())
-            .unwrap();
-        println!(
-            "ACTION: '{}' played Number {}. '{}' drew {} cards.",
-            player.name(),
-            action.attachment(),
-            player.name(),
-            action.attachment()
-        );
+    /// Handles a Jack played in reaction to a King with `referee::apply_with_cards`, then records
+    /// it in the game log.
+    fn handle_jack(&mut self, jack: &Action) -> error::Result<()> {
+        let before = self.game_state.clone();
+        let result = match self
+            .clients
+            .iter_mut()
+            .find(|client| client.player().name() == jack.from_player())
+        {
+            Some(client) => {
+                let rng = client.reshuffle_rng.as_mut().unwrap_or(&mut self.rng);
+                referee::apply_with_cards(
+                    &mut client.cards,
+                    jack,
+                    &mut self.game_state,
+                    &self.rules,
+                    rng,
+                )
+            }
+            None => Err(KingdomError::PlayerNotFound(jack.from_player().to_owned())),
+        };
+        self.log_action(jack, &result, &before);
+        if result.is_ok() {
+            self.print_action(jack);
+        }
+        result
     }
 
-    fn handle_black_ace(&mut self) {
-        todo!()
+    /// Prints `action` on the console, once it has been applied.
+    fn print_action(&self, action: &Action) {
+        let Ok(player) = self.player_details(action.from_player()) else {
+            return;
+        };
+        match action.action_type() {
+            ActionType::PlayKing => {
+                let Ok(to_player) = self.player_details(action.to_player()) else {
+                    return;
+                };
+                println!(
+                    "ACTION: '{}' played King with {} against {}. '{}' now has {} points.",
+                    action.from_player(),
+                    action.attachment(),
+                    action.to_player(),
+                    action.to_player(),
+                    to_player.points(),
+                );
+            }
+            ActionType::PlayQueen => println!(
+                "ACTION: '{}' played a Queen with {}. '{}' now has {}",
+                player.name(),
+                action.attachment(),
+                player.name(),
+                player.points(),
+            ),
+            ActionType::PlayJack => println!(
+                "ACTION: '{}' blocked '{}''s King with a Jack. '{}' now has {} points.",
+                player.name(),
+                action.to_player(),
+                player.name(),
+                player.points(),
+            ),
+            ActionType::PlayNumber => println!(
+                "ACTION: '{}' played Number {}. '{}' drew {} cards.",
+                player.name(),
+                action.attachment(),
+                player.name(),
+                action.attachment()
+            ),
+            _ => {}
+        }
     }
 
-    fn handle_red_ace(&mut self) {
-        todo!()
+    /// Returns the details of the player named `name`, or an error if there is no such player.
+    fn player_details(&self, name: &str) -> error::Result<&PlayerDetails> {
+        self.game_state
+            .player_by_name(name)
+            .ok_or_else(|| KingdomError::PlayerNotFound(name.to_owned()))
     }
 
+    /// Moves on to whoever's turn it is in the game state. Clients are seated in the same order
+    /// as the players in the game state.
     fn move_next_player(&mut self) {
         println!("Ending {}'s turn", self.current_client().player().name());
-        self.current_client = (self.current_client + 1) % self.clients.len();
+        self.current_client = self.game_state.current_player_index();
     }
 
     fn current_client(&self) -> &Client {
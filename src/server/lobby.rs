@@ -0,0 +1,487 @@
+//! This module contains the lobby, where players wait before the game starts. Players join, pick
+//! their names and say when they are ready, while the host chooses the rules and how many seats
+//! there are. Every client is handled on its own thread while it is in the lobby, so the lobby is
+//! shared between them.
+
+use std::{fmt::Display, str::FromStr, sync::Mutex};
+
+use crate::{
+    error::{self, KingdomError},
+    game::rules::Rules,
+    logging,
+    utils::perror_in_fn,
+};
+
+use super::{
+    commentator::Commentator,
+    constants::{MAX_PLAYERS, MAX_USERNAME_LEN},
+    response::Response,
+    response::ResponseParseError,
+    StreamWriter,
+};
+
+/// Fewest players a game can be started with.
+pub const MIN_PLAYERS: usize = 2;
+
+/// Where a player stands in the lobby.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum LobbyStatus {
+    /// Waiting for the host to start the game.
+    Waiting,
+    /// The host has started the game.
+    Starting,
+    /// The host has kicked the player out of the lobby.
+    Kicked,
+}
+
+impl LobbyStatus {
+    fn to_symbol(self) -> &'static str {
+        match self {
+            LobbyStatus::Waiting => "W",
+            LobbyStatus::Starting => "S",
+            LobbyStatus::Kicked => "K",
+        }
+    }
+
+    fn from_symbol(symbol: &str) -> Option<LobbyStatus> {
+        match symbol {
+            "W" => Some(LobbyStatus::Waiting),
+            "S" => Some(LobbyStatus::Starting),
+            "K" => Some(LobbyStatus::Kicked),
+            _ => None,
+        }
+    }
+}
+
+/// What a player is shown of the lobby: how many seats there are, who has taken them and who is
+/// ready.
+#[derive(PartialEq, Debug, Clone)]
+pub struct LobbyState {
+    status: LobbyStatus,
+    seats: u8,
+    players: Vec<(String, bool)>,
+}
+
+impl LobbyState {
+    /// Returns a new instance of `LobbyState`, where `players` is the name of every player in
+    /// seat order and whether they are ready.
+    pub fn new(status: LobbyStatus, seats: u8, players: Vec<(String, bool)>) -> LobbyState {
+        LobbyState {
+            status,
+            seats,
+            players,
+        }
+    }
+
+    /// Returns `self.status`.
+    pub fn status(&self) -> LobbyStatus {
+        self.status
+    }
+
+    /// Returns `self.seats`.
+    pub fn seats(&self) -> u8 {
+        self.seats
+    }
+
+    /// Returns the name of every player in seat order, and whether they are ready.
+    pub fn players(&self) -> &[(String, bool)] {
+        &self.players
+    }
+}
+
+impl Display for LobbyState {
+    /// Format: `{STATUS},{SEATS},{P1_NAME}:{Y or N},{P2_NAME}:{Y or N}...`, where `STATUS` is
+    /// `W(aiting)`, `S(tarting)` or `K(icked)`, and `Y` means the player is ready.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.status.to_symbol(), self.seats)?;
+        for (name, ready) in self.players.iter() {
+            let ready = if *ready { "Y" } else { "N" };
+            write!(f, ",{name}:{ready}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for LobbyState {
+    type Err = ResponseParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let status = parts
+            .next()
+            .and_then(LobbyStatus::from_symbol)
+            .ok_or(ResponseParseError::InvalidLobby)?;
+        let seats = parts
+            .next()
+            .ok_or(ResponseParseError::InvalidNumArguments)?
+            .parse()
+            .map_err(|_| ResponseParseError::ParseIntError)?;
+        let players = parts
+            .map(|player| match player.split_once(':') {
+                Some((name, "Y")) => Ok((name.to_owned(), true)),
+                Some((name, "N")) => Ok((name.to_owned(), false)),
+                _ => Err(ResponseParseError::InvalidLobby),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(LobbyState::new(status, seats, players))
+    }
+}
+
+/// A player who has taken a seat in the lobby.
+struct LobbyMember {
+    name: String,
+    ready: bool,
+    /// Used to send the lobby to the player. This is `None` until the player has been told that
+    /// their name was accepted, so that nothing else is sent to them first.
+    writer: Option<StreamWriter>,
+}
+
+/// Everything in the lobby that can change.
+struct LobbyInner {
+    rules: Rules,
+    seats: u8,
+    members: Vec<LobbyMember>,
+    /// Names of players who were kicked, who can't join again.
+    kicked: Vec<String>,
+    started: bool,
+    /// Names of the players in the game being resumed, if any. Only these players can join, and
+    /// the rules and seats can't be changed.
+    saved_names: Option<Vec<String>>,
+}
+
+/// The lobby that the host keeps while players are joining. It is shared between the host and the
+/// threads handling each client.
+pub struct Lobby {
+    inner: Mutex<LobbyInner>,
+    /// True if the game starts as soon as everyone is ready, instead of when the host starts it.
+    auto_start: bool,
+}
+
+impl Lobby {
+    /// Creates an empty lobby with `MAX_PLAYERS` seats. When resuming a saved game, `saved_names`
+    /// are the names of its players, and there is exactly one seat for each of them.
+    pub fn new(rules: Rules, saved_names: Option<Vec<String>>) -> Lobby {
+        let seats = saved_names
+            .as_ref()
+            .map_or(MAX_PLAYERS, |names| names.len()) as u8;
+        Lobby {
+            inner: Mutex::new(LobbyInner {
+                rules,
+                seats,
+                members: Vec::with_capacity(MAX_PLAYERS),
+                kicked: Vec::new(),
+                started: false,
+                saved_names,
+            }),
+            auto_start: false,
+        }
+    }
+
+    /// Creates an empty lobby for a room that has no host to start the game (e.g. a room that a
+    /// player created). Instead, the game starts as soon as there are enough players and all of
+    /// them are ready. See `Lobby::new` for `saved_names`.
+    pub fn auto_starting(rules: Rules, saved_names: Option<Vec<String>>) -> Lobby {
+        Lobby {
+            auto_start: true,
+            ..Lobby::new(rules, saved_names)
+        }
+    }
+
+    /// Returns true if the game hasn't started and there is an empty seat.
+    pub fn is_open(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        !inner.started && inner.members.len() < inner.seats as usize
+    }
+
+    /// Checks that `name` is 1 to `MAX_USERNAME_LEN - 1` ASCII letters, so that it can be sent in
+    /// any message (e.g. it can't contain the `:`, `,` and `|` that messages are split on).
+    pub fn validate_name(name: &str) -> error::Result<()> {
+        let is_valid = !name.is_empty()
+            && name.len() < MAX_USERNAME_LEN
+            && name.chars().all(|c| c.is_ascii_alphabetic());
+        if is_valid {
+            Ok(())
+        } else {
+            Err(KingdomError::InvalidName(format!(
+                "'{name}' must be 1 to {} letters, with no special characters or numbers \
+                 (e.g. 1, $, ç, ♥︎)",
+                MAX_USERNAME_LEN - 1
+            )))
+        }
+    }
+
+    /// Gives the player named `name` a seat, if there is an empty one and the name can be used.
+    /// Names must be valid (see `validate_name`), unique, can't belong to a kicked player, and
+    /// must be one of the saved names when resuming. Nothing is sent to the player until
+    /// `welcome` is called.
+    pub fn join(&self, name: &str) -> error::Result<()> {
+        Lobby::validate_name(name)?;
+        let mut inner = self.inner.lock().unwrap();
+        if inner.started || inner.members.len() >= inner.seats as usize {
+            return Err(KingdomError::LobbyRejected(String::from(
+                "there are no empty seats",
+            )));
+        }
+        let is_saved = inner
+            .saved_names
+            .as_ref()
+            .is_none_or(|names| names.iter().any(|saved| saved == name));
+        if !is_saved {
+            return Err(KingdomError::InvalidName(format!(
+                "'{name}' isn't a player in the saved game"
+            )));
+        }
+        if inner.kicked.iter().any(|kicked| kicked == name) {
+            return Err(KingdomError::InvalidName(format!(
+                "'{name}' was kicked from the lobby"
+            )));
+        }
+        if inner.members.iter().any(|member| member.name == name) {
+            return Err(KingdomError::InvalidName(format!("'{name}' is taken")));
+        }
+        inner.members.push(LobbyMember {
+            name: name.to_owned(),
+            ready: false,
+            writer: None,
+        });
+        Ok(())
+    }
+
+    /// Sends the player named `name` the rules, then shows everyone the lobby with them in it.
+    pub fn welcome(&self, name: &str, writer: StreamWriter) {
+        let mut inner = self.inner.lock().unwrap();
+        let rules = Response::from_rules(inner.rules.clone());
+        if let Err(err) = writer.write_line(&rules.to_string()) {
+            perror_in_fn("Lobby::welcome", name, err);
+        }
+        if let Some(member) = inner.members.iter_mut().find(|member| member.name == name) {
+            member.writer = Some(writer);
+        }
+        println!("'{name}' joined the lobby.");
+        Lobby::broadcast(&inner);
+    }
+
+    /// Marks the player named `name` as ready or not ready.
+    pub fn set_ready(&self, name: &str, ready: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.started {
+            return;
+        }
+        if let Some(member) = inner.members.iter_mut().find(|member| member.name == name) {
+            member.ready = ready;
+            let ready = if ready { "ready" } else { "not ready" };
+            println!("'{name}' is {ready}.");
+        }
+        Lobby::broadcast(&inner);
+        /* Everyone is shown the last player readying up before they are told it is starting. */
+        if self.auto_start && Lobby::check_start(&inner).is_ok() {
+            inner.started = true;
+        }
+    }
+
+    /// Frees the seat of the player named `name` (e.g. when they disconnect). Returns false if
+    /// the game has already started, in which case the player keeps their seat.
+    pub fn leave(&self, name: &str) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.started {
+            return false;
+        }
+        inner.members.retain(|member| member.name != name);
+        println!("'{name}' left the lobby.");
+        Lobby::broadcast(&inner);
+        true
+    }
+
+    /// Kicks the player named `name` out of the lobby. They can't join again with the same name.
+    pub fn kick(&self, name: &str) -> error::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.members.iter().any(|member| member.name == name) {
+            return Err(KingdomError::PlayerNotFound(name.to_owned()));
+        }
+        inner.members.retain(|member| member.name != name);
+        inner.kicked.push(name.to_owned());
+        println!("Kicked '{name}'.");
+        Lobby::broadcast(&inner);
+        Ok(())
+    }
+
+    /// Sets how many seats there are. There must be between `MIN_PLAYERS` and `MAX_PLAYERS`
+    /// seats, and at least one for every player already in the lobby. Everyone has to ready up
+    /// again afterwards.
+    pub fn set_seats(&self, seats: u8) -> error::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.saved_names.is_some() {
+            return Err(rejected("the seats of a resumed game can't be changed"));
+        }
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&(seats as usize)) {
+            return Err(rejected(&format!(
+                "there must be between {MIN_PLAYERS} and {MAX_PLAYERS} seats"
+            )));
+        }
+        if (seats as usize) < inner.members.len() {
+            return Err(rejected(&format!(
+                "{} players are already seated",
+                inner.members.len()
+            )));
+        }
+        inner.seats = seats;
+        Lobby::unready_all(&mut inner);
+        Lobby::broadcast(&inner);
+        Ok(())
+    }
+
+    /// Makes `changes` to the rules (see `Rules::with_changes`), and sends the new rules to
+    /// everyone. Everyone has to ready up again afterwards. Returns the new rules.
+    pub fn change_rules(&self, changes: &str) -> error::Result<Rules> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.saved_names.is_some() {
+            return Err(rejected("the rules of a resumed game can't be changed"));
+        }
+        inner.rules = inner.rules.with_changes(changes)?;
+        let response = Response::from_rules(inner.rules.clone());
+        for member in inner.members.iter() {
+            if let Some(writer) = &member.writer {
+                if let Err(err) = writer.write_line(&response.to_string()) {
+                    perror_in_fn("Lobby::change_rules", &member.name, err);
+                }
+            }
+        }
+        Lobby::unready_all(&mut inner);
+        Lobby::broadcast(&inner);
+        Ok(inner.rules.clone())
+    }
+
+    /// Returns the rules the game will be played with.
+    pub fn rules(&self) -> Rules {
+        self.inner.lock().unwrap().rules.clone()
+    }
+
+    /// Starts the game, if there are enough players and every one of them is ready. When
+    /// resuming, every saved player must have rejoined. Returns the names of the players in seat
+    /// order.
+    pub fn start(&self) -> error::Result<Vec<String>> {
+        let mut inner = self.inner.lock().unwrap();
+        Lobby::check_start(&inner)?;
+        inner.started = true;
+        Ok(Lobby::names(&inner))
+    }
+
+    /// Returns the names of the players in seat order once the game has started, or `None` if
+    /// it hasn't started yet.
+    pub fn seated(&self) -> Option<Vec<String>> {
+        let inner = self.inner.lock().unwrap();
+        inner.started.then(|| Lobby::names(&inner))
+    }
+
+    /// Returns true once the game has started.
+    pub fn has_started(&self) -> bool {
+        self.inner.lock().unwrap().started
+    }
+
+    /// Returns how many players have taken a seat.
+    pub fn num_players(&self) -> usize {
+        self.inner.lock().unwrap().members.len()
+    }
+
+    /// Returns how many seats there are.
+    pub fn seats(&self) -> u8 {
+        self.inner.lock().unwrap().seats
+    }
+
+    /// Returns the name of every player in the lobby, and whether they are ready.
+    pub fn players(&self) -> Vec<(String, bool)> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .members
+            .iter()
+            .map(|member| (member.name.clone(), member.ready))
+            .collect()
+    }
+
+    /// Returns where the player named `name` stands in the lobby.
+    pub fn status_of(&self, name: &str) -> LobbyStatus {
+        let inner = self.inner.lock().unwrap();
+        Lobby::status(&inner, name)
+    }
+
+    /// Returns the lobby as it is shown to the player named `name`.
+    pub fn state_for(&self, name: &str) -> LobbyState {
+        let inner = self.inner.lock().unwrap();
+        Lobby::state(&inner, Lobby::status(&inner, name))
+    }
+
+    /// Prints the lobby for the host.
+    pub fn print(&self) {
+        let inner = self.inner.lock().unwrap();
+        Commentator::lobby(&Lobby::state(&inner, LobbyStatus::Waiting));
+        println!("Rules: {}", inner.rules);
+    }
+
+    /// Returns an error if the game can't be started yet.
+    fn check_start(inner: &LobbyInner) -> error::Result<()> {
+        let num_players = inner.members.len();
+        if let Some(saved_names) = &inner.saved_names {
+            if num_players < saved_names.len() {
+                return Err(rejected("not every saved player has rejoined"));
+            }
+        }
+        if num_players < MIN_PLAYERS {
+            return Err(rejected(&format!(
+                "at least {MIN_PLAYERS} players are needed"
+            )));
+        }
+        if let Some(member) = inner.members.iter().find(|member| !member.ready) {
+            return Err(rejected(&format!("'{}' is not ready", member.name)));
+        }
+        Ok(())
+    }
+
+    fn names(inner: &LobbyInner) -> Vec<String> {
+        inner
+            .members
+            .iter()
+            .map(|member| member.name.clone())
+            .collect()
+    }
+
+    fn status(inner: &LobbyInner, name: &str) -> LobbyStatus {
+        if inner.kicked.iter().any(|kicked| kicked == name) {
+            LobbyStatus::Kicked
+        } else if inner.started {
+            LobbyStatus::Starting
+        } else {
+            LobbyStatus::Waiting
+        }
+    }
+
+    fn state(inner: &LobbyInner, status: LobbyStatus) -> LobbyState {
+        let players = inner
+            .members
+            .iter()
+            .map(|member| (member.name.clone(), member.ready))
+            .collect();
+        LobbyState::new(status, inner.seats, players)
+    }
+
+    fn unready_all(inner: &mut LobbyInner) {
+        for member in inner.members.iter_mut() {
+            member.ready = false;
+        }
+    }
+
+    /// Sends the lobby to every player in it.
+    fn broadcast(inner: &LobbyInner) {
+        let response = Response::from_lobby(Lobby::state(inner, LobbyStatus::Waiting));
+        for member in inner.members.iter() {
+            if let Some(writer) = &member.writer {
+                if let Err(err) = writer.write_line(&response.to_string()) {
+                    logging::warn(&member.name, &format!("Unable to send lobby: {err}"));
+                }
+            }
+        }
+    }
+}
+
+fn rejected(reason: &str) -> KingdomError {
+    KingdomError::LobbyRejected(reason.to_owned())
+}
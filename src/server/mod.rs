@@ -4,70 +4,148 @@
 //! and `Responses` are serialized as plain text before being sent, before being deserialized on the
 //! other side into the appropriate struct.
 
+pub mod admin;
+pub mod api;
+pub mod chat;
 pub mod client;
 pub mod commentator;
 pub mod constants;
 pub mod host;
+pub mod lobby;
 pub mod request;
 pub mod response;
+pub mod rooms;
+pub mod session;
 pub mod utils;
+pub mod view;
+pub mod web;
+pub mod websocket;
 
 use std::{
-    fmt,
     io::{self, BufRead, BufReader, Write},
-    net::TcpStream,
+    net::{Shutdown, TcpStream},
     str::FromStr,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use request::{Request, RequestParseError, RequestType};
-use response::{Response, ResponseParseError, ResponseType};
+use chat::ChatMessage;
+use request::Request;
+use response::Response;
 use utils::remove_newline;
 
-use crate::utils::variant_eq;
-
-/// This is the type used for representing server-side errors.
-pub enum ServerError {
-    /// Failed to connect to server at port.
-    FailedToConnect(String),
-    /// Expected a request of a different type.
-    ExpectedRequestType(RequestType),
-    /// Expected a response of a different type.
-    ExpectedResponseType(ResponseType),
-    /// Encounted a request parsing error.
-    RequestError(RequestParseError),
-    /// Encountered a response parsing error.
-    ReponseError(ResponseParseError),
-    /// Expected same request types, received different types.
-    MismatchedRequestTypes(RequestType, RequestType),
-    /// Expected same response types, received different types.
-    MismatchedResponseTypes(ResponseType, ResponseType),
-    /// Encountered a standard io::Error.
-    IoError(io::Error),
+use crate::{
+    error::{self, KingdomError},
+    logging,
+    utils::variant_eq,
+};
+
+/// Anything that can be received over a stream.
+#[derive(PartialEq, Debug)]
+pub enum Message {
+    Request(Request),
+    Response(Response),
 }
 
-impl fmt::Display for ServerError {
-    /// When converted to strings, ServerErrors contain a descriptive message
-    /// of what the error was as well as any specific details about the error.
-    /// It does not include where the error occured.
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            ServerError::FailedToConnect(msg) => write!(f, "Failed to connect to {msg}"),
-            ServerError::RequestError(err) => write!(f, "Request error: {err:?}"),
-            ServerError::ReponseError(err) => write!(f, "Response error: {err:?}"),
-            ServerError::IoError(err) => write!(f, "IO Error: {err:?}"),
-            ServerError::ExpectedRequestType(request_type) => {
-                write!(f, "Expected request of type {request_type}")
+/// Start of every chat message sent over a stream.
+const CHAT_PREFIX: &str = "RES,CHAT";
+
+/// Called with every chat message received over a stream.
+type ChatHook = Box<dyn Fn(ChatMessage) + Send>;
+
+/// Called with every other line received over a stream, as soon as it is read.
+type LineHook = Box<dyn Fn(&str) + Send>;
+
+/// Where the lines written to a stream go.
+#[derive(Clone)]
+enum LineSink {
+    /// Written straight to a TCP stream.
+    Tcp(Arc<Mutex<TcpStream>>),
+    /// Passed to the thread that owns the connection (e.g. a WebSocket), which sends them.
+    /// `None` tells that thread to close the connection.
+    Channel(mpsc::Sender<Option<String>>),
+}
+
+/// A handle for writing lines to a stream, which can be shared between threads. Lines written
+/// from different threads (e.g. chat that is relayed while the game is being played) are never
+/// mixed together.
+#[derive(Clone)]
+pub struct StreamWriter {
+    sink: LineSink,
+}
+
+impl StreamWriter {
+    /// Returns a writer that passes every line to `sender`, for connections that are owned by a
+    /// thread of their own.
+    pub(crate) fn channel(sender: mpsc::Sender<Option<String>>) -> StreamWriter {
+        StreamWriter {
+            sink: LineSink::Channel(sender),
+        }
+    }
+
+    /// Writes `line` to the stream. Newline is used a delimiting character to avoid requests
+    /// being mangled.
+    pub fn write_line(&self, line: &str) -> error::Result<()> {
+        match &self.sink {
+            LineSink::Tcp(stream) => {
+                let mut stream = stream.lock().unwrap();
+                stream.write_all(format!("{line}\n").as_bytes())?;
+                stream.flush()?;
             }
-            ServerError::ExpectedResponseType(response_type) => {
-                write!(f, "Expected response of type {response_type}")
+            LineSink::Channel(sender) => sender
+                .send(Some(line.to_owned()))
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?,
+        }
+        Ok(())
+    }
+
+    /// Closes the stream, which disconnects the peer (e.g. when they are kicked).
+    pub fn close(&self) {
+        match &self.sink {
+            LineSink::Tcp(stream) => {
+                let _ = stream.lock().unwrap().shutdown(Shutdown::Both);
             }
-            ServerError::MismatchedRequestTypes(one, two) => {
-                write!(f, "Expected same request types. Received {one} and {two}")
+            LineSink::Channel(sender) => {
+                let _ = sender.send(None);
             }
-            ServerError::MismatchedResponseTypes(one, two) => {
-                write!(f, "Expected same response types. Received {one} and {two}")
+        }
+    }
+
+    /// Sends `message` over the stream as a chat response.
+    pub fn send_chat(&self, message: ChatMessage) -> error::Result<()> {
+        self.write_line(&Response::from_chat(message).to_string())
+    }
+}
+
+/// Where the thread reading a stream delivers every line it reads.
+struct Inbox {
+    sender: mpsc::Sender<io::Result<String>>,
+    chat_hook: Arc<Mutex<Option<ChatHook>>>,
+    line_hook: Arc<Mutex<Option<LineHook>>>,
+}
+
+impl Inbox {
+    /// Delivers `line` (with the newline removed) to the handler, except for chat messages,
+    /// which are passed to the chat hook. Every other line is shown to the line hook first.
+    /// Errors are delivered too, and mean that nothing more will be read. Returns false once
+    /// nothing more should be read, either because of an error or because the handler has been
+    /// dropped.
+    fn deliver(&self, line: io::Result<String>) -> bool {
+        if let Ok(line) = &line {
+            if line.starts_with(CHAT_PREFIX) {
+                StreamHandler::receive_chat(line, &self.chat_hook);
+                return true;
+            }
+            if let Some(hook) = self.line_hook.lock().unwrap().as_ref() {
+                hook(line);
             }
         }
+        let is_err = line.is_err();
+        self.sender.send(line).is_ok() && !is_err
     }
 }
 
@@ -77,8 +155,22 @@ impl fmt::Display for ServerError {
 ///  requests/responses, use the `await_request` and `await_response` methods.
 /// A `send_request` should always be followed by an `await_response` (same
 /// with) `send_response` and `await_request`.
+///
+/// Lines are read from the stream on a separate thread, so that receiving can be given a
+/// timeout (e.g. for turn clocks). Chat messages can arrive at any time, so that thread passes
+/// them to the hook set with `on_chat` instead.
 pub struct StreamHandler {
-    reader: BufReader<TcpStream>,
+    writer: StreamWriter,
+    lines: Receiver<io::Result<String>>,
+    chat_hook: Arc<Mutex<Option<ChatHook>>>,
+    line_hook: Arc<Mutex<Option<LineHook>>>,
+    /// Name of whoever is on the other end of the stream, used when logging.
+    peer: String,
+    /// False once reading from or writing to the stream has failed.
+    connected: bool,
+    /// True after a request to the peer ran out of time, until the peer sends its next request.
+    /// A response received in the meantime is the late answer to that request, so it's dropped.
+    late_response: bool,
 }
 
 impl StreamHandler {
@@ -86,90 +178,202 @@ impl StreamHandler {
     /// Multiple `StreamHandler`s should not be attached to the same
     /// stream at the same time.
     pub fn new(stream: TcpStream) -> StreamHandler {
-        StreamHandler {
-            reader: BufReader::new(stream),
+        let peer = match stream.peer_addr() {
+            Ok(addr) => addr.to_string(),
+            Err(_) => String::from("unknown"),
+        };
+        let reader = stream.try_clone();
+        let writer = StreamWriter {
+            sink: LineSink::Tcp(Arc::new(Mutex::new(stream))),
+        };
+        let (handler, inbox) = StreamHandler::with_writer(peer, writer);
+        match reader {
+            Ok(reader) => {
+                thread::spawn(move || StreamHandler::read_lines(reader, inbox));
+            }
+            Err(err) => {
+                /* The handler will report the error the first time it reads. */
+                inbox.deliver(Err(err));
+            }
         }
+        handler
+    }
+
+    /// Creates a handler for the peer named `peer`, which writes with `writer`. Returns the
+    /// handler, and the inbox that every line read from the peer has to be delivered to.
+    fn with_writer(peer: String, writer: StreamWriter) -> (StreamHandler, Inbox) {
+        let (sender, lines) = mpsc::channel();
+        let chat_hook: Arc<Mutex<Option<ChatHook>>> = Arc::default();
+        let line_hook: Arc<Mutex<Option<LineHook>>> = Arc::default();
+        let inbox = Inbox {
+            sender,
+            chat_hook: Arc::clone(&chat_hook),
+            line_hook: Arc::clone(&line_hook),
+        };
+        let handler = StreamHandler {
+            writer,
+            lines,
+            chat_hook,
+            line_hook,
+            peer,
+            connected: true,
+            late_response: false,
+        };
+        (handler, inbox)
+    }
+
+    /// Reads lines from `stream` until it is closed, delivering every line (with the newline
+    /// removed) to `inbox`. Reaching the end of the stream is delivered as an error, since it
+    /// means the peer has disconnected.
+    fn read_lines(stream: TcpStream, inbox: Inbox) {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let mut line = String::new();
+            let result = match reader.read_line(&mut line) {
+                Ok(0) => Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => {
+                    remove_newline(&mut line);
+                    Ok(line)
+                }
+                Err(err) => Err(err),
+            };
+            if !inbox.deliver(result) {
+                break;
+            }
+        }
+    }
+
+    /// Parses the chat message in `line` and passes it to `chat_hook`. Chat messages are dropped
+    /// if they are invalid, or if nothing is listening for chat.
+    fn receive_chat(line: &str, chat_hook: &Mutex<Option<ChatHook>>) {
+        let message = Response::from_str(line)
+            .map_err(KingdomError::from)
+            .and_then(Response::into_chat);
+        match (message, chat_hook.lock().unwrap().as_ref()) {
+            (Ok(message), Some(hook)) => hook(message),
+            (Ok(message), None) => {
+                logging::debug("chat", &format!("Dropped chat message '{message}'"))
+            }
+            (Err(err), _) => logging::warn("chat", &format!("Dropped invalid chat message: {err}")),
+        }
+    }
+
+    /// Sets the hook that every chat message received from the peer is passed to. The hook is
+    /// called from the thread that reads the stream.
+    pub fn on_chat(&self, hook: impl Fn(ChatMessage) + Send + 'static) {
+        *self.chat_hook.lock().unwrap() = Some(Box::new(hook));
+    }
+
+    /// Sets the hook that every line other than chat is shown to as soon as it is read, before it
+    /// is received (e.g. to stop waiting for input when the peer sends something unasked). The
+    /// hook is called from the thread that reads the stream.
+    pub fn on_line(&self, hook: impl Fn(&str) + Send + 'static) {
+        *self.line_hook.lock().unwrap() = Some(Box::new(hook));
+    }
+
+    /// Returns a handle for writing to the stream from other threads (e.g. to send chat).
+    pub fn writer(&self) -> StreamWriter {
+        self.writer.clone()
+    }
+
+    /// Returns the name of the peer on the other end of the stream. Until `set_peer_name` is
+    /// called, this is the peer's socket address.
+    pub fn peer(&self) -> &str {
+        &self.peer
+    }
+
+    /// Returns false once the peer has disconnected, or the stream has failed in some other way.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Closes the stream, which disconnects the peer (e.g. when they are kicked).
+    pub fn close(&mut self) {
+        self.writer.close();
+        self.connected = false;
+    }
+
+    /// Sets the name used for the peer in log messages (e.g. once a player has chosen a name).
+    pub fn set_peer_name(&mut self, name: &str) {
+        self.peer = name.to_owned();
     }
 
     /// Sends `response` as string over `stream`.
-    pub fn send_response(&mut self, response: &Response) -> std::io::Result<()> {
-        // let response_type = response.response_type();
-        let response_str = response.to_string();
-        let mut response = response.to_string();
-        /* Newline is used a delimiting character to avoid requests being mangled. */
-        response.push('\n');
-        let response = response.as_bytes();
-        // dbg!(response);
-        let stream = self.reader.get_mut();
-        stream.write_all(response)?;
-        stream.flush()?;
-        print_internal_info(&format!("Sent response of type '{}'", response_str));
+    pub fn send_response(&mut self, response: &Response) -> error::Result<()> {
+        let response = response.to_string();
+        self.write_line(&response)?;
+        logging::trace(&self.peer, &format!("Sent response '{response}'"));
         Ok(())
     }
 
     /// Blocks the current thread until a `Request` is received. If the request
-    /// received is of the wrong type, then this function will return an error.
-    pub fn await_request(&mut self, request: &Request) -> Result<Request, ServerError> {
+    /// received is of the wrong type, then this function will return an error. Any responses
+    /// received while waiting are left over from an earlier exchange (e.g. an action that
+    /// arrived after the turn clock ran out), so they are skipped.
+    pub fn await_request(&mut self, request: &Request) -> error::Result<Request> {
         let request_type = request.request_type().to_owned();
-        let received = &mut String::new();
-        print_internal_info(&format!("Awaiting request of type {request_type}"));
-        if let Err(err) = self.reader.read_line(received) {
-            return Err(ServerError::IoError(err));
-        }
-        remove_newline(received);
-        print_internal_info(&format!("Received: {received}"));
-        let request = Request::from_str(received);
-        match request {
-            Ok(request) => {
-                if variant_eq(request.request_type(), &request_type) {
-                    Ok(request)
-                } else {
-                    Err(ServerError::ExpectedRequestType(request_type))
+        logging::trace(
+            &self.peer,
+            &format!("Awaiting request of type {request_type}"),
+        );
+        let request = loop {
+            match self.await_message(None)? {
+                Message::Request(request) => break request,
+                Message::Response(response) => {
+                    logging::warn(&self.peer, &format!("Skipped stale response '{response}'"))
                 }
             }
-            Err(err) => Err(ServerError::RequestError(err)),
+        };
+        if variant_eq(request.request_type(), &request_type) {
+            Ok(request)
+        } else {
+            Err(KingdomError::ExpectedRequestType(request_type))
         }
     }
 
     /// Sends `request` over stream as a string.
-    pub fn send_request(&mut self, request: &Request) -> std::io::Result<()> {
-        // let request_type = request.request_type();
-        let request_str = request.to_string();
-        let mut request = request_str.clone();
-        /* Newline is used a delimiting character to avoid requests being mangled. */
-        request.push('\n');
-        let request = request.as_bytes();
-        let stream = self.reader.get_mut();
-        stream.write_all(request)?;
-        stream.flush()?;
-        print_internal_info(&format!("Sent request of type '{request_str}'"));
+    pub fn send_request(&mut self, request: &Request) -> error::Result<()> {
+        let request = request.to_string();
+        self.write_line(&request)?;
+        logging::trace(&self.peer, &format!("Sent request '{request}'"));
         Ok(())
     }
 
     /// Blocks the current thread until a `Response` is received. If the response
     /// received is of the wrong type, then this function will return an error.
-    pub fn await_response(&mut self, response: &Response) -> Result<Response, ServerError> {
-        let response_type = response.response_type().to_owned();
-        let received = &mut String::new();
+    pub fn await_response(&mut self, response: &Response) -> error::Result<Response> {
+        self.await_response_timeout(response, None)
+    }
 
-        print_internal_info(&format!("Awaiting response of type {response_type}"));
-        /* Using read_line() because requests/responses are separated by newline delimeter */
-        // received.clear();
-        if let Err(e) = self.reader.read_line(received) {
-            return Err(ServerError::IoError(e));
+    /// Same as `await_response`, but returns `KingdomError::TimedOut` if no response is
+    /// received within `timeout`.
+    pub fn await_response_timeout(
+        &mut self,
+        response: &Response,
+        timeout: Option<Duration>,
+    ) -> error::Result<Response> {
+        let response_type = response.response_type().to_owned();
+        logging::trace(
+            &self.peer,
+            &format!("Awaiting response of type {response_type}"),
+        );
+        let received = self.read_line(timeout)?;
+        let response = Response::from_str(&received)?;
+        if variant_eq(response.response_type(), &response_type) {
+            Ok(response)
+        } else {
+            Err(KingdomError::ExpectedResponseType(response_type))
         }
-        remove_newline(received);
-        print_internal_info(&format!("Received: {received}"));
-        let response = Response::from_str(received);
-        match response {
-            Ok(request) => {
-                if variant_eq(request.response_type(), &response_type) {
-                    Ok(request)
-                } else {
-                    Err(ServerError::ExpectedResponseType(response_type))
-                }
-            }
-            Err(err) => Err(ServerError::ReponseError(err)),
+    }
+
+    /// Blocks the current thread until either a request or a response is received. Returns
+    /// `KingdomError::TimedOut` if nothing is received within `timeout`.
+    pub fn await_message(&mut self, timeout: Option<Duration>) -> error::Result<Message> {
+        let received = self.read_line(timeout)?;
+        if received.starts_with("REQ") {
+            Ok(Message::Request(Request::from_str(&received)?))
+        } else {
+            Ok(Message::Response(Response::from_str(&received)?))
         }
     }
 
@@ -181,22 +385,27 @@ impl StreamHandler {
         &mut self,
         request: &Request,
         response: &Response,
-    ) -> Result<Response, ServerError> {
+    ) -> error::Result<Response> {
+        self.send_request_await_response_timeout(request, response, None)
+    }
+
+    /// Same as `send_request_await_response`, but returns `KingdomError::TimedOut` if no
+    /// response is received within `timeout`.
+    pub fn send_request_await_response_timeout(
+        &mut self,
+        request: &Request,
+        response: &Response,
+        timeout: Option<Duration>,
+    ) -> error::Result<Response> {
         let equiv_req = Request::from_response(response);
         if !variant_eq(request.request_type(), equiv_req.request_type()) {
-            return Err(ServerError::MismatchedRequestTypes(
+            return Err(KingdomError::MismatchedRequestTypes(
                 request.request_type().to_owned(),
                 equiv_req.request_type().to_owned(),
             ));
         }
-        if let Err(err) = self.send_request(request) {
-            return Err(ServerError::IoError(err));
-        }
-        let status = self.await_response(response);
-        match status {
-            Ok(response) => Ok(response),
-            Err(err) => Err(err),
-        }
+        self.send_request(request)?;
+        self.await_response_timeout(response, timeout)
     }
 
     /// Blocks the current thread until a request is received, then sends `response` over stream.
@@ -204,23 +413,71 @@ impl StreamHandler {
         &mut self,
         request: &Request,
         response: &Response,
-    ) -> Result<(), ServerError> {
+    ) -> error::Result<()> {
         let equiv_req = Request::from_response(response);
         if !variant_eq(request.request_type(), equiv_req.request_type()) {
-            return Err(ServerError::MismatchedRequestTypes(
+            return Err(KingdomError::MismatchedRequestTypes(
                 request.request_type().to_owned(),
                 equiv_req.request_type().to_owned(),
             ));
         }
         self.await_request(request)?;
-        if let Err(err) = self.send_response(response) {
-            return Err(ServerError::IoError(err));
-        }
+        self.send_response(response)
+    }
 
-        Ok(())
+    /// Writes `line` to the stream.
+    fn write_line(&mut self, line: &str) -> error::Result<()> {
+        let result = self.writer.write_line(line);
+        self.connected &= result.is_ok();
+        result
     }
-}
 
-fn print_internal_info(s: &str) {
-    println!("\x1b[34m{s}\x1b[0m");
+    /// Drops the next response from the peer if it arrives before their next request, because the
+    /// request it answers has already run out of time. Peers answer requests in order, so this
+    /// stops a late answer from being read as the answer to a later request.
+    pub fn ignore_late_response(&mut self) {
+        self.late_response = true;
+    }
+
+    /// Waits for the next line from the stream, for at most `timeout` if one is given. A late
+    /// response (see `ignore_late_response`) is dropped, and waiting continues.
+    fn read_line(&mut self, timeout: Option<Duration>) -> error::Result<String> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let timeout =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let received = self.receive_line(timeout)?;
+            if !self.late_response {
+                return Ok(received);
+            }
+            self.late_response = false;
+            if received.starts_with("REQ") {
+                return Ok(received);
+            }
+            logging::warn(&self.peer, &format!("Dropped late response '{received}'"));
+        }
+    }
+
+    /// Receives the next line from the stream, for at most `timeout` if one is given.
+    fn receive_line(&mut self, timeout: Option<Duration>) -> error::Result<String> {
+        let received = match timeout {
+            Some(timeout) => self.lines.recv_timeout(timeout).map_err(|err| match err {
+                RecvTimeoutError::Timeout => KingdomError::TimedOut,
+                RecvTimeoutError::Disconnected => {
+                    KingdomError::Io(io::ErrorKind::UnexpectedEof.into())
+                }
+            }),
+            None => self
+                .lines
+                .recv()
+                .map_err(|_| KingdomError::Io(io::ErrorKind::UnexpectedEof.into())),
+        }
+        .and_then(|line| line.map_err(KingdomError::from));
+        if let Err(KingdomError::Io(_)) = received {
+            self.connected = false;
+        }
+        let received = received?;
+        logging::trace(&self.peer, &format!("Received '{received}'"));
+        Ok(received)
+    }
 }
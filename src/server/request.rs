@@ -1,11 +1,9 @@
 use std::{fmt::Display, str::FromStr};
 
+use crate::error::{self, KingdomError};
 use crate::utils::variant_eq;
 
-use super::{
-    response::{Response, ResponseType},
-    ServerError,
-};
+use super::response::{Response, ResponseType};
 
 #[derive(PartialEq, Debug)]
 pub enum RequestType {
@@ -17,6 +15,24 @@ pub enum RequestType {
     PlayerAction,
     /// Format: `REQ,GAME`.
     GameState,
+    /// Format: `REQ,CARDS`.
+    Cards,
+    /// Format: `REQ,RULES`.
+    Rules,
+    /// Format: `REQ,CHAT`. Chat messages are sent without being requested, so this is never
+    /// sent. It only exists to pair with `ResponseType::Chat`.
+    Chat,
+    /// Format: `REQ,LOBBY`. The lobby is sent to players whenever it changes, so like `Chat`,
+    /// this is never sent.
+    Lobby,
+    /// Format: `REQ,ROOM`. Sent by the server to ask a client which room they want.
+    Room,
+    /// Format: `REQ,ROOMS`. Sent by a client to get the rooms that match their choice.
+    Rooms,
+    /// Format: `REQ,VIEW`.
+    View,
+    /// Format: `REQ,DEAL`.
+    Deal,
 }
 
 impl ToOwned for RequestType {
@@ -28,6 +44,14 @@ impl ToOwned for RequestType {
             RequestType::Status => RequestType::Status,
             RequestType::PlayerAction => RequestType::PlayerAction,
             RequestType::GameState => RequestType::GameState,
+            RequestType::Cards => RequestType::Cards,
+            RequestType::Rules => RequestType::Rules,
+            RequestType::Chat => RequestType::Chat,
+            RequestType::Lobby => RequestType::Lobby,
+            RequestType::Room => RequestType::Room,
+            RequestType::Rooms => RequestType::Rooms,
+            RequestType::View => RequestType::View,
+            RequestType::Deal => RequestType::Deal,
         }
     }
 }
@@ -41,6 +65,14 @@ impl Display for RequestType {
             RequestType::PlayerAction => "ACT",
             RequestType::Status => "STATUS",
             RequestType::GameState => "GAME",
+            RequestType::Cards => "CARDS",
+            RequestType::Rules => "RULES",
+            RequestType::Chat => "CHAT",
+            RequestType::Lobby => "LOBBY",
+            RequestType::Room => "ROOM",
+            RequestType::Rooms => "ROOMS",
+            RequestType::View => "VIEW",
+            RequestType::Deal => "DEAL",
         };
 
         write!(f, "{type_str}")
@@ -68,6 +100,14 @@ impl Request {
             ResponseType::Status(_) => RequestType::Status,
             ResponseType::PlayerAction(_) => RequestType::PlayerAction,
             ResponseType::GameState(_) => RequestType::GameState,
+            ResponseType::Cards(_) => RequestType::Cards,
+            ResponseType::Rules(_) => RequestType::Rules,
+            ResponseType::Chat(_) => RequestType::Chat,
+            ResponseType::Lobby(_) => RequestType::Lobby,
+            ResponseType::Room(_) => RequestType::Room,
+            ResponseType::Rooms(_) => RequestType::Rooms,
+            ResponseType::View(_) => RequestType::View,
+            ResponseType::Deal(_) => RequestType::Deal,
         };
         Request { request_type }
     }
@@ -78,12 +118,16 @@ impl Request {
     }
 
     /// Checks to see if there were any errors on request, unwrapping it and returing it if there
-    /// were none.
-    pub fn validate(request: Result<Request, ServerError>, request_type: RequestType) {
-        match request {
-            Ok(request) if variant_eq(request.request_type(), &request_type) => (),
-            Err(err) => eprintln!("An error occured: {err}"),
-            _ => unreachable!("Received request of incorrect type."),
+    /// were none. Returns an error if the request is not of type `request_type`.
+    pub fn validate(
+        request: error::Result<Request>,
+        request_type: RequestType,
+    ) -> error::Result<Request> {
+        let request = request?;
+        if variant_eq(request.request_type(), &request_type) {
+            Ok(request)
+        } else {
+            Err(KingdomError::ExpectedRequestType(request_type))
         }
     }
 }
@@ -109,6 +153,19 @@ pub enum RequestParseError {
     InvalidType,
 }
 
+impl Display for RequestParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            RequestParseError::NotARequest => "not a request",
+            RequestParseError::InvalidNumArguments => "invalid number of arguments",
+            RequestParseError::InvalidType => "invalid request type",
+        };
+        write!(f, "{msg}")
+    }
+}
+
+impl std::error::Error for RequestParseError {}
+
 impl FromStr for Request {
     type Err = RequestParseError;
 
@@ -118,18 +175,13 @@ impl FromStr for Request {
     /// when parsing the string.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut parts = s.split(",");
-        let first = parts.next().unwrap();
-
-        if first != "REQ" {
+        if parts.next() != Some("REQ") {
             return Err(RequestParseError::NotARequest);
         }
 
-        /* Count should be 1 instead of 2 because first is consumed. */
-        if parts.clone().count() != 1 {
+        let (Some(request_type), None) = (parts.next(), parts.next()) else {
             return Err(RequestParseError::InvalidNumArguments);
-        }
-
-        let request_type = parts.next().unwrap();
+        };
 
         match request_type {
             "NAME" => Ok(Request {
@@ -144,6 +196,30 @@ impl FromStr for Request {
             "GAME" => Ok(Request {
                 request_type: RequestType::GameState,
             }),
+            "CARDS" => Ok(Request {
+                request_type: RequestType::Cards,
+            }),
+            "RULES" => Ok(Request {
+                request_type: RequestType::Rules,
+            }),
+            "CHAT" => Ok(Request {
+                request_type: RequestType::Chat,
+            }),
+            "LOBBY" => Ok(Request {
+                request_type: RequestType::Lobby,
+            }),
+            "ROOM" => Ok(Request {
+                request_type: RequestType::Room,
+            }),
+            "ROOMS" => Ok(Request {
+                request_type: RequestType::Rooms,
+            }),
+            "VIEW" => Ok(Request {
+                request_type: RequestType::View,
+            }),
+            "DEAL" => Ok(Request {
+                request_type: RequestType::Deal,
+            }),
             _ => Err(RequestParseError::InvalidType),
         }
     }
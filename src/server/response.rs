@@ -2,12 +2,18 @@ use core::str::Split;
 use std::fmt::Display;
 use std::str::FromStr;
 
-use crate::game::card::{Card, Color, Value};
-use crate::game::game_state::{GameState, PlayerDetails};
-use crate::utils::{perror_in_fn, variant_eq};
-
+use crate::error::{self, KingdomError};
+use crate::game::card::{Card, Color, Play, Value};
+use crate::game::fair_deal::DealMessage;
+use crate::game::game_state::GameState;
+use crate::game::rules::Rules;
+use crate::utils::variant_eq;
+
+use super::chat::ChatMessage;
+use super::lobby::LobbyState;
 use super::request::{Request, RequestType};
-use super::ServerError;
+use super::rooms::{RoomChoice, RoomInfo};
+use super::view::{OwnCards, TableView};
 
 /// Used for asking the server whether an operation is valid or not.
 /// `Yes` means the operation is fine, and `No` means the operation
@@ -31,37 +37,47 @@ impl Display for StatusType {
 }
 
 impl FromStr for StatusType {
-    type Err = ();
+    type Err = ResponseParseError;
 
     /// Converts from status ("Y" or "N") to StatusType.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.trim() {
             "Y" => Ok(StatusType::Yes),
             "N" => Ok(StatusType::No),
-            _ => Err(()),
+            _ => Err(ResponseParseError::ExpectedStatus),
         }
     }
 }
 
+/// Actions that play a card can also end with the cards that were played (e.g.
+/// `ACT,K,5,{FROM_PLAYER},{TO_PLAYER},KH+5S`), written the same way as `Play`.
 #[derive(PartialEq, Debug)]
 pub enum ActionType {
-    /// Format: ACT,K,{ATTACHMENT},{FROM_PLAYER},{TO_PLAYER}
+    /// Format: ACT,K,{ATTACHMENT},{FROM_PLAYER},{TO_PLAYER}[,{PLAY}]
     PlayKing,
-    /// Format: ACT,Q,{ATTACHMENT},{FROM_PLAYER},{TO_PLAYER}
+    /// Format: ACT,Q,{ATTACHMENT},{FROM_PLAYER},{TO_PLAYER}[,{PLAY}]
     PlayQueen,
-    /// Format: ACT,J,TODO,{FROM_PLAYER},{ORIGINAL_PLAYER}
-    /// `ORIGINAL_PLAYER` is the player who originally played the damaging card (e.g. K).
+    /// Format: ACT,J,{POINTS_RESTORED},{FROM_PLAYER},{ORIGINAL_PLAYER}[,{PLAY}]
+    /// `ORIGINAL_PLAYER` is the player who originally played the damaging card (e.g. K). Jacks
+    /// are only played as reactions, and `POINTS_RESTORED` is filled in by the host.
     PlayJack,
-    /// Format: ACT,N,{NUM_VALUE},{FROM_PLAYER},{NONE}
+    /// Format: ACT,N,{NUM_VALUE},{FROM_PLAYER},{NONE}[,{PLAY}]
     PlayNumber,
     /// Format: ACT,B,0,{FROM_PLAYER},{NONE}
     PlayBlackAce,
     /// Format: ACT,R,{NUM_DAMAGE},{FROM_PLAYER},{NONE}
     PlayRedAce,
-    /// Format: ACT,S,0,{PLAYER},{NONE}
+    /// Format: ACT,S,{SECONDS},{PLAYER},{NONE}
+    /// `SECONDS` is how long the turn lasts, or 0 if there is no turn clock.
     TurnStart,
     /// Format: ACT,E,0,{PLAYER},{NONE}
     TurnEnd,
+    /// Format: ACT,O,0,{WINNER},{NONE}
+    /// Sent to every player in place of the next `TurnStart` once only one player is left.
+    GameOver,
+    /// Format: ACT,P,{POINTS},{PLAYER},{NONE}
+    /// Only the host makes these, to set a player's points (e.g. to 0 when they are kicked).
+    SetPoints,
     /// Format: ACT,T,[0 or 1],{PLAYER},{NONE}
     Status,
     /// Format: ACT,X,0,{NONE},{NONE}
@@ -81,6 +97,8 @@ impl ToOwned for ActionType {
             ActionType::PlayRedAce => ActionType::PlayRedAce,
             ActionType::TurnStart => ActionType::TurnStart,
             ActionType::TurnEnd => ActionType::TurnEnd,
+            ActionType::GameOver => ActionType::GameOver,
+            ActionType::SetPoints => ActionType::SetPoints,
             ActionType::None => ActionType::None,
             ActionType::Status => ActionType::Status,
         }
@@ -100,6 +118,8 @@ impl ActionType {
             ActionType::PlayRedAce => "R",
             ActionType::TurnStart => "S",
             ActionType::TurnEnd => "E",
+            ActionType::GameOver => "O",
+            ActionType::SetPoints => "P",
             ActionType::None => "X",
             ActionType::Status => "T",
         }
@@ -117,6 +137,8 @@ impl ActionType {
             "R" => Some(ActionType::PlayRedAce),
             "S" => Some(ActionType::TurnStart),
             "E" => Some(ActionType::TurnEnd),
+            "O" => Some(ActionType::GameOver),
+            "P" => Some(ActionType::SetPoints),
             "X" => Some(ActionType::None),
             "T" => Some(ActionType::Status),
             _ => None,
@@ -153,6 +175,10 @@ pub struct Action {
     attachment: u16,
     from_player: String,
     to_player: String,
+    /// The cards that were played, if the action played any and they are known.
+    play: Option<Play>,
+    /// The cards that were discarded for a Number, which are drawn again by the host.
+    discards: Vec<Card>,
 }
 
 impl Action {
@@ -168,15 +194,41 @@ impl Action {
             attachment,
             from_player,
             to_player,
+            play: None,
+            discards: Vec::new(),
         }
     }
 
+    /// Returns a new action for `play`, where the action type and attachment come from the
+    /// cards that were played.
+    pub fn from_play(play: Play, from_player: String, to_player: String) -> Action {
+        Action {
+            action_type: ActionType::from_card(&play.card),
+            attachment: match play.card.value().to_number_value() {
+                Some(num_value) => num_value,
+                None => play.attachment_value(),
+            },
+            from_player,
+            to_player,
+            play: Some(play),
+            discards: Vec::new(),
+        }
+    }
+
+    /// Returns this action, recording that it was made by playing `play`.
+    pub fn with_play(mut self, play: Play) -> Action {
+        self.play = Some(play);
+        self
+    }
+
     pub fn new_turn_start(name: &str) -> Action {
         Action {
             action_type: ActionType::TurnStart,
             attachment: 0,
             from_player: name.to_owned(),
             to_player: String::new(),
+            play: None,
+            discards: Vec::new(),
         }
     }
 
@@ -186,6 +238,8 @@ impl Action {
             attachment: 0,
             from_player: name.to_owned(),
             to_player: String::new(),
+            play: None,
+            discards: Vec::new(),
         }
     }
 
@@ -195,6 +249,8 @@ impl Action {
             attachment: if status { 1 } else { 0 },
             from_player: name.to_owned(),
             to_player: String::new(),
+            play: None,
+            discards: Vec::new(),
         }
     }
 
@@ -218,6 +274,70 @@ impl Action {
         &self.to_player
     }
 
+    /// Returns the cards that were played, if they are known.
+    pub fn play(&self) -> Option<&Play> {
+        self.play.as_ref()
+    }
+
+    /// Returns this action, recording that `discards` were discarded for it.
+    pub fn with_discards(mut self, discards: Vec<Card>) -> Action {
+        self.discards = discards;
+        self
+    }
+
+    /// Returns the cards that were discarded for a Number. This is empty for every other action.
+    pub fn discards(&self) -> &[Card] {
+        &self.discards
+    }
+
+    /// Returns a short description of the action for people to read, using the cards that were
+    /// played if they are known (e.g. `Alice played KH+5S against Bob`).
+    pub fn summary(&self) -> String {
+        let (from, to) = (&self.from_player, &self.to_player);
+        let cards = |default: &str| match &self.play {
+            Some(play) => play.to_string(),
+            None => default.to_owned(),
+        };
+        match self.action_type {
+            ActionType::PlayKing => format!("{from} played {} against {to}", cards("a King")),
+            ActionType::PlayQueen => format!("{from} played {} on {to}", cards("a Queen")),
+            ActionType::PlayJack => {
+                format!("{from} blocked {to}'s King with {}", cards("a Jack"))
+            }
+            ActionType::PlayNumber => match self.attachment {
+                0 => format!("{from} played {}", cards("a number card")),
+                value => format!("{from} played {}", cards(&format!("a {value}"))),
+            },
+            ActionType::PlayBlackAce => format!("{from} played {}", cards("a Black Ace")),
+            ActionType::PlayRedAce => format!("{from} played {}", cards("a Red Ace")),
+            ActionType::TurnStart => format!("{from}'s turn started"),
+            ActionType::TurnEnd => format!("{from} ended their turn"),
+            ActionType::GameOver => format!("{from} won the game"),
+            ActionType::SetPoints => {
+                format!("the host set {from}'s points to {}", self.attachment)
+            }
+            ActionType::Status | ActionType::None => self.to_string(),
+        }
+    }
+
+    /// Returns true if the cards that were played (if any) could have made this action. Jacks
+    /// only have to be a Jack, since their attachment is filled in by the host.
+    pub fn matches_play(&self) -> bool {
+        let Some(play) = &self.play else {
+            return true;
+        };
+        if !variant_eq(&ActionType::from_card(&play.card), &self.action_type) {
+            return false;
+        }
+        match self.action_type {
+            ActionType::PlayKing | ActionType::PlayQueen => {
+                play.attachment_value() == self.attachment
+            }
+            ActionType::PlayNumber => play.card.value().to_number_value() == Some(self.attachment),
+            _ => true,
+        }
+    }
+
     pub fn set_self(
         &mut self,
         action_type: ActionType,
@@ -229,6 +349,8 @@ impl Action {
         self.attachment = attachment;
         self.from_player = from_player;
         self.to_player = to_player;
+        self.play = None;
+        self.discards.clear();
     }
 }
 
@@ -239,6 +361,8 @@ impl Default for Action {
             attachment: 0,
             from_player: String::default(),
             to_player: String::default(),
+            play: None,
+            discards: Vec::new(),
         }
     }
 }
@@ -252,50 +376,106 @@ impl ToOwned for Action {
             attachment: self.attachment,
             from_player: self.from_player.to_owned(),
             to_player: self.to_player.to_owned(),
+            play: self.play,
+            discards: self.discards.clone(),
         }
     }
 }
 
 #[allow(clippy::enum_variant_names)]
+#[derive(Debug)]
 pub enum ActionParseError {
     InvalidType,
     InvalidAttatchment,
     InvalidNumArguments,
+    InvalidPlay,
+}
+
+impl Display for ActionParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            ActionParseError::InvalidType => "invalid action type",
+            ActionParseError::InvalidAttatchment => "invalid attachment",
+            ActionParseError::InvalidNumArguments => "invalid number of arguments",
+            ActionParseError::InvalidPlay => "invalid cards played",
+        };
+        write!(f, "{msg}")
+    }
+}
+
+impl std::error::Error for ActionParseError {}
+
+impl Display for Action {
+    /// Format: `{SYMBOL},{ATTACHMENT},{FROM_PLAYER},{TO_PLAYER}[,{PLAY}[,{DISCARDS}]]`, where
+    /// `DISCARDS` is a space-separated list of card codes.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.action_type.to_symbol(),
+            self.attachment,
+            self.from_player,
+            self.to_player
+        )?;
+        if let Some(play) = &self.play {
+            write!(f, ",{play}")?;
+            if !self.discards.is_empty() {
+                let codes: Vec<String> = self.discards.iter().map(Card::to_string).collect();
+                write!(f, ",{}", codes.join(" "))?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl FromStr for Action {
     type Err = ActionParseError;
 
-    /// Converts from a string in the format `{SYMBOL},{ATTACHMENT},{FROM_PLAYER},{TO_PLAYER}`
-    /// to an Action.
+    /// Converts from a string in the format
+    /// `{SYMBOL},{ATTACHMENT},{FROM_PLAYER},{TO_PLAYER}[,{PLAY}[,{DISCARDS}]]` to an Action.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split(",");
-
-        if parts.clone().count() != 4 {
+        let parts: Vec<&str> = s.split(",").collect();
+        let [first, attachment, from_player, to_player, ref rest @ ..] = parts[..] else {
+            return Err(ActionParseError::InvalidNumArguments);
+        };
+        if rest.len() > 2 {
             return Err(ActionParseError::InvalidNumArguments);
         }
+        let mut rest = rest.iter();
 
-        let first = parts.next().unwrap();
         let action = ActionType::from_symbol(first);
         let action: ActionType = match action {
             Some(v) => v,
             None => return Err(ActionParseError::InvalidType),
         };
 
-        let attachment = parts.next().unwrap();
         let attachment: u16 = match attachment.parse() {
             Ok(v) => v,
             Err(_) => return Err(ActionParseError::InvalidAttatchment),
         };
 
-        let from_player = parts.next().unwrap().to_string();
-        let to_player = parts.next().unwrap().to_string();
+        let from_player = from_player.to_string();
+        let to_player = to_player.to_string();
+        let play = match rest.next() {
+            Some(play) => Some(Play::from_str(play).map_err(|_| ActionParseError::InvalidPlay)?),
+            None => None,
+        };
+        let discards = match rest.next() {
+            Some(discards) => discards
+                .split_whitespace()
+                .map(Card::from_str)
+                .collect::<Result<_, _>>()
+                .map_err(|_| ActionParseError::InvalidPlay)?,
+            None => Vec::new(),
+        };
 
         Ok(Action {
             action_type: action,
             attachment,
             from_player,
             to_player,
+            play,
+            discards,
         })
     }
 }
@@ -306,12 +486,42 @@ pub enum ResponseType {
     Name(Option<String>),
     /// Format: `RES,STATUS,{Y or N}`.
     Status(Option<StatusType>),
-    /// Format: `RES,ACT,{SYMBOL},{ATTATCHMENT},{FROM_PLAYER},{TO_PLAYER}`.
+    /// Format: `RES,ACT,{SYMBOL},{ATTATCHMENT},{FROM_PLAYER},{TO_PLAYER}[,{PLAY}[,{DISCARDS}]]`.
     /// Types of actions are `K(ing), Q(ueen), J(ack), N(umber), B(lack Ace), R(ed Ace),
     /// (Turn) S(tart), (Turn) E(nd)`.
     PlayerAction(Option<Action>),
     /// Format `RES,GAME,{NUM_PLAYERS},{P1_NAME}:{P1_POINTS},{P2_NAME}:{P2_POINTS}...`.
     GameState(Option<GameState>),
+    /// Format `RES,CARDS,{DECK_SIZE},{HAND},{DISCARD_PILE}` (see `OwnCards`). `RES,CARDS` on its
+    /// own means there are no saved cards to restore. The cards are boxed to keep `ResponseType`
+    /// (and `KingdomError`) small.
+    Cards(Option<Box<OwnCards>>),
+    /// Format `RES,RULES,{RULES}`, where `RULES` is a comma-separated list of `key=value` pairs.
+    Rules(Option<Rules>),
+    /// Format `RES,CHAT,{FROM_PLAYER},{TO_PLAYER},{TEXT}`, where `TO_PLAYER` is empty unless the
+    /// message is a whisper. Chat messages are sent without being requested, and are picked out
+    /// of the stream before the rest of the protocol sees them. The message is boxed for the same
+    /// reason as `Cards`.
+    Chat(Option<Box<ChatMessage>>),
+    /// Format `RES,LOBBY,{STATUS},{SEATS},{P1_NAME}:{Y or N},{P2_NAME}:{Y or N}...`. The lobby is
+    /// sent to every player in it whenever it changes, without being requested.
+    Lobby(Option<Box<LobbyState>>),
+    /// Format `RES,ROOM,L`, `RES,ROOM,C,{NAME},{RULES}` or `RES,ROOM,J,{CODE}`, for listing the
+    /// open rooms, creating a room, or joining a room with its join code. The choice is boxed for
+    /// the same reason as `Cards`.
+    Room(Option<Box<RoomChoice>>),
+    /// Format `RES,ROOMS,{R1_CODE}:{R1_NAME}:{R1_PLAYERS}:{R1_SEATS},...`. After creating or
+    /// joining a room, the list only has the room that was joined, and is empty if it couldn't
+    /// be.
+    Rooms(Option<Vec<RoomInfo>>),
+    /// Format `RES,VIEW,{P1_NAME}:{P1_HAND_SIZE}:{P1_DECK_SIZE}:{P1_DISCARD_PILE}:{P1_HAND},...`,
+    /// where each pile is a space-separated list of card codes, and a hand is `?` unless it
+    /// belongs to whoever the view is sent to. The view is boxed for the same reason as `Cards`.
+    View(Option<Box<TableView>>),
+    /// Format `RES,DEAL,C,{P1_NAME}:{P1_HASH},...`, `RES,DEAL,E,{ENTROPY}` or
+    /// `RES,DEAL,R,{SALT},{P1_NAME}:{P1_ENTROPY}:{P1_DECK},...`, for the steps of the fair deal
+    /// (see `DealMessage`). The message is boxed for the same reason as `Cards`.
+    Deal(Option<Box<DealMessage>>),
 }
 
 impl ToOwned for ResponseType {
@@ -323,6 +533,14 @@ impl ToOwned for ResponseType {
             ResponseType::Status(_) => ResponseType::Status(None),
             ResponseType::PlayerAction(_) => ResponseType::PlayerAction(None),
             ResponseType::GameState(_) => ResponseType::GameState(None),
+            ResponseType::Cards(_) => ResponseType::Cards(None),
+            ResponseType::Rules(_) => ResponseType::Rules(None),
+            ResponseType::Chat(_) => ResponseType::Chat(None),
+            ResponseType::Lobby(_) => ResponseType::Lobby(None),
+            ResponseType::Room(_) => ResponseType::Room(None),
+            ResponseType::Rooms(_) => ResponseType::Rooms(None),
+            ResponseType::View(_) => ResponseType::View(None),
+            ResponseType::Deal(_) => ResponseType::Deal(None),
         }
     }
 }
@@ -336,6 +554,14 @@ impl Display for ResponseType {
             ResponseType::PlayerAction(_) => "ACT",
             ResponseType::Status(_) => "STATUS",
             ResponseType::GameState(_) => "GAME",
+            ResponseType::Cards(_) => "CARDS",
+            ResponseType::Rules(_) => "RULES",
+            ResponseType::Chat(_) => "CHAT",
+            ResponseType::Lobby(_) => "LOBBY",
+            ResponseType::Room(_) => "ROOM",
+            ResponseType::Rooms(_) => "ROOMS",
+            ResponseType::View(_) => "VIEW",
+            ResponseType::Deal(_) => "DEAL",
         };
 
         write!(f, "{response_type}")
@@ -343,7 +569,7 @@ impl Display for ResponseType {
 }
 
 impl FromStr for ResponseType {
-    type Err = ();
+    type Err = ResponseParseError;
 
     /// Converts from a string to `ResponseType`. The value contained within the
     /// `ResponseType` is whatever the "default" value is for each variant.
@@ -353,7 +579,15 @@ impl FromStr for ResponseType {
             "ACT" => Ok(ResponseType::PlayerAction(None)),
             "STATUS" => Ok(ResponseType::Status(None)),
             "GAME" => Ok(ResponseType::GameState(None)),
-            _ => Err(()),
+            "CARDS" => Ok(ResponseType::Cards(None)),
+            "RULES" => Ok(ResponseType::Rules(None)),
+            "CHAT" => Ok(ResponseType::Chat(None)),
+            "LOBBY" => Ok(ResponseType::Lobby(None)),
+            "ROOM" => Ok(ResponseType::Room(None)),
+            "ROOMS" => Ok(ResponseType::Rooms(None)),
+            "VIEW" => Ok(ResponseType::View(None)),
+            "DEAL" => Ok(ResponseType::Deal(None)),
+            _ => Err(ResponseParseError::InvalidType),
         }
     }
 }
@@ -378,6 +612,14 @@ impl Response {
             RequestType::Status => ResponseType::Status(None),
             RequestType::PlayerAction => ResponseType::PlayerAction(None),
             RequestType::GameState => ResponseType::GameState(None),
+            RequestType::Cards => ResponseType::Cards(None),
+            RequestType::Rules => ResponseType::Rules(None),
+            RequestType::Chat => ResponseType::Chat(None),
+            RequestType::Lobby => ResponseType::Lobby(None),
+            RequestType::Room => ResponseType::Room(None),
+            RequestType::Rooms => ResponseType::Rooms(None),
+            RequestType::View => ResponseType::View(None),
+            RequestType::Deal => ResponseType::Deal(None),
         };
         Response { response_type }
     }
@@ -400,6 +642,56 @@ impl Response {
         }
     }
 
+    /// Creates a cards response. If `cards` is `None`, the response means that there are no
+    /// saved cards to restore.
+    pub fn from_cards(cards: Option<OwnCards>) -> Response {
+        Response {
+            response_type: ResponseType::Cards(cards.map(Box::new)),
+        }
+    }
+
+    pub fn from_rules(rules: Rules) -> Response {
+        Response {
+            response_type: ResponseType::Rules(Some(rules)),
+        }
+    }
+
+    pub fn from_view(view: TableView) -> Response {
+        Response {
+            response_type: ResponseType::View(Some(Box::new(view))),
+        }
+    }
+
+    pub fn from_deal(message: DealMessage) -> Response {
+        Response {
+            response_type: ResponseType::Deal(Some(Box::new(message))),
+        }
+    }
+
+    pub fn from_chat(message: ChatMessage) -> Response {
+        Response {
+            response_type: ResponseType::Chat(Some(Box::new(message))),
+        }
+    }
+
+    pub fn from_lobby(lobby: LobbyState) -> Response {
+        Response {
+            response_type: ResponseType::Lobby(Some(Box::new(lobby))),
+        }
+    }
+
+    pub fn from_room(choice: RoomChoice) -> Response {
+        Response {
+            response_type: ResponseType::Room(Some(Box::new(choice))),
+        }
+    }
+
+    pub fn from_rooms(rooms: Vec<RoomInfo>) -> Response {
+        Response {
+            response_type: ResponseType::Rooms(Some(rooms)),
+        }
+    }
+
     // pub fn new_player_details(name: String, points: u16) -> Response {
     //     Response {
     //         response_type: ResponseType::Details(Some(PlayerDetails::new(name, points))),
@@ -433,47 +725,175 @@ impl Response {
         &self.response_type
     }
 
-    pub fn validate(response: Result<Response, ServerError>, response_type: ResponseType) {
-        match response {
-            Ok(response) if variant_eq(response.response_type(), &response_type) => (),
-            Err(err) => perror_in_fn("Response::validate", err),
-            _ => unreachable!("Received response of invalid type"),
+    /// Checks to see if there were any errors on response, unwrapping it and returning it if
+    /// there were none. Returns an error if the response is not of type `response_type`.
+    pub fn validate(
+        response: error::Result<Response>,
+        response_type: ResponseType,
+    ) -> error::Result<Response> {
+        let response = response?;
+        if variant_eq(response.response_type(), &response_type) {
+            Ok(response)
+        } else {
+            Err(KingdomError::ExpectedResponseType(response_type))
+        }
+    }
+
+    /// Consumes the response, returning the name it contains. Returns an error if this is not a
+    /// name response.
+    pub fn into_name(self) -> error::Result<String> {
+        match self.response_type {
+            ResponseType::Name(Some(name)) => Ok(name),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::Name(None))),
+        }
+    }
+
+    /// Consumes the response, returning the status it contains. Returns an error if this is not
+    /// a status response.
+    pub fn into_status(self) -> error::Result<StatusType> {
+        match self.response_type {
+            ResponseType::Status(Some(status)) => Ok(status),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::Status(
+                None,
+            ))),
+        }
+    }
+
+    /// Consumes the response, returning the action it contains. Returns an error if this is not
+    /// an action response.
+    pub fn into_action(self) -> error::Result<Action> {
+        match self.response_type {
+            ResponseType::PlayerAction(Some(action)) => Ok(action),
+            _ => Err(KingdomError::ExpectedResponseType(
+                ResponseType::PlayerAction(None),
+            )),
+        }
+    }
+
+    /// Consumes the response, returning the game state it contains. Returns an error if this is
+    /// not a game state response.
+    pub fn into_game_state(self) -> error::Result<GameState> {
+        match self.response_type {
+            ResponseType::GameState(Some(game_state)) => Ok(game_state),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::GameState(
+                None,
+            ))),
+        }
+    }
+
+    /// Consumes the response, returning the cards it contains (if any). Returns an error if this
+    /// is not a cards response.
+    pub fn into_cards(self) -> error::Result<Option<OwnCards>> {
+        match self.response_type {
+            ResponseType::Cards(cards) => Ok(cards.map(|cards| *cards)),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::Cards(
+                None,
+            ))),
+        }
+    }
+
+    /// Consumes the response, returning the chat message it contains. Returns an error if this is
+    /// not a chat response.
+    pub fn into_chat(self) -> error::Result<ChatMessage> {
+        match self.response_type {
+            ResponseType::Chat(Some(message)) => Ok(*message),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::Chat(None))),
+        }
+    }
+
+    /// Consumes the response, returning the lobby it contains. Returns an error if this is not a
+    /// lobby response.
+    pub fn into_lobby(self) -> error::Result<LobbyState> {
+        match self.response_type {
+            ResponseType::Lobby(Some(lobby)) => Ok(*lobby),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::Lobby(
+                None,
+            ))),
+        }
+    }
+
+    /// Consumes the response, returning the room choice it contains. Returns an error if this is
+    /// not a room response.
+    pub fn into_room(self) -> error::Result<RoomChoice> {
+        match self.response_type {
+            ResponseType::Room(Some(choice)) => Ok(*choice),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::Room(None))),
+        }
+    }
+
+    /// Consumes the response, returning the rooms it contains. Returns an error if this is not a
+    /// rooms response.
+    pub fn into_rooms(self) -> error::Result<Vec<RoomInfo>> {
+        match self.response_type {
+            ResponseType::Rooms(Some(rooms)) => Ok(rooms),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::Rooms(
+                None,
+            ))),
+        }
+    }
+
+    /// Consumes the response, returning the view of the table it contains. Returns an error if
+    /// this is not a view response.
+    pub fn into_view(self) -> error::Result<TableView> {
+        match self.response_type {
+            ResponseType::View(Some(view)) => Ok(*view),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::View(None))),
+        }
+    }
+
+    /// Consumes the response, returning the fair deal message it contains. Returns an error if
+    /// this is not a deal response.
+    pub fn into_deal(self) -> error::Result<DealMessage> {
+        match self.response_type {
+            ResponseType::Deal(Some(message)) => Ok(*message),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::Deal(None))),
+        }
+    }
+
+    /// Consumes the response, returning the rules it contains. Returns an error if this is not
+    /// a rules response.
+    pub fn into_rules(self) -> error::Result<Rules> {
+        match self.response_type {
+            ResponseType::Rules(Some(rules)) => Ok(rules),
+            _ => Err(KingdomError::ExpectedResponseType(ResponseType::Rules(
+                None,
+            ))),
         }
     }
 }
 
 impl Display for Response {
-    /// Format: `RES,{RESPONSE_TYPE},{...ARGUMENTS}`.
+    /// Format: `RES,{RESPONSE_TYPE},{...ARGUMENTS}`. Responses without a value (e.g.
+    /// `NAME_RESPONSE`) are written as `RES,{RESPONSE_TYPE}`.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let response = match &self.response_type {
-            ResponseType::Name(name) => format!("RES,NAME,{}", name.as_ref().unwrap()),
-            ResponseType::PlayerAction(action) => {
-                let action = action.as_ref().unwrap();
-                format!(
-                    "RES,ACT,{},{},{},{}",
-                    action.action_type.to_symbol(),
-                    action.attachment,
-                    action.from_player,
-                    action.to_player
-                )
-            }
-            ResponseType::Status(status) => {
-                format!("RES,STATUS,{}", status.as_ref().unwrap())
-            }
-            ResponseType::GameState(game_state) => {
-                let game_state = game_state.as_ref().unwrap();
-                let mut response = String::from("RES,GAME,");
-                let num_players = game_state.num_players().to_string();
-                response.push_str(&num_players);
-                response.push(',');
-                for player in game_state.all_players().iter() {
-                    let name = player.name();
-                    let points = player.points().to_string();
-                    let player_details = format!("{name}:{points},");
-                    response.push_str(&player_details);
-                }
-                response.pop(); /* removing trailing comma */
-                response
+            ResponseType::Name(None)
+            | ResponseType::Status(None)
+            | ResponseType::PlayerAction(None)
+            | ResponseType::GameState(None)
+            | ResponseType::Cards(None)
+            | ResponseType::Rules(None)
+            | ResponseType::Chat(None)
+            | ResponseType::Lobby(None)
+            | ResponseType::Room(None)
+            | ResponseType::Rooms(None)
+            | ResponseType::View(None)
+            | ResponseType::Deal(None) => format!("RES,{}", self.response_type),
+            ResponseType::Name(Some(name)) => format!("RES,NAME,{name}"),
+            ResponseType::PlayerAction(Some(action)) => format!("RES,ACT,{action}"),
+            ResponseType::Status(Some(status)) => format!("RES,STATUS,{status}"),
+            ResponseType::GameState(Some(game_state)) => format!("RES,GAME,{game_state}"),
+            ResponseType::Cards(Some(cards)) => format!("RES,CARDS,{cards}"),
+            ResponseType::Rules(Some(rules)) => format!("RES,RULES,{rules}"),
+            ResponseType::Chat(Some(message)) => format!("RES,CHAT,{message}"),
+            ResponseType::Lobby(Some(lobby)) => format!("RES,LOBBY,{lobby}"),
+            ResponseType::Room(Some(choice)) => format!("RES,ROOM,{choice}"),
+            ResponseType::View(Some(view)) => format!("RES,VIEW,{view}"),
+            ResponseType::Deal(Some(message)) => format!("RES,DEAL,{message}"),
+            ResponseType::Rooms(Some(rooms)) => {
+                rooms.iter().fold(String::from("RES,ROOMS"), |line, room| {
+                    format!("{line},{room}")
+                })
             }
         };
 
@@ -492,8 +912,41 @@ pub enum ResponseParseError {
     ExpectedPoints,
     UnableToParseAction,
     ParseIntError,
+    InvalidCards,
+    InvalidRules,
+    InvalidChat,
+    InvalidLobby,
+    InvalidRoom,
+    InvalidView,
+    InvalidDeal,
+}
+
+impl Display for ResponseParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            ResponseParseError::InvalidNumArguments => "invalid number of arguments",
+            ResponseParseError::NotAResponse => "not a response",
+            ResponseParseError::InvalidType => "invalid response type",
+            ResponseParseError::ExpectedName => "expected name",
+            ResponseParseError::ExpectedNumPlayers => "expected number of players",
+            ResponseParseError::ExpectedStatus => "expected status",
+            ResponseParseError::ExpectedPoints => "expected points",
+            ResponseParseError::UnableToParseAction => "unable to parse action",
+            ResponseParseError::ParseIntError => "unable to parse integer",
+            ResponseParseError::InvalidCards => "invalid cards",
+            ResponseParseError::InvalidRules => "invalid rules",
+            ResponseParseError::InvalidChat => "invalid chat message",
+            ResponseParseError::InvalidLobby => "invalid lobby",
+            ResponseParseError::InvalidRoom => "invalid room",
+            ResponseParseError::InvalidView => "invalid view",
+            ResponseParseError::InvalidDeal => "invalid fair deal message",
+        };
+        write!(f, "{msg}")
+    }
 }
 
+impl std::error::Error for ResponseParseError {}
+
 impl FromStr for Response {
     type Err = ResponseParseError;
 
@@ -510,6 +963,14 @@ impl FromStr for Response {
             ResponseType::PlayerAction(_) => parts_to_action(&mut parts),
             ResponseType::Status(_) => parts_to_status(&mut parts),
             ResponseType::GameState(_) => parts_to_game_state(&mut parts),
+            ResponseType::Cards(_) => parts_to_cards(&mut parts),
+            ResponseType::Rules(_) => parts_to_rules(&mut parts),
+            ResponseType::Chat(_) => parts_to_chat(&mut parts),
+            ResponseType::Lobby(_) => parts_to_lobby(&mut parts),
+            ResponseType::Room(_) => parts_to_room(&mut parts),
+            ResponseType::Rooms(_) => parts_to_rooms(&mut parts),
+            ResponseType::View(_) => parts_to_view(&mut parts),
+            ResponseType::Deal(_) => parts_to_deal(&mut parts),
         }
     }
 }
@@ -519,8 +980,7 @@ fn validate_parts(parts: &mut Split<&str>) -> Result<(), ResponseParseError> {
         return Err(ResponseParseError::InvalidNumArguments);
     }
 
-    let first = parts.next().unwrap();
-    if first != "RES" {
+    if parts.next() != Some("RES") {
         return Err(ResponseParseError::NotAResponse);
     }
 
@@ -528,12 +988,10 @@ fn validate_parts(parts: &mut Split<&str>) -> Result<(), ResponseParseError> {
 }
 
 fn validate_response_type(parts: &mut Split<&str>) -> Result<ResponseType, ResponseParseError> {
-    let response_type = parts.next().unwrap();
-    let response_type = ResponseType::from_str(response_type);
-    match response_type {
-        Ok(response_type) => Ok(response_type),
-        Err(_) => Err(ResponseParseError::InvalidType),
-    }
+    let response_type = parts
+        .next()
+        .ok_or(ResponseParseError::InvalidNumArguments)?;
+    ResponseType::from_str(response_type)
 }
 
 fn parts_to_name(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
@@ -559,47 +1017,71 @@ fn parts_to_action(parts: &mut Split<&str>) -> Result<Response, ResponseParseErr
 
 fn parts_to_status(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
     if let Some(status) = parts.next() {
-        let status = StatusType::from_str(status);
-        match status {
-            Ok(status) => Ok(Response::new(ResponseType::Status(Some(status)))),
-            Err(_) => Err(ResponseParseError::ExpectedStatus),
-        }
+        let status = StatusType::from_str(status)?;
+        Ok(Response::new(ResponseType::Status(Some(status))))
     } else {
         Err(ResponseParseError::ExpectedStatus)
     }
 }
 
 fn parts_to_game_state(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
-    if let Some(num_players) = parts.next() {
-        let num_players = match num_players.parse::<u8>() {
-            Ok(n) => n,
-            Err(_) => return Err(ResponseParseError::ParseIntError),
-        };
-
-        let mut game_state = GameState::new();
-        for _ in 0..num_players {
-            if let Some(player) = parts.next() {
-                let player_details = &mut player.split(":");
-                if player_details.clone().count() != 2 {
-                    return Err(ResponseParseError::InvalidNumArguments);
-                }
-                let name = player_details.next().unwrap();
-                let points = player_details.next().unwrap();
-                let points = match points.parse::<u16>() {
-                    Ok(n) => n,
-                    Err(_) => return Err(ResponseParseError::ParseIntError),
-                };
-                let player = PlayerDetails::new(name.to_string(), points);
-                game_state.add_player(player);
-            } else {
-                return Err(ResponseParseError::InvalidNumArguments);
-            }
-        }
+    let parts: Vec<&str> = parts.collect();
+    let game_state = GameState::from_str(&parts.join(","))?;
+    Ok(Response::from_game_state(game_state))
+}
 
-        Ok(Response::from_game_state(game_state))
-    } else {
-        Err(ResponseParseError::ExpectedNumPlayers)
+fn parts_to_cards(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
+    let parts: Vec<&str> = parts.collect();
+    if parts.is_empty() {
+        return Ok(Response::from_cards(None));
     }
+    let cards = OwnCards::from_str(&parts.join(","))?;
+    Ok(Response::from_cards(Some(cards)))
+}
+
+fn parts_to_rules(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
+    let parts: Vec<&str> = parts.collect();
+    let rules = Rules::from_str(&parts.join(",")).map_err(|_| ResponseParseError::InvalidRules)?;
+    Ok(Response::from_rules(rules))
+}
+
+fn parts_to_chat(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
+    let parts: Vec<&str> = parts.collect();
+    let message =
+        ChatMessage::from_str(&parts.join(",")).map_err(|_| ResponseParseError::InvalidChat)?;
+    Ok(Response::from_chat(message))
+}
+
+fn parts_to_lobby(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
+    let parts: Vec<&str> = parts.collect();
+    let lobby = LobbyState::from_str(&parts.join(","))?;
+    Ok(Response::from_lobby(lobby))
+}
+
+fn parts_to_room(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
+    let parts: Vec<&str> = parts.collect();
+    let choice = RoomChoice::from_str(&parts.join(","))?;
+    Ok(Response::from_room(choice))
+}
+
+fn parts_to_view(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
+    let parts: Vec<&str> = parts.collect();
+    let view = TableView::from_str(&parts.join(","))?;
+    Ok(Response::from_view(view))
+}
+
+fn parts_to_deal(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
+    let parts: Vec<&str> = parts.collect();
+    let message = DealMessage::from_str(&parts.join(","))?;
+    Ok(Response::from_deal(message))
+}
+
+fn parts_to_rooms(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
+    let rooms = parts
+        .filter(|room| !room.is_empty())
+        .map(RoomInfo::from_str)
+        .collect::<Result<_, _>>()?;
+    Ok(Response::from_rooms(rooms))
 }
 
 // fn parts_to_details(parts: &mut Split<&str>) -> Result<Response, ResponseParseError> {
@@ -0,0 +1,649 @@
+//! This module contains `RoomManager`, which lets one server host many rooms at once. Every room
+//! has its own join code, lobby, chat, rules and game. Clients connect to the same listener, then
+//! list the open rooms, create a room of their own, or join a room with its join code. Clients
+//! can also connect over WebSocket (e.g. from a browser), and are then handled the same way.
+
+use std::{
+    fmt::Display,
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+
+use crate::{
+    error::{self, KingdomError},
+    game::{profiles::ProfileStore, rules::Rules},
+    logging,
+    utils::perror_in_fn,
+};
+
+use super::{
+    admin::GameMonitor,
+    chat::ChatRelay,
+    constants::{
+        JOIN_CODE_LEN, MAX_ROOMS, MAX_ROOM_NAME_LEN, NAME_REQUEST, NAME_RESPONSE, ROOMS_REQUEST,
+        ROOM_REQUEST, ROOM_RESPONSE, STATUS_REQUEST, STATUS_RESPONSE_NO, STATUS_RESPONSE_YES,
+    },
+    host::ServerInstance,
+    lobby::{Lobby, LobbyStatus},
+    response::{Response, ResponseParseError, StatusType},
+    websocket, Message, StreamHandler,
+};
+
+/// How often a client's thread checks whether the game has started while they are in the lobby.
+const LOBBY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a room that a player created stays open if nobody joins it.
+const EMPTY_ROOM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many random join codes are tried for a new room before giving up.
+const NEW_CODE_ATTEMPTS: usize = 100;
+
+/// Where the stream of every player in a room is received, with their name, once its game starts.
+pub type RoomHandlers = Receiver<(String, StreamHandler)>;
+
+/// What a client chose to do before joining a room.
+#[derive(PartialEq, Debug, Clone)]
+pub enum RoomChoice {
+    /// List the open rooms.
+    List,
+    /// Create a room with this name, and these changes to the default rules (see
+    /// `Rules::with_changes`).
+    Create { name: String, rules: String },
+    /// Join the room with this join code.
+    Join(String),
+}
+
+impl Display for RoomChoice {
+    /// Format: `L`, `C,{NAME},{RULES}` or `J,{CODE}`, where `RULES` is a comma-separated list of
+    /// `key=value` pairs, which may be empty.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoomChoice::List => write!(f, "L"),
+            RoomChoice::Create { name, rules } => write!(f, "C,{name},{rules}"),
+            RoomChoice::Join(code) => write!(f, "J,{code}"),
+        }
+    }
+}
+
+impl FromStr for RoomChoice {
+    type Err = ResponseParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ',');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("L"), None, None) => Ok(RoomChoice::List),
+            (Some("C"), Some(name), rules) => Ok(RoomChoice::Create {
+                name: name.to_owned(),
+                rules: rules.unwrap_or_default().to_owned(),
+            }),
+            (Some("J"), Some(code), None) => Ok(RoomChoice::Join(code.to_owned())),
+            _ => Err(ResponseParseError::InvalidRoom),
+        }
+    }
+}
+
+/// What clients are shown of a room when choosing one to join.
+#[derive(PartialEq, Debug, Clone)]
+pub struct RoomInfo {
+    code: String,
+    name: String,
+    players: u8,
+    seats: u8,
+}
+
+impl RoomInfo {
+    /// Returns a new instance of `RoomInfo`.
+    pub fn new(code: String, name: String, players: u8, seats: u8) -> RoomInfo {
+        RoomInfo {
+            code,
+            name,
+            players,
+            seats,
+        }
+    }
+
+    /// Returns a reference to `self.code`.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Returns a reference to `self.name`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns how many players have taken a seat.
+    pub fn players(&self) -> u8 {
+        self.players
+    }
+
+    /// Returns `self.seats`.
+    pub fn seats(&self) -> u8 {
+        self.seats
+    }
+}
+
+impl Display for RoomInfo {
+    /// Format: `{CODE}:{NAME}:{PLAYERS}:{SEATS}`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.code, self.name, self.players, self.seats
+        )
+    }
+}
+
+impl FromStr for RoomInfo {
+    type Err = ResponseParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let [code, name, players, seats] = parts[..] else {
+            return Err(ResponseParseError::InvalidRoom);
+        };
+        let players = players
+            .parse()
+            .map_err(|_| ResponseParseError::ParseIntError)?;
+        let seats = seats
+            .parse()
+            .map_err(|_| ResponseParseError::ParseIntError)?;
+        Ok(RoomInfo::new(
+            code.to_owned(),
+            name.to_owned(),
+            players,
+            seats,
+        ))
+    }
+}
+
+/// A room on the server, which players join with its join code. Every client is handled on its
+/// own thread while it is in the room's lobby, and is handed over to the room's game once it
+/// starts.
+pub struct Room {
+    code: String,
+    name: String,
+    lobby: Lobby,
+    /// Passes chat on between the players in this room, from the threads that read their streams.
+    chat: Arc<ChatRelay>,
+    /// Where the stream of every player is sent, with their name, once the game starts.
+    handlers: Sender<(String, StreamHandler)>,
+    /// What the host can see of the room's game, and the commands the host sends it.
+    monitor: GameMonitor,
+}
+
+impl Room {
+    /// Returns a reference to `self.code`.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Returns a reference to `self.name`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns a reference to `self.lobby`.
+    pub fn lobby(&self) -> &Lobby {
+        &self.lobby
+    }
+
+    /// Returns a reference to `self.monitor`.
+    pub fn monitor(&self) -> &GameMonitor {
+        &self.monitor
+    }
+
+    /// Sends `text` to everyone in the room's chat from the host.
+    pub fn announce(&self, text: &str) {
+        self.chat.announce(text);
+    }
+
+    /// Removes the player named `name` from the room's chat (e.g. when they are kicked).
+    pub fn leave_chat(&self, name: &str) {
+        self.chat.leave(name);
+    }
+
+    /// Returns what clients are shown of this room.
+    pub fn info(&self) -> RoomInfo {
+        RoomInfo::new(
+            self.code.clone(),
+            self.name.clone(),
+            self.lobby.num_players() as u8,
+            self.lobby.seats(),
+        )
+    }
+
+    /// Waits until the game in this room starts, returning the names of the players in seat
+    /// order. Returns `None` if the room is abandoned first, either because everyone who joined
+    /// has left or because nobody joined within `EMPTY_ROOM_TIMEOUT`.
+    pub fn await_start(&self) -> Option<Vec<String>> {
+        let created = Instant::now();
+        let mut was_joined = false;
+        loop {
+            if let Some(names) = self.lobby.seated() {
+                return Some(names);
+            }
+            let is_empty = self.lobby.num_players() == 0;
+            was_joined |= !is_empty;
+            if is_empty && (was_joined || created.elapsed() >= EMPTY_ROOM_TIMEOUT) {
+                return None;
+            }
+            thread::sleep(LOBBY_POLL_INTERVAL);
+        }
+    }
+
+    /// Tells a client that just chose this room whether it is open. If it is, the client chooses
+    /// a name and waits in the lobby until the game starts.
+    fn admit(&self, mut handler: StreamHandler) {
+        let is_open = self.lobby.is_open();
+        let status = if is_open {
+            STATUS_RESPONSE_YES
+        } else {
+            STATUS_RESPONSE_NO
+        };
+        /* Client asking if room is full. */
+        if let Err(err) = handler.await_request_send_response(STATUS_REQUEST, status) {
+            perror_in_fn("Room::admit", handler.peer(), err);
+            return;
+        }
+        if !is_open {
+            return;
+        }
+
+        let Some(name) = self.name_player(&mut handler) else {
+            return;
+        };
+        handler.set_peer_name(&name);
+        self.join_chat(&handler, &name);
+        self.lobby.welcome(&name, handler.writer());
+        self.wait_in_lobby(handler, name);
+    }
+
+    /// Prompts the client to enter a username until they choose one that can join the lobby.
+    /// Returns `None` if the client disconnects first.
+    fn name_player(&self, handler: &mut StreamHandler) -> Option<String> {
+        loop {
+            let status = handler
+                .send_request_await_response(NAME_REQUEST, NAME_RESPONSE)
+                .and_then(Response::into_name);
+            let name = match status {
+                Ok(name) => name,
+                Err(err) => {
+                    perror_in_fn("name_player", handler.peer(), err);
+                    return None;
+                }
+            };
+            let joined = self.lobby.join(&name);
+            if let Err(err) = &joined {
+                perror_in_fn("name_player", handler.peer(), err);
+            }
+            let is_accepted = joined.is_ok();
+            let status = if is_accepted {
+                STATUS_RESPONSE_YES
+            } else {
+                STATUS_RESPONSE_NO
+            };
+            if let Err(err) = handler.await_request_send_response(STATUS_REQUEST, status) {
+                perror_in_fn("name_player", handler.peer(), err);
+                if is_accepted {
+                    self.lobby.leave(&name);
+                }
+                return None;
+            }
+            if is_accepted {
+                return Some(name);
+            }
+        }
+    }
+
+    /// Adds the player named `name` to the chat, and relays every chat message they send.
+    fn join_chat(&self, handler: &StreamHandler, name: &str) {
+        self.chat.join(name, handler.writer(), true);
+        let chat = Arc::clone(&self.chat);
+        let sender = name.to_owned();
+        handler.on_chat(move |message| chat.relay(&sender, message));
+    }
+
+    /// Marks the player named `name` as ready or not ready whenever they say so, until the game
+    /// starts or they are kicked. When the game starts, the player is told, and their stream is
+    /// handed over to the game.
+    fn wait_in_lobby(&self, mut handler: StreamHandler, name: String) {
+        loop {
+            /* The player is only told the game is starting from this thread, so that nothing
+             * they send after it is read here. */
+            let status = self.lobby.status_of(&name);
+            if !matches!(status, LobbyStatus::Waiting) {
+                let response = Response::from_lobby(self.lobby.state_for(&name));
+                if let Err(err) = handler.send_response(&response) {
+                    perror_in_fn("wait_in_lobby", handler.peer(), err);
+                }
+                if matches!(status, LobbyStatus::Starting) {
+                    let _ = self.handlers.send((name, handler));
+                } else {
+                    self.chat.leave(&name);
+                }
+                return;
+            }
+
+            match handler.await_message(Some(LOBBY_POLL_INTERVAL)) {
+                Ok(Message::Response(response)) => match response.into_status() {
+                    Ok(status) => self
+                        .lobby
+                        .set_ready(&name, matches!(status, StatusType::Yes)),
+                    Err(err) => perror_in_fn("wait_in_lobby", handler.peer(), err),
+                },
+                Ok(Message::Request(request)) => logging::warn(
+                    handler.peer(),
+                    &format!("Ignored request '{request}' in the lobby"),
+                ),
+                Err(KingdomError::TimedOut) => {}
+                Err(err) => {
+                    perror_in_fn("wait_in_lobby", handler.peer(), err);
+                    /* Once the game has started, the player keeps their seat. */
+                    if self.lobby.leave(&name) {
+                        self.chat.leave(&name);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Accepts every client that connects to the server, and keeps track of every room on it.
+/// Rooms are removed once their game is over, or once they are abandoned before it starts.
+pub struct RoomManager {
+    listener: TcpListener,
+    rooms: Mutex<Vec<Arc<Room>>>,
+    /// Profiles of the players in every room, if any are being kept.
+    profiles: Option<ProfileStore>,
+    /// Directory that every room's game logs and saves are written to, if any.
+    data_dir: Option<PathBuf>,
+}
+
+impl RoomManager {
+    /// Binds the server to `address`, with no rooms. Every room's games are recorded in
+    /// `profiles`, if there are any, and their game logs and saves are written to `data_dir`, if
+    /// there is one.
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if it is unable to bind to `address`.
+    pub fn bind(
+        address: &str,
+        profiles: Option<ProfileStore>,
+        data_dir: Option<PathBuf>,
+    ) -> error::Result<Arc<RoomManager>> {
+        let listener = TcpListener::bind(address)
+            .map_err(|_| KingdomError::FailedToConnect(address.to_owned()))?;
+        Ok(Arc::new(RoomManager {
+            listener,
+            rooms: Mutex::default(),
+            profiles,
+            data_dir,
+        }))
+    }
+
+    /// Returns the profiles of the players in every room, if any are being kept.
+    pub fn profiles(&self) -> Option<&ProfileStore> {
+        self.profiles.as_ref()
+    }
+
+    /// Returns the directory that every room's game logs and saves are written to, if any.
+    pub fn data_dir(&self) -> Option<&Path> {
+        self.data_dir.as_deref()
+    }
+
+    /// Creates a room named `name` with `lobby`, and gives it a new join code. Returns the room,
+    /// and where the stream of every player in it is sent once its game starts.
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if `MAX_ROOMS` rooms are already open, or if no join
+    /// code could be found for the room.
+    pub fn create_room(
+        &self,
+        name: &str,
+        lobby: Lobby,
+    ) -> error::Result<(Arc<Room>, RoomHandlers)> {
+        let mut rooms = self.rooms.lock().unwrap();
+        if rooms.len() >= MAX_ROOMS {
+            return Err(KingdomError::TooManyRooms);
+        }
+        let (handlers, receiver) = mpsc::channel();
+        let room = Arc::new(Room {
+            code: RoomManager::new_code(&rooms)?,
+            name: name.to_owned(),
+            lobby,
+            chat: Arc::default(),
+            handlers,
+            monitor: GameMonitor::default(),
+        });
+        rooms.push(Arc::clone(&room));
+        Ok((room, receiver))
+    }
+
+    /// Removes the room with the join code `code`, so that it can no longer be joined or listed.
+    pub fn close(&self, code: &str) {
+        let mut rooms = self.rooms.lock().unwrap();
+        if let Some(index) = rooms.iter().position(|room| room.code == code) {
+            let room = rooms.remove(index);
+            println!("Closed room '{}' ({}).", room.name, room.code);
+        }
+    }
+
+    /// Returns every room that can still be joined.
+    pub fn open_rooms(&self) -> Vec<RoomInfo> {
+        self.rooms
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|room| room.lobby.is_open())
+            .map(|room| room.info())
+            .collect()
+    }
+
+    /// Returns every room on the server, including rooms that are playing.
+    pub fn rooms(&self) -> Vec<Arc<Room>> {
+        self.rooms.lock().unwrap().clone()
+    }
+
+    /// Prints every room on the server for the host, including rooms that are playing.
+    pub fn print(&self) {
+        let rooms = self.rooms.lock().unwrap();
+        println!("Rooms ({}):", rooms.len());
+        for room in rooms.iter() {
+            let info = room.info();
+            println!(
+                "  {}  {:<25} {}/{} seats taken, {}",
+                info.code,
+                info.name,
+                info.players,
+                info.seats,
+                room.monitor.status()
+            );
+        }
+    }
+
+    /// Starts a new thread that accepts every client that connects, and lets them choose a room
+    /// on a thread of their own. Returns the address that was bound (e.g. to find the port when
+    /// binding port 0).
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if the listener can't be shared with the new thread.
+    pub fn accept(self: &Arc<Self>) -> error::Result<SocketAddr> {
+        let listener = self.listener.try_clone()?;
+        let local_addr = listener.local_addr()?;
+        println!("Accepting players...");
+        self.accept_with(listener, |connection| Ok(StreamHandler::new(connection)));
+        Ok(local_addr)
+    }
+
+    /// Binds to `address`, then starts a new thread that accepts every client that connects to it
+    /// over WebSocket. Once the handshake is done, they choose a room the same as every other
+    /// client. Returns the address that was bound (e.g. to find the port when binding port 0).
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if it is unable to bind to `address`.
+    pub fn accept_websockets(self: &Arc<Self>, address: &str) -> error::Result<SocketAddr> {
+        let listener = TcpListener::bind(address)
+            .map_err(|_| KingdomError::FailedToConnect(address.to_owned()))?;
+        let local_addr = listener.local_addr()?;
+        println!("Accepting players over WebSocket at ws://{local_addr}/");
+        self.accept_with(listener, websocket::accept);
+        Ok(local_addr)
+    }
+
+    /// Accepts every client that connects to `listener` on a new thread, and lets each of them
+    /// choose a room on a thread of their own, once `connect` has made a handler for them.
+    fn accept_with(
+        self: &Arc<Self>,
+        listener: TcpListener,
+        connect: fn(TcpStream) -> error::Result<StreamHandler>,
+    ) {
+        let rooms = Arc::clone(self);
+        thread::spawn(move || {
+            for connection in listener.incoming() {
+                let connection = match connection {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        perror_in_fn("RoomManager::accept", "listener", err);
+                        continue;
+                    }
+                };
+                let rooms = Arc::clone(&rooms);
+                thread::spawn(move || {
+                    let mut handler = match connect(connection) {
+                        Ok(handler) => handler,
+                        Err(err) => {
+                            perror_in_fn("RoomManager::accept", "listener", err);
+                            return;
+                        }
+                    };
+                    if let Some(room) = rooms.choose_room(&mut handler) {
+                        room.admit(handler);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Asks the client what they want to do until they have created or chosen a room to join.
+    /// Returns `None` if the client disconnects first.
+    fn choose_room(self: &Arc<Self>, handler: &mut StreamHandler) -> Option<Arc<Room>> {
+        loop {
+            let status = handler
+                .send_request_await_response(ROOM_REQUEST, ROOM_RESPONSE)
+                .and_then(Response::into_room);
+            let choice = match status {
+                Ok(choice) => choice,
+                Err(err) => {
+                    perror_in_fn("choose_room", handler.peer(), err);
+                    return None;
+                }
+            };
+            let (room, rooms) = match choice {
+                RoomChoice::List => (None, self.open_rooms()),
+                RoomChoice::Create { name, rules } => {
+                    let room = self.create_player_room(handler.peer(), &name, &rules);
+                    let rooms = room.iter().map(|room| room.info()).collect();
+                    (room, rooms)
+                }
+                RoomChoice::Join(code) => {
+                    let room = self.room_by_code(&code);
+                    let rooms = room.iter().map(|room| room.info()).collect();
+                    (room, rooms)
+                }
+            };
+            /* The room that was created or joined is sent on its own, and an empty list means
+             * it couldn't be. */
+            let response = Response::from_rooms(rooms);
+            if let Err(err) = handler.await_request_send_response(ROOMS_REQUEST, &response) {
+                perror_in_fn("choose_room", handler.peer(), err);
+                return None;
+            }
+            if room.is_some() {
+                return room;
+            }
+        }
+    }
+
+    /// Creates a room named `name` for the player at `peer`, with `rules` changed from the
+    /// default rules, and starts its game on a new thread. Returns `None` if the name or rules
+    /// are invalid.
+    fn create_player_room(
+        self: &Arc<Self>,
+        peer: &str,
+        name: &str,
+        rules: &str,
+    ) -> Option<Arc<Room>> {
+        if let Err(err) = RoomManager::validate_name(name) {
+            perror_in_fn("create_player_room", peer, err);
+            return None;
+        }
+        let rules = match Rules::default().with_changes(rules) {
+            Ok(rules) => rules,
+            Err(err) => {
+                perror_in_fn("create_player_room", peer, err);
+                return None;
+            }
+        };
+        let room = match ServerInstance::open_player_room(self, name, rules) {
+            Ok(room) => room,
+            Err(err) => {
+                perror_in_fn("create_player_room", peer, err);
+                return None;
+            }
+        };
+        println!(
+            "'{peer}' created room '{name}' with join code {}.",
+            room.code
+        );
+        Some(room)
+    }
+
+    /// Returns the room with the join code `code`, if there is one.
+    pub fn room_by_code(&self, code: &str) -> Option<Arc<Room>> {
+        self.rooms
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|room| room.code == code)
+            .cloned()
+    }
+
+    /// Checks that a room name is made of only letters, numbers and spaces, so that it can be
+    /// sent in a list of rooms.
+    fn validate_name(name: &str) -> error::Result<()> {
+        let is_valid = !name.trim().is_empty()
+            && name.len() <= MAX_ROOM_NAME_LEN
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ');
+        if is_valid {
+            Ok(())
+        } else {
+            Err(KingdomError::InvalidRoomName(name.to_owned()))
+        }
+    }
+
+    /// Returns a random four digit join code that no other room has, trying at most
+    /// `NEW_CODE_ATTEMPTS` codes.
+    fn new_code(rooms: &[Arc<Room>]) -> error::Result<String> {
+        let mut rng = rand::thread_rng();
+        let num_codes = 10u32.pow(JOIN_CODE_LEN as u32);
+        (0..NEW_CODE_ATTEMPTS)
+            .map(|_| format!("{:0JOIN_CODE_LEN$}", rng.gen_range(0..num_codes)))
+            .find(|code| !rooms.iter().any(|room| room.code == *code))
+            .ok_or(KingdomError::TooManyRooms)
+    }
+}
@@ -0,0 +1,691 @@
+//! This module contains `ClientSession`, which plays one player's side of the protocol without
+//! reading from or printing to the terminal, so that any frontend can be built on it. The
+//! terminal client (`ClientInstance`) is one, and a GUI or web frontend can be another.
+//!
+//! A session is driven by calling `next_event` until the game is over. Each event says what
+//! changed, and the session keeps the player's cards, the game state, the rules and their view of
+//! the table up to date, which can be read at any time. When the host is waiting for this player
+//! (`SessionEvent::ActionRequested` or `SessionEvent::ReactionRequested`), they answer with
+//! `submit_action` or `respond_to_reaction` before asking for the next event.
+//!
+//! ```ignore
+//! let mut session = ClientSession::connect("127.0.0.1:5464")?;
+//! session.choose_room(RoomChoice::Join("1234".to_owned()))?;
+//! session.choose_name("Alice")?;
+//! session.handle().set_ready(true)?;
+//! loop {
+//!     match session.next_event()? {
+//!         SessionEvent::ActionRequested => {
+//!             session.end_turn()?;
+//!         }
+//!         SessionEvent::ReactionRequested(_) => session.respond_to_reaction(true)?,
+//!         SessionEvent::GameOver { .. } => break,
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use std::{net::TcpStream, str::FromStr};
+
+use crate::{
+    error::{self, KingdomError},
+    game::{
+        card::{Card, Play},
+        fair_deal::{self, Audit, DealMessage},
+        game_state::GameState,
+        player::Player,
+        rules::Rules,
+    },
+    logging,
+    utils::{perror_in_fn, variant_eq},
+};
+
+use super::{
+    chat::ChatMessage,
+    constants::{
+        ACTION_REQUEST, ACTION_RESPONSE, CARDS_REQUEST, CARDS_RESPONSE, DEAL_REQUEST,
+        DEAL_RESPONSE, GAME_STATE_REQUEST, GAME_STATE_RESPONSE, NAME_REQUEST, ROOMS_REQUEST,
+        ROOMS_RESPONSE, ROOM_REQUEST, RULES_REQUEST, RULES_RESPONSE, STATUS_REQUEST,
+        STATUS_RESPONSE, STATUS_RESPONSE_NO, STATUS_RESPONSE_YES, VIEW_REQUEST, VIEW_RESPONSE,
+    },
+    lobby::{Lobby, LobbyState, LobbyStatus},
+    request::RequestType,
+    response::{Action, ActionType, Response, ResponseType, StatusType},
+    rooms::{RoomChoice, RoomInfo},
+    view::{OwnCards, TableView},
+    Message, StreamHandler, StreamWriter,
+};
+
+/// Something that happened in the game, returned by `ClientSession::next_event`.
+#[derive(Debug)]
+pub enum SessionEvent {
+    /// The lobby changed (e.g. a player joined, or said they were ready). Once its status is
+    /// `Starting`, the next event is `GameStarted`. Once it is `Kicked`, the session is over.
+    Lobby(LobbyState),
+    /// The host changed the rules in the lobby.
+    Rules(Rules),
+    /// The game started, and the game state, rules and this player's cards have been received.
+    GameStarted,
+    /// `player`'s turn started, with `seconds` on the turn clock (0 if there is no turn clock).
+    /// This player's view of the table has been received.
+    TurnStarted { player: String, seconds: u16 },
+    /// The host is waiting for this player's next action (see `ClientSession::submit_action`).
+    ActionRequested,
+    /// An action the host sent was applied to the game state (e.g. another player's card, or
+    /// the end of a turn).
+    Action(Action),
+    /// This King was played against this player, and the host is waiting for them to say whether
+    /// they block it (see `ClientSession::respond_to_reaction`).
+    ReactionRequested(Action),
+    /// `winner` won the game. If the deal was fair (see `fair_deal`), `fair_deal` is the result
+    /// of checking it.
+    GameOver {
+        winner: String,
+        fair_deal: Option<error::Result<()>>,
+    },
+}
+
+/// What the host said about an action this player submitted.
+#[derive(Debug, PartialEq)]
+pub struct ActionResult {
+    /// Whether the host accepted the action. If it didn't, the player's cards were given back.
+    pub accepted: bool,
+    /// The cards drawn because of the action (e.g. for a Number).
+    pub drawn: Vec<Card>,
+}
+
+/// Where a session is in the protocol, which decides what it can be asked to do.
+#[derive(Debug)]
+enum Phase {
+    /// Choosing a room to join.
+    Room,
+    /// Choosing a username.
+    Name,
+    /// Waiting in the lobby for the game to start.
+    Lobby,
+    /// The game is starting, and the game state, rules and cards haven't been received yet.
+    Starting,
+    /// Waiting for the next turn to start, or for the game to end.
+    BetweenTurns,
+    /// Playing this player's turn.
+    OwnTurn,
+    /// The host is waiting for this player's action.
+    ActionRequested,
+    /// Watching another player's turn.
+    OtherTurn,
+    /// The host is waiting for this player's reaction to a King played by the named player.
+    ReactionRequested(String),
+    /// The game is over, or this player was kicked.
+    Over,
+}
+
+impl Phase {
+    /// Describes what a session in this phase is waiting for, for `KingdomError::OutOfOrder`.
+    fn waiting_for(&self) -> &'static str {
+        match self {
+            Phase::Room => "a room to be chosen",
+            Phase::Name => "a username to be chosen",
+            Phase::ActionRequested => "an action to be submitted",
+            Phase::ReactionRequested(_) => "a reaction to the King",
+            Phase::Over => "nothing, since the game is over",
+            _ => "the next event",
+        }
+    }
+}
+
+/// A handle for sending chat and saying whether the player is ready, which can be used from any
+/// thread while the session is waiting for the next event.
+#[derive(Clone)]
+pub struct SessionHandle {
+    writer: StreamWriter,
+    name: String,
+}
+
+impl SessionHandle {
+    /// Sends `message` to the room's chat.
+    pub fn send_chat(&self, message: ChatMessage) -> error::Result<()> {
+        self.writer.send_chat(message)
+    }
+
+    /// Sends the chat command `line` (e.g. `/say hi` or `/w Bob hi`) as this player. Returns
+    /// `KingdomError::InvalidCommand` if it isn't a chat command.
+    pub fn send_chat_command(&self, line: &str) -> error::Result<()> {
+        match ChatMessage::from_command(&self.name, line) {
+            Some(message) => self.send_chat(message),
+            None => Err(KingdomError::InvalidCommand(format!(
+                "'{line}' is not a chat command"
+            ))),
+        }
+    }
+
+    /// Tells the host whether this player is ready to start, which only matters in the lobby.
+    pub fn set_ready(&self, ready: bool) -> error::Result<()> {
+        let status = if ready {
+            STATUS_RESPONSE_YES
+        } else {
+            STATUS_RESPONSE_NO
+        };
+        self.writer.write_line(&status.to_string())
+    }
+}
+
+/// One player's connection to a game (see the module documentation).
+pub struct ClientSession {
+    handler: StreamHandler,
+    phase: Phase,
+    /// This player's hand and discard pile. Their deck stays with the host, so it is empty here.
+    player: Player,
+    /// How many cards are in this player's deck.
+    deck_size: usize,
+    game_state: GameState,
+    rules: Rules,
+    /// What this player was shown of everyone's cards at the start of the turn.
+    view: TableView,
+    /// What this player keeps to check the deal once the game is over, if the deal is fair.
+    audit: Option<Audit>,
+    /// A forced `TurnEnd` that arrived in place of the answer to an action, which is the next
+    /// event.
+    forced_turn_end: Option<Action>,
+}
+
+impl ClientSession {
+    /// Connects to the host at `address`.
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if it is unable to connect.
+    pub fn connect(address: &str) -> error::Result<ClientSession> {
+        let stream = TcpStream::connect(address)
+            .map_err(|_| KingdomError::FailedToConnect(address.to_owned()))?;
+        Ok(ClientSession::new(StreamHandler::new(stream)))
+    }
+
+    /// Starts a session over a stream that is already connected to the host.
+    pub fn new(handler: StreamHandler) -> ClientSession {
+        ClientSession {
+            handler,
+            phase: Phase::Room,
+            player: Player::new(),
+            deck_size: 0,
+            game_state: GameState::new(),
+            rules: Rules::default(),
+            view: TableView::default(),
+            audit: None,
+            forced_turn_end: None,
+        }
+    }
+
+    /// Returns this player, with their name and cards.
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    /// Returns this player, so that cards can be moved out of their hand while an action is
+    /// staged (see `StagedAction`). The host's copy of their cards replaces them once it answers.
+    pub fn player_mut(&mut self) -> &mut Player {
+        &mut self.player
+    }
+
+    /// Returns how many cards are in this player's deck, as of the last time the host sent their
+    /// cards.
+    pub fn deck_size(&self) -> usize {
+        self.deck_size
+    }
+
+    /// Returns the game state, with every action the host has sent applied to it.
+    pub fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+
+    /// Returns the rules of the game.
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Returns what this player was shown of everyone's cards at the start of the turn.
+    pub fn view(&self) -> &TableView {
+        &self.view
+    }
+
+    /// Returns true if the deal will be checked once the game is over. A fair deal can't be
+    /// checked when the game was resumed from a save.
+    pub fn audits_deal(&self) -> bool {
+        self.audit.is_some()
+    }
+
+    /// Returns true once the game is over, or this player was kicked.
+    pub fn is_over(&self) -> bool {
+        matches!(self.phase, Phase::Over)
+    }
+
+    /// Returns a handle for sending chat and saying whether the player is ready from other
+    /// threads. The handle sends as the name the player has at the time.
+    pub fn handle(&self) -> SessionHandle {
+        SessionHandle {
+            writer: self.handler.writer(),
+            name: self.player.name().to_owned(),
+        }
+    }
+
+    /// Sets the hook that every chat message from the room is passed to. The hook is called from
+    /// the thread that reads the stream, so it is called even while `next_event` is waiting.
+    pub fn on_chat(&self, hook: impl Fn(ChatMessage) + Send + 'static) {
+        self.handler.on_chat(hook);
+    }
+
+    /// Sets the hook that is called as soon as the host ends this player's turn for them (e.g.
+    /// when the turn clock runs out, or the host skips the turn), even while `next_event` or
+    /// `submit_action` isn't waiting. A prompt for the player's next action can then stop
+    /// waiting, and the `TurnEnd` is still received as usual.
+    pub fn on_forced_turn_end(&self, hook: impl Fn() + Send + 'static) {
+        let name = self.player.name().to_owned();
+        self.handler.on_line(move |line| {
+            let forced = Response::from_str(line)
+                .ok()
+                .and_then(|response| response.into_action().ok());
+            if forced.is_some_and(|action| {
+                variant_eq(action.action_type(), &ActionType::TurnEnd)
+                    && action.from_player() == name
+            }) {
+                hook();
+            }
+        });
+    }
+
+    /// Returns an error unless the session is in `phase`.
+    fn expect_phase(&self, phase: Phase) -> error::Result<()> {
+        if variant_eq(&self.phase, &phase) {
+            Ok(())
+        } else {
+            Err(KingdomError::OutOfOrder(
+                self.phase.waiting_for().to_owned(),
+            ))
+        }
+    }
+
+    /// Lists the open rooms, creates a room, or joins a room with its join code. Returns the
+    /// open rooms for `RoomChoice::List`, or else the room that was entered, or nothing if it
+    /// couldn't be. Once a room is entered, a username has to be chosen (see `choose_name`).
+    ///
+    /// ## Returns
+    ///
+    /// This function will return `KingdomError::RoomFull` if the room was entered but has no
+    /// empty seats, after which the host closes the connection.
+    pub fn choose_room(&mut self, choice: RoomChoice) -> error::Result<Vec<RoomInfo>> {
+        self.expect_phase(Phase::Room)?;
+        let is_list = matches!(choice, RoomChoice::List);
+        let response = Response::from_room(choice);
+        self.handler
+            .await_request_send_response(ROOM_REQUEST, &response)?;
+        let rooms = self
+            .handler
+            .send_request_await_response(ROOMS_REQUEST, ROOMS_RESPONSE)?
+            .into_rooms()?;
+        let Some(room) = rooms.first().filter(|_| !is_list) else {
+            return Ok(rooms);
+        };
+        let status = self
+            .handler
+            .send_request_await_response(STATUS_REQUEST, STATUS_RESPONSE)?
+            .into_status()?;
+        match status {
+            StatusType::Yes => {
+                self.phase = Phase::Name;
+                Ok(rooms)
+            }
+            StatusType::No => {
+                self.phase = Phase::Over;
+                Err(KingdomError::RoomFull(room.name().to_owned()))
+            }
+        }
+    }
+
+    /// Checks that `name` is a username the host accepts (see `Lobby::validate_name`).
+    pub fn validate_name(name: &str) -> error::Result<()> {
+        Lobby::validate_name(name)
+    }
+
+    /// Asks the host to seat this player in the room's lobby as `name`. Returns false if the host
+    /// rejected the name (e.g. because it is taken), in which case another can be tried. Once it
+    /// is accepted, the session waits in the lobby.
+    pub fn choose_name(&mut self, name: &str) -> error::Result<bool> {
+        self.expect_phase(Phase::Name)?;
+        ClientSession::validate_name(name)?;
+        let response = Response::from_name(name.to_owned());
+        self.handler
+            .await_request_send_response(NAME_REQUEST, &response)?;
+        let status = self
+            .handler
+            .send_request_await_response(STATUS_REQUEST, STATUS_RESPONSE)?
+            .into_status()?;
+        let is_accepted = matches!(status, StatusType::Yes);
+        if is_accepted {
+            self.player.set_name(name.to_owned());
+            self.phase = Phase::Lobby;
+        }
+        Ok(is_accepted)
+    }
+
+    /// Waits for the next thing that happens in the game (see `SessionEvent`).
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if the connection is lost, or if the host is waiting
+    /// for this player to choose a room or name, submit an action or react to a King.
+    pub fn next_event(&mut self) -> error::Result<SessionEvent> {
+        if let Some(action) = self.forced_turn_end.take() {
+            return Ok(SessionEvent::Action(action));
+        }
+        match self.phase {
+            Phase::Lobby => self.await_lobby_event(),
+            Phase::Starting => self.start_game(),
+            Phase::BetweenTurns => self.await_turn(),
+            Phase::OwnTurn => self.await_own_turn_event(),
+            Phase::OtherTurn => self.await_other_turn_event(),
+            _ => Err(KingdomError::OutOfOrder(
+                self.phase.waiting_for().to_owned(),
+            )),
+        }
+    }
+
+    /// Waits for the lobby or the rules to change.
+    fn await_lobby_event(&mut self) -> error::Result<SessionEvent> {
+        loop {
+            let response = match self.handler.await_message(None)? {
+                Message::Response(response) => response,
+                Message::Request(request) => {
+                    logging::warn(
+                        self.handler.peer(),
+                        &format!("Ignored request '{request}' in the lobby"),
+                    );
+                    continue;
+                }
+            };
+            if variant_eq(response.response_type(), RULES_RESPONSE.response_type()) {
+                self.rules = response.into_rules()?;
+                break Ok(SessionEvent::Rules(self.rules.clone()));
+            }
+            let lobby = response.into_lobby()?;
+            match lobby.status() {
+                LobbyStatus::Waiting => {}
+                LobbyStatus::Starting => self.phase = Phase::Starting,
+                LobbyStatus::Kicked => self.phase = Phase::Over,
+            }
+            break Ok(SessionEvent::Lobby(lobby));
+        }
+    }
+
+    /// Requests the game state, the rules and this player's cards, and starts the fair deal if
+    /// the rules ask for one.
+    fn start_game(&mut self) -> error::Result<SessionEvent> {
+        self.game_state = self
+            .handler
+            .send_request_await_response(GAME_STATE_REQUEST, GAME_STATE_RESPONSE)?
+            .into_game_state()?;
+        self.rules = self
+            .handler
+            .send_request_await_response(RULES_REQUEST, RULES_RESPONSE)?
+            .into_rules()?;
+        if self.rules.fair_deal {
+            self.start_fair_deal()?;
+        }
+        let cards = self
+            .handler
+            .send_request_await_response(CARDS_REQUEST, CARDS_RESPONSE)?
+            .into_cards()?;
+        if let Some(cards) = cards {
+            if let Some(audit) = &mut self.audit {
+                audit.set_dealt(cards.hand.clone());
+            }
+            self.set_cards(cards);
+        }
+        self.phase = Phase::BetweenTurns;
+        Ok(SessionEvent::GameStarted)
+    }
+
+    /// Requests the host's commitment to every player's deck, then sends the host this player's
+    /// entropy, so that the deal can be checked once the game is over (see `fair_deal`).
+    fn start_fair_deal(&mut self) -> error::Result<()> {
+        let message = self
+            .handler
+            .send_request_await_response(DEAL_REQUEST, DEAL_RESPONSE)?
+            .into_deal()?;
+        let DealMessage::Commit(commitments) = message else {
+            return Err(KingdomError::ExpectedResponseType(ResponseType::Deal(None)));
+        };
+        /* A resumed game was dealt before it was saved, so there is nothing to check. */
+        if commitments.is_empty() {
+            return Ok(());
+        }
+        let entropy = fair_deal::random_hex(&mut rand::thread_rng());
+        let response = Response::from_deal(DealMessage::Entropy(entropy.clone()));
+        self.handler
+            .await_request_send_response(DEAL_REQUEST, &response)?;
+        self.audit = Some(Audit::new(commitments, entropy));
+        Ok(())
+    }
+
+    /// Replaces this player's cards with what the host sent of them.
+    fn set_cards(&mut self, cards: OwnCards) {
+        self.deck_size = cards.deck_size;
+        self.player.set_cards(cards.into_player_cards());
+    }
+
+    /// Requests the decks that the host committed to, and checks that this player was dealt
+    /// what they should have been. Returns `None` if the deal isn't being checked.
+    fn check_fair_deal(&mut self) -> error::Result<Option<error::Result<()>>> {
+        let Some(audit) = self.audit.take() else {
+            return Ok(None);
+        };
+        let message = self
+            .handler
+            .send_request_await_response(DEAL_REQUEST, DEAL_RESPONSE)?
+            .into_deal()?;
+        let DealMessage::Reveal(reveal) = message else {
+            return Err(KingdomError::ExpectedResponseType(ResponseType::Deal(None)));
+        };
+        Ok(Some(audit.check(
+            self.player.name(),
+            &reveal,
+            self.rules.hand_size,
+        )))
+    }
+
+    /// Waits for the next turn to start, or for the game to end. A forced `TurnEnd` that arrives
+    /// after this player already ended their turn is skipped.
+    fn await_turn(&mut self) -> error::Result<SessionEvent> {
+        self.handler.send_request(ACTION_REQUEST)?;
+        let action = loop {
+            let action = self
+                .handler
+                .await_response(ACTION_RESPONSE)?
+                .into_action()?;
+            match action.action_type() {
+                ActionType::TurnStart | ActionType::GameOver => break action,
+                ActionType::TurnEnd => logging::warn(
+                    self.handler.peer(),
+                    &format!("Skipped late TurnEnd for {}", action.from_player()),
+                ),
+                other => return Err(KingdomError::InvalidAction(other.to_owned())),
+            }
+        };
+        if variant_eq(action.action_type(), &ActionType::GameOver) {
+            let fair_deal = self.check_fair_deal()?;
+            self.phase = Phase::Over;
+            return Ok(SessionEvent::GameOver {
+                winner: action.from_player().to_owned(),
+                fair_deal,
+            });
+        }
+        self.view = self
+            .handler
+            .send_request_await_response(VIEW_REQUEST, VIEW_RESPONSE)?
+            .into_view()?;
+        /* Turn player is stored as "from_player" in Action, and their seconds as the
+         * attachment. */
+        let player = action.from_player().to_owned();
+        self.phase = if player == self.player.name() {
+            Phase::OwnTurn
+        } else {
+            Phase::OtherTurn
+        };
+        Ok(SessionEvent::TurnStarted {
+            player,
+            seconds: action.attachment(),
+        })
+    }
+
+    /// Waits for the host to ask for this player's next action. Actions the host sends without
+    /// being asked, such as a Jack played against this player's King or a forced `TurnEnd` when
+    /// the turn clock runs out, are applied as they arrive.
+    fn await_own_turn_event(&mut self) -> error::Result<SessionEvent> {
+        match self.handler.await_message(None)? {
+            Message::Request(request) => {
+                if !variant_eq(request.request_type(), &RequestType::PlayerAction) {
+                    return Err(KingdomError::ExpectedRequestType(RequestType::PlayerAction));
+                }
+                self.phase = Phase::ActionRequested;
+                Ok(SessionEvent::ActionRequested)
+            }
+            Message::Response(response) => {
+                let action = response.into_action()?;
+                if variant_eq(action.action_type(), &ActionType::TurnEnd) {
+                    self.phase = Phase::BetweenTurns;
+                }
+                self.apply("await_own_turn_event", &action);
+                Ok(SessionEvent::Action(action))
+            }
+        }
+    }
+
+    /// Requests the next action of another player's turn and applies it. If it is a King played
+    /// against this player, the host then waits for their reaction.
+    fn await_other_turn_event(&mut self) -> error::Result<SessionEvent> {
+        let action = self
+            .handler
+            .send_request_await_response(ACTION_REQUEST, ACTION_RESPONSE)?
+            .into_action()?;
+        self.apply("await_other_turn_event", &action);
+        let name = self.player.name();
+        match action.action_type() {
+            ActionType::PlayKing if action.to_player() == name && action.from_player() != name => {
+                self.handler.await_request(ACTION_REQUEST)?;
+                self.phase = Phase::ReactionRequested(action.from_player().to_owned());
+                return Ok(SessionEvent::ReactionRequested(action));
+            }
+            /* The Jack is only discarded once the host has accepted it. */
+            ActionType::PlayJack if action.from_player() == name => {
+                self.player.discard_jack();
+            }
+            ActionType::TurnEnd => self.phase = Phase::BetweenTurns,
+            _ => {}
+        }
+        Ok(SessionEvent::Action(action))
+    }
+
+    /// Applies `action` from the host to the game state. Since the host has already accepted it,
+    /// an error only means the copy of the game state is out of step, so it is logged.
+    fn apply(&mut self, fn_name: &str, action: &Action) {
+        if let Err(err) = self.game_state.apply_action(action, &self.rules) {
+            perror_in_fn(fn_name, self.handler.peer(), err);
+        }
+    }
+
+    /// Sends `action` as this player's next action, after `SessionEvent::ActionRequested`. The
+    /// cards it uses should already be out of the player's hand (e.g. with `StagedAction`).
+    /// Once the host answers, the player's cards are replaced with the host's copy, and an
+    /// accepted action is applied to the game state. A `TurnEnd` ends the turn, and so does a
+    /// forced `TurnEnd` from the host that arrives before the answer, in which case the action
+    /// isn't accepted, its cards go back in the hand and the `TurnEnd` is the next event.
+    pub fn submit_action(&mut self, action: Action) -> error::Result<ActionResult> {
+        self.expect_phase(Phase::ActionRequested)?;
+        let is_turn_end = variant_eq(action.action_type(), &ActionType::TurnEnd);
+        self.handler
+            .send_response(&Response::from_action(action.to_owned()))?;
+        let mut result = ActionResult {
+            accepted: true,
+            drawn: Vec::new(),
+        };
+        if is_turn_end {
+            self.phase = Phase::BetweenTurns;
+        } else {
+            self.phase = Phase::OwnTurn;
+            let hand = self.player.hand().clone();
+            let status = match self.handler.await_message(None)? {
+                Message::Response(response) => match response.response_type() {
+                    /* The host ended the turn before it got the action (e.g. the turn clock ran
+                     * out, or the host skipped the turn), so the action is never answered. */
+                    ResponseType::PlayerAction(Some(forced))
+                        if variant_eq(forced.action_type(), &ActionType::TurnEnd) =>
+                    {
+                        let forced = response.into_action()?;
+                        /* The host never took the cards, so they go back in the hand. */
+                        let mut cards = self.player.cards();
+                        if let Some(play) = action.play() {
+                            cards
+                                .hand
+                                .extend([Some(play.card), play.attachment].iter().flatten());
+                        }
+                        cards.hand.extend(action.discards());
+                        self.player.set_cards(cards);
+                        self.phase = Phase::BetweenTurns;
+                        self.apply("submit_action", &forced);
+                        self.forced_turn_end = Some(forced);
+                        result.accepted = false;
+                        return Ok(result);
+                    }
+                    _ => response.into_status()?,
+                },
+                Message::Request(_) => {
+                    return Err(KingdomError::ExpectedResponseType(ResponseType::Status(
+                        None,
+                    )))
+                }
+            };
+            let cards = self.handler.await_response(CARDS_RESPONSE)?.into_cards()?;
+            if let Some(cards) = cards {
+                self.set_cards(cards);
+            }
+            result.accepted = matches!(status, StatusType::Yes);
+            result.drawn = self
+                .player
+                .hand()
+                .iter()
+                .filter(|card| !hand.contains(card))
+                .copied()
+                .collect();
+            if let Some(audit) = &mut self.audit {
+                if !result.drawn.is_empty() {
+                    audit.record_draw(hand, result.drawn.clone());
+                }
+            }
+        }
+        if result.accepted {
+            self.apply("submit_action", &action);
+        }
+        Ok(result)
+    }
+
+    /// Ends this player's turn, after `SessionEvent::ActionRequested`.
+    pub fn end_turn(&mut self) -> error::Result<ActionResult> {
+        let action = Action::new_turn_end(self.player.name());
+        self.submit_action(action)
+    }
+
+    /// Answers `SessionEvent::ReactionRequested`. If `block` is true and the player has a Jack,
+    /// the King is blocked with it. Otherwise, the reaction is declined.
+    pub fn respond_to_reaction(&mut self, block: bool) -> error::Result<()> {
+        let Phase::ReactionRequested(king_player) = &self.phase else {
+            return Err(KingdomError::OutOfOrder(
+                self.phase.waiting_for().to_owned(),
+            ));
+        };
+        let name = self.player.name().to_owned();
+        let reaction = match self.player.jack().filter(|_| block) {
+            Some(jack) => Action::from_play(Play::new(jack, None), name, king_player.to_owned()),
+            None => Action::new(ActionType::None, 0, name, String::new()),
+        };
+        self.phase = Phase::OtherTurn;
+        self.handler.send_response(&Response::from_action(reaction))
+    }
+}
@@ -2,8 +2,7 @@
 //! For the most part, this includes functions that accept and validate user
 //! input.  
 
-
-use crate::ui::get_num_input;
+use crate::ui::input::InputSource;
 
 /// Whether the user wants to be a server or client.
 pub enum Mode {
@@ -11,12 +10,11 @@ pub enum Mode {
     ConnectGame,
 }
 
-/// Prompt player to choose whether to start server or connect to server.
-pub fn choose_mode() -> Mode {
+/// Prompt player to choose whether to start server or connect to server, answered from `input`.
+pub fn choose_mode(input: &mut dyn InputSource) -> Mode {
     println!("1. Host a game");
     println!("2. Join a game\n");
-    let input = get_num_input("Choose an option [1 or 2]: ", 1, 2);
-    match input {
+    match input.get_num_input("Choose an option [1 or 2]: ", 1, 2) {
         1 => Mode::HostGame,
         2 => Mode::ConnectGame,
         /* get_num_input() ensures that num is between 1 and 2 */
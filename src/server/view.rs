@@ -0,0 +1,235 @@
+//! This module contains the views of the table that the host sends out, which only show what
+//! whoever they are sent to is allowed to know. A player's own hand is shown in full, while every
+//! other hand only shows how many cards are in it. Discard piles are face up, so everyone sees
+//! them, and nobody is shown the order of any deck, including their own. Players are sent their
+//! own cards as `OwnCards`, which only says how many cards are in their deck.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::game::{card::Card, player::PlayerCards};
+
+use super::response::{Action, ResponseParseError};
+
+/// Who a message from the host is being sent to.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Viewer {
+    /// A seated player, who can see their own hand.
+    Player(String),
+    /// Someone watching the game, who can't see any hand.
+    Spectator,
+}
+
+impl Viewer {
+    /// Returns true if this viewer may see the hand of the player named `name`.
+    pub fn can_see_hand(&self, name: &str) -> bool {
+        matches!(self, Viewer::Player(viewer) if viewer == name)
+    }
+}
+
+/// What a viewer is shown of one player's cards.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SeatView {
+    pub name: String,
+    /// The cards in the player's hand, if the viewer may see them.
+    pub hand: Option<Vec<Card>>,
+    pub hand_size: usize,
+    pub deck_size: usize,
+    /// Every card the player has played or discarded, which everyone can see.
+    pub discard_pile: Vec<Card>,
+}
+
+/// What a player is shown of their own cards: their hand, their discard pile and how many cards
+/// are in their deck. The order of the deck stays with the host.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct OwnCards {
+    pub deck_size: usize,
+    pub hand: Vec<Card>,
+    pub discard_pile: Vec<Card>,
+}
+
+impl OwnCards {
+    /// Returns what the owner of `cards` may see of them.
+    pub fn of(cards: &PlayerCards) -> OwnCards {
+        OwnCards {
+            deck_size: cards.deck.len(),
+            hand: cards.hand.clone(),
+            discard_pile: cards.discard_pile.clone(),
+        }
+    }
+
+    /// Returns the owner's hand and discard pile as `PlayerCards`, with an empty deck, since the
+    /// owner never knows which cards are in it.
+    pub fn into_player_cards(self) -> PlayerCards {
+        PlayerCards {
+            deck: Vec::new(),
+            hand: self.hand,
+            discard_pile: self.discard_pile,
+        }
+    }
+}
+
+/// What a viewer is shown of every player's cards, in seat order.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct TableView {
+    seats: Vec<SeatView>,
+}
+
+impl TableView {
+    /// Builds the view that `viewer` is allowed to see from every player's cards as the host
+    /// keeps them, where `cards` is each player's name and cards in seat order.
+    pub fn build(cards: &[(String, PlayerCards)], viewer: &Viewer) -> TableView {
+        let seats = cards
+            .iter()
+            .map(|(name, cards)| SeatView {
+                name: name.clone(),
+                hand: viewer.can_see_hand(name).then(|| cards.hand.clone()),
+                hand_size: cards.hand.len(),
+                deck_size: cards.deck.len(),
+                discard_pile: cards.discard_pile.clone(),
+            })
+            .collect();
+        TableView { seats }
+    }
+
+    pub fn seats(&self) -> &[SeatView] {
+        &self.seats
+    }
+
+    /// Returns what is shown of the cards of the player named `name`, if they have a seat.
+    pub fn seat(&self, name: &str) -> Option<&SeatView> {
+        self.seats.iter().find(|seat| seat.name == name)
+    }
+}
+
+/// Returns the parts of `action` that every player may see, so that it can be sent to anyone.
+/// Fields are copied one by one rather than cloning the action, so anything private that is
+/// added to actions later stays with the host unless it is copied here. The cards that were
+/// played and discarded are copied, since they go face up on the discard pile.
+pub fn public_action(action: &Action) -> Action {
+    let mut public = Action::new(
+        action.action_type().to_owned(),
+        action.attachment(),
+        action.from_player().to_owned(),
+        action.to_player().to_owned(),
+    );
+    if let Some(play) = action.play() {
+        public = public.with_play(*play);
+    }
+    public.with_discards(action.discards().to_vec())
+}
+
+/// Returns the parts of `action` that every player may see when it was rejected: who played
+/// what kind of action against whom. Its cards and attachment are left out, since cards that
+/// are rejected stay hidden in the player's hand.
+pub fn rejected_action(action: &Action) -> Action {
+    Action::new(
+        action.action_type().to_owned(),
+        0,
+        action.from_player().to_owned(),
+        action.to_player().to_owned(),
+    )
+}
+
+/// Writes `cards` as a space-separated list of card codes.
+fn write_cards(f: &mut std::fmt::Formatter<'_>, cards: &[Card]) -> std::fmt::Result {
+    let codes: Vec<String> = cards.iter().map(Card::to_string).collect();
+    write!(f, "{}", codes.join(" "))
+}
+
+fn parse_cards(cards: &str) -> Result<Vec<Card>, ResponseParseError> {
+    cards
+        .split_whitespace()
+        .map(Card::from_str)
+        .collect::<Result<_, _>>()
+        .map_err(|_| ResponseParseError::InvalidView)
+}
+
+impl Display for SeatView {
+    /// Format: `{NAME}:{HAND_SIZE}:{DECK_SIZE}:{DISCARD_PILE}:{HAND}`, where each pile is a
+    /// space-separated list of card codes, and `HAND` is `?` if the viewer can't see it.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}:", self.name, self.hand_size, self.deck_size)?;
+        write_cards(f, &self.discard_pile)?;
+        write!(f, ":")?;
+        match &self.hand {
+            Some(hand) => write_cards(f, hand),
+            None => write!(f, "?"),
+        }
+    }
+}
+
+impl FromStr for SeatView {
+    type Err = ResponseParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(':').collect();
+        let [name, hand_size, deck_size, discard_pile, hand] = fields[..] else {
+            return Err(ResponseParseError::InvalidView);
+        };
+        Ok(SeatView {
+            name: name.to_owned(),
+            hand: match hand {
+                "?" => None,
+                hand => Some(parse_cards(hand)?),
+            },
+            hand_size: hand_size
+                .parse()
+                .map_err(|_| ResponseParseError::ParseIntError)?,
+            deck_size: deck_size
+                .parse()
+                .map_err(|_| ResponseParseError::ParseIntError)?,
+            discard_pile: parse_cards(discard_pile)?,
+        })
+    }
+}
+
+impl Display for OwnCards {
+    /// Format: `{DECK_SIZE},{HAND},{DISCARD_PILE}`, where each pile is a space-separated list of
+    /// card codes.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},", self.deck_size)?;
+        write_cards(f, &self.hand)?;
+        write!(f, ",")?;
+        write_cards(f, &self.discard_pile)
+    }
+}
+
+impl FromStr for OwnCards {
+    type Err = ResponseParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let piles: Vec<&str> = s.split(',').collect();
+        let [deck_size, hand, discard_pile] = piles[..] else {
+            return Err(ResponseParseError::InvalidCards);
+        };
+        let parse_pile = |pile| parse_cards(pile).map_err(|_| ResponseParseError::InvalidCards);
+        Ok(OwnCards {
+            deck_size: deck_size
+                .parse()
+                .map_err(|_| ResponseParseError::ParseIntError)?,
+            hand: parse_pile(hand)?,
+            discard_pile: parse_pile(discard_pile)?,
+        })
+    }
+}
+
+impl Display for TableView {
+    /// Format: `{SEAT_1},{SEAT_2}...` (see `SeatView`).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let seats: Vec<String> = self.seats.iter().map(SeatView::to_string).collect();
+        write!(f, "{}", seats.join(","))
+    }
+}
+
+impl FromStr for TableView {
+    type Err = ResponseParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seats = s
+            .split(',')
+            .filter(|seat| !seat.is_empty())
+            .map(SeatView::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(TableView { seats })
+    }
+}
@@ -0,0 +1,68 @@
+//! This module contains the web table, a small HTML and JavaScript client that the host serves
+//! over HTTP. The page is built into the binary, so nothing has to be installed to play from a
+//! browser. It connects to the host's WebSocket port and plays through the same protocol as the
+//! terminal client, showing the player's hand, their opponents, the game log and reaction
+//! prompts.
+
+use std::{net::SocketAddr, thread};
+
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    error::{self, KingdomError},
+    utils::perror_in_fn,
+};
+
+const INDEX_HTML: &str = include_str!("../../web/index.html");
+const TABLE_JS: &str = include_str!("../../web/table.js");
+const TABLE_CSS: &str = include_str!("../../web/table.css");
+
+/// Placeholder in `INDEX_HTML` that is replaced with the port the page connects to over
+/// WebSocket.
+const WEBSOCKET_PORT_PLACEHOLDER: &str = "{WEBSOCKET_PORT}";
+
+/// Binds to `address`, then serves the web table on a new thread. The page connects to the
+/// WebSocket port `websocket_port` on the same host it was loaded from. Returns the address that
+/// was bound (e.g. to find the port when binding port 0).
+///
+/// ## Returns
+///
+/// This function will return an error if it is unable to bind to `address`.
+pub fn serve(address: &str, websocket_port: u16) -> error::Result<SocketAddr> {
+    let server =
+        Server::http(address).map_err(|_| KingdomError::FailedToConnect(address.to_owned()))?;
+    let local_addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| KingdomError::FailedToConnect(address.to_owned()))?;
+    println!("Serving the web table at http://{local_addr}/");
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            respond(request, websocket_port);
+        }
+    });
+    Ok(local_addr)
+}
+
+/// Answers `request` with the file it asks for, or with 404 if there is no such file.
+fn respond(request: Request, websocket_port: u16) {
+    let path = request.url().split('?').next().unwrap_or_default();
+    let file = match (request.method(), path) {
+        (Method::Get, "/" | "/index.html") => Some((
+            INDEX_HTML.replace(WEBSOCKET_PORT_PLACEHOLDER, &websocket_port.to_string()),
+            "text/html; charset=utf-8",
+        )),
+        (Method::Get, "/table.js") => Some((TABLE_JS.to_owned(), "text/javascript; charset=utf-8")),
+        (Method::Get, "/table.css") => Some((TABLE_CSS.to_owned(), "text/css; charset=utf-8")),
+        _ => None,
+    };
+    let response = match file {
+        Some((body, content_type)) => Response::from_string(body).with_header(
+            Header::from_bytes("Content-Type", content_type).expect("header is valid"),
+        ),
+        None => Response::from_string("Not found").with_status_code(404),
+    };
+    if let Err(err) = request.respond(response) {
+        perror_in_fn("web::respond", "browser", err);
+    }
+}
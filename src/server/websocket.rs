@@ -0,0 +1,122 @@
+//! This module contains the WebSocket transport, which lets players connect from a browser. Every
+//! line of the protocol is sent as one text frame, without the newline, so once a WebSocket is
+//! connected it is wrapped in a `StreamHandler` and handled exactly like a TCP stream.
+
+use std::{
+    io::{self, ErrorKind},
+    net::TcpStream,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use tungstenite::{Message, WebSocket};
+
+use crate::error::{self, KingdomError};
+
+use super::{Inbox, StreamHandler, StreamWriter};
+
+/// How long the thread that owns a WebSocket waits for a frame before sending any lines that
+/// are waiting to be sent.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Completes the WebSocket handshake with a client that connected to the host's WebSocket port,
+/// then returns a handler for it.
+///
+/// ## Returns
+///
+/// This function will return an error if the client isn't making a WebSocket handshake.
+pub fn accept(stream: TcpStream) -> error::Result<StreamHandler> {
+    let peer = peer_name(&stream);
+    let socket = tungstenite::accept(stream)
+        .map_err(|err| KingdomError::WebSocket(format!("handshake with {peer} failed: {err}")))?;
+    start(socket, peer)
+}
+
+/// Connects to the host's WebSocket port at `address` (e.g. `127.0.0.1:5465`), then returns a
+/// handler for it. This is how a Rust client (e.g. a test) plays over WebSocket.
+///
+/// ## Returns
+///
+/// This function will return an error if it is unable to connect.
+pub fn connect(address: &str) -> error::Result<StreamHandler> {
+    let stream = TcpStream::connect(address)
+        .map_err(|_| KingdomError::FailedToConnect(address.to_owned()))?;
+    let peer = peer_name(&stream);
+    let (socket, _) = tungstenite::client(format!("ws://{address}/"), stream)
+        .map_err(|err| KingdomError::WebSocket(format!("handshake with {peer} failed: {err}")))?;
+    start(socket, peer)
+}
+
+fn peer_name(stream: &TcpStream) -> String {
+    match stream.peer_addr() {
+        Ok(addr) => format!("ws://{addr}"),
+        Err(_) => String::from("unknown"),
+    }
+}
+
+/// Starts the thread that owns `socket`, and returns a handler that reads and writes through it.
+fn start(socket: WebSocket<TcpStream>, peer: String) -> error::Result<StreamHandler> {
+    /* Reads time out, so that lines written from other threads are sent while waiting. */
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+    let (sender, outgoing) = mpsc::channel();
+    let (handler, inbox) = StreamHandler::with_writer(peer, StreamWriter::channel(sender));
+    thread::spawn(move || pump(socket, outgoing, inbox));
+    Ok(handler)
+}
+
+/// Sends every line in `outgoing` as a text frame, and delivers every line of every text frame
+/// received to `inbox`, until either side closes the connection. The connection is closed once
+/// a writer closes it, or once every writer for it has been dropped.
+fn pump(mut socket: WebSocket<TcpStream>, outgoing: Receiver<Option<String>>, inbox: Inbox) {
+    loop {
+        loop {
+            match outgoing.try_recv() {
+                Ok(Some(line)) => {
+                    if let Err(err) = socket.send(Message::text(line)) {
+                        inbox.deliver(Err(into_io_error(err)));
+                        return;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    let _ = socket.close(None).and_then(|()| socket.flush());
+                    return;
+                }
+            }
+        }
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                for line in text.lines() {
+                    if !inbox.deliver(Ok(line.to_owned())) {
+                        return;
+                    }
+                }
+            }
+            Ok(Message::Close(_)) => {
+                inbox.deliver(Err(ErrorKind::UnexpectedEof.into()));
+                return;
+            }
+            /* Pings are answered by tungstenite, and nothing else is part of the protocol. */
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(err) => {
+                inbox.deliver(Err(into_io_error(err)));
+                return;
+            }
+        }
+    }
+}
+
+/// Converts a WebSocket error to the `io::Error` that a TCP stream would have given, so that a
+/// closed WebSocket is treated as a disconnected peer.
+fn into_io_error(err: tungstenite::Error) -> io::Error {
+    match err {
+        tungstenite::Error::Io(err) => err,
+        tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => {
+            ErrorKind::UnexpectedEof.into()
+        }
+        err => io::Error::other(err.to_string()),
+    }
+}
@@ -0,0 +1,383 @@
+//! This module contains the seats that can be played at a tournament table: bots, which play
+//! one of a few simple strategies, and humans, who take turns at the terminal.
+
+use std::{fmt::Display, str::FromStr};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    error::KingdomError,
+    game::{
+        card::{Card, Play, Value},
+        game_state::{GameState, PlayerDetails},
+        player::Player,
+        rules::Rules,
+    },
+    server::{commentator::Commentator, response::Action},
+    ui::input::{InputSource, Terminal},
+};
+
+/// Something that plays one seat at a tournament table.
+pub trait Seat {
+    /// Plays the next card of the turn, moving every card it uses out of `player`'s hand. The
+    /// table checks the action against its own copy of the cards, and deals any cards drawn.
+    /// Returns `None` to end the turn.
+    fn next_action(
+        &mut self,
+        player: &mut Player,
+        game_state: &GameState,
+        rules: &Rules,
+    ) -> Option<Action>;
+
+    /// Returns true if `player` blocks `king` with a Jack. This is only asked if `player` has a
+    /// Jack, and the table discards it.
+    fn blocks(&mut self, player: &Player, king: &Action, game_state: &GameState) -> bool;
+
+    /// Returns true if someone is watching this seat, so the table should print every action.
+    fn is_watching(&self) -> bool {
+        false
+    }
+}
+
+/// How a bot chooses which cards to play.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Strategy {
+    /// Plays any card it can, or ends its turn, at random.
+    Random,
+    /// Plays Kings against whoever is closest to being eliminated, and only heals when it has
+    /// less than half of its starting points.
+    Aggressive,
+    /// Heals whenever it has lost points, and plays Kings against whoever has the most points.
+    Cautious,
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let strategy = match self {
+            Strategy::Random => "random",
+            Strategy::Aggressive => "aggressive",
+            Strategy::Cautious => "cautious",
+        };
+        write!(f, "{strategy}")
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = KingdomError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Strategy::Random),
+            "aggressive" => Ok(Strategy::Aggressive),
+            "cautious" => Ok(Strategy::Cautious),
+            _ => Err(KingdomError::InvalidTournament(format!(
+                "unknown strategy '{s}'"
+            ))),
+        }
+    }
+}
+
+/// A seat that is played by a strategy. Bots make their random choices with their own seeded
+/// random number generator, so the same seed always plays the same game.
+pub struct Bot {
+    strategy: Strategy,
+    rng: ChaCha8Rng,
+}
+
+impl Bot {
+    pub fn new(strategy: Strategy, seed: u64) -> Bot {
+        Bot {
+            strategy,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Plays a card at random from every card that can be played, including ending the turn.
+    fn random_action(&mut self, player: &mut Player, game_state: &GameState) -> Option<Action> {
+        let opponents = opponents(player, game_state);
+        let playable: Vec<Card> = player
+            .hand()
+            .iter()
+            .filter(|card| match card.value() {
+                Value::King => !opponents.is_empty(),
+                Value::Queen => true,
+                value => value
+                    .to_number_value()
+                    .is_some_and(|num_value| can_play_number(player, num_value)),
+            })
+            .copied()
+            .collect();
+        let index = self.rng.gen_range(0..=playable.len());
+        let card = *playable.get(index)?;
+        match card.value() {
+            Value::King => {
+                let target = opponents.choose(&mut self.rng)?.name().to_owned();
+                let with_attachment = self.rng.gen_bool(0.5);
+                Some(play_king_queen(player, card, target, with_attachment))
+            }
+            Value::Queen => {
+                let with_attachment = self.rng.gen_bool(0.5);
+                let name = player.name().to_owned();
+                Some(play_king_queen(player, card, name, with_attachment))
+            }
+            _ => play_number(player, card),
+        }
+    }
+
+    /// Plays a King against the opponent with the fewest points, or heals below half points.
+    /// When there are teams, opponents are only players on other teams, and teammates are healed
+    /// too.
+    fn aggressive_action(
+        &mut self,
+        player: &mut Player,
+        game_state: &GameState,
+        rules: &Rules,
+    ) -> Option<Action> {
+        let target = opponents(player, game_state)
+            .into_iter()
+            .min_by_key(|opponent| opponent.points())
+            .map(|opponent| opponent.name().to_owned());
+        if let (Some(king), Some(target)) = (find_card(player, Value::King), target) {
+            return Some(play_king_queen(player, king, target, true));
+        }
+        if let Some(action) = self.heal(player, game_state, rules.starting_points / 2) {
+            return Some(action);
+        }
+        self.dig_for_cards(player)
+    }
+
+    /// Heals whenever it has lost points, or plays a King against the opponent with the most
+    /// points.
+    fn cautious_action(
+        &mut self,
+        player: &mut Player,
+        game_state: &GameState,
+        rules: &Rules,
+    ) -> Option<Action> {
+        if let Some(action) = self.heal(player, game_state, rules.starting_points) {
+            return Some(action);
+        }
+        let target = opponents(player, game_state)
+            .into_iter()
+            .max_by_key(|opponent| opponent.points())
+            .map(|opponent| opponent.name().to_owned());
+        if let (Some(king), Some(target)) = (find_card(player, Value::King), target) {
+            return Some(play_king_queen(player, king, target, true));
+        }
+        self.dig_for_cards(player)
+    }
+
+    /// Plays a Queen on whichever of `player` and their teammates has the fewest points, if they
+    /// have fewer than `below` points.
+    fn heal(&mut self, player: &mut Player, game_state: &GameState, below: u16) -> Option<Action> {
+        let target = neediest_teammate(player, game_state)
+            .filter(|target| target.points() < below)?
+            .name()
+            .to_owned();
+        let queen = find_card(player, Value::Queen)?;
+        Some(play_king_queen(player, queen, target, true))
+    }
+
+    /// Plays the highest Number that can be played, to draw new cards, as long as there are
+    /// cards left to draw, counting the discard pile that is shuffled back in.
+    fn dig_for_cards(&mut self, player: &mut Player) -> Option<Action> {
+        if player.deck_size() == 0 && player.cards().discard_pile.is_empty() {
+            return None;
+        }
+        let number = player
+            .hand()
+            .iter()
+            .filter(|card| {
+                card.value()
+                    .to_number_value()
+                    .is_some_and(|num_value| can_play_number(player, num_value))
+            })
+            .max_by_key(|card| card.value().to_number_value())
+            .copied()?;
+        play_number(player, number)
+    }
+}
+
+impl Seat for Bot {
+    fn next_action(
+        &mut self,
+        player: &mut Player,
+        game_state: &GameState,
+        rules: &Rules,
+    ) -> Option<Action> {
+        match self.strategy {
+            Strategy::Random => self.random_action(player, game_state),
+            Strategy::Aggressive => self.aggressive_action(player, game_state, rules),
+            Strategy::Cautious => self.cautious_action(player, game_state, rules),
+        }
+    }
+
+    fn blocks(&mut self, _player: &Player, _king: &Action, _game_state: &GameState) -> bool {
+        match self.strategy {
+            Strategy::Random => self.rng.gen_bool(0.5),
+            Strategy::Aggressive | Strategy::Cautious => true,
+        }
+    }
+}
+
+/// A seat that is played by a person at the terminal, who is shown the table before each of
+/// their cards.
+pub struct Human {
+    input: Box<dyn InputSource>,
+}
+
+impl Human {
+    /// A person at the terminal.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Human {
+        Human::with_input(Box::new(Terminal))
+    }
+
+    /// A person whose answers come from `input` (e.g. a recorded session).
+    pub fn with_input(input: Box<dyn InputSource>) -> Human {
+        Human { input }
+    }
+}
+
+impl Seat for Human {
+    fn next_action(
+        &mut self,
+        player: &mut Player,
+        game_state: &GameState,
+        rules: &Rules,
+    ) -> Option<Action> {
+        println!();
+        Commentator::table(game_state);
+        println!("It is {}'s turn.", player.name());
+        loop {
+            match player.get_action(game_state, self.input.as_mut()) {
+                Ok(Some(staged)) => {
+                    let input = self.input.as_mut();
+                    if let Some(action) =
+                        staged.confirm_with_prompt(player, game_state, rules, input)
+                    {
+                        break Some(action);
+                    }
+                }
+                Ok(None) => break None,
+                Err(KingdomError::Cancelled) => {
+                    println!("Cancelled. Your cards are back in your hand.")
+                }
+                Err(err) => {
+                    println!("Error! {err}");
+                    break None;
+                }
+            }
+        }
+    }
+
+    fn blocks(&mut self, player: &Player, king: &Action, _game_state: &GameState) -> bool {
+        self.input.get_bool_input(
+            &format!(
+                "{}, block {}'s King with a Jack? [y/n]: ",
+                player.name(),
+                king.from_player()
+            ),
+            "y",
+            "n",
+        )
+    }
+
+    fn is_watching(&self) -> bool {
+        true
+    }
+}
+
+/// Returns every player at the table other than `player` and their teammates who hasn't been
+/// eliminated.
+fn opponents<'a>(player: &Player, game_state: &'a GameState) -> Vec<&'a PlayerDetails> {
+    game_state
+        .all_players()
+        .iter()
+        .filter(|other| {
+            other.name() != player.name()
+                && !other.is_eliminated()
+                && !game_state.are_teammates(player.name(), other.name())
+        })
+        .collect()
+}
+
+/// Returns whoever has the fewest points out of `player` and their teammates who are left.
+fn neediest_teammate<'a>(player: &Player, game_state: &'a GameState) -> Option<&'a PlayerDetails> {
+    game_state
+        .all_players()
+        .iter()
+        .filter(|other| {
+            other.name() == player.name()
+                || (!other.is_eliminated() && game_state.are_teammates(player.name(), other.name()))
+        })
+        .min_by_key(|other| other.points())
+}
+
+fn find_card(player: &Player, value: Value) -> Option<Card> {
+    player
+        .hand()
+        .iter()
+        .find(|card| *card.value() == value)
+        .copied()
+}
+
+/// Returns true if a Number worth `num_value` leaves enough other cards in `player`'s hand to
+/// discard.
+fn can_play_number(player: &Player, num_value: u16) -> bool {
+    player.hand_size() > num_value as usize
+}
+
+/// Plays `card` (a King or Queen) against `target`. If `with_attachment` is true, the highest
+/// Number in `player`'s hand is attached to it.
+fn play_king_queen(
+    player: &mut Player,
+    card: Card,
+    target: String,
+    with_attachment: bool,
+) -> Action {
+    player.remove_card_from_hand(&card);
+    let attachment = player
+        .hand()
+        .iter()
+        .filter(|card| card.value().is_number())
+        .max_by_key(|card| card.value().to_number_value())
+        .copied()
+        .filter(|_| with_attachment);
+    if let Some(attachment) = attachment {
+        player.remove_card_from_hand(&attachment);
+    }
+    Action::from_play(
+        Play::new(card, attachment),
+        player.name().to_owned(),
+        target,
+    )
+}
+
+/// Plays the Number `card`, discarding as many of the least useful cards in `player`'s hand. The
+/// table deals the same number of cards once it accepts the action.
+fn play_number(player: &mut Player, card: Card) -> Option<Action> {
+    let num_value = card.value().to_number_value()?;
+    player.remove_card_from_hand(&card);
+    let mut discards = player.hand().clone();
+    discards.sort_by_key(|card| match card.value() {
+        Value::King => 4,
+        Value::Queen => 3,
+        Value::Jack => 2,
+        Value::Ace => 1,
+        _ => 0,
+    });
+    discards.truncate(num_value.into());
+    for discard in discards.iter() {
+        player.remove_card_from_hand(discard);
+    }
+    Some(
+        Action::from_play(
+            Play::new(card, None),
+            player.name().to_owned(),
+            String::new(),
+        )
+        .with_discards(discards),
+    )
+}
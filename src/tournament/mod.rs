@@ -0,0 +1,505 @@
+//! This module contains the tournament runner, which plays many games between bots and humans
+//! and keeps the standings. Every round, the entrants are split between tables, either so that
+//! everyone plays everyone else (round-robin) or so that entrants with similar scores play each
+//! other (Swiss). At each table, one game is played for every seat, with the seating rotated each
+//! game, so that every entrant takes the first turn once.
+
+pub mod bot;
+pub mod table;
+
+use std::{collections::HashSet, fmt::Display, fs, path::PathBuf, str::FromStr};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    error::{self, KingdomError},
+    game::{
+        profiles::{Profiles, INITIAL_RATING},
+        rules::Rules,
+    },
+    server::{
+        constants::MAX_PLAYERS,
+        lobby::{Lobby, MIN_PLAYERS},
+    },
+};
+
+use self::{
+    bot::{Bot, Human, Seat, Strategy},
+    table::{GameResult, Table},
+};
+
+/// How the entrants are split between tables every round.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Format {
+    /// Every group of entrants that can fill a table plays once.
+    #[default]
+    RoundRobin,
+    /// Entrants with the closest scores play each other, avoiding anyone they have already
+    /// played where possible.
+    Swiss,
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = match self {
+            Format::RoundRobin => "round-robin",
+            Format::Swiss => "swiss",
+        };
+        write!(f, "{format}")
+    }
+}
+
+impl FromStr for Format {
+    type Err = KingdomError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Format::RoundRobin),
+            "swiss" => Ok(Format::Swiss),
+            _ => Err(KingdomError::InvalidTournament(format!(
+                "unknown format '{s}'"
+            ))),
+        }
+    }
+}
+
+/// Who plays an entrant's seat.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EntrantKind {
+    Bot(Strategy),
+    /// A person at the terminal.
+    Human,
+}
+
+/// A player in a tournament.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entrant {
+    name: String,
+    kind: EntrantKind,
+}
+
+impl Entrant {
+    pub fn new(name: &str, kind: EntrantKind) -> Entrant {
+        Entrant {
+            name: name.to_owned(),
+            kind,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> EntrantKind {
+        self.kind
+    }
+
+    /// Returns the seat that plays for this entrant in a game, where bots make their choices
+    /// using `seed`.
+    fn seat(&self, seed: u64) -> Box<dyn Seat> {
+        match self.kind {
+            EntrantKind::Bot(strategy) => Box::new(Bot::new(strategy, seed)),
+            EntrantKind::Human => Box::new(Human::new()),
+        }
+    }
+}
+
+impl Display for Entrant {
+    /// Format: `{NAME}:{STRATEGY}`, or `{NAME}:human`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            EntrantKind::Bot(strategy) => write!(f, "{}:{strategy}", self.name),
+            EntrantKind::Human => write!(f, "{}:human", self.name),
+        }
+    }
+}
+
+impl FromStr for Entrant {
+    type Err = KingdomError;
+
+    /// Converts from `{NAME}:{STRATEGY}` or `{NAME}:human` to an `Entrant`. Names follow the
+    /// same rules as usernames.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, kind) = s.split_once(':').ok_or_else(|| {
+            KingdomError::InvalidTournament(format!("expected NAME:STRATEGY, got '{s}'"))
+        })?;
+        Lobby::validate_name(name)?;
+        let kind = match kind {
+            "human" => EntrantKind::Human,
+            strategy => EntrantKind::Bot(Strategy::from_str(strategy)?),
+        };
+        Ok(Entrant::new(name, kind))
+    }
+}
+
+/// Settings that a tournament is run with.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TournamentConfig {
+    pub format: Format,
+    /// Number of rounds to play. If this is `None`, a round-robin plays until everyone has
+    /// played everyone, and a Swiss tournament plays enough rounds to find a clear winner.
+    pub rounds: Option<u32>,
+    /// Most entrants that play at each table.
+    pub table_size: usize,
+    /// Rules every game is played with.
+    pub rules: Rules,
+    /// Seed for every game in the tournament. If this is `None`, a random seed is used.
+    pub seed: Option<u64>,
+    /// Directory that the game logs are written to. If this is `None`, the game logs are
+    /// written to `tournament-{SEED}` in the current directory.
+    pub log_dir: Option<PathBuf>,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        TournamentConfig {
+            format: Format::default(),
+            rounds: None,
+            table_size: MIN_PLAYERS,
+            rules: Rules::default(),
+            seed: None,
+            log_dir: None,
+        }
+    }
+}
+
+/// How an entrant is doing in a tournament.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Standing {
+    pub name: String,
+    /// One point for every player the entrant placed above in each game, and a win's worth of
+    /// points for every bye.
+    pub score: u32,
+    pub games_played: u32,
+    pub wins: u32,
+    pub eliminations: u32,
+    pub byes: u32,
+    /// Elo rating, calculated the same way as player profiles.
+    pub rating: f64,
+}
+
+/// A tournament that is played from start to finish in this process.
+pub struct Tournament {
+    config: TournamentConfig,
+    entrants: Vec<Entrant>,
+    standings: Vec<Standing>,
+    ratings: Profiles,
+    /// Every pair of entrants who have played at the same table, in alphabetical order.
+    met: HashSet<(String, String)>,
+    seed: u64,
+    rng: ChaCha8Rng,
+    log_dir: PathBuf,
+}
+
+impl Tournament {
+    /// Creates a tournament between `entrants`.
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if there are too few entrants to fill a table, two
+    /// entrants have the same name, or the table size is invalid.
+    pub fn new(entrants: Vec<Entrant>, config: TournamentConfig) -> error::Result<Tournament> {
+        let invalid = |reason: String| Err(KingdomError::InvalidTournament(reason));
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&config.table_size) {
+            return invalid(format!(
+                "tables must seat {MIN_PLAYERS} to {MAX_PLAYERS} players"
+            ));
+        }
+        if entrants.len() < config.table_size {
+            return invalid(format!(
+                "{} entrants can't fill a table of {}",
+                entrants.len(),
+                config.table_size
+            ));
+        }
+        for (index, entrant) in entrants.iter().enumerate() {
+            if entrants[..index]
+                .iter()
+                .any(|other| other.name == entrant.name)
+            {
+                return invalid(format!("'{}' was entered twice", entrant.name));
+            }
+        }
+
+        let seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let log_dir = config
+            .log_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("tournament-{seed}")));
+        let ratings = Profiles::default();
+        let standings = entrants
+            .iter()
+            .map(|entrant| Standing {
+                name: entrant.name.clone(),
+                score: 0,
+                games_played: 0,
+                wins: 0,
+                eliminations: 0,
+                byes: 0,
+                rating: INITIAL_RATING,
+            })
+            .collect();
+        Ok(Tournament {
+            config,
+            entrants,
+            standings,
+            ratings,
+            met: HashSet::new(),
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            log_dir,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Plays every round, then returns the final standings.
+    ///
+    /// ## Returns
+    ///
+    /// This function will return an error if the game logs can't be written.
+    pub fn run(&mut self) -> error::Result<Vec<Standing>> {
+        fs::create_dir_all(&self.log_dir)?;
+        println!(
+            "Starting a {} tournament between {} entrants with seed {}. Writing game logs to {}",
+            self.config.format,
+            self.entrants.len(),
+            self.seed,
+            self.log_dir.display()
+        );
+
+        let schedule = match self.config.format {
+            Format::RoundRobin => self.round_robin_schedule(),
+            Format::Swiss => Vec::new(),
+        };
+        let num_rounds = match (self.config.rounds, self.config.format) {
+            (Some(rounds), _) => rounds as usize,
+            (None, Format::RoundRobin) => schedule.len(),
+            (None, Format::Swiss) => {
+                /* Enough rounds for one entrant to beat everyone in a knockout. */
+                (self.entrants.len() as f64).log2().ceil().max(1.0) as usize
+            }
+        };
+
+        for round in 1..=num_rounds {
+            let tables = match self.config.format {
+                Format::RoundRobin => {
+                    /* Extra rounds go around the schedule again. */
+                    let Some(tables) = schedule.get((round - 1) % schedule.len().max(1)) else {
+                        break;
+                    };
+                    tables.clone()
+                }
+                Format::Swiss => self.swiss_tables(),
+            };
+            println!("\nRound {round}:");
+            for (index, names) in tables.iter().enumerate() {
+                self.play_table(round, index + 1, names)?;
+            }
+        }
+
+        let standings = self.standings();
+        println!("\nFinal standings:");
+        Tournament::print_standings(&standings);
+        Ok(standings)
+    }
+
+    /// Returns the standings from first place to last, by score and then by rating.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings = self.standings.clone();
+        standings.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| b.rating.total_cmp(&a.rating))
+        });
+        standings
+    }
+
+    /// Prints `standings`, with every entrant's stats.
+    pub fn print_standings(standings: &[Standing]) {
+        println!(
+            "{:<4} {:<25} {:>5} {:>6} {:>5} {:>4} {:>5} {:>4}",
+            "Rank", "Entrant", "Score", "Rating", "Games", "Wins", "Elims", "Byes"
+        );
+        for (index, standing) in standings.iter().enumerate() {
+            println!(
+                "{:<4} {:<25} {:>5} {:>6.0} {:>5} {:>4} {:>5} {:>4}",
+                index + 1,
+                standing.name,
+                standing.score,
+                standing.rating,
+                standing.games_played,
+                standing.wins,
+                standing.eliminations,
+                standing.byes,
+            );
+        }
+    }
+
+    /// Splits every group of entrants that can fill a table into rounds, so that nobody plays
+    /// twice in the same round.
+    fn round_robin_schedule(&self) -> Vec<Vec<Vec<String>>> {
+        let names: Vec<String> = self
+            .entrants
+            .iter()
+            .map(|entrant| entrant.name.clone())
+            .collect();
+        let mut rounds: Vec<Vec<Vec<String>>> = Vec::new();
+        for group in combinations(&names, self.config.table_size) {
+            let free = rounds
+                .iter_mut()
+                .find(|tables| !tables.iter().flatten().any(|seated| group.contains(seated)));
+            match free {
+                Some(tables) => tables.push(group),
+                None => rounds.push(vec![group]),
+            }
+        }
+        rounds
+    }
+
+    /// Seats entrants with the closest standings together, avoiding anyone they have already
+    /// played where possible. If only one entrant is left over, they get a bye.
+    fn swiss_tables(&mut self) -> Vec<Vec<String>> {
+        let mut unseated: Vec<String> = self
+            .standings()
+            .into_iter()
+            .map(|standing| standing.name)
+            .collect();
+        let mut tables = Vec::new();
+        while !unseated.is_empty() {
+            if unseated.len() < MIN_PLAYERS {
+                let name = unseated.remove(0);
+                println!("'{name}' has a bye this round.");
+                self.give_bye(&name);
+                break;
+            }
+            let mut table = vec![unseated.remove(0)];
+            while table.len() < self.config.table_size && !unseated.is_empty() {
+                let pick = unseated
+                    .iter()
+                    .position(|name| !table.iter().any(|seated| self.have_met(seated, name)))
+                    .unwrap_or(0);
+                table.push(unseated.remove(pick));
+            }
+            tables.push(table);
+        }
+        tables
+    }
+
+    /// Plays one game for every seat at the table of entrants `names`, rotating the seating by
+    /// one each game.
+    fn play_table(&mut self, round: usize, table: usize, names: &[String]) -> error::Result<()> {
+        for game in 0..names.len() {
+            let mut seating = names.to_vec();
+            seating.rotate_left(game);
+            let seed: u64 = self.rng.gen();
+            let seats = seating
+                .iter()
+                .enumerate()
+                .map(|(index, name)| {
+                    let entrant = self.entrant(name);
+                    (name.clone(), entrant.seat(seed.wrapping_add(index as u64)))
+                })
+                .collect();
+            let path = self.log_dir.join(format!(
+                "round-{round}-table-{table}-game-{}.kklog",
+                game + 1
+            ));
+            /* A rerun of the same tournament replaces its game logs. */
+            let _ = fs::remove_file(&path);
+            let result = Table::new(seats, &self.config.rules, seed, Some(&path))?.play();
+            println!(
+                "  Table {table}, game {}: {} won in {} turns ({}) [{}]",
+                game + 1,
+                result.winner(),
+                result.turns,
+                seating.join(" vs "),
+                path.display()
+            );
+            self.record_game(&result);
+        }
+        for (index, name) in names.iter().enumerate() {
+            for other in &names[index + 1..] {
+                self.met.insert(Tournament::pair(name, other));
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the standings and ratings of everyone in a finished game.
+    fn record_game(&mut self, result: &GameResult) {
+        let eliminators: Vec<String> = result
+            .eliminations
+            .iter()
+            .map(|(eliminator, _)| eliminator.clone())
+            .collect();
+        self.ratings.record_game(&result.placements, &eliminators);
+        let num_tiers = result.placements.len() as u32;
+        for (place, tier) in result.placements.iter().enumerate() {
+            for name in tier {
+                let rating = self.ratings.get(name).map(|profile| profile.rating);
+                let standing = self.standing_mut(name);
+                standing.games_played += 1;
+                standing.score += num_tiers - 1 - place as u32;
+                standing.wins += u32::from(place == 0);
+                if let Some(rating) = rating {
+                    standing.rating = rating;
+                }
+            }
+        }
+        for eliminator in eliminators {
+            self.standing_mut(&eliminator).eliminations += 1;
+        }
+    }
+
+    fn give_bye(&mut self, name: &str) {
+        let points = self.config.table_size as u32 - 1;
+        let standing = self.standing_mut(name);
+        standing.score += points;
+        standing.byes += 1;
+    }
+
+    fn have_met(&self, a: &str, b: &str) -> bool {
+        self.met.contains(&Tournament::pair(a, b))
+    }
+
+    fn pair(a: &str, b: &str) -> (String, String) {
+        if a < b {
+            (a.to_owned(), b.to_owned())
+        } else {
+            (b.to_owned(), a.to_owned())
+        }
+    }
+
+    fn entrant(&self, name: &str) -> &Entrant {
+        self.entrants
+            .iter()
+            .find(|entrant| entrant.name == name)
+            .unwrap()
+    }
+
+    fn standing_mut(&mut self, name: &str) -> &mut Standing {
+        self.standings
+            .iter_mut()
+            .find(|standing| standing.name == name)
+            .unwrap()
+    }
+}
+
+/// Returns every group of `size` items from `items`, keeping the order of `items`.
+fn combinations(items: &[String], size: usize) -> Vec<Vec<String>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    let mut groups = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        for mut rest in combinations(&items[index + 1..], size - 1) {
+            rest.insert(0, item.clone());
+            groups.push(rest);
+        }
+    }
+    groups
+}
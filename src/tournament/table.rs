@@ -0,0 +1,281 @@
+//! This module contains `Table`, which plays a single tournament game between seats in the same
+//! process, without a server. Every action goes through `GameState::apply_action` and is written
+//! to a game log, so tournament games can be replayed like any other game.
+
+use std::path::Path;
+
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    error,
+    game::{
+        card::{Card, Play},
+        game_log::GameLog,
+        game_state::{GameState, PlayerDetails},
+        player::{Player, PlayerCards},
+        referee,
+        rules::Rules,
+    },
+    server::{
+        commentator::Commentator,
+        response::{Action, ActionType},
+    },
+    utils::perror_in_fn,
+};
+
+use super::bot::Seat;
+
+/// Most turns a game can last. If nobody has won by then, the players are placed by their
+/// points, the same as when every player passes in a row.
+pub const MAX_TURNS: u32 = 500;
+
+/// How many rounds in a row every player who is left can pass their turn before the game is
+/// over. Cards are only drawn by playing Numbers, so a table that keeps passing never changes.
+const STALEMATE_ROUNDS: usize = 3;
+
+/// Most cards a seat can play in one turn, so that a seat can't keep a game going forever.
+const MAX_ACTIONS_PER_TURN: u32 = 20;
+
+/// How a finished game turned out.
+#[derive(Debug, PartialEq)]
+pub struct GameResult {
+    /// Names of every player in tiers that placed together, from the winners to whoever placed
+    /// last (see `GameState::placement_tiers`).
+    pub placements: Vec<Vec<String>>,
+    /// Who eliminated whom, as `(ELIMINATOR, ELIMINATED)`, in the order they were eliminated.
+    pub eliminations: Vec<(String, String)>,
+    /// How many turns were played.
+    pub turns: u32,
+}
+
+impl GameResult {
+    pub fn winner(&self) -> &str {
+        &self.placements[0][0]
+    }
+}
+
+/// A game between seats, each of which has their own cards.
+pub struct Table {
+    game_state: GameState,
+    rules: Rules,
+    /// Every seat's cards. Seats only ever play on a copy, so the table can check each action.
+    players: Vec<Player>,
+    /// Shuffles every deck, then the discard piles whenever a deck runs out.
+    rng: ChaCha8Rng,
+    seats: Vec<Box<dyn Seat>>,
+    game_log: Option<GameLog>,
+    eliminations: Vec<(String, String)>,
+    /// Whether every action is printed, for any humans at the table.
+    watched: bool,
+}
+
+impl Table {
+    /// Deals a shuffled deck to each seat in `seats`, seated in order, using `seed`. If there
+    /// are teams, seats are put on them in turn. If `game_log` is set, the game is logged to it.
+    pub fn new(
+        seats: Vec<(String, Box<dyn Seat>)>,
+        rules: &Rules,
+        seed: u64,
+        game_log: Option<&Path>,
+    ) -> error::Result<Table> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut game_state = GameState::new();
+        let mut players = Vec::with_capacity(seats.len());
+        let mut boxed_seats = Vec::with_capacity(seats.len());
+        for (name, seat) in seats {
+            let mut deck = Card::full_deck();
+            deck.shuffle(&mut rng);
+            let cards = PlayerCards {
+                deck,
+                ..Default::default()
+            };
+            let mut player = Player::with_cards(name.clone(), cards);
+            player.draw_n_times(rules.hand_size);
+            game_state.add_player(PlayerDetails::new(name, rules.starting_points));
+            players.push(player);
+            boxed_seats.push(seat);
+        }
+        game_state.assign_teams(rules.teams);
+        let game_log = match game_log {
+            Some(path) => Some(GameLog::create(path, rules, seed, &game_state)?),
+            None => None,
+        };
+        Ok(Table {
+            watched: boxed_seats.iter().any(|seat| seat.is_watching()),
+            game_state,
+            rules: rules.clone(),
+            players,
+            rng,
+            seats: boxed_seats,
+            game_log,
+            eliminations: Vec::new(),
+        })
+    }
+
+    /// Plays turns until only one player is left, every player who is left passes their turn
+    /// for `STALEMATE_ROUNDS` rounds in a row, or `MAX_TURNS` have been played.
+    pub fn play(mut self) -> GameResult {
+        let mut turns = 0;
+        let mut passes = 0;
+        while self.game_state.winner().is_none() && turns < MAX_TURNS {
+            if self.play_turn() {
+                passes = 0;
+            } else {
+                passes += 1;
+            }
+            turns += 1;
+            let remaining = self
+                .game_state
+                .all_players()
+                .iter()
+                .filter(|player| !player.is_eliminated())
+                .count();
+            if passes >= remaining * STALEMATE_ROUNDS {
+                break;
+            }
+        }
+
+        let placements = self.placements();
+        let game_over = Action::new(
+            ActionType::GameOver,
+            0,
+            placements[0][0].clone(),
+            String::new(),
+        );
+        self.apply(&game_over);
+        GameResult {
+            placements,
+            eliminations: self.eliminations,
+            turns,
+        }
+    }
+
+    /// Plays the current player's turn, from its `TurnStart` to its `TurnEnd`. Returns false if
+    /// the player passed without playing a card.
+    fn play_turn(&mut self) -> bool {
+        let index = self.game_state.current_player_index();
+        let name = self.players[index].name().to_owned();
+        self.apply(&Action::new_turn_start(&name));
+        let mut played = false;
+        for _ in 0..MAX_ACTIONS_PER_TURN {
+            let mut cards = Player::with_cards(name.clone(), self.players[index].cards());
+            let action = self.seats[index].next_action(&mut cards, &self.game_state, &self.rules);
+            let Some(action) = action else {
+                break;
+            };
+            let before = self.game_state.clone();
+            if self.play_cards(index, &action) {
+                played = true;
+                self.react(&action, &before);
+                self.record_eliminations(&before, &name);
+            }
+            if self.game_state.winner().is_some() {
+                break;
+            }
+        }
+        self.apply(&Action::new_turn_end(&name));
+        played
+    }
+
+    /// Gives the target of a King the chance to block it with a Jack, where `before` is the game
+    /// state before the King.
+    fn react(&mut self, king: &Action, before: &GameState) {
+        let Some(points_before) = referee::reaction_target_points(king, before) else {
+            return;
+        };
+        let Some(target) = self
+            .players
+            .iter()
+            .position(|player| player.name() == king.to_player())
+        else {
+            return;
+        };
+        let Some(jack_card) = self.players[target].jack() else {
+            return;
+        };
+        if !self.seats[target].blocks(&self.players[target], king, &self.game_state) {
+            return;
+        }
+        let play = Play::new(jack_card, None);
+        let jack = referee::block(king, points_before, Some(play), &self.game_state);
+        self.play_cards(target, &jack);
+    }
+
+    /// Plays `action` for the player at `index` with `referee::apply_with_cards`, then logs it.
+    /// Returns true if it was applied.
+    fn play_cards(&mut self, index: usize, action: &Action) -> bool {
+        let before = self.game_state.clone();
+        let result = referee::apply_with_cards(
+            &mut self.players[index],
+            action,
+            &mut self.game_state,
+            &self.rules,
+            &mut self.rng,
+        );
+        self.record(action, &result, &before)
+    }
+
+    /// Applies `action`, which plays no cards, to the game state and logs it. Returns true if it
+    /// was applied.
+    fn apply(&mut self, action: &Action) -> bool {
+        let before = self.game_state.clone();
+        let result = self.game_state.apply_action(action, &self.rules);
+        self.record(action, &result, &before)
+    }
+
+    /// Logs `action`, where `before` is the game state before it, and prints it for anyone
+    /// watching if it was applied. Returns true if it was applied.
+    fn record(&mut self, action: &Action, result: &error::Result<()>, before: &GameState) -> bool {
+        if let Some(game_log) = self.game_log.as_mut() {
+            if let Err(err) = game_log.log_action(action, result, before, &self.game_state) {
+                perror_in_fn("Table::apply", "table", err);
+            }
+        }
+        if self.watched && result.is_ok() {
+            Commentator::action(action, &self.game_state);
+        }
+        result.is_ok()
+    }
+
+    /// Credits `eliminator` with every player who has been eliminated since `before`.
+    fn record_eliminations(&mut self, before: &GameState, eliminator: &str) {
+        for player in self.game_state.all_players() {
+            let was_eliminated = before
+                .player_by_name(player.name())
+                .is_none_or(PlayerDetails::is_eliminated);
+            if player.is_eliminated() && !was_eliminated {
+                self.eliminations
+                    .push((eliminator.to_owned(), player.name().to_owned()));
+            }
+        }
+    }
+
+    /// Returns every player's name from first place to last, in tiers of teammates who place
+    /// together. Players who are left place by their points, then by their seat, and players who
+    /// were eliminated place in the reverse of the order they were eliminated in.
+    fn placements(&self) -> Vec<Vec<String>> {
+        let mut remaining: Vec<&PlayerDetails> = self
+            .game_state
+            .all_players()
+            .iter()
+            .filter(|player| !player.is_eliminated())
+            .collect();
+        remaining.sort_by_key(|player| std::cmp::Reverse(player.points()));
+        let mut placements: Vec<String> = remaining
+            .iter()
+            .map(|player| player.name().to_owned())
+            .collect();
+        for (_, name) in self.eliminations.iter().rev() {
+            if !placements.contains(name) {
+                placements.push(name.clone());
+            }
+        }
+        for player in self.game_state.all_players() {
+            if !placements.iter().any(|name| name == player.name()) {
+                placements.push(player.name().to_owned());
+            }
+        }
+        self.game_state.placement_tiers(&placements)
+    }
+}
@@ -0,0 +1,222 @@
+//! This module contains the text commands that players can type on their turn in command mode,
+//! instead of choosing cards by number. Cards are written the same way as in game logs, except
+//! that the suit can be left out, so `play K+7 bob` plays any King with any 7 attached against
+//! Bob.
+
+use std::str::FromStr;
+
+use crate::{
+    error::{self, KingdomError},
+    game::{
+        card::{Card, CardToken, Play, Value},
+        game_state::GameState,
+        player::Player,
+        rules::Rules,
+        staged::StagedAction,
+    },
+    server::response::Action,
+};
+
+/// The name of every command, for tab completion.
+pub const COMMAND_NAMES: [&str; 7] = ["play", "number", "hand", "state", "log", "help", "end"];
+
+/// Printed by the `help` command.
+pub const HELP: &str = "\
+Commands:
+  play {CARD}[+{NUMBER}] [{PLAYER}]   Play a King or Queen, e.g. 'play K+7 bob'
+  number {NUMBER} discard {CARDS}    Play a Number, e.g. 'number 3 discard 2 5 9'
+  hand                               Show your hand
+  state                              Show every player's points and cards
+  log                                Show the last few actions
+  help                               Show this list
+  end                                End your turn
+Cards are written as their value then their suit (e.g. KH or 10S). Leave out the suit to use any
+card of that value. Queens are played on yourself unless a player is given. Press tab to complete
+commands, cards and player names.";
+
+/// A command typed by a player on their turn.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Plays a King or Queen, with a Number attached if there is one, against `target`. A Queen
+    /// with no target is played on the player themselves.
+    Play {
+        card: CardToken,
+        attachment: Option<CardToken>,
+        target: Option<String>,
+    },
+    /// Plays a Number, discarding each of `discards`.
+    Number {
+        card: CardToken,
+        discards: Vec<CardToken>,
+    },
+    /// Shows the player's hand.
+    Hand,
+    /// Shows every player's points.
+    State,
+    /// Shows the last few actions.
+    Log,
+    /// Shows every command.
+    Help,
+    /// Ends the player's turn.
+    End,
+}
+
+impl FromStr for Command {
+    type Err = KingdomError;
+
+    /// Converts from a typed command (e.g. `play KH+5S @bob`) to `Command`. Command names can be
+    /// typed in any case, and players can be written with or without an `@` in front.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = KingdomError::InvalidCommand;
+        let mut words = s.split_whitespace();
+        let name = words.next().unwrap_or_default().to_ascii_lowercase();
+        let command = match name.as_str() {
+            "play" => {
+                let cards = words
+                    .next()
+                    .ok_or_else(|| invalid("say which card to play, e.g. 'play K+7 bob'".into()))?;
+                let (card, attachment) = match cards.split_once('+') {
+                    Some((card, attachment)) => (card, Some(CardToken::from_str(attachment)?)),
+                    None => (cards, None),
+                };
+                Command::Play {
+                    card: CardToken::from_str(card)?,
+                    attachment,
+                    target: words
+                        .next()
+                        .map(|target| target.trim_start_matches('@').to_owned()),
+                }
+            }
+            "number" => {
+                let card = words.next().ok_or_else(|| {
+                    invalid("say which Number to play, e.g. 'number 3 discard 2 5 9'".into())
+                })?;
+                let card = CardToken::from_str(card)?;
+                let discards = match words.next() {
+                    Some(word) if word.eq_ignore_ascii_case("discard") => words
+                        .by_ref()
+                        .map(CardToken::from_str)
+                        .collect::<error::Result<_>>()?,
+                    Some(word) => return Err(invalid(format!("expected 'discard', not '{word}'"))),
+                    None => Vec::new(),
+                };
+                Command::Number { card, discards }
+            }
+            "hand" => Command::Hand,
+            "state" => Command::State,
+            "log" => Command::Log,
+            "help" => Command::Help,
+            "end" => Command::End,
+            "" => return Err(invalid("type 'help' for a list of commands".into())),
+            other => {
+                return Err(invalid(format!(
+                    "unknown command '{other}', type 'help' for a list of commands"
+                )))
+            }
+        };
+        if let Some(word) = words.next() {
+            return Err(invalid(format!("unexpected '{word}'")));
+        }
+        Ok(command)
+    }
+}
+
+impl Command {
+    /// Stages a `play` or `number` command as `player`'s action, moving the cards it uses out of
+    /// their hand until it is confirmed or cancelled. Returns an error, without moving any cards,
+    /// if the command doesn't play cards, or if it would break the rules.
+    pub fn stage(
+        &self,
+        player: &mut Player,
+        game_state: &GameState,
+        rules: &Rules,
+    ) -> error::Result<StagedAction> {
+        let cards = player.cards();
+        let action = self.to_action(player, game_state, rules)?;
+        Ok(StagedAction::new(action, cards))
+    }
+
+    fn to_action(
+        &self,
+        player: &mut Player,
+        game_state: &GameState,
+        rules: &Rules,
+    ) -> error::Result<Action> {
+        match self {
+            Command::Play {
+                card,
+                attachment,
+                target,
+            } => {
+                if !matches!(card.value, Value::King | Value::Queen) {
+                    return Err(KingdomError::InvalidPlay(format!(
+                        "'{card}' can't be played with 'play', only Kings and Queens can"
+                    )));
+                }
+                let card = find(player, card, &[])?;
+                let attachment = match attachment {
+                    Some(token) => Some(find(player, token, &[card])?),
+                    None => None,
+                };
+                let target = match target {
+                    Some(target) => find_player(game_state, target)?,
+                    None if *card.value() == Value::Queen => player.name().to_owned(),
+                    None => {
+                        return Err(KingdomError::InvalidPlay(
+                            "say who to play the King against, e.g. 'play K bob'".to_owned(),
+                        ))
+                    }
+                };
+                let play = Play::new(card, attachment);
+                let action = Action::from_play(play, player.name().to_owned(), target.clone());
+                game_state.check_action(&action, rules)?;
+                player.play_cards(&play, &target)
+            }
+            Command::Number { card, discards } => {
+                let mut taken = vec![find(player, card, &[])?];
+                for token in discards {
+                    let discard = find(player, token, &taken)?;
+                    taken.push(discard);
+                }
+                player.play_number_with_discards(taken[0], &taken[1..])
+            }
+            _ => Err(KingdomError::InvalidCommand(
+                "only 'play' and 'number' play cards".to_owned(),
+            )),
+        }
+    }
+}
+
+/// Returns the first card in `player`'s hand that `token` stands for, skipping any card in
+/// `taken`.
+fn find(player: &Player, token: &CardToken, taken: &[Card]) -> error::Result<Card> {
+    player
+        .find_card(token, taken)
+        .ok_or_else(|| KingdomError::CardNotInHand(token.to_string()))
+}
+
+/// Returns the name of the player in `game_state` called `name`, in any case.
+fn find_player(game_state: &GameState, name: &str) -> error::Result<String> {
+    game_state
+        .all_players()
+        .iter()
+        .find(|player| player.name().eq_ignore_ascii_case(name))
+        .map(|player| player.name().to_owned())
+        .ok_or_else(|| KingdomError::PlayerNotFound(name.to_owned()))
+}
+
+/// Returns every word that can be tab-completed on `player`'s turn: the commands, every player's
+/// name and the cards in `player`'s hand.
+pub fn completions(player: &Player, game_state: &GameState) -> Vec<String> {
+    let commands = COMMAND_NAMES.iter().map(|name| name.to_string());
+    let players = game_state
+        .all_players()
+        .iter()
+        .map(|player| player.name().to_owned());
+    let cards = player.hand().iter().map(|card| card.to_string());
+    commands
+        .chain(["discard".to_owned()])
+        .chain(players)
+        .chain(cards)
+        .collect()
+}
@@ -0,0 +1,230 @@
+//! This module contains `InputSource`, which is where every prompt gets its answer from. The
+//! terminal is the usual source, but prompts can also be answered from a script (e.g. in tests,
+//! or to replay a recorded session) or by a bot, so that client flows can be automated.
+
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::error;
+
+/// Somewhere that answers to prompts come from. Only `read_line` has to be implemented, and the
+/// other methods keep prompting until they get a valid answer, the same as on the terminal.
+pub trait InputSource: Send {
+    /// Shows `prompt` and returns the next line of input, with whitespace trimmed. Returns `None`
+    /// if there is no input to give (e.g. when the input deadline passes, or a script runs out).
+    fn read_line(&mut self, prompt: &str) -> Option<String>;
+
+    /// Returns true if input has stopped being accepted (see `ui::set_input_deadline`). Input
+    /// read after this point should be thrown away.
+    fn timed_out(&self) -> bool {
+        false
+    }
+
+    /// Prompts with `prompt` until the input is shorter than `max_len`. Returns an empty string if
+    /// there is no input to give.
+    fn get_input(&mut self, prompt: &str, max_len: usize) -> String {
+        loop {
+            if self.timed_out() {
+                return String::new();
+            }
+            let Some(input) = self.read_line(prompt) else {
+                return String::new();
+            };
+            if input.len() < max_len {
+                return input;
+            }
+        }
+    }
+
+    /// Prompts with `prompt` until a number in the range `[min, max]` is entered. Returns `min` if
+    /// there is no input to give.
+    fn get_num_input(&mut self, prompt: &str, min: i32, max: i32) -> i32 {
+        loop {
+            if self.timed_out() {
+                return min;
+            }
+            let Some(input) = self.read_line(prompt) else {
+                return min;
+            };
+            if let Ok(n) = input.parse::<i32>() {
+                if (min..=max).contains(&n) {
+                    return n;
+                }
+            }
+        }
+    }
+
+    /// Prompts with `prompt` until `true_opt` or `false_opt` is entered. Returns false if there is
+    /// no input to give.
+    fn get_bool_input(&mut self, prompt: &str, true_opt: &str, false_opt: &str) -> bool {
+        loop {
+            if self.timed_out() {
+                return false;
+            }
+            let Some(input) = self.read_line(prompt) else {
+                return false;
+            };
+            if input == true_opt {
+                return true;
+            } else if input == false_opt {
+                return false;
+            }
+        }
+    }
+}
+
+/// Input typed at the terminal, which follows the input deadline.
+pub struct Terminal;
+
+impl InputSource for Terminal {
+    fn read_line(&mut self, prompt: &str) -> Option<String> {
+        super::print_prompt(prompt);
+        super::read_line()
+    }
+
+    fn timed_out(&self) -> bool {
+        super::input_timed_out()
+    }
+}
+
+/// Input given ahead of time, one line per prompt. Once every line has been used, prompts are
+/// passed on to the fallback if there is one, or get no input otherwise.
+pub struct ScriptedInput {
+    lines: VecDeque<String>,
+    fallback: Option<Box<dyn InputSource>>,
+}
+
+impl ScriptedInput {
+    pub fn new<S: Into<String>>(lines: impl IntoIterator<Item = S>) -> ScriptedInput {
+        ScriptedInput {
+            lines: lines.into_iter().map(Into::into).collect(),
+            fallback: None,
+        }
+    }
+
+    /// Reads the lines from the file at `path`, such as a session recorded with `RecordingInput`.
+    pub fn load(path: &Path) -> error::Result<ScriptedInput> {
+        Ok(ScriptedInput::new(fs::read_to_string(path)?.lines()))
+    }
+
+    /// Passes prompts on to `fallback` once every line has been used (e.g. so that a player can
+    /// carry on from the end of a recorded session).
+    pub fn then(mut self, fallback: Box<dyn InputSource>) -> ScriptedInput {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Returns how many lines are left.
+    pub fn remaining(&self) -> usize {
+        self.lines.len()
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn read_line(&mut self, prompt: &str) -> Option<String> {
+        match self.lines.pop_front() {
+            Some(line) => Some(line.trim().to_owned()),
+            None => self.fallback.as_mut()?.read_line(prompt),
+        }
+    }
+
+    fn timed_out(&self) -> bool {
+        self.lines.is_empty()
+            && self
+                .fallback
+                .as_ref()
+                .is_some_and(|input| input.timed_out())
+    }
+}
+
+/// Input from a bot, which picks any valid number or option at random, and answers every other
+/// prompt with nothing. Bots are seeded, so the same seed always gives the same answers.
+pub struct BotInput {
+    rng: ChaCha8Rng,
+}
+
+impl BotInput {
+    pub fn new(seed: u64) -> BotInput {
+        BotInput {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl InputSource for BotInput {
+    fn read_line(&mut self, _prompt: &str) -> Option<String> {
+        None
+    }
+
+    fn get_num_input(&mut self, _prompt: &str, min: i32, max: i32) -> i32 {
+        self.rng.gen_range(min..=max)
+    }
+
+    fn get_bool_input(&mut self, _prompt: &str, _true_opt: &str, _false_opt: &str) -> bool {
+        self.rng.gen()
+    }
+}
+
+/// Passes every prompt on to another source, and writes each answer to a file, one per line, so
+/// that the session can be replayed with `ScriptedInput::load`.
+pub struct RecordingInput {
+    input: Box<dyn InputSource>,
+    file: File,
+}
+
+impl RecordingInput {
+    /// Records the answers that `input` gives to the file at `path`, replacing it if it exists.
+    pub fn create(path: &Path, input: Box<dyn InputSource>) -> error::Result<RecordingInput> {
+        Ok(RecordingInput {
+            input,
+            file: File::create(path)?,
+        })
+    }
+
+    /// Writes `answer` to the recording. A recording that can't be written to stops being
+    /// written, but the session carries on.
+    fn record(&mut self, answer: &str) {
+        if let Err(err) = writeln!(self.file, "{answer}").and_then(|()| self.file.flush()) {
+            crate::logging::warn("input", &format!("Unable to record input: {err}"));
+        }
+    }
+}
+
+impl InputSource for RecordingInput {
+    fn read_line(&mut self, prompt: &str) -> Option<String> {
+        let line = self.input.read_line(prompt)?;
+        self.record(&line);
+        Some(line)
+    }
+
+    fn timed_out(&self) -> bool {
+        self.input.timed_out()
+    }
+
+    /* Answers are recorded once they are valid, since the source may not read them line by line
+     * (e.g. a bot). */
+    fn get_input(&mut self, prompt: &str, max_len: usize) -> String {
+        let input = self.input.get_input(prompt, max_len);
+        self.record(&input);
+        input
+    }
+
+    fn get_num_input(&mut self, prompt: &str, min: i32, max: i32) -> i32 {
+        let n = self.input.get_num_input(prompt, min, max);
+        self.record(&n.to_string());
+        n
+    }
+
+    fn get_bool_input(&mut self, prompt: &str, true_opt: &str, false_opt: &str) -> bool {
+        let answer = self.input.get_bool_input(prompt, true_opt, false_opt);
+        self.record(if answer { true_opt } else { false_opt });
+        answer
+    }
+}
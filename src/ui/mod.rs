@@ -1,67 +1,273 @@
 //! This module contains all the code responsible for the program's user interface.
 
-use std::io::{self, Write};
+pub mod commands;
+pub mod input;
 
-/// Prompts the user with `prompt` and returns input (with whitespace trimmed).
-pub fn get_input(prompt: &str, max_len: usize) -> String {
-    let input = &mut String::new();
-    let stdout = &mut io::stdout();
-    loop {
-        print!("{prompt}");
-        stdout.flush().expect("couldn't flush stdout");
-        input.clear();
-        io::stdin().read_line(input).expect("unable to read input");
-        let input = input.trim().to_string();
-        if input.len() < max_len {
-            return input;
+use std::{
+    io::{self, BufRead, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Mutex, OnceLock,
+    },
+    thread,
+    time::Instant,
+};
+
+use input::{InputSource, Terminal};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Context, Editor, ExternalPrinter, Helper,
+};
+
+/// Lines read from stdin by a background thread, so that input can be given a deadline.
+static STDIN_LINES: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+
+/// Sends to `STDIN_LINES` alongside the thread reading stdin, to wake up a prompt that is
+/// waiting for a line (see `interrupt_input`).
+static STDIN_WAKER: OnceLock<Mutex<Sender<String>>> = OnceLock::new();
+
+/// Sent in place of a line by `interrupt_input`. Lines read from stdin never contain it.
+const WAKE_LINE: &str = "\0";
+
+/// Called with every line typed that starts with `/`, instead of the line being used as input.
+type CommandHook = Box<dyn Fn(&str) + Send>;
+
+/// Hook that commands (e.g. `/say hello`) are passed to, if any.
+static COMMAND_HOOK: Mutex<Option<CommandHook>> = Mutex::new(None);
+
+/// When input stops being accepted (e.g. when the turn clock runs out), if ever.
+static INPUT_DEADLINE: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Whether lines are read with line editing, history and tab completion when stdin is a
+/// terminal.
+static LINE_EDITING: AtomicBool = AtomicBool::new(false);
+
+/// Words that can be tab-completed while line editing.
+static COMPLETIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Prints above the line being edited, so prompts aren't drawn over while line editing.
+static PROMPT_PRINTER: Mutex<Option<Box<dyn ExternalPrinter + Send>>> = Mutex::new(None);
+
+/// Turns on line editing, with history and tab completion of the words set with
+/// `set_completions`. This only has an effect if it is called before any input is read, and
+/// stdin is a terminal.
+pub fn enable_line_editing() {
+    LINE_EDITING.store(true, Ordering::SeqCst);
+}
+
+/// Sets the words that can be tab-completed while line editing (e.g. card codes and player
+/// names). Words are completed in any case.
+pub fn set_completions(words: Vec<String>) {
+    *COMPLETIONS.lock().unwrap() = words;
+}
+
+/// Sets when input stops being accepted. Once the deadline has passed, every prompt returns
+/// straight away with its default answer (see `input_timed_out`). Use `None` to remove the
+/// deadline.
+pub fn set_input_deadline(deadline: Option<Instant>) {
+    *INPUT_DEADLINE.lock().unwrap() = deadline;
+}
+
+/// Stops accepting input at once, as if the input deadline had just passed, and wakes up any
+/// prompt that is waiting for a line. This can be called from any thread (e.g. when the host
+/// ends the player's turn while they are choosing an action).
+pub fn interrupt_input() {
+    set_input_deadline(Some(Instant::now()));
+    stdin_lines();
+    if let Some(waker) = STDIN_WAKER.get() {
+        let _ = waker.lock().unwrap().send(WAKE_LINE.to_owned());
+    }
+}
+
+/// Returns true if there is an input deadline and it has passed. Input read after this point
+/// should be thrown away.
+pub fn input_timed_out() -> bool {
+    INPUT_DEADLINE
+        .lock()
+        .unwrap()
+        .is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Sets the hook that every line starting with `/` (e.g. `/say hello`) is passed to. Commands are
+/// handled as soon as they are typed, even while no prompt is shown. Use `None` to remove the
+/// hook, after which commands are used as input like any other line.
+pub fn set_command_hook(hook: Option<CommandHook>) {
+    stdin_lines();
+    *COMMAND_HOOK.lock().unwrap() = hook;
+}
+
+/// Prints `prompt`, with the number of seconds left before the input deadline in front of it if
+/// there is one. While line editing, the prompt is printed on its own line above the line being
+/// edited.
+fn print_prompt(prompt: &str) {
+    let deadline = *INPUT_DEADLINE.lock().unwrap();
+    let prompt = match deadline {
+        Some(deadline) => {
+            let secs_left = deadline.saturating_duration_since(Instant::now()).as_secs();
+            format!("[{secs_left}s left] {prompt}")
+        }
+        None => prompt.to_owned(),
+    };
+    if let Some(printer) = PROMPT_PRINTER.lock().unwrap().as_mut() {
+        if printer.print(prompt.trim_end().to_owned()).is_ok() {
+            return;
         }
     }
+    print!("{prompt}");
+    io::stdout().flush().expect("Unable to flush stdout");
 }
 
-/// Prompts the user to enter a number. This function will keep prompting
-/// the user until they enter a number that fits within the range `[a, b]`.
-pub fn get_num_input(prompt: &str, min: i32, max: i32) -> i32 {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
-    let input = &mut String::new();
+/// Completes the word before the cursor from the words set with `set_completions`. Words end at
+/// spaces, and at the `+` and `@` used in commands (e.g. `play KH+5S @bob`).
+struct WordCompleter;
+
+impl Completer for WordCompleter {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind([' ', '+', '@']).map_or(0, |i| i + 1);
+        let prefix = line[start..pos].to_lowercase();
+        let words = COMPLETIONS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|word| word.to_lowercase().starts_with(&prefix))
+            .cloned()
+            .collect();
+        Ok((start, words))
+    }
+}
+
+impl Hinter for WordCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for WordCompleter {}
+
+impl Validator for WordCompleter {}
+
+impl Helper for WordCompleter {}
+
+/// Passes `line` to the command hook if it is a command, or sends it to be used as input
+/// otherwise. Returns false if nothing is receiving input anymore.
+fn handle_line(line: String, sender: &Sender<String>) -> bool {
+    if line.trim_start().starts_with('/') {
+        if let Some(hook) = COMMAND_HOOK.lock().unwrap().as_ref() {
+            hook(line.trim());
+            return true;
+        }
+    }
+    sender.send(line).is_ok()
+}
+
+/// Reads lines from the terminal with line editing until stdin is closed. Returns false if line
+/// editing couldn't be started, in which case no lines were read.
+fn read_edited_lines(sender: &Sender<String>) -> bool {
+    let Ok(mut editor) = Editor::<WordCompleter, DefaultHistory>::new() else {
+        return false;
+    };
+    editor.set_helper(Some(WordCompleter));
+    if let Ok(printer) = editor.create_external_printer() {
+        *PROMPT_PRINTER.lock().unwrap() = Some(Box::new(printer));
+    }
     loop {
-        print!("{prompt}");
-        stdout.flush().expect("Unable to flush stdout");
-        input.clear();
-        stdin.read_line(input).expect("Unable to read input");
-        let input = input.trim().to_owned();
-
-        let num_input = input.parse::<i32>();
-        if let Ok(n) = num_input {
-            if (n < min) || (n > max) {
-                continue;
-            } else {
-                return n;
+        match editor.readline("") {
+            Ok(line) => {
+                let _ = editor.add_history_entry(line.as_str());
+                if !handle_line(line, sender) {
+                    break;
+                }
             }
+            /* Ctrl-C quits, the same as it does without line editing. */
+            Err(ReadlineError::Interrupted) => std::process::exit(130),
+            Err(_) => break,
         }
     }
+    *PROMPT_PRINTER.lock().unwrap() = None;
+    true
 }
 
-pub fn get_bool_input(prompt: &str, true_opt: &str, false_opt: &str) -> bool {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
-    let input = &mut String::new();
+/// Returns the lines read from stdin, starting the thread that reads them if it hasn't been
+/// started yet. Commands are passed to the command hook instead.
+fn stdin_lines() -> &'static Mutex<Receiver<String>> {
+    STDIN_LINES.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        let _ = STDIN_WAKER.set(Mutex::new(sender.clone()));
+        thread::spawn(move || {
+            let line_editing = LINE_EDITING.load(Ordering::SeqCst) && io::stdin().is_terminal();
+            if line_editing && read_edited_lines(&sender) {
+                return;
+            }
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if !handle_line(line, &sender) {
+                    break;
+                }
+            }
+        });
+        Mutex::new(receiver)
+    })
+}
+
+/// Reads a line from stdin (with whitespace trimmed). Returns `None` if the input deadline
+/// passes first.
+fn read_line() -> Option<String> {
+    let lines = stdin_lines().lock().unwrap();
     loop {
-        print!("{prompt}");
-        stdout.flush().expect("Unable to flush stdout");
-        input.clear();
-        stdin.read_line(input).expect("Unable to read input");
-        let input = input.trim().to_owned();
-        if input == true_opt {
-            return true;
-        } else if input == false_opt {
-            return false;
+        let deadline = *INPUT_DEADLINE.lock().unwrap();
+        let line = match deadline {
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                match lines.recv_timeout(timeout) {
+                    Ok(line) => line,
+                    Err(RecvTimeoutError::Timeout) => {
+                        println!("\nTime's up!");
+                        return None;
+                    }
+                    /* Stdin was closed. */
+                    Err(RecvTimeoutError::Disconnected) => String::new(),
+                }
+            }
+            None => lines.recv().unwrap_or_default(),
+        };
+        if line != WAKE_LINE {
+            return Some(line.trim().to_owned());
+        }
+        /* A wakeup that arrives after the prompt it was meant for has nothing to stop. */
+        if input_timed_out() {
+            println!();
+            return None;
         }
     }
 }
 
+/// Prompts the user with `prompt` and returns input (with whitespace trimmed). Returns an empty
+/// string if the input deadline passes.
+pub fn get_input(prompt: &str, max_len: usize) -> String {
+    Terminal.get_input(prompt, max_len)
+}
+
+/// Prompts the user to enter a number. This function will keep prompting
+/// the user until they enter a number that fits within the range `[a, b]`.
+/// Returns `min` if the input deadline passes.
+pub fn get_num_input(prompt: &str, min: i32, max: i32) -> i32 {
+    Terminal.get_num_input(prompt, min, max)
+}
+
+/// Prompts the user until they enter `true_opt` or `false_opt`. Returns false if the input
+/// deadline passes.
+pub fn get_bool_input(prompt: &str, true_opt: &str, false_opt: &str) -> bool {
+    Terminal.get_bool_input(prompt, true_opt, false_opt)
+}
+
 /// Prompts the user to use arrow keys to select an option, returning the index (starting from 0) of
 /// the selected option.
-pub fn select_option(options: &[&str]) -> usize {
+pub fn select_option(_options: &[&str]) -> usize {
     todo!()
 }
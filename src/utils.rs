@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use crate::logging;
+
 /// Clears terminal screen.
 pub fn clear_screen() {
     print!("\x1b[2J\x1b[H");
@@ -10,7 +12,8 @@ pub fn variant_eq<T>(a: &T, b: &T) -> bool {
     std::mem::discriminant(a) == std::mem::discriminant(b)
 }
 
-/// Prints to `stderr` in format `An error occured in {fn_name}(): {err}`
-pub fn perror_in_fn<T: Display>(fn_name: &str, err: T) {
-    eprintln!("\x1b[33mAn error occured in {fn_name}(): {err}\x1b[0m");
+/// Logs an error in format `An error occured in {fn_name}(): {err}`, where `peer` is the name of
+/// whoever was on the other end of the stream when the error occured.
+pub fn perror_in_fn<T: Display>(fn_name: &str, peer: &str, err: T) {
+    logging::error(peer, &format!("An error occured in {fn_name}(): {err}"));
 }
@@ -0,0 +1,873 @@
+// The web table plays one player's side of the Kingdom Kards protocol over WebSocket, in the same
+// order as `ClientSession` does in Rust. Every line of the protocol is one text frame, and the
+// host checks every card that is played, so this page only keeps a copy of the game to show it.
+"use strict";
+
+const SUITS = { H: "♥", D: "♦", S: "♠", C: "♣" };
+const EMOTE_PREFIX = "/me ";
+
+// A WebSocket to the host, read one protocol line at a time. Chat lines are passed to `onChat`
+// as they arrive, so they never get in the way of the rest of the protocol.
+class Connection {
+  constructor(url, onChat, onClose) {
+    this.lines = [];
+    this.listeners = [];
+    this.closed = false;
+    this.socket = new WebSocket(url);
+    this.opened = new Promise((resolve, reject) => {
+      this.socket.onopen = resolve;
+      this.socket.onerror = () => reject(new Error(`Unable to connect to ${url}`));
+    });
+    this.socket.onmessage = (event) => {
+      for (const line of event.data.split("\n")) {
+        if (line.startsWith("RES,CHAT,")) {
+          onChat(parseChat(line.slice("RES,CHAT,".length)));
+        } else {
+          this.lines.push(line);
+        }
+      }
+      this.notify();
+    };
+    this.socket.onclose = () => {
+      this.closed = true;
+      this.notify();
+      onClose();
+    };
+  }
+
+  notify() {
+    const listeners = this.listeners;
+    this.listeners = [];
+    listeners.forEach((listener) => listener());
+  }
+
+  // Resolves once a line can be read, without reading it.
+  available() {
+    if (this.lines.length || this.closed) {
+      return Promise.resolve();
+    }
+    return new Promise((resolve) => this.listeners.push(resolve));
+  }
+
+  async next() {
+    await this.available();
+    if (!this.lines.length) {
+      throw new Error("Disconnected from the host.");
+    }
+    return this.lines.shift();
+  }
+
+  send(line) {
+    this.socket.send(line);
+  }
+
+  async awaitRequest(type) {
+    const line = await this.next();
+    if (line !== `REQ,${type}`) {
+      throw new Error(`Expected REQ,${type} from the host, not '${line}'.`);
+    }
+  }
+
+  respond(type, value) {
+    this.send(value === undefined ? `RES,${type}` : `RES,${type},${value}`);
+  }
+
+  async awaitResponse(type) {
+    const line = await this.next();
+    const prefix = `RES,${type}`;
+    if (line === prefix) {
+      return "";
+    }
+    if (!line.startsWith(`${prefix},`)) {
+      throw new Error(`Expected ${prefix} from the host, not '${line}'.`);
+    }
+    return line.slice(prefix.length + 1);
+  }
+
+  request(type) {
+    this.send(`REQ,${type}`);
+    return this.awaitResponse(type);
+  }
+}
+
+// Parsing, written to match the `Display` implementations in Rust.
+
+function cardValue(card) {
+  return card.slice(0, -1);
+}
+
+function cardSuit(card) {
+  return card.slice(-1);
+}
+
+// Returns what a Number is worth, or `null` if the card isn't a Number.
+function numberValue(card) {
+  const value = Number(cardValue(card));
+  return value >= 2 && value <= 10 ? value : null;
+}
+
+function parsePile(pile) {
+  return pile.split(" ").filter((card) => card);
+}
+
+function parseRules(s) {
+  const rules = {};
+  for (const pair of s.split(",")) {
+    const [key, value] = pair.split("=");
+    rules[key] = Number(value);
+  }
+  return rules;
+}
+
+function parseGameState(s) {
+  const [, ...players] = s.split(",");
+  return players.map((player) => {
+    const [name, points, team] = player.split(":");
+    return { name, points: Number(points), team: team === undefined ? null : Number(team) };
+  });
+}
+
+function parseCards(s) {
+  const [deckSize, hand, discard] = s.split(",");
+  return { deckSize: Number(deckSize), hand: parsePile(hand), discard: parsePile(discard) };
+}
+
+function parseView(s) {
+  return s
+    .split(",")
+    .filter((seat) => seat)
+    .map((seat) => {
+      const [name, handSize, deckSize, discard, hand] = seat.split(":");
+      return {
+        name,
+        handSize: Number(handSize),
+        deckSize: Number(deckSize),
+        discard: parsePile(discard),
+        hand: hand === "?" ? null : parsePile(hand),
+      };
+    });
+}
+
+function parseLobby(s) {
+  const [status, seats, ...players] = s.split(",");
+  return {
+    status,
+    seats: Number(seats),
+    players: players.map((player) => {
+      const [name, ready] = player.split(":");
+      return { name, ready: ready === "Y" };
+    }),
+  };
+}
+
+function parseRooms(s) {
+  return s
+    .split(",")
+    .filter((room) => room)
+    .map((room) => {
+      const [code, name, players, seats] = room.split(":");
+      return { code, name, players: Number(players), seats: Number(seats) };
+    });
+}
+
+function parseAction(s) {
+  const [type, attachment, from, to, play, discards] = s.split(",");
+  return {
+    type,
+    attachment: Number(attachment),
+    from,
+    to,
+    play: play || null,
+    discards: discards ? parsePile(discards) : [],
+  };
+}
+
+function formatAction(action) {
+  let line = `${action.type},${action.attachment},${action.from},${action.to}`;
+  if (action.play) {
+    line += `,${action.play}`;
+    if (action.discards.length) {
+      line += `,${action.discards.join(" ")}`;
+    }
+  }
+  return line;
+}
+
+function parseChat(s) {
+  const [from, to, ...text] = s.split(",");
+  return { from, to: to || null, text: text.join(",") };
+}
+
+// Describes an action the same way as `Action::summary`.
+function summary(action) {
+  const { from, to } = action;
+  const cards = (fallback) => action.play || fallback;
+  switch (action.type) {
+    case "K":
+      return `${from} played ${cards("a King")} against ${to}`;
+    case "Q":
+      return `${from} played ${cards("a Queen")} on ${to}`;
+    case "J":
+      return `${from} blocked ${to}'s King with ${cards("a Jack")}`;
+    case "N":
+      return `${from} played ${cards(`a ${action.attachment}`)}`;
+    case "B":
+      return `${from} played ${cards("a Black Ace")}`;
+    case "R":
+      return `${from} played ${cards("a Red Ace")}`;
+    case "S":
+      return `${from}'s turn started`;
+    case "E":
+      return `${from} ended their turn`;
+    case "O":
+      return `${from} won the game`;
+    case "P":
+      return `the host set ${from}'s points to ${action.attachment}`;
+    default:
+      return formatAction(action);
+  }
+}
+
+// The game, as this player knows it.
+
+const game = {
+  name: "",
+  rules: {},
+  players: [],
+  current: 0,
+  cards: { deckSize: 0, hand: [], discard: [] },
+  view: [],
+  audited: false,
+  ready: false,
+  over: false,
+};
+
+function player(name) {
+  return game.players.find((player) => player.name === name);
+}
+
+function areTeammates(a, b) {
+  const teamA = player(a)?.team ?? null;
+  return teamA !== null && teamA === (player(b)?.team ?? null);
+}
+
+// Applies an action from the host to the players' points and whose turn it is, the same way as
+// `GameState::apply_action`.
+function applyAction(action) {
+  const { rules } = game;
+  switch (action.type) {
+    case "K": {
+      const target = player(action.to);
+      if (target) {
+        target.points = Math.max(0, target.points - (rules.king + action.attachment));
+      }
+      break;
+    }
+    case "Q": {
+      const healed = areTeammates(action.from, action.to) ? action.to : action.from;
+      const target = player(healed);
+      if (target) {
+        target.points += rules.queen + action.attachment;
+      }
+      break;
+    }
+    case "J": {
+      const target = player(action.from);
+      if (target) {
+        target.points += action.attachment;
+      }
+      break;
+    }
+    case "P": {
+      const target = player(action.from);
+      if (target) {
+        target.points = action.attachment;
+      }
+      break;
+    }
+    case "E":
+      for (let i = 0; i < game.players.length; i++) {
+        game.current = (game.current + 1) % game.players.length;
+        if (game.players[game.current].points > 0) {
+          break;
+        }
+      }
+      break;
+    default:
+      break;
+  }
+}
+
+// The page.
+
+const $ = (id) => document.getElementById(id);
+
+function show(section) {
+  for (const id of ["join", "lobby", "table"]) {
+    $(id).hidden = id !== section;
+  }
+}
+
+function setStatus(text) {
+  $("status").textContent = text;
+}
+
+function log(text, className) {
+  const item = document.createElement("li");
+  item.textContent = text;
+  if (className) {
+    item.className = className;
+  }
+  $("log").append(item);
+  $("log").scrollTop = $("log").scrollHeight;
+}
+
+function logAction(action) {
+  log(summary(action));
+}
+
+function addChat(message) {
+  const emote = message.text.startsWith(EMOTE_PREFIX);
+  const text = emote ? message.text.slice(EMOTE_PREFIX.length) : message.text;
+  if (emote) {
+    log(`* ${message.from} ${text}`, "chat");
+  } else if (message.to) {
+    log(`${message.from} (whisper to ${message.to}): ${text}`, "chat");
+  } else {
+    log(`${message.from}: ${text}`, "chat");
+  }
+}
+
+function cardElement(card) {
+  const element = document.createElement("div");
+  const suit = cardSuit(card);
+  element.className = suit === "H" || suit === "D" ? "card red" : "card";
+  element.textContent = `${cardValue(card)}${SUITS[suit] ?? suit}`;
+  element.title = card;
+  return element;
+}
+
+function render() {
+  const opponents = $("opponents");
+  opponents.replaceChildren();
+  game.players.forEach((details, index) => {
+    const seat = document.createElement("div");
+    seat.className = "seat";
+    if (index === game.current && !game.over) {
+      seat.classList.add("current");
+    }
+    if (details.points === 0) {
+      seat.classList.add("eliminated");
+    }
+    const view = game.view.find((view) => view.name === details.name);
+    const you = details.name === game.name ? " (you)" : "";
+    const team = details.team === null ? "" : `, team ${details.team}`;
+    const lines = [`${details.name}${you}${team}`, `${details.points}`];
+    if (view) {
+      const top = view.discard.length ? view.discard[view.discard.length - 1] : "none";
+      lines.push(`${view.handSize} in hand, ${view.deckSize} in deck, top discard ${top}`);
+    }
+    lines.forEach((text, line) => {
+      const element = document.createElement("div");
+      element.textContent = text;
+      if (line === 1) {
+        element.className = "points";
+      }
+      seat.append(element);
+    });
+    opponents.append(seat);
+  });
+
+  $("me").textContent = game.name ? `(${game.name})` : "";
+  const hand = $("hand");
+  hand.replaceChildren();
+  for (const card of game.cards.hand) {
+    const element = cardElement(card);
+    if (card === chosen.card) {
+      element.classList.add("chosen");
+    } else if (chosen.discards.includes(card)) {
+      element.classList.add("discard");
+    }
+    element.onclick = () => chooseCard(card);
+    hand.append(element);
+  }
+  $("piles").textContent =
+    `${game.cards.deckSize} in your deck, ${game.cards.discard.length} in your discard pile`;
+}
+
+// Choosing an action, while the host is waiting for one.
+
+const chosen = { card: null, discards: [], resolve: null };
+
+function chooseCard(card) {
+  if (!chosen.resolve) {
+    return;
+  }
+  const worth = chosen.card ? numberValue(chosen.card) : null;
+  if (worth !== null && card !== chosen.card) {
+    /* Other cards are discarded for a Number. */
+    const index = chosen.discards.indexOf(card);
+    if (index >= 0) {
+      chosen.discards.splice(index, 1);
+    } else if (chosen.discards.length < worth) {
+      chosen.discards.push(card);
+    }
+  } else {
+    chosen.card = card === chosen.card ? null : card;
+    chosen.discards = [];
+  }
+  updateControls();
+  render();
+}
+
+function fillSelect(select, options) {
+  select.replaceChildren();
+  for (const [value, text] of options) {
+    const option = document.createElement("option");
+    option.value = value;
+    option.textContent = text;
+    select.append(option);
+  }
+}
+
+function updateControls() {
+  const card = chosen.card;
+  const value = card ? cardValue(card) : null;
+  const worth = card ? numberValue(card) : null;
+  const isKingOrQueen = value === "K" || value === "Q";
+  $("attachment-field").hidden = !isKingOrQueen;
+  $("target-field").hidden = !isKingOrQueen;
+  let canPlay = false;
+  if (!card) {
+    $("hint").textContent = "Choose a card to play.";
+  } else if (isKingOrQueen) {
+    const numbers = game.cards.hand.filter((other) => other !== card && numberValue(other));
+    fillSelect($("attachment"), [["", "Nothing"], ...numbers.map((number) => [number, number])]);
+    const targets = game.players.filter(
+      (target) => target.points > 0 && (value === "Q" || target.name !== game.name),
+    );
+    fillSelect(
+      $("target"),
+      targets.map((target) => [target.name, `${target.name} (${target.points})`]),
+    );
+    if (value === "Q") {
+      $("target").value = game.name;
+    }
+    $("hint").textContent = value === "K" ? "Choose who to play the King against." : "";
+    canPlay = targets.length > 0;
+  } else if (worth !== null) {
+    const left = worth - chosen.discards.length;
+    $("hint").textContent =
+      left > 0 ? `Choose ${left} more card(s) to discard.` : "Ready to play the Number.";
+    canPlay = left === 0;
+  } else if (value === "J") {
+    $("hint").textContent = "Jacks can only be played to block a King.";
+  } else {
+    $("hint").textContent = "Aces can't be played yet.";
+  }
+  $("play").disabled = !canPlay;
+}
+
+// Returns the chosen action, or `null` if the host sent something first (e.g. a `TurnEnd` when
+// the turn clock ran out).
+async function chooseAction() {
+  chosen.card = null;
+  chosen.discards = [];
+  $("controls").hidden = false;
+  updateControls();
+  render();
+  const choice = new Promise((resolve) => {
+    chosen.resolve = resolve;
+  });
+  const action = await Promise.race([choice, conn.available().then(() => null)]);
+  chosen.resolve = null;
+  chosen.card = null;
+  chosen.discards = [];
+  $("controls").hidden = true;
+  render();
+  return action;
+}
+
+function playChosen() {
+  const card = chosen.card;
+  if (!chosen.resolve || !card || $("play").disabled) {
+    return;
+  }
+  let action;
+  const worth = numberValue(card);
+  if (worth !== null) {
+    action = { type: "N", attachment: worth, from: game.name, to: "", play: card };
+    action.discards = [...chosen.discards];
+  } else {
+    const attachment = $("attachment").value;
+    action = {
+      type: cardValue(card),
+      attachment: attachment ? numberValue(attachment) : 0,
+      from: game.name,
+      to: $("target").value,
+      play: attachment ? `${card}+${attachment}` : card,
+      discards: [],
+    };
+  }
+  chosen.resolve(action);
+}
+
+function endTurn() {
+  if (chosen.resolve) {
+    chosen.resolve({ type: "E", attachment: 0, from: game.name, to: "", play: null, discards: [] });
+  }
+}
+
+// Clocks.
+
+let clock = null;
+
+// Counts down `seconds` in `element` with `label`, then calls `onExpired`. A clock of 0 seconds
+// never runs out.
+function startClock(element, label, seconds, onExpired) {
+  stopClock();
+  if (!seconds) {
+    element.textContent = label;
+    return;
+  }
+  const deadline = Date.now() + seconds * 1000;
+  const tick = () => {
+    const left = Math.max(0, Math.ceil((deadline - Date.now()) / 1000));
+    element.textContent = `${label} (${left}s left)`;
+    if (left === 0) {
+      stopClock();
+      onExpired?.();
+    }
+  };
+  tick();
+  clock = setInterval(tick, 250);
+}
+
+function stopClock() {
+  if (clock !== null) {
+    clearInterval(clock);
+    clock = null;
+  }
+}
+
+// The protocol, in the same steps as `ClientSession`.
+
+let conn;
+
+function formSubmitted(form) {
+  return new Promise((resolve) => {
+    form.onsubmit = (event) => {
+      event.preventDefault();
+      resolve();
+    };
+  });
+}
+
+async function joinRoom() {
+  show("join");
+  setStatus("Choose a room.");
+  await conn.awaitRequest("ROOM");
+  conn.respond("ROOM", "L");
+  const rooms = parseRooms(await conn.request("ROOMS"));
+  const list = $("rooms");
+  list.replaceChildren();
+  for (const room of rooms) {
+    const item = document.createElement("li");
+    item.textContent = `${room.name} (${room.players}/${room.seats}) - join code ${room.code}`;
+    item.onclick = () => {
+      $("join-code").value = room.code;
+    };
+    list.append(item);
+  }
+
+  let joined = false;
+  for (;;) {
+    await formSubmitted($("join-form"));
+    const name = $("join-name").value.trim();
+    if (!/^[A-Za-z]+$/.test(name)) {
+      $("join-error").textContent = "Usernames can only contain letters.";
+      continue;
+    }
+    if (!joined) {
+      const code = $("join-code").value.trim();
+      await conn.awaitRequest("ROOM");
+      conn.respond("ROOM", `J,${code}`);
+      const room = parseRooms(await conn.request("ROOMS"))[0];
+      if (!room) {
+        $("join-error").textContent = `There is no room with join code ${code}.`;
+        continue;
+      }
+      if ((await conn.request("STATUS")) !== "Y") {
+        throw new Error(`Room '${room.name}' is full.`);
+      }
+      joined = true;
+      $("join-code").disabled = true;
+    }
+    await conn.awaitRequest("NAME");
+    conn.respond("NAME", name);
+    if ((await conn.request("STATUS")) === "Y") {
+      game.name = name;
+      return;
+    }
+    $("join-error").textContent = `The name '${name}' is taken.`;
+  }
+}
+
+async function waitInLobby() {
+  show("lobby");
+  setStatus("Waiting for the game to start.");
+  for (;;) {
+    const line = await conn.next();
+    if (line.startsWith("RES,RULES,")) {
+      game.rules = parseRules(line.slice("RES,RULES,".length));
+      $("lobby-rules").textContent =
+        `${game.rules.points} points, ${game.rules.hand} cards, ` +
+        `${game.rules.turn ? `${game.rules.turn}s turns` : "no turn clock"}` +
+        `${game.rules.teams ? `, ${game.rules.teams} teams` : ""}`;
+      continue;
+    }
+    if (!line.startsWith("RES,LOBBY,")) {
+      continue;
+    }
+    const lobby = parseLobby(line.slice("RES,LOBBY,".length));
+    const list = $("lobby-players");
+    list.replaceChildren();
+    for (const seat of lobby.players) {
+      const item = document.createElement("li");
+      item.textContent = `${seat.name}${seat.ready ? " (ready)" : ""}`;
+      list.append(item);
+    }
+    if (lobby.status === "S") {
+      return;
+    }
+    if (lobby.status === "K") {
+      throw new Error("You were removed from the lobby.");
+    }
+  }
+}
+
+function toggleReady() {
+  game.ready = !game.ready;
+  $("ready").textContent = game.ready ? "Not Ready" : "Ready";
+  conn.send(`RES,STATUS,${game.ready ? "Y" : "N"}`);
+}
+
+function randomHex() {
+  const bytes = new Uint8Array(32);
+  crypto.getRandomValues(bytes);
+  return Array.from(bytes, (byte) => byte.toString(16).padStart(2, "0")).join("");
+}
+
+async function startGame() {
+  show("table");
+  setStatus("The game is starting.");
+  game.players = parseGameState(await conn.request("GAME"));
+  game.current = 0;
+  game.rules = parseRules(await conn.request("RULES"));
+  if (game.rules.fair_deal) {
+    const commit = await conn.request("DEAL");
+    /* A resumed game was dealt before it was saved, so there is nothing to add entropy to. */
+    if (commit !== "C") {
+      await conn.awaitRequest("DEAL");
+      conn.respond("DEAL", `E,${randomHex()}`);
+      game.audited = true;
+    }
+  }
+  const cards = await conn.request("CARDS");
+  if (cards) {
+    game.cards = parseCards(cards);
+  }
+  render();
+}
+
+// Waits for the next turn to start. Returns false once the game is over. The host can also set a
+// player's points between turns (e.g. when it kicks them), after which the next action is asked
+// for again.
+async function awaitTurn() {
+  conn.send("REQ,ACT");
+  let action;
+  for (;;) {
+    action = parseAction(await conn.awaitResponse("ACT"));
+    if (action.type === "S" || action.type === "O") {
+      break;
+    }
+    if (action.type === "P") {
+      applyAction(action);
+      logAction(action);
+      render();
+      conn.send("REQ,ACT");
+      continue;
+    }
+    if (action.type !== "E") {
+      throw new Error(`Expected a turn to start, not '${summary(action)}'.`);
+    }
+  }
+  logAction(action);
+  if (action.type === "O") {
+    if (game.audited) {
+      await conn.request("DEAL");
+      log("The host revealed every deck. The terminal client can check the deal.");
+    }
+    game.over = true;
+    stopClock();
+    $("turn").textContent = "";
+    setStatus(`${action.from} won the game!`);
+    render();
+    return false;
+  }
+  game.view = parseView(await conn.request("VIEW"));
+  const own = game.view.find((seat) => seat.name === game.name);
+  if (own?.hand) {
+    game.cards.hand = own.hand;
+  }
+  game.current = Math.max(0, game.players.findIndex((seat) => seat.name === action.from));
+  const label = action.from === game.name ? "Your turn" : `${action.from}'s turn`;
+  setStatus(label);
+  startClock($("turn"), label, action.attachment);
+  render();
+  return true;
+}
+
+async function playOwnTurn() {
+  for (;;) {
+    const line = await conn.next();
+    if (line === "REQ,ACT") {
+      const action = await chooseAction();
+      if (!action) {
+        continue;
+      }
+      conn.respond("ACT", formatAction(action));
+      if (action.type === "E") {
+        applyAction(action);
+        logAction(action);
+        render();
+        return;
+      }
+      const reply = await conn.next();
+      if (reply.startsWith("RES,ACT,")) {
+        /* The host ended the turn before it got the action, so it is never answered. */
+        const forced = parseAction(reply.slice("RES,ACT,".length));
+        applyAction(forced);
+        logAction(forced);
+        render();
+        return;
+      }
+      if (!reply.startsWith("RES,STATUS,")) {
+        throw new Error(`Expected RES,STATUS from the host, not '${reply}'.`);
+      }
+      const status = reply.slice("RES,STATUS,".length);
+      const cards = await conn.awaitResponse("CARDS");
+      if (cards) {
+        game.cards = parseCards(cards);
+      }
+      if (status === "Y") {
+        applyAction(action);
+        logAction(action);
+      } else {
+        log(`The host rejected ${action.play}. Your cards are back in your hand.`);
+      }
+      render();
+    } else if (line.startsWith("RES,ACT,")) {
+      /* A Jack played against this player's King, or the turn clock running out. */
+      const action = parseAction(line.slice("RES,ACT,".length));
+      applyAction(action);
+      logAction(action);
+      render();
+      if (action.type === "E") {
+        return;
+      }
+    } else {
+      throw new Error(`Unexpected message from the host: '${line}'.`);
+    }
+  }
+}
+
+// Asks whether to block `king` with a Jack. Declines once the reaction clock runs out.
+function askReaction(king) {
+  const jack = game.cards.hand.find((card) => cardValue(card) === "J");
+  $("reaction").hidden = false;
+  $("block").disabled = !jack;
+  return new Promise((resolve) => {
+    const answer = (block) => {
+      stopClock();
+      $("reaction").hidden = true;
+      resolve(block ? jack : null);
+    };
+    $("block").onclick = () => answer(true);
+    $("decline").onclick = () => answer(false);
+    const label = `${summary(king)}. ${jack ? "Block it with your Jack?" : "You have no Jack."}`;
+    startClock($("reaction-text"), label, game.rules.reaction, () => answer(false));
+  });
+}
+
+async function watchTurn() {
+  for (;;) {
+    const action = parseAction(await conn.request("ACT"));
+    applyAction(action);
+    logAction(action);
+    render();
+    if (action.type === "K" && action.to === game.name && action.from !== game.name) {
+      await conn.awaitRequest("ACT");
+      const jack = await askReaction(action);
+      const reaction = jack
+        ? { type: "J", attachment: 0, from: game.name, to: action.from, play: jack, discards: [] }
+        : { type: "X", attachment: 0, from: game.name, to: "", play: null, discards: [] };
+      conn.respond("ACT", formatAction(reaction));
+    } else if (action.type === "J" && action.from === game.name) {
+      /* The Jack is only discarded once the host has accepted it. */
+      const played = game.cards.hand.indexOf(action.play);
+      const index = played >= 0 ? played : game.cards.hand.findIndex((c) => cardValue(c) === "J");
+      if (index >= 0) {
+        game.cards.discard.push(...game.cards.hand.splice(index, 1));
+      }
+      render();
+    } else if (action.type === "E") {
+      return;
+    }
+  }
+}
+
+function sendChat(event) {
+  event.preventDefault();
+  const text = $("chat").value.trim();
+  if (!text || !game.name) {
+    return;
+  }
+  $("chat").value = "";
+  const whisper = text.match(/^\/w (\S+) (.+)$/);
+  if (whisper) {
+    conn.send(`RES,CHAT,${game.name},${whisper[1]},${whisper[2]}`);
+  } else {
+    conn.send(`RES,CHAT,${game.name},,${text}`);
+  }
+}
+
+async function main() {
+  const port = document.querySelector('meta[name="websocket-port"]').content;
+  conn = new Connection(`ws://${location.hostname}:${port}/`, addChat, () => {
+    if (!game.over) {
+      setStatus("Disconnected from the host.");
+    }
+  });
+  $("ready").onclick = toggleReady;
+  $("play").onclick = playChosen;
+  $("end-turn").onclick = endTurn;
+  $("chat-form").onsubmit = sendChat;
+  try {
+    await conn.opened;
+    await joinRoom();
+    await waitInLobby();
+    await startGame();
+    while (await awaitTurn()) {
+      if (game.players[game.current].name === game.name) {
+        await playOwnTurn();
+      } else {
+        await watchTurn();
+      }
+    }
+  } catch (err) {
+    setStatus(err.message);
+  }
+}
+
+main();